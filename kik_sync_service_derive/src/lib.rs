@@ -0,0 +1,189 @@
+//! # kik_sync_service_derive
+//!
+//! *#[derive(Message)]* for kik_sync_service, re-exported by it with the "derive" feature. Use it from there, as *kik_sync_service::message::Message*.
+//!
+//! The struct marks the field that holds the result with *#[data]* and the one that holds the input with *#[input]*. The derive writes *set_input*,
+//! *take_input*, *clone_message_data*, *swap_message_data* and *take_message_data*. *new* is left to *Default*, which the struct derives too.
+//! *work* is left to the user, in a plain impl block of the struct:
+//!
+//! ```ignore
+//! use kik_sync_service::message::Message;
+//!
+//! #[derive(Clone, Default, Message)]
+//! pub struct Square{
+//!     #[input]
+//!     input: u64,
+//!     #[data]
+//!     output: u64,
+//! }
+//!
+//! impl Square{
+//!     fn work(&mut self){
+//!         self.output = self.input * self.input;
+//!     }
+//! }
+//! ```
+//!
+//! The crate has no dependencies, so the struct is read token by token. Only structs with named fields and no generics are supported.
+//!
+//!
+
+use proc_macro::{Delimiter, TokenStream, TokenTree};
+
+/// Implement *Message* for a struct with a *#[data]* and an *#[input]* field. See the crate documentation.
+#[proc_macro_derive(Message, attributes(data, input))]
+pub fn derive_message(item: TokenStream) -> TokenStream{
+    let code = match MessageStruct::parse(item){
+        Ok(message) => message.implement(),
+        Err(error) => format!("compile_error!({:?});", format!("derive(Message): {}", error)),
+    };
+    // The code is built from tokens that were already valid, so it always parses.
+    code.parse().unwrap()
+}
+
+// One named field of the struct.
+struct Field{
+    name: String,
+    ty: String,
+    // The attributes of the field this derive looks for. "data" or "input".
+    marks: Vec<String>,
+}
+
+// What the derive needs to know about the struct.
+struct MessageStruct{
+    name: String,
+    fields: Vec<Field>,
+}
+
+impl MessageStruct{
+    fn parse(item: TokenStream) -> Result<Self, String>{
+        let mut tokens = item.into_iter().peekable();
+        // Attributes and visibility come before the struct keyword.
+        let name = loop{
+            match tokens.next(){
+                Some(TokenTree::Ident(ident)) if ident.to_string() == "struct" => match tokens.next(){
+                    Some(TokenTree::Ident(name)) => break name.to_string(),
+                    _ => return Err(String::from("expected the name of the struct")),
+                },
+                Some(TokenTree::Ident(ident)) if ident.to_string() == "enum" || ident.to_string() == "union" => {
+                    return Err(String::from("only structs can be messages"));
+                },
+                Some(_) => continue,
+                None => return Err(String::from("expected a struct")),
+            }
+        };
+        let body = match tokens.next(){
+            Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Brace => group.stream(),
+            Some(TokenTree::Punct(punct)) if punct.as_char() == '<' => return Err(String::from("structs with generics aren't supported")),
+            _ => return Err(String::from("only structs with named fields are supported")),
+        };
+        Ok(MessageStruct{
+            name,
+            fields: Self::parse_fields(body)?,
+        })
+    }
+
+    // Split the body at the commas outside of angle brackets, reading the attributes, name and type of each field.
+    fn parse_fields(body: TokenStream) -> Result<Vec<Field>, String>{
+        let mut fields: Vec<Field> = Vec::new();
+        let mut tokens = body.into_iter().peekable();
+        while tokens.peek().is_some(){
+            let mut marks: Vec<String> = Vec::new();
+            let name = loop{
+                match tokens.next(){
+                    // #[attribute]
+                    Some(TokenTree::Punct(punct)) if punct.as_char() == '#' => {
+                        if let Some(TokenTree::Group(group)) = tokens.next(){
+                            if let Some(TokenTree::Ident(mark)) = group.stream().into_iter().next(){
+                                marks.push(mark.to_string());
+                            }
+                        }
+                    },
+                    // pub and pub(crate)
+                    Some(TokenTree::Ident(ident)) if ident.to_string() == "pub" => {
+                        if let Some(TokenTree::Group(group)) = tokens.peek(){
+                            if group.delimiter() == Delimiter::Parenthesis{
+                                tokens.next();
+                            }
+                        }
+                    },
+                    Some(TokenTree::Ident(ident)) => break ident.to_string(),
+                    _ => return Err(String::from("expected the name of a field")),
+                }
+            };
+            match tokens.next(){
+                Some(TokenTree::Punct(punct)) if punct.as_char() == ':' => {},
+                _ => return Err(format!("expected the type of field {}", name)),
+            }
+            let mut ty = TokenStream::new();
+            let mut depth: usize = 0;
+            for token in tokens.by_ref(){
+                if let TokenTree::Punct(punct) = &token{
+                    match punct.as_char(){
+                        ',' if depth == 0 => break,
+                        '<' => depth += 1,
+                        '>' => depth = depth.saturating_sub(1),
+                        _ => {},
+                    }
+                }
+                ty.extend(std::iter::once(token));
+            }
+            fields.push(Field{
+                name,
+                ty: ty.to_string(),
+                marks,
+            });
+        }
+        Ok(fields)
+    }
+
+    // The single field with the given mark.
+    fn marked(&self, mark: &str) -> Result<&Field, String>{
+        let mut marked = self.fields.iter().filter(|field| field.marks.iter().any(|found| found == mark));
+        match (marked.next(), marked.next()){
+            (Some(field), None) => Ok(field),
+            (None, _) => Err(format!("mark the field that holds the {} with #[{}]", mark, mark)),
+            (Some(_), Some(_)) => Err(format!("only one field can be marked with #[{}]", mark)),
+        }
+    }
+
+    fn implement(&self) -> String{
+        let (data, input) = match (self.marked("data"), self.marked("input")){
+            (Ok(data), Ok(input)) => (data, input),
+            (Err(error), _) | (_, Err(error)) => return format!("compile_error!({:?});", format!("derive(Message): {}", error)),
+        };
+        let name = &self.name;
+        format!("
+            impl ::kik_sync_service::message::Message<{data_ty}, {input_ty}> for {name}{{
+                fn set_input(&mut self, message_input: {input_ty}){{
+                    self.{input} = message_input;
+                }}
+
+                fn work(&mut self){{
+                    {name}::work(self)
+                }}
+
+                fn take_input(&mut self) -> ::std::option::Option<{input_ty}>{{
+                    ::std::option::Option::Some(::std::mem::take(&mut self.{input}))
+                }}
+
+                fn clone_message_data(&self) -> {data_ty}{{
+                    ::std::clone::Clone::clone(&self.{data})
+                }}
+
+                fn swap_message_data(&mut self, spare: {data_ty}) -> {data_ty}{{
+                    ::std::mem::replace(&mut self.{data}, spare)
+                }}
+
+                fn take_message_data(self) -> {data_ty}{{
+                    self.{data}
+                }}
+            }}",
+            name = name,
+            data = data.name,
+            data_ty = data.ty,
+            input = input.name,
+            input_ty = input.ty,
+        )
+    }
+}
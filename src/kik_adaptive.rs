@@ -0,0 +1,84 @@
+//! # Adaptive
+//!
+//! Sizing of the package window when *ChannelConfig::set_adaptive_packages* is used. Used by kik_feeder, not meant to be used directly.
+//!
+//! Each package that comes back tells three things: how long it waited in the queue before a worker took it, how long it was worked,
+//! and how long its result waited for the feeder. Once a window's worth of packages is back, their averages decide the next window:
+//!
+//! - Results waited longer than the work itself. Whoever iterates is the bottleneck, so messages are only sitting on their buffers. Shrink by one.
+//! - Messages waited in the queue longer than the work itself. There's well over one extra for each worker. Shrink by one.
+//! - Messages were taken almost as soon as they were sent. A worker may be waiting for the next one. Grow by one.
+//!
+//! Anything in between keeps the window as it is. It never leaves the band given in the config.
+//!
+//!
+
+use std::time::{Duration, Instant};
+
+// Queue waits under this fraction of the work time count as workers running out of messages.
+const STARVING_FRACTION: u32 = 4;
+
+/// Decides how many messages should be in flight, within a band. See the module documentation.
+pub struct AdaptiveWindow{
+    min: usize,
+    max: usize,
+    // Packages recorded since the last decision.
+    samples: usize,
+    queue_wait: Duration,
+    work_time: Duration,
+    result_wait: Duration,
+}
+
+impl AdaptiveWindow{
+    /// Window that moves between min and max packages, both included.
+    pub fn new(min: usize, max: usize) -> Self{
+        AdaptiveWindow{
+            min: min.min(max),
+            max: min.max(max),
+            samples: 0,
+            queue_wait: Duration::from_secs(0),
+            work_time: Duration::from_secs(0),
+            result_wait: Duration::from_secs(0),
+        }
+    }
+
+    /// The closest package number inside the band.
+    pub fn clamp(&self, package_number: usize) -> usize{
+        package_number.clamp(self.min, self.max)
+    }
+
+    /// Move the band up, or down if negative, keeping at least one package. Used when workers are added or removed.
+    pub fn shift(&mut self, by: isize){
+        self.min = self.min.saturating_add_signed(by).max(1);
+        self.max = self.max.saturating_add_signed(by).max(self.min);
+    }
+
+    /// The band, as (min, max).
+    pub fn get_band(&self) -> (usize, usize){
+        (self.min, self.max)
+    }
+
+    /// Record a package that came back now. Once as many were recorded as the current package number, returns the next package number.
+    pub fn record(&mut self, package_number: usize, dispatched_at: Instant, completed_at: Instant, work_time: Duration) -> Option<usize>{
+        self.queue_wait += completed_at.saturating_duration_since(dispatched_at).saturating_sub(work_time);
+        self.work_time += work_time;
+        self.result_wait += completed_at.elapsed();
+        self.samples += 1;
+        if self.samples < package_number.max(1){
+            return None;
+        }
+        // Sums of the same number of samples compare like their averages.
+        let next = if self.result_wait > self.work_time || self.queue_wait > self.work_time{
+            package_number.saturating_sub(1)
+        } else if self.queue_wait < self.work_time / STARVING_FRACTION{
+            package_number + 1
+        } else {
+            package_number
+        };
+        self.samples = 0;
+        self.queue_wait = Duration::from_secs(0);
+        self.work_time = Duration::from_secs(0);
+        self.result_wait = Duration::from_secs(0);
+        Some(self.clamp(next))
+    }
+}
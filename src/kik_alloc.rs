@@ -0,0 +1,69 @@
+//! # Alloc
+//!
+//! Counts the heap allocations made by each thread, for checking that iterating a *DeliveryService* stays allocation-free.
+//!
+//! Nothing is counted unless *CountingAllocator* is the global allocator of the binary. It's meant for tests and benchmarks:
+//!
+//! ```ignore
+//! #[global_allocator]
+//! static ALLOCATOR: CountingAllocator = CountingAllocator;
+//! ```
+//!
+//! Then enable *ChannelConfig::set_alloc_tracking* and read *DeliveryService::alloc_stats*. Only the caller's thread is counted,
+//! allocations made by the workers (inside *work*, mostly) are theirs.
+//!
+//! ## Guarantee
+//!
+//! Once a run has sent its first *package_number* messages, retrieving a result, recycling its message and dispatching the next input
+//! allocate nothing, as long as the user's code doesn't either: *Message::clone_message_data*, *Message::set_input*, *MessageInput::weight* and the *Scheduler*.
+//! The optional tracking in *ChannelConfig* (memory, throughput, metrics, reports from *drain*), the *PoolEvent* and result subscriptions and the *ChannelObserver*s are not covered.
+//!
+//! Feeding with *feed_feeder* doesn't allocate either once the internal queue and the default schedulers have held that many inputs before.
+//! A new run builds its messages again, and building them is only allocation-free if *Message::new* is.
+//!
+//!
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+thread_local!{
+    // No destructor and no lazy initialization, so it can be touched from inside the allocator.
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Global allocator that forwards to *System*, counting how many allocations each thread made. See the module documentation.
+pub struct CountingAllocator;
+
+impl CountingAllocator{
+    /// How many allocations the current thread made so far. Always 0 if *CountingAllocator* isn't the global allocator.
+    pub fn get_thread_allocations() -> usize{
+        ALLOCATIONS.try_with(Cell::get).unwrap_or(0)
+    }
+}
+
+/// Count one allocation for the current thread. Does nothing while the thread is being torn down.
+fn count_allocation(){
+    let _ = ALLOCATIONS.try_with(|allocations| allocations.set(allocations.get() + 1));
+}
+
+unsafe impl GlobalAlloc for CountingAllocator{
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8{
+        count_allocation();
+        System.alloc(layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8{
+        count_allocation();
+        System.alloc_zeroed(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout){
+        System.dealloc(ptr, layout)
+    }
+
+    // Growing a buffer is as bad as allocating a new one.
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8{
+        count_allocation();
+        System.realloc(ptr, layout, new_size)
+    }
+}
@@ -0,0 +1,109 @@
+//! # Backoff
+//!
+//! How the feeder and the workers wait when they can't go on right away: the channel they're sending to is full, or the one they're
+//! receiving from is empty. Used by kik_feeder and kik_worker, not meant to be used directly.
+//!
+//! The *WaitStrategy* set in *ChannelConfig* decides it. By default, each wait gets a small budget of retries. Between them the thread spins
+//! for a random while, growing with each retry, and then yields. The randomness keeps several workers that found the deliverer full at the
+//! same moment from retrying in lockstep. Once the budget is spent, the thread gives up on retrying and sleeps until there's room, or work.
+//!
+//!
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::hint::spin_loop;
+use std::thread::yield_now;
+
+/// How many times a full channel is retried before blocking.
+pub const SEND_RETRIES: u32 = 16;
+
+// Retries after this one yield the thread instead of only spinning. The spins stop growing here too.
+const SPIN_RETRIES: u32 = 6;
+
+/// How the feeder and the workers wait when they can't go on right away. Set it with *ChannelConfig::set_wait_strategy*.
+///
+/// The busy strategies never sleep, not even while the channel is idle, so every waiting thread keeps a core busy until there's something to do.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum WaitStrategy{
+    /// Retry with a *spin_loop* hint in between, without ever giving up the core. Lowest latency, for real-time work like audio.
+    SpinHint,
+    /// Retry, yielding the thread in between. Lets other threads run on a busy machine, but still never sleeps.
+    Yield,
+    /// Spin for a random, growing while, then yield, a few times. Then sleep until the other side wakes the thread.
+    #[default]
+    ExponentialBackoff,
+    /// Sleep right away until the other side wakes the thread. Idle threads cost nothing, waking them costs a little latency.
+    Park,
+}
+
+/// Retry budget of a single wait.
+pub struct Backoff{
+    strategy: WaitStrategy,
+    retries: u32,
+    // Xorshift state for the jitter. Zero until the first retry, since most sends never need one.
+    state: u64,
+}
+
+impl Backoff{
+    /// A full budget of retries, backing off exponentially.
+    pub fn new() -> Self{
+        Self::with_strategy(WaitStrategy::ExponentialBackoff)
+    }
+
+    /// A full budget of retries for the given strategy. The busy strategies never run out, *WaitStrategy::Park* has none.
+    pub fn with_strategy(strategy: WaitStrategy) -> Self{
+        Backoff{
+            strategy,
+            retries: 0,
+            state: 0,
+        }
+    }
+
+    /// Wait a little before the next retry. Returns false once the budget is spent, then the caller should block instead.
+    pub fn retry(&mut self) -> bool{
+        match self.strategy{
+            WaitStrategy::SpinHint => {
+                spin_loop();
+                return true;
+            },
+            WaitStrategy::Yield => {
+                yield_now();
+                return true;
+            },
+            WaitStrategy::Park => return false,
+            WaitStrategy::ExponentialBackoff => {},
+        }
+        if self.retries >= SEND_RETRIES{
+            return false;
+        }
+        let step = self.retries.min(SPIN_RETRIES);
+        // Anywhere between none and 2^step spins.
+        let spins = self.next_random() % (1u64 << step);
+        for _ in 0..spins{
+            spin_loop();
+        }
+        if self.retries >= SPIN_RETRIES{
+            yield_now();
+        }
+        self.retries += 1;
+        true
+    }
+
+    /// Next jitter value.
+    fn next_random(&mut self) -> u64{
+        if self.state == 0{
+            // A new RandomState is seeded differently for each one made, with no dependencies. Zero would stick forever.
+            self.state = RandomState::new().build_hasher().finish() | 1;
+        }
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        self.state
+    }
+}
+
+impl Default for Backoff{
+    fn default() -> Self{
+        Self::new()
+    }
+}
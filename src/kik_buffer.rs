@@ -0,0 +1,301 @@
+//! # Buffers
+//!
+//! Ready-made *Message* types for the most common workload: fill a buffer for each part of a larger one. They need no trait implementation,
+//! only a function that fills the buffer, set in a message template (see *DeliveryService::set_message_template*).
+//!
+//! *VecMessage* fills a *Chunk* of *ChunkInput::len* values, for the range starting at *ChunkInput::offset*. Think of samples of a sound, or rows of a table.
+//!
+//! ```
+//! use kik_sync_service::buffer::{ChunkInput, Chunk, VecMessage};
+//! use kik_sync_service::channel::DeliveryService;
+//!
+//! let mut channel: DeliveryService<Chunk<u64>, ChunkInput, VecMessage<u64>> = DeliveryService::default();
+//! channel.set_message_template(VecMessage::with_fill(|offset, buffer| {
+//!     for (index, value) in buffer.iter_mut().enumerate(){
+//!         *value = ((offset + index) * 2) as u64;
+//!     }
+//! }));
+//! channel.feed_feeder(&mut ChunkInput::split(1000, 64));
+//! let mut doubled = vec![0; 1000];
+//! for chunk in &mut channel{
+//!     doubled[chunk.offset..chunk.offset + chunk.data.len()].copy_from_slice(&chunk.data);
+//! }
+//! assert_eq!(doubled[999], 1998);
+//! ```
+//!
+//! *TileMessage* fills a *Tile* of W by H values, for the tile whose top left corner is at *TileInput*. Think of pixels of an image.
+//! The buffers are kept in each message and reused for the next input, like any other *Message*. Results given back with *DeliveryService::return_buffer*
+//! are swapped in too, so the same allocations go around instead of being cloned for each result.
+//!
+//!
+
+use std::convert::TryInto;
+use std::mem::size_of;
+
+use crate::kik_message::{Message, MessageInput, MessageData};
+use crate::kik_grid::tiles;
+
+/// Fills the buffer of a chunk, given its offset. Plain functions and closures that capture nothing can be used.
+pub type FillChunk<V> = fn(usize, &mut [V]);
+
+/// Fills the rows of a tile, given the position of its top left corner as (x, y).
+pub type FillTile<V, const W: usize, const H: usize> = fn(usize, usize, &mut [[V; W]; H]);
+
+/// The range of a buffer to be filled by a *VecMessage*.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ChunkInput{
+    pub offset: usize,
+    pub len: usize,
+}
+
+impl ChunkInput{
+    /// Split the range from zero to total_len in chunks of chunk_len values. The last one is shorter if chunk_len doesn't divide total_len. A chunk_len of zero is treated as one.
+    pub fn split(total_len: usize, chunk_len: usize) -> Vec<ChunkInput>{
+        let chunk_len = chunk_len.max(1);
+        (0..total_len).step_by(chunk_len).map(|offset| ChunkInput{
+            offset,
+            len: chunk_len.min(total_len - offset),
+        }).collect()
+    }
+}
+
+impl<T> MessageInput<T> for ChunkInput where T: MessageData{
+    fn new() -> Self{
+        ChunkInput{
+            offset: 0,
+            len: 0,
+        }
+    }
+
+    /// The length of the chunk.
+    fn weight(&self) -> usize{
+        self.len
+    }
+}
+
+/// Values filled by a *VecMessage*, for the range starting at offset.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Chunk<V>{
+    pub offset: usize,
+    pub data: Vec<V>,
+}
+
+impl<V> Default for Chunk<V>{
+    fn default() -> Self{
+        Chunk{
+            offset: 0,
+            data: Vec::new(),
+        }
+    }
+}
+
+impl<V> MessageData for Chunk<V> where V: Sync + Send + Clone + 'static{
+    fn size_hint(&self) -> usize{
+        std::mem::size_of::<Self>() + self.data.capacity() * std::mem::size_of::<V>()
+    }
+}
+
+/// Fills a buffer of *ChunkInput::len* values with the function given to *with_fill*. The buffer is reused by the next input.
+#[derive(Clone)]
+pub struct VecMessage<V>{
+    input: ChunkInput,
+    data: Vec<V>,
+    fill: Option<FillChunk<V>>,
+}
+
+impl<V> VecMessage<V>{
+    /// A message that fills its buffer with the given function. Use it as the channel's message template.
+    pub fn with_fill(fill: FillChunk<V>) -> Self{
+        VecMessage{
+            input: ChunkInput{
+                offset: 0,
+                len: 0,
+            },
+            data: Vec::new(),
+            fill: Some(fill),
+        }
+    }
+}
+
+/// A message with no fill function. It panics when worked, the channel needs a template made by *with_fill*.
+impl<V> Default for VecMessage<V>{
+    fn default() -> Self{
+        VecMessage{
+            input: ChunkInput{
+                offset: 0,
+                len: 0,
+            },
+            data: Vec::new(),
+            fill: None,
+        }
+    }
+}
+
+impl<V> Message<Chunk<V>, ChunkInput> for VecMessage<V> where V: Default + Sync + Send + Clone + 'static{
+    fn set_input(&mut self, message_input: ChunkInput){
+        self.input = message_input;
+    }
+
+    /// Panics if the message has no fill function, the channel then gives back *WorkError::Panicked*.
+    fn work(&mut self){
+        let fill = match self.fill{
+            Some(fill) => fill,
+            None => panic!("VecMessage has no fill function, set one with DeliveryService::set_message_template(VecMessage::with_fill(...))"),
+        };
+        self.data.clear();
+        self.data.resize(self.input.len, V::default());
+        fill(self.input.offset, &mut self.data);
+    }
+
+    fn take_input(&mut self) -> Option<ChunkInput>{
+        Some(self.input)
+    }
+
+    fn swap_message_data(&mut self, spare: Chunk<V>) -> Chunk<V>{
+        Chunk{
+            offset: self.input.offset,
+            data: std::mem::replace(&mut self.data, spare.data),
+        }
+    }
+
+    fn clone_message_data(&self) -> Chunk<V>{
+        Chunk{
+            offset: self.input.offset,
+            data: self.data.clone(),
+        }
+    }
+
+    fn take_message_data(self) -> Chunk<V>{
+        Chunk{
+            offset: self.input.offset,
+            data: self.data,
+        }
+    }
+
+    fn payload_size(&self) -> usize{
+        size_of::<Self>() + self.data.capacity() * size_of::<V>()
+    }
+}
+
+/// Position of the top left corner of a tile to be filled by a *TileMessage*.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TileInput{
+    pub x: usize,
+    pub y: usize,
+}
+
+impl<T> MessageInput<T> for TileInput where T: MessageData{
+    fn new() -> Self{
+        TileInput{
+            x: 0,
+            y: 0,
+        }
+    }
+}
+
+/// Values filled by a *TileMessage*, H rows of W, for the tile whose top left corner is at (x, y). On the heap, so that large tiles don't fill the stack.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Tile<V, const W: usize, const H: usize>{
+    pub x: usize,
+    pub y: usize,
+    pub rows: Box<[[V; W]; H]>,
+}
+
+impl<V, const W: usize, const H: usize> Tile<V, W, H> where V: Default + Copy{
+    // Every value is V::default(). Built through a Vec so that the array never sits on the stack.
+    fn blank() -> Box<[[V; W]; H]>{
+        match vec![[V::default(); W]; H].into_boxed_slice().try_into(){
+            Ok(rows) => rows,
+            Err(_) => unreachable!("The vector has H rows"),
+        }
+    }
+}
+
+impl<V, const W: usize, const H: usize> Default for Tile<V, W, H> where V: Default + Copy{
+    fn default() -> Self{
+        Tile{
+            x: 0,
+            y: 0,
+            rows: Self::blank(),
+        }
+    }
+}
+
+impl<V, const W: usize, const H: usize> MessageData for Tile<V, W, H> where V: Default + Copy + Sync + Send + 'static{
+    fn size_hint(&self) -> usize{
+        std::mem::size_of::<Self>() + std::mem::size_of::<[[V; W]; H]>()
+    }
+}
+
+/// Fills a tile of W by H values with the function given to *with_fill*. The tile is reused by the next input,
+/// so the function gets the values the previous one left and should write every value.
+#[derive(Clone)]
+pub struct TileMessage<V, const W: usize, const H: usize>{
+    data: Tile<V, W, H>,
+    fill: Option<FillTile<V, W, H>>,
+}
+
+impl<V, const W: usize, const H: usize> TileMessage<V, W, H> where V: Default + Copy + Sync + Send + 'static{
+    /// A message that fills its tile with the given function. Use it as the channel's message template.
+    pub fn with_fill(fill: FillTile<V, W, H>) -> Self{
+        TileMessage{
+            data: Tile::new(),
+            fill: Some(fill),
+        }
+    }
+
+    /// The corners of every tile covering an area of width by height, row by row. Tiles on the right and bottom edges may go past the area. Same as *util::grid::tiles*.
+    pub fn grid(width: usize, height: usize) -> Vec<TileInput>{
+        tiles(width, height, W, H)
+    }
+}
+
+/// A message with no fill function. It panics when worked, the channel needs a template made by *with_fill*.
+impl<V, const W: usize, const H: usize> Default for TileMessage<V, W, H> where V: Default + Copy{
+    fn default() -> Self{
+        TileMessage{
+            data: Tile::default(),
+            fill: None,
+        }
+    }
+}
+
+impl<V, const W: usize, const H: usize> Message<Tile<V, W, H>, TileInput> for TileMessage<V, W, H> where V: Default + Copy + Sync + Send + 'static{
+    fn set_input(&mut self, message_input: TileInput){
+        self.data.x = message_input.x;
+        self.data.y = message_input.y;
+    }
+
+    /// Panics if the message has no fill function, the channel then gives back *WorkError::Panicked*.
+    fn work(&mut self){
+        let fill = match self.fill{
+            Some(fill) => fill,
+            None => panic!("TileMessage has no fill function, set one with DeliveryService::set_message_template(TileMessage::with_fill(...))"),
+        };
+        fill(self.data.x, self.data.y, &mut self.data.rows);
+    }
+
+    fn take_input(&mut self) -> Option<TileInput>{
+        Some(TileInput{
+            x: self.data.x,
+            y: self.data.y,
+        })
+    }
+
+    fn swap_message_data(&mut self, spare: Tile<V, W, H>) -> Tile<V, W, H>{
+        // The spare's position is stale, the next input sets it.
+        std::mem::replace(&mut self.data, spare)
+    }
+
+    fn clone_message_data(&self) -> Tile<V, W, H>{
+        self.data.clone()
+    }
+
+    fn take_message_data(self) -> Tile<V, W, H>{
+        self.data
+    }
+
+    fn payload_size(&self) -> usize{
+        size_of::<Self>() + size_of::<[[V; W]; H]>()
+    }
+}
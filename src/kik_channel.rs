@@ -3,18 +3,73 @@
 //! 
 
 
-use std::thread::JoinHandle;
 use std::default::Default;
 use std::marker::PhantomData;
+use std::convert::Infallible;
+use std::time::{Duration, Instant};
 
 // use std::thread;
-use std::thread::{Builder};
-use std::sync::{Arc, Mutex, TryLockError};
+use std::any::Any;
+use std::io::{self, Read, Write};
+use std::iter::FusedIterator;
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Arc, Mutex, RwLock, PoisonError};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::mpsc::{Receiver, SyncSender, sync_channel};
 
-use crate::kik_message::{Message, MessageInput, MessageData};
-use crate::kik_worker::Worker;
-use crate::kik_feeder::FeederRecycler;
+use crate::kik_message::{Message, MessageInput, MessageData, Merge, merge_message};
+use crate::kik_worker::{Worker, IdleHook};
+use crate::kik_feeder::{FeederRecycler, PressureProbe};
+use crate::kik_package::Package;
+use crate::kik_queue::{WorkReceiver, work_queue, stealing_queue, keyed_queue};
+use crate::kik_backoff::WaitStrategy;
+use crate::kik_throttle::{PauseGate, Throttle};
+use crate::kik_spawner::{StdSpawner, ThreadSpawner, WorkerHandle, WorkerThread};
+use crate::kik_report::{AllocStats, BatchReport, ChannelStats, MemoryStats, PoolHealth, ShutdownReport, StackUsage, Throughput, WorkProgress, WorkerStatus};
+use crate::kik_error::{WorkError, FailureReason, StopReason, ConfigError, KikError, Timeout};
+use crate::kik_sender::{BatchId, Inbox, InboxInputs, InputGate, WeakInputSender, FeederHandle, FeedSignal, FeedReceipt, JobHandle};
+use crate::kik_split::ResultReceiver;
+use crate::kik_tee::{tee, ResultTee};
+use crate::kik_reader::{ChunkReader, ReadHandle};
+#[cfg(feature = "remote")]
+use crate::kik_remote::RemoteLink;
+#[cfg(feature = "serde")]
+use serde::{Serialize, de::DeserializeOwned};
+#[cfg(feature = "spill")]
+use crate::kik_spill::{SharedSpill, SpillFile, lock_spill};
+#[cfg(feature = "spill")]
+use std::path::PathBuf;
+#[cfg(feature = "serde")]
+use crate::kik_wire::{read_checkpoint, write_checkpoint};
+#[cfg(feature = "remote")]
+use std::net::ToSocketAddrs;
+#[cfg(feature = "process")]
+use crate::kik_process::{Backend, ProcessCommand, ProcessConnect};
+#[cfg(feature = "os")]
+use crate::kik_os::{apply_settings, CoreSet, OsErrors, ThreadPriority};
+#[cfg(feature = "async")]
+use crate::kik_stream::{ResultStream, AsyncFeeder, InputBudget};
+use crate::kik_scheduler::{DispatchOrder, Priority, Scheduler};
+use crate::kik_context::{CancellationToken, ProgressBoard, SharedContext, StackPeaks, WorkContext, WorkerInit};
+use crate::kik_event::{ChannelObserver, EventSenders, LogHook, PoolEvent, ResultSenders};
+
+/// A result set aside by *DeliveryService::iter_batch*, with its batch.
+type HeldResult<T, E> = (Option<BatchId>, Result<T, WorkError<E>>);
+
+/// Where the result of a submitted input goes, see *JobHandle*.
+type JobSender<T, E> = SyncSender<Result<T, WorkError<E>>>;
+
+/// Callback set with *DeliveryService::on_result*.
+type ResultCallback<T> = Box<dyn FnMut(T) + Send>;
+
+/// True when compiled for Miri or with the "inline" feature. Every channel then works its messages on the caller's thread, with no worker threads.
+const INLINE_ONLY: bool = cfg!(any(miri, feature = "inline"));
+
+/// Default of *DeliveryService::set_drop_grace*.
+const DROP_GRACE: Duration = Duration::from_secs(1);
+
+/// Smallest stack size accepted by *ChannelConfigBuilder::build*, 16 KiB. Less than that won't even fit the worker's own bookkeeping.
+pub const MIN_STACK_SIZE: usize = 16 * 1024;
 
 /// To be used when the user needs to set specific configurations before creating a *DeliveryService* channel. Optional type.
 /// 
@@ -24,20 +79,56 @@ use crate::kik_feeder::FeederRecycler;
 /// 
 /// - Set custom values for it.
 /// 
-/// - Construct a new *DeliveryService* instance using *DeliveryService::new(your_channel_config_name)*
+/// - Construct a new *DeliveryService* instance using *DeliveryService::try_new(your_channel_config_name)*
 /// 
 /// # Methods
 /// 
 /// Be wary that some sets will change others. The titles above the list might change the values of the ones below.
-/// 
-/// 
-/// 
-
 pub struct ChannelConfig{
     stack_size: usize,
     worker_number: usize,
     package_number: usize,
+    adaptive_packages: Option<(usize, usize)>,
     channel_size: usize,
+    ordered: bool,
+    dispatch_order: DispatchOrder,
+    wait_strategy: WaitStrategy,
+    throttle: Option<Throttle>,
+    stall_timeout: Option<Duration>,
+    heartbeat_timeout: Option<Duration>,
+    result_ttl: Option<Duration>,
+    idle_hook: Option<(Duration, IdleHook)>,
+    idle_timeout: Option<Duration>,
+    log_hook: Option<LogHook>,
+    worker_context: Option<WorkerInit>,
+    thread_spawner: Option<Arc<dyn ThreadSpawner>>,
+    memory_tracking: bool,
+    throughput_history: Option<Duration>,
+    alloc_tracking: bool,
+    metrics: bool,
+    memory_pressure: Option<PressureProbe>,
+    stack_probe: bool,
+    inline: bool,
+    name: Option<String>,
+    fast_first_result: bool,
+    max_weight: Option<usize>,
+    memory_budget: Option<usize>,
+    message_pool: bool,
+    work_stealing: bool,
+    keyed_dispatch: bool,
+    quarantine: Option<usize>,
+    keep_alive: bool,
+    spawn_eagerly: bool,
+    #[cfg(feature = "os")]
+    thread_priority: Option<ThreadPriority>,
+    #[cfg(feature = "os")]
+    core_affinity: Option<CoreSet>,
+    #[cfg(feature = "process")]
+    backend: Backend,
+    #[cfg(feature = "spill")]
+    spill_threshold: Option<usize>,
+    #[cfg(feature = "spill")]
+    spill_dir: Option<PathBuf>,
 }
 
 impl Default for ChannelConfig{
@@ -53,6 +144,46 @@ impl Default for ChannelConfig{
             worker_number,
             channel_size,
             package_number,
+            adaptive_packages: None,
+            ordered: false,
+            dispatch_order: DispatchOrder::Fifo,
+            wait_strategy: WaitStrategy::ExponentialBackoff,
+            throttle: None,
+            stall_timeout: None,
+            heartbeat_timeout: None,
+            result_ttl: None,
+            idle_hook: None,
+            idle_timeout: None,
+            log_hook: None,
+            worker_context: None,
+            thread_spawner: None,
+            memory_tracking: false,
+            throughput_history: None,
+            alloc_tracking: false,
+            metrics: false,
+            memory_pressure: None,
+            stack_probe: false,
+            inline: INLINE_ONLY,
+            name: None,
+            fast_first_result: false,
+            max_weight: None,
+            memory_budget: None,
+            message_pool: false,
+            work_stealing: false,
+            keyed_dispatch: false,
+            quarantine: None,
+            keep_alive: false,
+            spawn_eagerly: false,
+            #[cfg(feature = "os")]
+            thread_priority: None,
+            #[cfg(feature = "os")]
+            core_affinity: None,
+            #[cfg(feature = "process")]
+            backend: Backend::Threads,
+            #[cfg(feature = "spill")]
+            spill_threshold: None,
+            #[cfg(feature = "spill")]
+            spill_dir: None,
         }
     }
 }
@@ -65,30 +196,288 @@ impl ChannelConfig{
         Self::default()
     }
 
-    /// Set worker number. Package_number will be set to twice the value. Panics if less than 1. Default value is 8.
+    /// Create a builder that checks the worker number, channel size, package number and stack size together. See *ChannelConfigBuilder*.
+    pub fn builder() -> ChannelConfigBuilder{
+        ChannelConfigBuilder::new()
+    }
+
+    /// Set worker number. Package_number will be set to twice the value. Default value is 8.
     /// Changing worker number changes channel size to the same value. Also change package number to twice the value.
+    /// 
+    /// Zero is the same as *set_inline(true)* with one worker: the caller's thread works every message.
     pub fn set_worker_number(&mut self, worker_number: usize){
-        if worker_number < 1{
-            panic!("Error ChannelConfig::set_worker_number: There must be at least one worker thread (currently {}).", worker_number);
+        if worker_number == 0{
+            self.set_inline(true);
+            self.set_worker_number(1);
+            return;
         }
         self.worker_number = worker_number;
         self.channel_size = worker_number;
         self.package_number = worker_number * 2;
     }
 
-    /// Set the number of packages roaming in the delivery system. Minimum value is worker_number + 1. Panics if value is invalid. Default is channel_size * 2.
+    /// Set the number of packages roaming in the delivery system. Minimum value is worker_number + 1, checked by *validate*. Default is channel_size * 2.
     pub fn set_package_number(&mut self, package_number: usize){
-        if package_number <= self.worker_number{
-            panic!("There's not enough packages for every worker to use.");
-        }
         self.package_number = package_number;
     }
 
+    /// Let the feeder pick the package number by itself, between min and max packages, both included. It watches how long messages wait for a worker,
+    /// how long they're worked and how long their results wait to be taken, adding packages while workers might run out of them and
+    /// dropping them while they only wait, so that big buffers aren't held for nothing. The package number set here is where it starts. See kik_adaptive.
+    /// 
+    /// Both ends follow the same rules as the package number, checked by *validate*. Workers added or removed later move the band along. Default None (fixed package number).
+    pub fn set_adaptive_packages(&mut self, band: Option<(usize, usize)>){
+        self.adaptive_packages = band.map(|(min, max)| (min.min(max), min.max(max)));
+    }
+
     /// Set stack size for each of the workers. The new size will not be evaluated. Responsibility for the value relies on the user. Default 2 * 1024 * 1024.
     pub fn set_stack_size(&mut self, new_stack_size: usize){
         self.stack_size = new_stack_size;
     }
 
+    /// If true, results will be returned in the same order as the inputs were fed, at the cost of holding back results that finish early. Default false.
+    pub fn set_ordered(&mut self, ordered: bool){
+        self.ordered = ordered;
+    }
+
+    /// Choose the scheduler a new channel starts with. *DispatchOrder::Fifo* dispatches inputs in the order they were fed, *DispatchOrder::Lifo* the last one fed first.
+    /// Replaced by *DeliveryService::set_scheduler*. Default *DispatchOrder::Fifo*.
+    pub fn set_dispatch_order(&mut self, dispatch_order: DispatchOrder){
+        self.dispatch_order = dispatch_order;
+    }
+
+    /// Choose how the feeder and the workers wait when they can't go on right away: for results, for work, or for room in a full channel.
+    /// *WaitStrategy::SpinHint* and *WaitStrategy::Yield* never sleep, for latency-critical work. *WaitStrategy::Park* sleeps right away, for background work.
+    /// Default *WaitStrategy::ExponentialBackoff*, which spins and yields a few times before sleeping.
+    pub fn set_wait_strategy(&mut self, wait_strategy: WaitStrategy){
+        self.wait_strategy = wait_strategy;
+    }
+
+    /// Make every worker rest between messages, so that the channel doesn't take every core from the application it runs in. See the throttle module.
+    /// *Throttle::DutyCycle* is clamped between 0.01 and 1. Ignored by inline channels and remote workers. Default None, no rest.
+    pub fn set_throttle(&mut self, throttle: Option<Throttle>){
+        self.throttle = throttle.map(Throttle::clamped);
+    }
+
+    /// If no result arrives for this long while messages are in flight, the iterator returns None with *KikError::Stalled* as the stop reason,
+    /// instead of waiting forever for a worker that is stuck or a message that was lost. Default None (wait forever).
+    pub fn set_stall_timeout(&mut self, stall_timeout: Option<Duration>){
+        self.stall_timeout = stall_timeout;
+    }
+
+    /// Flag workers that go silent for this long inside a message, as hung. They're reported with *PoolEvent::WorkerHung* and listed by
+    /// *DeliveryService::health*, but left running: there's no safe way of stopping a thread from outside. See the heartbeat module.
+    /// Messages that can take longer should call *WorkPacer::checkpoint* as they go. Ignored by inline channels. Default None, no heartbeat.
+    pub fn set_heartbeat_timeout(&mut self, heartbeat_timeout: Option<Duration>){
+        self.heartbeat_timeout = heartbeat_timeout;
+    }
+
+    /// Successful results that have been waiting for longer than the given time are discarded instead of returned. Useful for streaming frames, where a late frame is worse than a missing one. Default None (never discard).
+    pub fn set_result_ttl(&mut self, result_ttl: Option<Duration>){
+        self.result_ttl = result_ttl;
+    }
+
+    /// Set a callback that each worker calls with its id and idle time whenever it waits for longer than the threshold without work. 
+    /// It keeps being called once per threshold until work arrives. Runs in the worker thread, good for per-thread maintenance during lulls. Default None.
+    pub fn set_idle_hook<F>(&mut self, threshold: Duration, hook: F) where F: Fn(usize, Duration) + Send + Sync + 'static{
+        self.idle_hook = Some((threshold, Arc::new(hook)));
+    }
+
+    /// Remove the idle callback set with *set_idle_hook*.
+    pub fn clear_idle_hook(&mut self){
+        self.idle_hook = None;
+    }
+
+    /// Workers that wait for work longer than this leave, and are spawned again by the next iteration that has something to work.
+    /// The last running worker always stays, parked until work arrives. For long-lived channels that only work in bursts. Default None, the workers stay.
+    pub fn set_idle_timeout(&mut self, idle_timeout: Option<Duration>){
+        self.idle_timeout = idle_timeout;
+    }
+
+    /// Set a callback that gets a line for each *PoolEvent*: workers starting, exiting or panicking, and batches completing. Called on the thread
+    /// where it happened. Plug it into any logger, e.g. `config.set_log_hook(|line| log::info!("{}", line))`. Default None (workers start and stop silently).
+    pub fn set_log_hook<F>(&mut self, hook: F) where F: Fn(&str) + Send + Sync + 'static{
+        self.log_hook = Some(Arc::new(hook));
+    }
+
+    /// Remove the log callback set with *set_log_hook*.
+    pub fn clear_log_hook(&mut self){
+        self.log_hook = None;
+    }
+
+    /// Set a closure that each worker calls once with its id when its thread starts. The value returned is the worker's own state (a file, a connection,
+    /// a random number generator, a scratch buffer...), reachable from every *Message::work_with_context* on that thread through *WorkContext::get_worker_context*.
+    /// When running inline, it's called once with id 0 when the channel is created. Default None.
+    pub fn set_worker_context<F, C>(&mut self, init: F) where F: Fn(usize) -> C + Send + Sync + 'static, C: Send + 'static{
+        self.worker_context = Some(Arc::new(move |worker_id| Box::new(init(worker_id))));
+    }
+
+    /// Remove the closure set with *set_worker_context*.
+    pub fn clear_worker_context(&mut self){
+        self.worker_context = None;
+    }
+
+    /// Create the worker threads with spawner instead of *std::thread::Builder*: to name or sandbox them, or to run the workers in an existing
+    /// thread pool. Closures taking a *WorkerThread* work too. See the spawner module. Default None, a new thread for each worker.
+    pub fn set_thread_spawner<Sp>(&mut self, spawner: Sp) where Sp: ThreadSpawner + 'static{
+        self.thread_spawner = Some(Arc::new(spawner));
+    }
+
+    /// Remove the spawner set with *set_thread_spawner*.
+    pub fn clear_thread_spawner(&mut self){
+        self.thread_spawner = None;
+    }
+
+    /// If true, the feeder records the peak *Message::payload_size* of each message it retrieves. Read it with *DeliveryService::get_memory_stats*. Default false.
+    pub fn set_memory_tracking(&mut self, memory_tracking: bool){
+        self.memory_tracking = memory_tracking;
+    }
+
+    /// How far back *DeliveryService::throughput* can look. The feeder keeps the completion time and payload size of every message it retrieved within it.
+    /// Default None (throughput isn't tracked).
+    pub fn set_throughput_history(&mut self, throughput_history: Option<Duration>){
+        self.throughput_history = throughput_history;
+    }
+
+    /// If true, the feeder counts the allocations made on the caller's thread while feeding and iterating. Read them with *DeliveryService::alloc_stats*.
+    /// Nothing is counted unless *CountingAllocator* is the global allocator. Meant for tests and benchmarks. Default false.
+    pub fn set_alloc_tracking(&mut self, alloc_tracking: bool){
+        self.alloc_tracking = alloc_tracking;
+    }
+
+    /// If true, the feeder keeps the dispatch and completion times of every message it retrieves. Read them with *DeliveryService::stats*.
+    /// Nothing is forgotten until *DeliveryService::reset_stats*, so long-lived channels should reset now and then. Default false.
+    pub fn set_metrics(&mut self, metrics: bool){
+        self.metrics = metrics;
+    }
+
+    /// Set a probe telling how much memory pressure the host application is under, from 0.0 (none) to 1.0 (the most). The feeder calls it before retrieving each result,
+    /// so it should be cheap (reading an atomic, for example). Under pressure, the messages allowed in flight shrink by that fraction of the package number,
+    /// down to one, and no new messages are built while there are others to recycle. The extra messages are dropped as they come back.
+    /// Once the pressure goes away, the package number is used again. Default None.
+    pub fn set_memory_pressure<F>(&mut self, probe: F) where F: Fn() -> f64 + Send + Sync + 'static{
+        self.memory_pressure = Some(Arc::new(probe));
+    }
+
+    /// Remove the probe set with *set_memory_pressure*.
+    pub fn clear_memory_pressure(&mut self){
+        self.memory_pressure = None;
+    }
+
+    /// If true, each worker measures how deep its stack goes. Read it with *DeliveryService::get_stack_usage*. Meant for debugging and for picking the stack size.
+    /// Ignored when running inline, since there are no worker threads. Default false.
+    pub fn set_stack_probe(&mut self, stack_probe: bool){
+        self.stack_probe = stack_probe;
+    }
+
+    /// Give the channel a name. It's attached to worker thread names, reports and panic messages, so that applications with several channels can tell where each came from. Default None.
+    pub fn set_name(&mut self, name: Option<String>){
+        self.name = name;
+    }
+
+    /// If true, when a run starts the feeder stops filling the package window as soon as the first result is back, and returns it right away.
+    /// The window is filled in the following iterations. Lowers the time to the first result for interactive workloads. Default false.
+    pub fn set_fast_first_result(&mut self, fast_first_result: bool){
+        self.fast_first_result = fast_first_result;
+    }
+
+    /// Limit the total *MessageInput::weight* of the inputs being worked at once. Inputs wait in the feeder until there's room for them.
+    /// An input heavier than the limit is still sent, but only when nothing else is being worked. Default None (only package_number limits it).
+    pub fn set_max_weight(&mut self, max_weight: Option<usize>){
+        self.max_weight = max_weight;
+    }
+
+    /// Stop dispatching new messages while the ones in flight would hold more than this many bytes. Each message is counted as the largest seen so far:
+    /// the larger of *MessageData::size_hint* of its result and *Message::payload_size*. Until the first result tells how big they are, only one message is sent.
+    /// One message is always allowed, so the run goes on even if a single one is over budget. Meant for multi-megabyte frames. Default None (only package_number limits it).
+    pub fn set_memory_budget(&mut self, memory_budget: Option<usize>){
+        self.memory_budget = memory_budget;
+    }
+
+    /// If true, the channel builds package_number messages when it's created, and again when the message template changes, and keeps them in a
+    /// *MessagePool*. New messages are loaned from it and go back to it instead of being dropped, so nothing is built while the channel runs.
+    /// For real-time users like audio, where allocating at the wrong moment causes a glitch. See kik_pool. Default false.
+    pub fn set_message_pool(&mut self, message_pool: bool){
+        self.message_pool = message_pool;
+    }
+
+    /// If true, each worker gets its own lane of the queue and the feeder spreads the messages between them. A worker whose lane is empty steals from the others.
+    /// Workers then rarely wait on the same lock, which helps when many cheap messages of uneven cost are worked at once.
+    /// There's one lane for each worker the channel starts with, workers added later share them. Default false (every worker takes from one shared queue).
+    pub fn set_work_stealing(&mut self, work_stealing: bool){
+        self.work_stealing = work_stealing;
+    }
+
+    /// If true, inputs with a *MessageInput::routing_key* are dispatched by key: every input with the same key is worked by the same worker,
+    /// one after the other, in the order they were sent. Workers can then keep state for each key in their worker context, like a decoder for each connection.
+    /// Inputs without a key are spread between the workers. There's one lane of the queue for each worker the channel starts with, and nothing is stolen.
+    /// When a worker is removed or dies, its keys move to another one. Overrides *set_work_stealing*. Default false.
+    pub fn set_keyed_dispatch(&mut self, keyed_dispatch: bool){
+        self.keyed_dispatch = keyed_dispatch;
+    }
+
+    /// Give messages that fail or panic up to max_attempts tries in all. A failed message is sent back to the workers as it is, with the same input,
+    /// and *WorkInfo::attempt* tells which try it's on. After the last one, the input is taken back with *Message::take_input* and set aside with its
+    /// *FailureReason*, for *DeliveryService::take_failed*, and gives no result. So one bad input can't wedge a whole job. Zero is treated as one.
+    /// Messages without *take_input*, and packages of *DeliveryService::map_reduce*, still yield their error. Default None (errors are yielded right away).
+    pub fn set_quarantine(&mut self, max_attempts: Option<usize>){
+        self.quarantine = max_attempts.map(|max_attempts| max_attempts.max(1));
+    }
+
+    /// If true, the iterator doesn't end when it runs out of work. It sleeps until a *WeakInputSender* sends more, and only returns None
+    /// once *DeliveryService::close_input* was called and everything fed before was worked. For server-style consumers fed from other threads.
+    /// Cancelling doesn't wake it up, close the input for that. Ignored by *DeliveryService::split*, which waits for its handles instead. Default false.
+    pub fn set_keep_alive(&mut self, keep_alive: bool){
+        self.keep_alive = keep_alive;
+    }
+
+    /// If true, every worker is spawned when the channel is created, like *DeliveryService::warm_up* does, instead of on the first iteration
+    /// that has something to work. The first result then doesn't wait for the threads. Ignored when inline. Default false.
+    pub fn set_spawn_eagerly(&mut self, spawn_eagerly: bool){
+        self.spawn_eagerly = spawn_eagerly;
+    }
+
+    /// If true, messages are worked on the caller's thread while iterating, with no worker threads. Used by *SequentialDeliveryService*.
+    /// Nothing runs between calls to *next*, so a run is deterministic and a debugger can step right into *Message::work*.
+    /// Can't be turned off under Miri or with the "inline" feature. Default false.
+    pub fn set_inline(&mut self, inline: bool){
+        self.inline = inline || INLINE_ONLY;
+    }
+
+    /// How the system schedules the worker threads against the other threads of the machine. Applied by each worker as it starts,
+    /// a priority that can't be set is reported by *DeliveryService::take_os_errors*. See the os module. Ignored when inline. Default None, the system's default.
+    #[cfg(feature = "os")]
+    pub fn set_thread_priority(&mut self, thread_priority: Option<ThreadPriority>){
+        self.thread_priority = thread_priority;
+    }
+
+    /// Keep the worker threads on the cores of the set, or each on a single one with *CoreSet::pinned*. Applied by each worker as it starts,
+    /// like *set_thread_priority*. Ignored when inline. Default None, any core.
+    #[cfg(feature = "os")]
+    pub fn set_core_affinity(&mut self, core_affinity: Option<CoreSet>){
+        self.core_affinity = core_affinity;
+    }
+
+    /// Where the workers run. With *Backend::Process*, each worker sends its messages to a child process of its own, so that a crash inside
+    /// *Message::work* doesn't take the whole program down. See the process module. Ignored when inline. Default *Backend::Threads*.
+    #[cfg(feature = "process")]
+    pub fn set_backend(&mut self, backend: Backend){
+        self.backend = backend;
+    }
+
+    /// How many inputs fed with *DeliveryService::feed_spilling* can wait in memory. The rest are written to a temporary file and read back
+    /// as messages free up, see the spill module. Clamped to at least 1. None keeps every input in memory, like *feed_feeder*. Default None.
+    #[cfg(feature = "spill")]
+    pub fn set_spill_threshold(&mut self, spill_threshold: Option<usize>){
+        self.spill_threshold = spill_threshold.map(|threshold| threshold.max(1));
+    }
+
+    /// Where the spill files are written. None for *std::env::temp_dir*. Default None.
+    #[cfg(feature = "spill")]
+    pub fn set_spill_dir(&mut self, spill_dir: Option<PathBuf>){
+        self.spill_dir = spill_dir;
+    }
+
     // get functions for each value
     /// Get stored stack_size configuration to use in a new kik_channel.
     pub fn get_stack_size(&self) -> usize{
@@ -110,6 +499,319 @@ impl ChannelConfig{
         self.channel_size
     }
 
+    /// Get whether results will be returned in the same order as the inputs.
+    pub fn get_ordered(&self) -> bool{
+        self.ordered
+    }
+
+    /// Get the order the inputs will be dispatched in, unless a scheduler is set.
+    pub fn get_dispatch_order(&self) -> DispatchOrder{
+        self.dispatch_order
+    }
+
+    /// Get how long a result can wait before being discarded. None means results are never discarded.
+    pub fn get_result_ttl(&self) -> Option<Duration>{
+        self.result_ttl
+    }
+
+    /// Get whether the feeder will record the payload size of each message.
+    pub fn get_memory_tracking(&self) -> bool{
+        self.memory_tracking
+    }
+
+    /// Get how far back throughput can be measured. None means it isn't tracked.
+    pub fn get_throughput_history(&self) -> Option<Duration>{
+        self.throughput_history
+    }
+
+    /// Get whether the feeder will count allocations.
+    pub fn get_alloc_tracking(&self) -> bool{
+        self.alloc_tracking
+    }
+
+    /// Get whether the feeder will keep message timings.
+    pub fn get_metrics(&self) -> bool{
+        self.metrics
+    }
+
+    /// Get whether the workers will measure their stack depth.
+    pub fn get_stack_probe(&self) -> bool{
+        self.stack_probe
+    }
+
+    /// Get whether messages will be worked on the caller's thread instead of worker threads. Always true under Miri or with the "inline" feature.
+    pub fn get_inline(&self) -> bool{
+        self.inline
+    }
+
+    /// Get how many inputs fed with *DeliveryService::feed_spilling* can wait in memory. None if they're never spilled.
+    #[cfg(feature = "spill")]
+    pub fn get_spill_threshold(&self) -> Option<usize>{
+        self.spill_threshold
+    }
+
+    /// Get where the spill files are written. None for *std::env::temp_dir*.
+    #[cfg(feature = "spill")]
+    pub fn get_spill_dir(&self) -> Option<&PathBuf>{
+        self.spill_dir.as_ref()
+    }
+
+    /// Get the priority of the worker threads. None for the system's default.
+    #[cfg(feature = "os")]
+    pub fn get_thread_priority(&self) -> Option<ThreadPriority>{
+        self.thread_priority
+    }
+
+    /// Get the cores the worker threads are kept on. None for any core.
+    #[cfg(feature = "os")]
+    pub fn get_core_affinity(&self) -> Option<&CoreSet>{
+        self.core_affinity.as_ref()
+    }
+
+    /// Get where the workers run.
+    #[cfg(feature = "process")]
+    pub fn get_backend(&self) -> &Backend{
+        &self.backend
+    }
+
+    /// Get whether each worker takes from its own lane, stealing from the others when it's empty.
+    pub fn get_work_stealing(&self) -> bool{
+        self.work_stealing
+    }
+
+    /// Get whether inputs with the same routing key are always worked by the same worker.
+    pub fn get_keyed_dispatch(&self) -> bool{
+        self.keyed_dispatch
+    }
+
+    /// Get how many times a failing message is tried before its input is set aside. None if there's no quarantine.
+    pub fn get_quarantine(&self) -> Option<usize>{
+        self.quarantine
+    }
+
+    /// Get whether the first result of a run is returned before the package window is filled.
+    pub fn get_fast_first_result(&self) -> bool{
+        self.fast_first_result
+    }
+
+    /// Get the band the package number moves in, as (min, max). None if the package number is fixed.
+    pub fn get_adaptive_packages(&self) -> Option<(usize, usize)>{
+        self.adaptive_packages
+    }
+
+    /// Get whether messages are built ahead of time and kept in a pool.
+    pub fn get_message_pool(&self) -> bool{
+        self.message_pool
+    }
+
+    /// Get how many bytes the messages in flight may hold. None means there's no budget.
+    pub fn get_memory_budget(&self) -> Option<usize>{
+        self.memory_budget
+    }
+
+    /// Get the limit for the total weight of the inputs being worked at once. None means there's no limit.
+    pub fn get_max_weight(&self) -> Option<usize>{
+        self.max_weight
+    }
+
+    /// Get how long a worker waits for work before leaving. None if the workers stay.
+    pub fn get_idle_timeout(&self) -> Option<Duration>{
+        self.idle_timeout
+    }
+
+    /// Get whether the iterator waits for more inputs until the input is closed.
+    pub fn get_keep_alive(&self) -> bool{
+        self.keep_alive
+    }
+
+    /// Get whether the workers are spawned when the channel is created.
+    pub fn get_spawn_eagerly(&self) -> bool{
+        self.spawn_eagerly
+    }
+
+    /// Get the name set with *set_name*, if any.
+    pub fn get_name(&self) -> Option<&str>{
+        self.name.as_deref()
+    }
+
+    /// Get how the feeder and the workers wait.
+    pub fn get_wait_strategy(&self) -> WaitStrategy{
+        self.wait_strategy
+    }
+
+    /// Get how much the workers rest between messages. None if they don't.
+    pub fn get_throttle(&self) -> Option<Throttle>{
+        self.throttle
+    }
+
+    /// Get how long the feeder waits for a result before reporting a stall. None means it waits forever.
+    pub fn get_stall_timeout(&self) -> Option<Duration>{
+        self.stall_timeout
+    }
+
+    /// Get how long a worker may go silent inside a message before it's flagged as hung.
+    pub fn get_heartbeat_timeout(&self) -> Option<Duration>{
+        self.heartbeat_timeout
+    }
+
+    /// Check the worker number, channel size, package number and stack size together. Returns the first problem found.
+    /// The setters only check what they can on their own, *DeliveryService::try_new* checks the rest with this.
+    pub fn validate(&self) -> Result<(), ConfigError>{
+        let worker_number = self.worker_number;
+        if worker_number < 1{
+            return Err(ConfigError::NoWorkers);
+        }
+        let channel_size = self.channel_size;
+        if channel_size < 1{
+            return Err(ConfigError::NoChannelSize);
+        }
+        let package_number = self.package_number;
+        if package_number <= worker_number{
+            return Err(ConfigError::NotEnoughPackages{ package_number, worker_number });
+        }
+        // Both channels full and every worker holding one. Any more and nobody can move.
+        let max_package_number = channel_size * 2 + worker_number;
+        if package_number > max_package_number{
+            return Err(ConfigError::TooManyPackages{ package_number, max_package_number });
+        }
+        if let Some((min, max)) = self.adaptive_packages{
+            if min <= worker_number{
+                return Err(ConfigError::NotEnoughPackages{ package_number: min, worker_number });
+            }
+            if max > max_package_number{
+                return Err(ConfigError::TooManyPackages{ package_number: max, max_package_number });
+            }
+        }
+        if self.stack_size < MIN_STACK_SIZE{
+            return Err(ConfigError::StackTooSmall{ stack_size: self.stack_size });
+        }
+        Ok(())
+    }
+
+    /// Get the log callback set with *set_log_hook*, if there is one.
+    pub fn get_log_hook(&self) -> Option<&LogHook>{
+        self.log_hook.as_ref()
+    }
+
+    /// Get the idle threshold set with *set_idle_hook*, if there is a hook.
+    pub fn get_idle_threshold(&self) -> Option<Duration>{
+        self.idle_hook.as_ref().map(|(threshold, _)| *threshold)
+    }
+
+    /// Get the probe set with *set_memory_pressure*, if any.
+    pub fn get_memory_pressure(&self) -> Option<&PressureProbe>{
+        self.memory_pressure.as_ref()
+    }
+
+    /// Get the spawner set with *set_thread_spawner*, if any.
+    pub fn get_thread_spawner(&self) -> Option<&Arc<dyn ThreadSpawner>>{
+        self.thread_spawner.as_ref()
+    }
+
+    /// Get the closure set with *set_worker_context*, if any.
+    pub fn get_worker_context(&self) -> Option<&WorkerInit>{
+        self.worker_context.as_ref()
+    }
+
+}
+
+
+/// Builds a *ChannelConfig*, checking the worker number, channel size, package number and stack size all at once instead of panicking on each.
+/// 
+/// Unlike *ChannelConfig::set_worker_number*, setting the worker number doesn't overwrite the others. Channel size and package number
+/// only follow the worker number when they weren't set. Everything else can be set on the *ChannelConfig* returned by *build*.
+/// 
+/// ```
+/// use kik_sync_service::channel::ChannelConfig;
+/// use kik_sync_service::error::ConfigError;
+/// 
+/// let config = ChannelConfig::builder().worker_number(4).package_number(6).build().unwrap();
+/// assert_eq!(config.get_channel_size(), 4);
+/// 
+/// let error = ChannelConfig::builder().worker_number(4).package_number(4).build().err();
+/// assert_eq!(error, Some(ConfigError::NotEnoughPackages{ package_number: 4, worker_number: 4 }));
+/// ```
+#[derive(Clone, Debug)]
+pub struct ChannelConfigBuilder{
+    worker_number: usize,
+    // None follows the worker number.
+    channel_size: Option<usize>,
+    // None is twice the channel size.
+    package_number: Option<usize>,
+    stack_size: usize,
+}
+
+impl Default for ChannelConfigBuilder{
+    fn default() -> Self{
+        let config = ChannelConfig::default();
+        ChannelConfigBuilder{
+            worker_number: config.worker_number,
+            channel_size: None,
+            package_number: None,
+            stack_size: config.stack_size,
+        }
+    }
+}
+
+impl ChannelConfigBuilder{
+    /// Create a builder with the same defaults as *ChannelConfig::default*.
+    pub fn new() -> Self{
+        Self::default()
+    }
+
+    /// How many worker threads. At least 1. Default 8.
+    pub fn worker_number(mut self, worker_number: usize) -> Self{
+        self.worker_number = worker_number;
+        self
+    }
+
+    /// How many packages each channel holds. At least 1. Default is the worker number.
+    pub fn channel_size(mut self, channel_size: usize) -> Self{
+        self.channel_size = Some(channel_size);
+        self
+    }
+
+    /// How many packages roam the delivery system. More than the worker number, and at most twice the channel size plus the worker number. Default is twice the channel size.
+    pub fn package_number(mut self, package_number: usize) -> Self{
+        self.package_number = Some(package_number);
+        self
+    }
+
+    /// Stack size of each worker thread. At least *MIN_STACK_SIZE*. Default 2 * 1024 * 1024.
+    pub fn stack_size(mut self, stack_size: usize) -> Self{
+        self.stack_size = stack_size;
+        self
+    }
+
+    /// Check every value and build the config. Returns the first problem found.
+    pub fn build(self) -> Result<ChannelConfig, ConfigError>{
+        let channel_size = self.channel_size.unwrap_or(self.worker_number);
+        let config = ChannelConfig{
+            stack_size: self.stack_size,
+            worker_number: self.worker_number,
+            channel_size,
+            package_number: self.package_number.unwrap_or(channel_size * 2),
+            ..ChannelConfig::default()
+        };
+        config.validate()?;
+        Ok(config)
+    }
+}
+
+
+/// What a *DeliveryService* does with the work left when it's dropped or shut down. Set with *DeliveryService::set_drop_policy*.
+#[derive(Default)]
+pub enum DropPolicy<R>{
+    /// Inputs not sent yet are dropped. Messages with the workers are waited for and their results dropped. This is the default.
+    #[default]
+    Abandon,
+    /// Every input left is worked and its result dropped, as if the channel was iterated to the end. The input is closed first, so a keep-alive channel doesn't wait for more. Never returns if a generator is endless.
+    Finish,
+    /// Inputs not sent yet are dropped, and the run is cancelled so that *Message::work_with_context* can give up early on the messages with the workers.
+    Abort,
+    /// Inputs not sent yet are handed to the sink, in the order they would have been sent, instead of being dropped. Generators are dropped.
+    /// Messages with the workers are waited for and their results dropped.
+    Persist(Box<dyn FnMut(Vec<R>) + Send>),
 }
 
 
@@ -125,25 +827,107 @@ impl ChannelConfig{
 /// 
 /// - Feed more values and iterate again to get more **T** results.
 /// 
+/// - Once every result was returned, *next* keeps returning None until the channel is fed again, then resumes with the new results. An iteration
+///   can also end early, because the channel was paused or stalled, and go on later without new inputs. *has_pending* tells both apart.
 /// 
+/// - Call *drain* instead of iterating to get all the results in a vector together with a *BatchReport*.
 /// 
-
-pub struct DeliveryService<T, R, S>  where 
+/// - If the *Message* can fail (**E** is the error of *Message::try_work*), iterate through *results* instead. It yields *Result<T, WorkError<E>>* for each message.
+///   Iterating through the channel directly ends the iteration when a message fails, and *last_stop_reason* gives *StopReason::Error(KikError::MessageFailed{..})*.
+///   Iterating again carries on with the other results.
+pub struct DeliveryService<T, R, S, E = Infallible>  where 
 T: MessageData + 'static,
 R: MessageInput<T> + 'static,
-S: Message<T, R> + Sync + Send + Clone + 'static,
+S: Message<T, R, E> + Sync + Send + 'static,
+E: Send + 'static,
 {
     stack_size: usize,
     worker_number: usize,
     last_id: usize,
+    // Attached to thread names, reports and panic messages.
+    name: Option<String>,
+    idle_hook: Option<(Duration, IdleHook)>,
+    idle_timeout: Option<Duration>,
+    // How many local workers are running, for the idle timeout. Shared with the workers.
+    running_workers: Arc<AtomicUsize>,
+    wait_strategy: WaitStrategy,
+    throttle: Option<Throttle>,
+    thread_spawner: Arc<dyn ThreadSpawner>,
+    worker_context: Option<WorkerInit>,
+    // Read-only value reachable from every WorkContext of the channel.
+    shared_context: SharedContext,
+    // Only Some if the stack probe is enabled.
+    stack_peaks: Option<StackPeaks>,
+    // Progress of the message each worker is working. Shared with the workers, and with the feeder when inline.
+    progress_board: ProgressBoard,
+    // No workers are built when inline.
+    inline: bool,
+    // Shared with the feeder, the workers and the user.
+    cancellation: CancellationToken,
     // () is the return value for each worker (which is nothing).
-    thread_vec: Vec<JoinHandle<()>>,
+    thread_vec: Vec<WorkerHandle>,
+    // The child process each worker spawns, with Backend::Process.
+    #[cfg(feature = "process")]
+    process: Option<(ProcessCommand, ProcessConnect<S, E>)>,
+    // The spill file being drained, and the settings from ChannelConfig.
+    #[cfg(feature = "spill")]
+    spill: Option<SharedSpill<R>>,
+    #[cfg(feature = "spill")]
+    spill_threshold: Option<usize>,
+    #[cfg(feature = "spill")]
+    spill_dir: PathBuf,
+    // Threads of the remote workers. Kept apart so that they don't count as local workers.
+    #[cfg(feature = "remote")]
+    remote_vec: Vec<WorkerHandle>,
+    // How many running workers should leave after their current message. Shared with the workers.
+    retiring: Arc<AtomicUsize>,
+    // Set once the channel closes, so that throttled workers stop resting. Shared with the workers.
+    closing: Arc<AtomicBool>,
+    // Closed by pause, the workers wait at it before each message. Shared with the workers.
+    pause_gate: Arc<PauseGate>,
+    #[cfg(feature = "os")]
+    thread_priority: Option<ThreadPriority>,
+    #[cfg(feature = "os")]
+    core_affinity: Option<CoreSet>,
+    // Settings the workers couldn't apply. Shared with the workers.
+    #[cfg(feature = "os")]
+    os_errors: OsErrors,
+    // Subscriptions created by events. Shared with the workers.
+    events: EventSenders,
+    // Subscriptions created by subscribe_results.
+    result_senders: ResultSenders<T>,
+    // Handed every result by run_until_empty.
+    result_callback: Option<ResultCallback<T>>,
+    // True from the first result of a run until the run ends, so that BatchCompleted is only sent once for it.
+    batch_running: bool,
+    // Workers that already left and were joined, and how many of those had panicked.
+    joined_workers: usize,
+    panicked_workers: usize,
+    // Workers joined since the last call to health, and how they ended.
+    ended_workers: BTreeMap<usize, WorkerStatus>,
     // Send and retrieve messages for the workers. Has tx_inserter and rx_deliverer channels.
-    feeder: FeederRecycler<T, R, S>,
+    feeder: FeederRecycler<T, R, S, E>,
+    // Inputs sent through WeakInputSenders, waiting to be moved into the feeder.
+    inbox: Inbox<R>,
+    // Results set aside by iter_batch while looking for another batch, with their batch. Returned first by the other iterators.
+    held_results: VecDeque<HeldResult<T, E>>,
+    // Inputs fed with submit, by their batch. Their results go to their JobHandle instead of the iterators.
+    jobs: BTreeMap<BatchId, JobSender<T, E>>,
+    // Wakes up the iterator in keep-alive mode. Shared with the WeakInputSenders.
+    input_gate: Arc<InputGate>,
+    // Shared with the AsyncFeeders, if there are any.
+    #[cfg(feature = "async")]
+    input_budget: Option<Arc<InputBudget>>,
+    keep_alive: bool,
+    // Applied once, by the first close.
+    drop_policy: DropPolicy<R>,
+    // How long drop waits for the workers. See set_drop_grace.
+    drop_grace: Duration,
 
     // What the workers use.
-    rx_inserter: Arc<Mutex<Receiver<S>>>,
-    tx_deliverer: SyncSender<S>,
+    rx_inserter: WorkReceiver<Package<S, E>>,
+    // None after shutdown, so that the feeder notices when every worker is gone.
+    tx_deliverer: Option<SyncSender<Package<S, E>>>,
 
     // Tells compiler that this data exists here, but is not a type stored in the struct.
     resource_type: PhantomData<T>,
@@ -152,34 +936,124 @@ S: Message<T, R> + Sync + Send + Clone + 'static,
 }
 
 
-impl<T, R, S> DeliveryService <T, R, S> where 
+impl<T, R, S, E> DeliveryService <T, R, S, E> where 
 T: MessageData + 'static,
 R: MessageInput<T> + 'static,
-S: Message<T, R> + Sync + Send + Clone + 'static,
+S: Message<T, R, E> + Sync + Send + 'static,
+E: Send + 'static,
 {
     /// Create a new DeliveryService instance using details set in ChannelConfig. If there's no need to set specific configuration, call DeliveryService::default() instead.
-    pub fn new(config: ChannelConfig) -> Self{
+    /// The config is checked first with *ChannelConfig::validate*, returning *KikError::Config* instead of building a channel that could deadlock.
+    /// Messages are built with *Message::new*, which calls *Default::default* unless the message has its own. For messages without *Default*, see *try_with_builder*.
+    pub fn try_new(config: ChannelConfig) -> Result<Self, KikError> where S: Default{
+        Self::try_with_builder(config, S::new)
+    }
+
+    /// Same as *try_new*, for messages without *Default*. builder is called for each new message instead of *Message::new*, unless there's a template.
+    pub fn try_with_builder(config: ChannelConfig, builder: fn() -> S) -> Result<Self, KikError>{
+        config.validate()?;
+        Ok(Self::build(config, builder)?)
+    }
+
+    /// Same as *try_new*, without checking the config. Panics if *Backend::Process* was made for other types than the channel's.
+    #[deprecated(since = "0.8.0", note = "use DeliveryService::try_new, which returns invalid configs as an error instead of building a channel that could deadlock")]
+    pub fn new(config: ChannelConfig) -> Self where S: Default{
+        match Self::build(config, S::new){
+            Ok(channel) => channel,
+            Err(error) => panic!("Error DeliveryService: {}. Use DeliveryService::try_new to get it as an error.", error),
+        }
+    }
+
+    /// Build the channel from a config that was already checked, or that the user chose not to check. Only the backend is checked here.
+    fn build(config: ChannelConfig, builder: fn() -> S) -> Result<Self, ConfigError>{
+        let spawn_eagerly = config.get_spawn_eagerly();
         let stack_size = config.get_stack_size();
         let worker_number = config.get_worker_number();
-        let thread_vec: Vec<JoinHandle<()>> = Vec::with_capacity(worker_number);
+        let thread_vec: Vec<WorkerHandle> = Vec::with_capacity(worker_number);
 
         let channel_size = config.get_channel_size();
-        let package_number = config.get_package_number();
 
-        // Setting both channels. There are several receivers (the workers) for the inserter channel. Therefore it needs to be coupled together with an arc + Mutex reference.
-        let (tx_inserter, rx_inserter) = sync_channel(channel_size);
-        let rx_inserter = Arc::new(Mutex::new(rx_inserter));
+        // Setting both channels. There are several receivers (the workers) for the inserter queue, see kik_queue for how they share it.
+        let (tx_inserter, rx_inserter) = if config.get_keyed_dispatch(){
+            keyed_queue(worker_number)
+        } else if config.get_work_stealing(){
+            stealing_queue(channel_size, worker_number)
+        } else {
+            work_queue(channel_size)
+        };
         let (tx_deliverer, rx_deliverer) = sync_channel(channel_size);
 
         // feeder manages both sending and receiving worker messages
-        let feeder: FeederRecycler<T, R, S> = FeederRecycler::new(0, package_number, tx_inserter, rx_deliverer);
+        let cancellation = CancellationToken::new();
+        let shared_context: SharedContext = Arc::new(RwLock::new(None));
+        let stack_peaks: Option<StackPeaks> = if config.get_stack_probe() && !config.get_inline() { Some(Arc::new(Mutex::new(BTreeMap::new()))) } else { None };
+        let progress_board: ProgressBoard = Arc::new(Mutex::new(BTreeMap::new()));
+        #[cfg(feature = "process")]
+        let process = match config.get_backend(){
+            Backend::Process(_) if config.get_inline() => None,
+            Backend::Process(backend) => match backend.get_connect::<S, E>(){
+                Some(connect) => Some((backend.get_command().clone(), connect)),
+                None => return Err(ConfigError::BackendTypes),
+            },
+            Backend::Threads => None,
+        };
+        let feeder: FeederRecycler<T, R, S, E> = FeederRecycler::new(&config, cancellation.clone(), shared_context.clone(), progress_board.clone(), tx_inserter, rx_deliverer, builder);
+        let events = feeder.get_events();
 
-        DeliveryService{
+        let mut channel = DeliveryService{
             stack_size,
             worker_number,
             last_id: 0,
+            inline: config.get_inline(),
+            name: config.name,
+            idle_hook: config.idle_hook,
+            idle_timeout: config.idle_timeout,
+            running_workers: Arc::new(AtomicUsize::new(0)),
+            wait_strategy: config.wait_strategy,
+            throttle: config.throttle,
+            thread_spawner: config.thread_spawner.unwrap_or_else(|| Arc::new(StdSpawner)),
+            worker_context: config.worker_context,
+            shared_context,
+            stack_peaks,
+            progress_board,
+            cancellation,
             thread_vec,
+            #[cfg(feature = "process")]
+            process,
+            #[cfg(feature = "spill")]
+            spill: None,
+            #[cfg(feature = "spill")]
+            spill_threshold: config.spill_threshold,
+            #[cfg(feature = "spill")]
+            spill_dir: config.spill_dir.clone().unwrap_or_else(std::env::temp_dir),
+            #[cfg(feature = "remote")]
+            remote_vec: Vec::new(),
+            retiring: Arc::new(AtomicUsize::new(0)),
+            closing: Arc::new(AtomicBool::new(false)),
+            pause_gate: Arc::new(PauseGate::default()),
+            #[cfg(feature = "os")]
+            thread_priority: config.thread_priority,
+            #[cfg(feature = "os")]
+            core_affinity: config.core_affinity,
+            #[cfg(feature = "os")]
+            os_errors: Arc::new(Mutex::new(Vec::new())),
+            events,
+            result_senders: ResultSenders::new(),
+            result_callback: None,
+            batch_running: false,
+            joined_workers: 0,
+            panicked_workers: 0,
+            ended_workers: BTreeMap::new(),
             feeder,
+            inbox: Arc::new(Mutex::new(InboxInputs::new())),
+            held_results: VecDeque::new(),
+            jobs: BTreeMap::new(),
+            input_gate: Arc::new(InputGate::new()),
+            #[cfg(feature = "async")]
+            input_budget: None,
+            keep_alive: config.keep_alive,
+            drop_policy: DropPolicy::default(),
+            drop_grace: DROP_GRACE,
 
             // Not used(yet)
             // channel_size,
@@ -187,105 +1061,1519 @@ S: Message<T, R> + Sync + Send + Clone + 'static,
         
             // What the workers use
             rx_inserter,
-            tx_deliverer,
+            tx_deliverer: Some(tx_deliverer),
         
             // Tells compiler that this data exists here, but is not a type stored in the struct.
             resource_type: PhantomData::<T>,
             resource_type2: PhantomData::<R>,
             resource_type3: PhantomData::<S>,
+        };
+        // Failures are retried by the first iteration, which reports them.
+        if spawn_eagerly{
+            let _ = channel.build_workers();
         }
+        Ok(channel)
     }
 
     /// Borrows a vector of inputs and append the values into the feeder. Borrowed vector will become empty.
-    pub fn feed_feeder(&mut self, input_vec: &mut Vec<R>){
-        self.feeder.append_input(input_vec);
+    /// 
+    /// The inputs wait in an internal queue that the feeder drains on each iteration, so this only needs a shared reference and can be called
+    /// from code that merely borrows the channel. While a *for* loop holds the channel, or from other threads, feed through a *weak_sender* or *split* instead.
+    /// 
+    /// Returns the *BatchId* of these inputs, for taking their results apart with *iter_batch*.
+    pub fn feed_feeder(&self, input_vec: &mut Vec<R>) -> BatchId{
+        self.feed_feeder_with_priority(input_vec, Priority::Normal)
     }
 
-    /// Tells how many values are still to be recovered. Includes messages that haven't been worked yet.
-    pub fn len(&mut self)-> usize{
-        self.feeder.get_remaining_messages()
+    /// Same as *feed_feeder*, into the lane of the given priority. *Priority::High* inputs are dispatched before the others, and *Priority::Low* ones
+    /// once nothing else is waiting, each lane in the order it was fed. Only *Priority::Normal* inputs go through the scheduler. See kik_scheduler.
+    pub fn feed_feeder_with_priority(&self, input_vec: &mut Vec<R>, priority: Priority) -> BatchId{
+        self.inbox.lock().unwrap_or_else(PoisonError::into_inner).append(input_vec, priority)
     }
 
-    /// Builds and append new workers until the max set value is reached.
-    fn build_workers(&mut self){
-        for _ in (self.thread_vec.len())..(self.worker_number){
-            self.last_id += 1;
-            let new_id = self.last_id;
-            
-            // let new_worker: Worker<'a, T, R, S> = Worker::new(self.last_id, new_rx_inserter, new_tx_deliverer);
-            let mut new_builder = Builder::new();
-            new_builder = new_builder.stack_size(self.stack_size);
-            new_builder = new_builder.name(format!("Worker {}", self.last_id));
+    /// Same as *feed_feeder*, but the inputs are only worth working until deadline. Inputs still queued once it passes are dropped
+    /// without being sent, they give no result and are counted by *get_expired_inputs*. Inputs already sent are worked anyway, but
+    /// *WorkInfo::deadline* tells them the deadline, so that a message can give up early. A frame rendered late is often worse than a dropped one.
+    pub fn feed_with_deadline(&mut self, input_vec: &mut Vec<R>, deadline: Instant) -> BatchId{
+        let batch = self.feed_feeder(input_vec);
+        self.feeder.set_deadline(batch, deadline);
+        batch
+    }
 
-            // Creating a weak reference so that it gets disconnected when the main reference (in this struct) is dropped.
-            let new_rx_inserter = Arc::downgrade(&self.rx_inserter);
-            let new_tx_deliverer = SyncSender::clone(&self.tx_deliverer);
-            
-            self.thread_vec.push(new_builder.spawn(
-                move || {
-                    let new_worker: Worker<T, R, S> = Worker::new(new_id, new_rx_inserter, new_tx_deliverer);
-                    new_worker.run();
-                    drop(new_worker);
+    /// How many inputs were dropped without being worked because their deadline had passed. See *feed_with_deadline*.
+    pub fn get_expired_inputs(&self) -> usize{
+        self.feeder.get_expired_inputs()
+    }
+
+    /// Same as *feed_feeder*, but inputs beyond the threshold set with *ChannelConfig::set_spill_threshold* are written to a temporary file
+    /// instead of being held in memory. They're read back in order as messages free up, after the ones in memory. See the spill module.
+    /// Returns how many inputs were spilled. Without a threshold, nothing is.
+    ///
+    /// If the file can't be written, the error is returned and the inputs that should have been spilled are left in input_vec. The ones
+    /// that fit in memory were fed. If it can't be read back later, the inputs left in it are lost, see *take_spill_error*.
+    #[cfg(feature = "spill")]
+    pub fn feed_spilling(&mut self, input_vec: &mut Vec<R>) -> io::Result<usize> where R: Serialize + DeserializeOwned{
+        let threshold = match self.spill_threshold{
+            Some(threshold) => threshold,
+            None => {
+                self.feed_feeder(input_vec);
+                return Ok(0);
+            },
+        };
+        // While a file is being drained, everything goes behind it to keep the order.
+        let spilling = self.spill.as_ref().is_some_and(|spill| !lock_spill(spill).is_drained());
+        if !spilling{
+            let room = threshold.saturating_sub(self.pending_inputs());
+            let mut kept: Vec<R> = input_vec.drain(..room.min(input_vec.len())).collect();
+            self.feed_feeder(&mut kept);
+        }
+        if input_vec.is_empty(){
+            return Ok(0);
+        }
+        if !spilling{
+            let mut new_spill = SpillFile::create(&self.spill_dir)?;
+            // Keep the error of the last file until it's taken.
+            if let Some(old_spill) = &self.spill{
+                if let Some(error) = lock_spill(old_spill).take_error(){
+                    new_spill.keep_error(error);
                 }
-            ).unwrap());
+            }
+            let spill = Arc::new(Mutex::new(new_spill));
+            let reader = spill.clone();
+            self.feed_generator(move || lock_spill(&reader).pop());
+            self.spill = Some(spill);
+        }
+        let spilled = input_vec.len();
+        match &self.spill{
+            Some(spill) => lock_spill(spill).push_all(input_vec)?,
+            None => unreachable!("a spill file was just created"),
         }
+        Ok(spilled)
     }
 
-}
+    /// How many inputs are waiting in the spill file. See *feed_spilling*.
+    #[cfg(feature = "spill")]
+    pub fn get_spilled_inputs(&self) -> usize{
+        self.spill.as_ref().map_or(0, |spill| lock_spill(spill).len())
+    }
 
-/// Creates new DeliveryService with default values. Useful for those in a hurry.
-impl<T, R, S> Default for DeliveryService<T, R, S> where 
-T: MessageData + 'static,
-R: MessageInput<T> + 'static,
-S: Message<T, R> + Sync + Send + Clone + 'static,
-{
-    fn default() -> Self{
-        let new_config = ChannelConfig::default();
-        DeliveryService::new(new_config)
+    /// Take the error that stopped a spill file from being read, if any. The inputs that were still in it are lost.
+    #[cfg(feature = "spill")]
+    pub fn take_spill_error(&mut self) -> Option<io::Error>{
+        self.spill.as_ref().and_then(|spill| lock_spill(spill).take_error())
     }
-}
 
-impl<T, R, S> Iterator for &mut DeliveryService<T, R, S>  where 
-T: MessageData + 'static,
-R: MessageInput<T> + 'static,
-S: Message<T, R> + Sync + Send + Clone + 'static,
-{
-    type Item = T;
+    /// Same as *feed_feeder*, but returns a *FeedReceipt* that tells when every input of this call has been sent to the workers.
+    /// These inputs skip the scheduler: they're sent before the ones fed with *feed_feeder*, in the order they were given, so that the receipt can tell them apart.
+    pub fn feed_feeder_ack(&mut self, input_vec: &mut Vec<R>) -> FeedReceipt{
+        self.feeder.append_input_ack(input_vec)
+    }
 
-    fn next(&mut self) -> Option<Self::Item> {
-        // This will only create workers if there is less than the required number in the vector.
-        self.build_workers();
-        // feeder will try to get a message and return the value. Returns None if there are no messages remaining.
-        self.feeder.next()
+    /// Feed a single input whose result goes to the returned *JobHandle* instead of the iterators. Meant for request/response code,
+    /// a web handler offloading one heavy computation, say, while the channel keeps streaming other results.
+    /// 
+    /// The result is handed over while the channel is iterated. Like *iter_batch*, a custom *Scheduler* can mix the input up with others fed at the same time.
+    /// If the message fans out (see *Message::fan_out*), only the first output goes to the handle, the iterators yield the rest.
+    pub fn submit(&mut self, input: R) -> JobHandle<T, E>{
+        let batch = self.inbox.lock().unwrap_or_else(PoisonError::into_inner).push(input);
+        let (tx, rx) = sync_channel(1);
+        self.jobs.insert(batch, tx);
+        JobHandle::new(rx)
     }
-}
 
-impl<T, R, S> Drop for DeliveryService<T, R, S> where 
-T: MessageData + 'static,
-R: MessageInput<T> + 'static,
-S: Message<T, R> + Sync + Send + Clone + 'static,
-{
-    fn drop(&mut self){
-        loop{
-            match self.rx_inserter.try_lock(){
-                Ok(lock) => {
-                    std::mem::drop(&lock);
-                    break;
-                },
-                Err(err) => {
-                    match err{
-                        TryLockError::Poisoned(_) => {
-                            break;
-                        },
-                        // try again later
-                        TryLockError::WouldBlock => {},
-                    }
+    /// Hand the result to its *JobHandle* if it came from *submit*. Gives it back otherwise.
+    fn route_job(&mut self, result: Result<T, WorkError<E>>) -> Option<Result<T, WorkError<E>>>{
+        let job = match self.feeder.get_last_batch(){
+            Some(batch) => self.jobs.remove(&batch),
+            None => None,
+        };
+        match job{
+            Some(job) => {
+                // The handle may have been dropped already, nobody wants the result then.
+                let _ = job.send(result);
+                #[cfg(feature = "async")]
+                self.settle_budget();
+                None
+            },
+            None => Some(result),
+        }
+    }
+
+    /// Feed inputs lazily. The generator is called for the next input only when a message is free to be sent, after every input fed with *feed_feeder* is gone.
+    /// It's dropped once it returns None. Useful for huge or endless streams of inputs that shouldn't be built all at once.
+    /// 
+    /// While a generator isn't exhausted, *len* counts it as a single value.
+    pub fn feed_generator<F>(&mut self, generator: F) where F: FnMut() -> Option<R> + Send + 'static{
+        self.feeder.append_generator(Box::new(generator));
+    }
+
+    /// Feed inputs read from reader, a file or a TCP stream for instance, in chunks of chunk_size bytes. Each chunk is turned into an input by convert.
+    /// Like *feed_generator*, a chunk is only read when a message is free to be sent, so the whole source is never held in memory.
+    /// Every chunk is full except the last one. A chunk_size of 0 is read as 1.
+    ///
+    /// The inputs end at the end of the reader or at its first error. The returned *ReadHandle* tells which, and how much was read.
+    pub fn feed_reader<Rd, F>(&mut self, reader: Rd, chunk_size: usize, convert: F) -> ReadHandle where Rd: Read + Send + 'static, F: FnMut(Vec<u8>) -> R + Send + 'static{
+        let (mut chunk_reader, handle) = ChunkReader::new(reader, chunk_size, convert);
+        self.feed_generator(move || chunk_reader.next_input());
+        handle
+    }
+
+    /// Replace the scheduler that decides which input is dispatched next. Inputs already queued are moved into the new scheduler.
+    /// 
+    /// In ordered mode, results are returned in the order the scheduler dispatches the inputs.
+    pub fn set_scheduler<Q>(&mut self, scheduler: Q) where Q: Scheduler<R> + 'static{
+        self.feeder.set_scheduler(Box::new(scheduler));
+    }
+
+    /// Build new messages by cloning this one instead of calling *Message::new*. Useful when a message carries state that is costly to build,
+    /// like lookup tables or precomputed plans. Only messages built from now on are affected, the ones being recycled keep what they have.
+    pub fn set_message_template(&mut self, template: S) where S: Clone{
+        self.feeder.set_message_template(Some(Box::new(move || template.clone())));
+    }
+
+    /// Go back to building new messages with *Message::new*, or the builder given to *try_with_builder*.
+    pub fn clear_message_template(&mut self){
+        self.feeder.set_message_template(None);
+    }
+
+    /// Create a handle for feeding this channel from other places without keeping it alive. Inputs sent through it are picked up on the next iteration.
+    /// It goes into the same queue as *feed_feeder*, and can be used from other threads or inside a *for* loop over the channel.
+    pub fn weak_sender(&self) -> WeakInputSender<R>{
+        WeakInputSender::new(&self.inbox, self.input_gate.clone())
+    }
+
+    /// End the stream of inputs. A keep-alive channel (see *ChannelConfig::set_keep_alive*) stops waiting, and its iterator returns None once
+    /// everything fed before was worked. The weak senders return *Closed* from then on. *feed_feeder* still works, without waiting for more.
+    pub fn close_input(&self){
+        self.input_gate.close(&self.inbox);
+        #[cfg(feature = "async")]
+        if let Some(budget) = &self.input_budget{
+            budget.close();
+        }
+    }
+
+    /// True once *close_input* was called, here or through a weak sender.
+    pub fn is_input_closed(&self) -> bool{
+        self.input_gate.is_closed()
+    }
+
+    /// In keep-alive mode, sleep until something is fed or the input is closed. Returns true if there are inputs to collect now.
+    fn wait_for_inputs(&self) -> bool{
+        self.keep_alive && self.input_gate.wait_for_inputs(&self.inbox)
+    }
+
+    /// Split the channel in a *FeederHandle* and a *ResultReceiver*, so that one thread can keep feeding while another keeps iterating.
+    /// The receiver owns the channel and sleeps when there's nothing left to work, until the handle sends more. It ends once every handle is dropped
+    /// and every result was returned. Inputs already fed, and the ones sent through weak senders, are worked too. See kik_split.
+    pub fn split(mut self) -> (FeederHandle<R>, ResultReceiver<T>){
+        // The receiver does its own waiting, on the handles.
+        self.keep_alive = false;
+        let signal = Arc::new(FeedSignal::new());
+        let feeder = FeederHandle::new(self.inbox.clone(), signal.clone());
+        let inbox = self.inbox.clone();
+        (feeder, ResultReceiver::new(self, inbox, signal))
+    }
+
+    /// Move the channel into consumers *ResultTee*s, each getting a clone of every result from now on. With a capacity, the consumers ahead wait
+    /// for the slowest one once it has that many results queued. The channel is dropped along with the last *ResultTee*. See kik_tee.
+    /// 
+    /// To keep feeding meanwhile, *split* the channel and tee the *ResultReceiver* instead.
+    pub fn tee(mut self, consumers: usize, capacity: Option<usize>) -> Vec<ResultTee<T>>{
+        // A failed message can't be cloned for every consumer, it's skipped instead of ending the iteration.
+        tee(Box::new(move || self.results().find_map(Result::ok)), consumers, capacity)
+    }
+
+    /// Create a handle for feeding this channel from async code. Its *feed* waits while the channel holds *budget* inputs or more, counting the queued ones,
+    /// the ones being worked and the results not yet taken. Every *AsyncFeeder* shares one budget, set by the last call. See kik_stream.
+    #[cfg(feature = "async")]
+    pub fn async_feeder(&mut self, budget: usize) -> AsyncFeeder<R>{
+        let input_budget = match &self.input_budget{
+            Some(input_budget) => {
+                input_budget.set_limit(budget);
+                input_budget.clone()
+            },
+            None => {
+                let input_budget = Arc::new(InputBudget::new(budget));
+                self.input_budget = Some(input_budget.clone());
+                input_budget
+            },
+        };
+        self.settle_budget();
+        AsyncFeeder::new(self.weak_sender(), input_budget)
+    }
+
+    /// Tell the async feeders how many inputs the channel holds now.
+    #[cfg(feature = "async")]
+    fn settle_budget(&mut self){
+        if let Some(input_budget) = self.input_budget.clone(){
+            input_budget.settle(|| self.len());
+        }
+    }
+
+    /// Move the channel into a thread of its own that iterates it, giving back a *ResultStream* whose results can be awaited. See kik_stream.
+    #[cfg(feature = "async")]
+    pub fn into_stream(self) -> ResultStream<T>{
+        ResultStream::new(self)
+    }
+
+    /// Move the inputs waiting in the inbox into the feeder. The inbox keeps its buffer, so feeding the same amount again doesn't allocate.
+    fn collect_inbox(&mut self){
+        let mut inbox = self.inbox.lock().unwrap_or_else(PoisonError::into_inner);
+        let feeder = &mut self.feeder;
+        inbox.drain_batches(|batch, priority, inputs| feeder.append_batch(batch, priority, inputs));
+    }
+
+    /// Tells how many values are still to be recovered. Includes messages that haven't been worked yet.
+    pub fn len(&mut self)-> usize{
+        self.collect_inbox();
+        self.feeder.get_remaining_messages() + self.held_results.len()
+    }
+
+    /// Lower and upper bound of how many results are left, as given by the *size_hint* of *results*. The iterators that only yield **T** keep
+    /// the upper bound alone, since a failed message ends them early. Both match *len* unless a message
+    /// can give other than one result: generators and *Message::fan_out* leave no upper bound, while quarantine, result_ttl, deadlines,
+    /// jobs, pausing and cancelling bring the lower one down to 0. Weak senders and keep-alive mode can add inputs anytime, so they leave
+    /// no upper bound either. Inputs shed by a scheduler aren't accounted for.
+    pub fn result_bounds(&self) -> (usize, Option<usize>){
+        let (lower, upper) = self.feeder.get_result_bounds();
+        let waiting = self.held_results.len() + self.inbox.lock().unwrap_or_else(PoisonError::into_inner).len();
+        // Results of jobs go to their handles instead, and a cancelled or paused run ends the iteration early.
+        let lower = if self.jobs.is_empty() && !self.cancellation.is_cancelled() && !self.pause_gate.is_paused() { lower + waiting } else { 0 };
+        let open = Arc::weak_count(&self.inbox) > 0 || (self.keep_alive && !self.input_gate.is_closed());
+        let upper = if open { None } else { upper.map(|upper| upper + waiting) };
+        (lower, upper)
+    }
+
+    /// How many inputs are waiting to be dispatched, including the ones sent through weak senders. Inputs a generator hasn't made yet aren't counted.
+    /// Together with *in_flight* and *ready_results*, tells where the work is. A channel whose inputs pile up here needs more workers, one whose results pile up needs a faster consumer.
+    pub fn pending_inputs(&mut self) -> usize{
+        self.collect_inbox();
+        self.feeder.get_pending_inputs()
+    }
+
+    /// How many messages are with the workers, waiting for one or being worked.
+    pub fn in_flight(&mut self) -> usize{
+        self.feeder.get_ready_results();
+        self.feeder.get_in_flight()
+    }
+
+    /// How many results are waiting to be returned by the iterator. Includes the ones held back in ordered mode, and the ones set aside by *iter_batch*.
+    pub fn ready_results(&mut self) -> usize{
+        self.feeder.get_ready_results() + self.held_results.len()
+    }
+
+    /// Abort the current run. Inputs not yet dispatched are dropped, workers skip the messages they haven't started and the results in flight are thrown away.
+    /// Messages already being worked are finished unless their *work_with_context* checks for cancellation. Returns how many inputs were thrown away.
+    /// 
+    /// The channel can be fed again afterwards. To cancel while iterating, use a token from *cancellation_token* instead.
+    pub fn cancel(&mut self) -> usize{
+        self.collect_inbox();
+        self.cancellation.cancel();
+        let cancelled = self.feeder.cancel_run() + self.held_results.len();
+        self.held_results.clear();
+        self.finish_batch();
+        #[cfg(feature = "async")]
+        self.settle_budget();
+        cancelled
+    }
+
+    /// Stop the run without throwing anything away, for example while the application needs the cpu for something else.
+    /// Workers finish the message they're working and then wait, holding on to the next one. Iterating while paused returns None
+    /// right away with *StopReason::Paused*, without dispatching anything, and *process* returns what it has so far. Inputs can still be fed.
+    pub fn pause(&mut self){
+        self.pause_gate.set_paused(true);
+    }
+
+    /// Let the workers go on after *pause*. The next iteration picks the run up where it stopped.
+    pub fn resume(&mut self){
+        self.pause_gate.set_paused(false);
+    }
+
+    /// True between *pause* and *resume*.
+    pub fn is_paused(&self) -> bool{
+        self.pause_gate.is_paused()
+    }
+
+    /// True if iteration has to stop because the channel is paused. Results already set aside are still returned.
+    fn stop_paused(&mut self) -> bool{
+        if !self.pause_gate.is_paused(){
+            return false;
+        }
+        self.feeder.set_paused();
+        true
+    }
+
+    /// What each worker is doing, how full the queues are and how long ago a message was last completed. Workers that panicked or left are
+    /// listed once, by the first call after they did, and are replaced on the next iteration. Inline channels have no workers to list.
+    /// 
+    /// A dead worker doesn't stop the channel, so call this from time to time to notice them, or subscribe to *events*.
+    pub fn health(&mut self) -> PoolHealth{
+        self.collect_inbox();
+        let ready_results = self.ready_results();
+        self.feeder.check_heartbeats();
+        self.join_finished_workers();
+        let mut workers = std::mem::take(&mut self.ended_workers);
+        let progress_board = self.progress_board.lock().unwrap_or_else(PoisonError::into_inner);
+        #[cfg(feature = "remote")]
+        let handles = self.thread_vec.iter().chain(self.remote_vec.iter());
+        #[cfg(not(feature = "remote"))]
+        let handles = self.thread_vec.iter();
+        for handle in handles{
+            let worker_id = handle.get_worker_id();
+            let status = if handle.has_panicked(){
+                WorkerStatus::Panicked
+            } else if handle.is_finished(){
+                WorkerStatus::Exited
+            } else if self.feeder.is_hung(worker_id){
+                WorkerStatus::Hung
+            } else {
+                match progress_board.get(&worker_id){
+                    Some(slot) if slot.is_working() => WorkerStatus::Running,
+                    // Not started yet, or waiting.
+                    _ => WorkerStatus::Idle,
                 }
+            };
+            workers.insert(worker_id, status);
+        }
+        drop(progress_board);
+        let queues = (self.feeder.get_pending_inputs(), self.feeder.get_in_flight(), ready_results);
+        let since_last_completed = self.feeder.get_last_completed_at().map(|completed_at| completed_at.elapsed());
+        PoolHealth::new(self.name.clone(), workers, queues, since_last_completed, self.pause_gate.is_paused())
+    }
+
+    /// Tell the subscriptions that the current run ended, if there was one.
+    fn finish_batch(&mut self){
+        // Submitted inputs with nothing left to come were thrown away. Their handles stop waiting.
+        let feeder = &self.feeder;
+        self.jobs.retain(|batch, _| feeder.get_batch_remaining(*batch) > 0);
+        if !self.batch_running{
+            return;
+        }
+        self.batch_running = false;
+        if let Some(stop_reason) = self.feeder.get_stop_reason(){
+            self.events.send(PoolEvent::BatchCompleted{stop_reason: stop_reason.clone()});
+        }
+    }
+
+    /// A handle to the cancellation token of this channel. Cancelling it from anywhere (another thread, or inside the loop) makes the iterator return None
+    /// as soon as the messages in flight come back, with *StopReason::Cancelled*.
+    pub fn cancellation_token(&self) -> CancellationToken{
+        self.cancellation.clone()
+    }
+
+    /// Subscribe to the events of this channel: workers starting, exiting or panicking, and batches completing.
+    /// Each call creates a new subscription that receives every event sent from now on. Drop the *Receiver* to unsubscribe.
+    pub fn events(&self) -> Receiver<PoolEvent>{
+        self.events.subscribe()
+    }
+
+    /// Register an observer, called for every message dispatched and worked, for the life of every worker, and for queues filling up, from now on.
+    /// Unlike *events*, the observer is called right away, on the feeder's or the worker's thread. See *ChannelObserver*.
+    pub fn add_observer(&self, observer: Arc<dyn ChannelObserver>){
+        self.events.observe(observer);
+    }
+
+    /// Subscribe to the results of this channel. Each call creates a new subscription that receives a clone of every successful result
+    /// yielded from now on, by any of the iterators, *try_next*, *next_timeout* or *drain*. Drop the *Receiver* to unsubscribe.
+    /// 
+    /// Nothing is sent while nobody iterates the channel, the subscriptions only watch. Failed messages aren't sent, *WorkError* can't be cloned.
+    pub fn subscribe_results(&mut self) -> Receiver<T>{
+        self.result_senders.subscribe()
+    }
+
+    /// Set the callback that *run_until_empty* hands each result to. Replaces the one set before, if any.
+    pub fn on_result<F>(&mut self, callback: F) where F: FnMut(T) + Send + 'static{
+        self.result_callback = Some(Box::new(callback));
+    }
+
+    /// Remove the callback set with *on_result*.
+    pub fn clear_on_result(&mut self){
+        self.result_callback = None;
+    }
+
+    /// Set the callback told how far the run is whenever a result comes back, with how many results it returned so far and how many it has in total.
+    /// For progress bars: it's called on the thread iterating the channel, by any of the iterators, *try_next* or *drain*. Replaces the one set before, if any.
+    /// Results taken by a *JobHandle* or discarded for being too old count as done too. See *progress* for how the total is counted.
+    pub fn on_progress<F>(&mut self, callback: F) where F: FnMut(usize, usize) + Send + 'static{
+        self.feeder.set_progress_callback(Some(Box::new(callback)));
+    }
+
+    /// Remove the callback set with *on_progress*.
+    pub fn clear_on_progress(&mut self){
+        self.feeder.set_progress_callback(None);
+    }
+
+    /// How many results the current run returned so far, and how many it has in total, counting the ones fed but not yet worked.
+    /// The total grows when more is fed during the run. Each generator counts as one until it's exhausted, and a message that fans out
+    /// as one until it comes back. Once the run is over, both stay the same until the channel is fed again.
+    pub fn progress(&mut self) -> (usize, usize){
+        self.collect_inbox();
+        self.feeder.get_progress()
+    }
+
+    /// Iterate until there are no results left, handing each one to the callback set with *on_result* instead of yielding it. Returns how many there were.
+    /// Results are dropped if there's no callback. Stops at a failed message, like the iterator does.
+    /// 
+    /// For event-driven code, which reacts to each result (writing a frame to a socket, say) instead of looping over them.
+    pub fn run_until_empty(&mut self) -> usize{
+        // Taken out while iterating, since the iterator borrows the whole channel.
+        let mut callback = self.result_callback.take();
+        let mut count = 0;
+        for data in &mut *self{
+            if let Some(callback) = callback.as_mut(){
+                callback(data);
             }
+            count += 1;
         }
-        std::mem::drop(&self.rx_inserter);
-        std::mem::drop(&self.feeder);
-        std::mem::drop(&self.tx_deliverer);
-        std::mem::drop(&self.thread_vec);
+        self.result_callback = callback;
+        count
+    }
+
+    /// Iterate until there are no results left, writing each one into writer with serialize, on this thread. Flushes the writer at the end.
+    /// Returns how many results were written. Stops at a failed message, like the iterator does.
+    /// 
+    /// For sending the results straight somewhere else, a file or a socket, without collecting them first. Wrap unbuffered writers in a *BufWriter*.
+    /// If writing fails, the error is returned right away. The result that failed is lost, the ones after it stay in the channel for the next iteration.
+    pub fn drain_to_writer<W, F>(&mut self, mut writer: W, mut serialize: F) -> io::Result<usize> where
+    W: Write,
+    F: FnMut(&T, &mut dyn Write) -> io::Result<()>,
+    {
+        let mut count = 0;
+        for data in &mut *self{
+            serialize(&data, &mut writer)?;
+            count += 1;
+        }
+        writer.flush()?;
+        Ok(count)
+    }
+
+    /// Take every input that wasn't dispatched yet out of the channel and write it into writer, for resuming the job later with *restore*.
+    /// Returns how many inputs were written. Messages in flight aren't touched, keep iterating to get their results.
+    /// Inputs still inside a generator aren't written either, the generator stays.
+    ///
+    /// For stopping a long job without losing its progress: checkpoint, drain what was in flight, exit. On restart, restore feeds the inputs
+    /// written here, and only those are worked. If writing fails the inputs are lost, write to a buffer first if that matters.
+    #[cfg(feature = "serde")]
+    pub fn checkpoint<W>(&mut self, mut writer: W) -> io::Result<usize> where W: Write, R: Serialize{
+        self.collect_inbox();
+        let inputs = self.feeder.take_queued_inputs();
+        #[cfg(feature = "async")]
+        self.settle_budget();
+        write_checkpoint(&mut writer, &inputs)
+    }
+
+    /// Same as *checkpoint*, but the messages in flight are cancelled too and their inputs written first, recovered with *Message::take_input*.
+    /// Inputs of messages that don't implement it are lost. So are results already worked but still held back in ordered mode.
+    /// The channel is left empty, like after *cancel*.
+    #[cfg(feature = "serde")]
+    pub fn checkpoint_all<W>(&mut self, mut writer: W) -> io::Result<usize> where W: Write, R: Serialize{
+        self.collect_inbox();
+        self.cancellation.cancel();
+        let inputs = self.feeder.cancel_run_inputs();
+        self.held_results.clear();
+        self.finish_batch();
+        #[cfg(feature = "async")]
+        self.settle_budget();
+        write_checkpoint(&mut writer, &inputs)
+    }
+
+    /// Feed the inputs of a checkpoint written by *checkpoint* or *checkpoint_all*, in the order they were written. Returns how many were fed.
+    /// If the checkpoint can't be read whole, nothing is fed and the error is returned.
+    #[cfg(feature = "serde")]
+    pub fn restore<Rd>(&mut self, mut reader: Rd) -> io::Result<usize> where Rd: Read, R: DeserializeOwned{
+        let mut inputs: Vec<R> = read_checkpoint(&mut reader)?;
+        let count = inputs.len();
+        self.feed_feeder(&mut inputs);
+        Ok(count)
+    }
+
+    /// Send a clone of a successful result to every result subscription. Any result makes room for the async feeders.
+    fn broadcast_result(&mut self, result: &Option<Result<T, WorkError<E>>>){
+        if let Some(Ok(data)) = result{
+            self.result_senders.send(data);
+        }
+        #[cfg(feature = "async")]
+        if result.is_some(){
+            self.settle_budget();
+        }
+    }
+
+    /// Share a read-only value with every worker, reachable in *Message::work_with_context* through *WorkContext::get_shared_context*.
+    /// Only the *Arc* is cloned. Replaces the value set before, if any. Messages already being worked keep the one they started with.
+    pub fn set_shared_context<C>(&mut self, shared_context: Arc<C>) where C: Send + Sync + 'static{
+        let shared_context: Arc<dyn Any + Send + Sync> = shared_context;
+        *self.shared_context.write().unwrap_or_else(PoisonError::into_inner) = Some(shared_context);
+    }
+
+    /// Remove the value set with *set_shared_context*.
+    pub fn clear_shared_context(&mut self){
+        *self.shared_context.write().unwrap_or_else(PoisonError::into_inner) = None;
+    }
+
+    /// The name given in *ChannelConfig::set_name*, if any.
+    pub fn get_name(&self) -> Option<&str>{
+        self.name.as_deref()
+    }
+
+    /// Why the last iteration returned None: every result was returned, the run was cancelled, or the channel failed.
+    /// None if no iteration has ended yet.
+    pub fn last_stop_reason(&self) -> Option<&StopReason>{
+        self.feeder.get_stop_reason()
+    }
+
+    /// How many messages are waiting in the pool to be loaned. Zero unless *ChannelConfig::set_message_pool* was used.
+    pub fn get_pooled_messages(&self) -> usize{
+        self.feeder.get_pooled_messages()
+    }
+
+    /// How many messages had to be built while the channel ran because the pool was empty. Zero unless *ChannelConfig::set_message_pool* was used.
+    pub fn get_pool_misses(&self) -> usize{
+        self.feeder.get_pool_misses()
+    }
+
+    /// Give a result back once it's no longer needed, so that its allocations are reused. The next message recycled takes it through
+    /// *Message::swap_message_data* and yields its own data instead of a clone. Messages that don't override it just drop the buffer.
+    /// Only as many buffers as the package number are kept, the rest are dropped.
+    pub fn return_buffer(&mut self, data: T){
+        self.feeder.return_buffer(data);
+    }
+
+    /// How many buffers given back with *return_buffer* are waiting for a message.
+    pub fn get_spare_buffers(&self) -> usize{
+        self.feeder.get_spare_buffers()
+    }
+
+    /// Estimated bytes held by the messages sent to the workers whose results weren't retrieved yet. Zero unless a memory budget is set in *ChannelConfig*.
+    pub fn get_in_flight_memory(&self) -> usize{
+        self.feeder.get_in_flight_memory()
+    }
+
+    /// Total *MessageInput::weight* of the inputs sent to the workers whose results weren't retrieved yet.
+    pub fn get_outstanding_weight(&self) -> usize{
+        self.feeder.get_outstanding_weight()
+    }
+
+    /// How many results were discarded so far for being older than the result TTL set in *ChannelConfig*.
+    pub fn get_discarded_results(&self) -> usize{
+        self.feeder.get_discarded_results()
+    }
+
+    /// Take the inputs set aside by the quarantine so far, each with the *FailureReason* of its last attempt. They gave no result.
+    /// Fix them and feed them again, or log them. Always empty unless *ChannelConfig::set_quarantine* was used.
+    pub fn take_failed(&mut self) -> Vec<(R, FailureReason<E>)>{
+        self.feeder.take_failed()
+    }
+
+    /// How many inputs the quarantine is holding, waiting for *take_failed*.
+    pub fn get_failed_inputs(&self) -> usize{
+        self.feeder.get_failed_inputs()
+    }
+
+    /// Peak payload size observed for each message slot. None unless memory tracking was enabled in *ChannelConfig*.
+    pub fn get_memory_stats(&self) -> Option<&MemoryStats>{
+        self.feeder.get_memory_stats()
+    }
+
+    /// Messages and bytes per second over the last window. The window is cut to the history set in *ChannelConfig::set_throughput_history*.
+    /// None unless throughput history was enabled there.
+    pub fn throughput(&self, window: Duration) -> Option<Throughput>{
+        self.feeder.get_throughput(window)
+    }
+
+    /// Allocations made on this thread while feeding and iterating, split by path. None unless allocation tracking was enabled in *ChannelConfig*.
+    /// Nothing is counted unless *CountingAllocator* is the global allocator. Run a warm-up batch, call *reset_alloc_stats*, then check that
+    /// a steady-state batch keeps the counts at zero. See kik_alloc for exactly what is guaranteed.
+    pub fn alloc_stats(&self) -> Option<&AllocStats>{
+        self.feeder.get_alloc_stats()
+    }
+
+    /// Set the counts returned by *alloc_stats* back to zero.
+    pub fn reset_alloc_stats(&mut self){
+        self.feeder.reset_alloc_stats();
+    }
+
+    /// Message counts, work time and latency percentiles, and how busy each worker was. None unless metrics were enabled in *ChannelConfig*.
+    pub fn stats(&self) -> Option<ChannelStats>{
+        self.feeder.get_stats()
+    }
+
+    /// Forget the timings returned by *stats* and start counting from now.
+    pub fn reset_stats(&mut self){
+        self.feeder.reset_stats();
+    }
+
+    /// Deepest stack use probed in each worker. None unless the stack probe was enabled in *ChannelConfig*, or if running inline.
+    pub fn get_stack_usage(&self) -> Option<StackUsage>{
+        let stack_peaks = self.stack_peaks.as_ref()?;
+        let worker_peaks = stack_peaks.lock().unwrap_or_else(PoisonError::into_inner).clone();
+        Some(StackUsage::new(self.name.clone(), self.stack_size, worker_peaks))
+    }
+
+    /// How far along each worker is in its current message, indexed by worker id. Only messages that use a *WorkPacer* tell their progress,
+    /// the others stay at zero done with no total. When inline, the caller's thread is worker 0.
+    pub fn get_work_progress(&self) -> BTreeMap<usize, WorkProgress>{
+        let progress_board = self.progress_board.lock().unwrap_or_else(PoisonError::into_inner);
+        progress_board.iter().map(|(worker_id, slot)| (*worker_id, slot.get_progress())).collect()
+    }
+
+    /// True if there are no values left to recover.
+    pub fn is_empty(&mut self) -> bool{
+        self.len() == 0
+    }
+
+    /// True if an iteration that returned None can still get results without the channel being fed: messages are still expected
+    /// (the channel is paused, stalled, or a generator isn't done), or inputs can still arrive through a weak sender, or in keep-alive mode.
+    /// False means the channel is done until it's fed again.
+    pub fn has_pending(&mut self) -> bool{
+        !self.is_empty() || Arc::weak_count(&self.inbox) > 0 || (self.keep_alive && !self.input_gate.is_closed())
+    }
+
+    /// Iterate through the results without assuming that they succeeded. Each item is either the **T** generated or the *WorkError* that prevented it.
+    pub fn results(&mut self) -> Results<'_, T, R, S, E>{
+        Results{
+            channel: self,
+            ended: false,
+        }
+    }
+
+    /// Return a result only if one is already waiting, without blocking. Meant for main loops that poll between frames instead of stalling inside *next*.
+    /// None doesn't mean the run is over, just that nothing arrived yet. Check *is_empty* for that. A failed message gives None
+    /// too, with *KikError::MessageFailed* in *last_stop_reason*.
+    ///
+    /// Messages are still dispatched to keep the workers busy. Inline channels work them right here, so this can take as long as a message does.
+    pub fn try_next(&mut self) -> Option<T>{
+        let result = self.try_next_result()?;
+        self.take_data(result)
+    }
+
+    /// Iterate through the results already waiting, without blocking. Ends as soon as *try_next* returns None.
+    pub fn try_iter(&mut self) -> TryIter<'_, T, R, S, E>{
+        TryIter{
+            channel: self,
+            ended: false,
+        }
+    }
+
+    /// Wait for the next result, but only until the timeout. For consumers that must meet a deadline, like an audio callback or a frame budget.
+    /// Returns Ok(None) when the run is over, like the iterator does, and *Timeout* if no result arrived in time. The run goes on after a timeout.
+    /// A failed message gives Ok(None) too, like the iterator does.
+    /// 
+    /// A cancelled run is thrown away like in *next*, which waits for the messages being worked. Inline channels work the messages right here,
+    /// so the deadline is only checked between them.
+    pub fn next_timeout(&mut self, timeout: Duration) -> Result<Option<T>, Timeout>{
+        let deadline = Instant::now() + timeout;
+        match self.next_result_until(deadline)?{
+            Some(result) => Ok(self.take_data(result)),
+            None => Ok(None),
+        }
+    }
+
+    /// Same as *next_timeout*, without assuming that the message succeeded.
+    fn next_result_until(&mut self, deadline: Instant) -> Result<Option<Result<T, WorkError<E>>>, Timeout>{
+        loop{
+            if self.cancellation.is_cancelled(){
+                return Ok(self.results().next());
+            }
+            if self.stop_paused(){
+                return Ok(None);
+            }
+            if let Some(result) = self.try_next_result(){
+                return Ok(Some(result));
+            }
+            // The last inputs may have been found to be gone while dispatching, for example an exhausted generator.
+            if self.feeder.get_remaining_messages() == 0{
+                if self.keep_alive && self.input_gate.wait_for_inputs_until(&self.inbox, deadline)?{
+                    continue;
+                }
+                self.feeder.set_completed();
+                self.finish_batch();
+                return Ok(None);
+            }
+            if !self.feeder.wait_for_package(deadline)?{
+                self.finish_batch();
+                return Ok(None);
+            }
+        }
+    }
+
+    /// Same as *try_next*, without assuming that the message succeeded.
+    fn try_next_result(&mut self) -> Option<Result<T, WorkError<E>>>{
+        if let Some((_, result)) = self.held_results.pop_front(){
+            return Some(result);
+        }
+        if self.stop_paused(){
+            return None;
+        }
+        loop{
+            let mark = self.feeder.alloc_mark();
+            self.collect_inbox();
+            if self.feeder.get_remaining_messages() == 0{
+                // More may come, the run isn't over.
+                if self.keep_alive && !self.input_gate.is_closed(){
+                    self.feeder.record_alloc(mark, AllocStats::record_yield);
+                    return None;
+                }
+                self.feeder.set_completed();
+                self.finish_batch();
+                self.feeder.record_alloc(mark, AllocStats::record_yield);
+                return None;
+            }
+            self.batch_running = true;
+            if !self.start_workers(){
+                self.feeder.record_alloc(mark, AllocStats::record_yield);
+                return None;
+            }
+            let next = match self.feeder.try_next(){
+                Some(result) => match self.route_job(result){
+                    Some(result) => Some(result),
+                    // Taken by its handle. Look for another one.
+                    None => {
+                        self.feeder.record_alloc(mark, AllocStats::record_yield);
+                        continue;
+                    },
+                },
+                None => None,
+            };
+            self.feeder.record_alloc(mark, AllocStats::record_yield);
+            self.broadcast_result(&next);
+            return next;
+        }
+    }
+
+    /// Iterate through the results of the inputs fed in one call to *feed_feeder*, ending once they've all been returned.
+    /// Results of other batches that arrive meanwhile are set aside, and returned first by the other iterators. Ends at a failed message, like the iterator does.
+    pub fn iter_batch(&mut self, batch: BatchId) -> BatchIter<'_, T, R, S, E>{
+        BatchIter{
+            channel: self,
+            batch,
+            ended: false,
+        }
+    }
+
+    /// Next result of the batch, without assuming that the message succeeded. None once the batch has nothing left.
+    fn next_batch_result(&mut self, batch: BatchId) -> Option<Result<T, WorkError<E>>>{
+        if let Some(index) = self.held_results.iter().position(|(held, _)| *held == Some(batch)){
+            return self.held_results.remove(index).map(|(_, result)| result);
+        }
+        self.collect_inbox();
+        while self.feeder.get_batch_remaining(batch) > 0{
+            let result = self.receive_result()?;
+            let received = self.feeder.get_last_batch();
+            if received == Some(batch){
+                return Some(result);
+            }
+            self.held_results.push_back((received, result));
+        }
+        None
+    }
+
+    /// Next result, starting with the ones set aside by *iter_batch*. Used by *Results*.
+    fn next_result(&mut self) -> Option<Result<T, WorkError<E>>>{
+        if let Some((_, result)) = self.held_results.pop_front(){
+            return Some(result);
+        }
+        self.receive_result()
+    }
+
+    /// Next result from the feeder, dispatching and waiting as needed.
+    fn receive_result(&mut self) -> Option<Result<T, WorkError<E>>>{
+        if self.stop_paused(){
+            return None;
+        }
+        loop{
+            let mark = self.feeder.alloc_mark();
+            self.collect_inbox();
+            // In keep-alive mode, wait for more instead of ending the run.
+            while self.feeder.get_remaining_messages() == 0 && self.wait_for_inputs(){
+                self.collect_inbox();
+            }
+            // Nothing fed and nothing in flight. Return right away instead of spawning workers for an empty run.
+            if self.feeder.get_remaining_messages() == 0{
+                self.feeder.set_completed();
+                self.finish_batch();
+                self.feeder.record_alloc(mark, AllocStats::record_yield);
+                return None;
+            }
+            self.batch_running = true;
+            // This will only create workers if there is less than the required number in the vector.
+            if !self.start_workers(){
+                self.feeder.record_alloc(mark, AllocStats::record_yield);
+                return None;
+            }
+            // feeder will try to get a message and return the value. Returns None if there are no messages remaining.
+            let next = match self.feeder.next(){
+                Some(result) => match self.route_job(result){
+                    Some(result) => Some(result),
+                    // Taken by its handle. Start over, the run may be over now.
+                    None => {
+                        self.feeder.record_alloc(mark, AllocStats::record_yield);
+                        continue;
+                    },
+                },
+                None => None,
+            };
+            if next.is_none(){
+                self.finish_batch();
+            }
+            self.feeder.record_alloc(mark, AllocStats::record_yield);
+            self.broadcast_result(&next);
+            return next;
+        }
+    }
+
+    /// Unwrap a result for the iterators that only yield **T**. A failed message gives None, ending the iteration with *KikError::MessageFailed*.
+    fn take_data(&mut self, result: Result<T, WorkError<E>>) -> Option<T>{
+        match result{
+            Ok(data) => Some(data),
+            Err(error) => {
+                self.feeder.fail(KikError::MessageFailed{worker_id: error.get_worker_id()});
+                None
+            },
+        }
+    }
+
+    /// Iterate until there are no results left, collecting them in a vector. Also returns a *BatchReport* with tuning data about the run.
+    pub fn drain(&mut self) -> (Vec<T>, BatchReport){
+        let mut results: Vec<T> = Vec::with_capacity(self.len());
+        self.feeder.start_report();
+        for data in &mut *self{
+            results.push(data);
+        }
+        // start_report was called above, so there is always a report here.
+        let report = self.feeder.finish_report().unwrap();
+        (results, report)
+    }
+
+    /// Iterate until there are no results left, folding each into the accumulator instead of storing it. Stops at a failed message, like the iterator does.
+    /// 
+    /// The results are folded on the caller's thread while the workers are busy with the next messages, so sums and histograms come almost for free.
+    pub fn fold<B, F>(&mut self, init: B, mut f: F) -> B where F: FnMut(B, T) -> B{
+        let mut accumulator = init;
+        for data in &mut *self{
+            accumulator = f(accumulator, data);
+        }
+        accumulator
+    }
+
+    /// Feed the inputs and collect their results, like a parallel map. If in_order is true, the results come in the order the inputs were sent
+    /// to the workers, which is the order they were fed unless a *Scheduler* changes it. Failed messages are left out, with *KikError::MessageFailed* in *last_stop_reason*.
+    ///
+    /// Results of other inputs that arrive meanwhile are set aside, as in *iter_batch*.
+    pub fn process(&mut self, input_vec: &mut Vec<R>, in_order: bool) -> Vec<T>{
+        let mut results: Vec<(usize, T)> = Vec::with_capacity(input_vec.len());
+        let batch = self.feed_feeder(input_vec);
+        self.collect_inbox();
+        while self.feeder.get_batch_remaining(batch) > 0{
+            let result = match self.receive_result(){
+                Some(result) => result,
+                None => break,
+            };
+            let received = self.feeder.get_last_batch();
+            if received == Some(batch){
+                let sequence = self.feeder.get_last_sequence();
+                results.extend(self.take_data(result).map(|data| (sequence, data)));
+            } else {
+                self.held_results.push_back((received, result));
+            }
+        }
+        if in_order{
+            // Stable, so that the outputs of a message that fanned out stay in their order.
+            results.sort_by_key(|(sequence, _)| *sequence);
+        }
+        results.into_iter().map(|(_, data)| data).collect()
+    }
+
+    /// Feed the inputs and combine all their results into one with *Merge::merge*. Each message sent to the workers
+    /// carries up to inputs_per_message inputs, and the worker merges their results before sending it back, so far fewer messages go through the channels.
+    /// 
+    /// The results that come back are merged on this thread, into the data of a new message. So besides being associative and commutative, merge
+    /// must leave a new message's data as the starting point: zero for a sum, an empty histogram. Failed messages are left out, as in *process*.
+    /// Results of other inputs that arrive meanwhile are set aside, as in *iter_batch*.
+    pub fn map_reduce(&mut self, input_vec: &mut Vec<R>, inputs_per_message: usize) -> T where S: Merge<T>{
+        let mut total = self.feeder.build_message();
+        let batch = self.feed_feeder(input_vec);
+        self.collect_inbox();
+        self.feeder.set_merging(Some((batch, inputs_per_message, merge_message::<T, R, E, S>)));
+        while self.feeder.get_batch_remaining(batch) > 0{
+            let result = match self.receive_result(){
+                Some(result) => result,
+                None => break,
+            };
+            let received = self.feeder.get_last_batch();
+            if received == Some(batch){
+                if let Some(data) = self.take_data(result){
+                    total.merge(data);
+                }
+            } else {
+                self.held_results.push_back((received, result));
+            }
+        }
+        self.feeder.set_merging(None);
+        total.take_message_data()
+    }
+
+    /// Choose what happens to the work left when the channel is dropped or shut down. Default *DropPolicy::Abandon*.
+    pub fn set_drop_policy(&mut self, drop_policy: DropPolicy<R>){
+        self.drop_policy = drop_policy;
+    }
+
+    /// How long dropping the channel waits for the workers, once the drop policy was applied. The run is cancelled first, so that messages
+    /// checking *WorkContext::is_cancelled* give up early. Workers still busy after that are left running on their own, and their messages are lost.
+    /// Only *shutdown* waits for every worker, however long it takes. *DropPolicy::Finish* still works every input left before that. Default 1 second.
+    pub fn set_drop_grace(&mut self, drop_grace: Duration){
+        self.drop_grace = drop_grace;
+    }
+
+    /// Stop the channel and wait for every worker thread to finish.
+    /// 
+    /// What happens to the work left depends on the *DropPolicy*. By default, inputs that weren't dispatched yet are dropped, and messages already with the workers are waited for and their results dropped.
+    /// The returned *ShutdownReport* tells how many inputs and results were lost, and whether any worker had panicked.
+    pub fn shutdown(mut self) -> ShutdownReport{
+        self.close(None)
+    }
+
+    /// Apply the drop policy, then disconnect and join the workers. Used by shutdown and drop. Calling it again does nothing.
+    /// With a grace, the run is cancelled and the workers are only waited for that long. The ones still running are detached, and not counted as joined.
+    fn close(&mut self, grace: Option<Duration>) -> ShutdownReport{
+        self.collect_inbox();
+        // Paused workers would never give their messages back.
+        self.pause_gate.set_paused(false);
+        let mut persisted_inputs: usize = 0;
+        match std::mem::take(&mut self.drop_policy){
+            DropPolicy::Abandon => (),
+            DropPolicy::Finish => {
+                // A keep-alive channel would wait for more inputs forever. What was fed already is still worked.
+                self.close_input();
+                for _ in self.results(){}
+            },
+            DropPolicy::Abort => self.cancellation.cancel(),
+            DropPolicy::Persist(mut sink) => {
+                let input_vec = self.feeder.take_queued_inputs();
+                persisted_inputs = input_vec.len();
+                if !input_vec.is_empty(){
+                    sink(input_vec);
+                }
+            },
+        }
+        #[cfg(feature = "async")]
+        if let Some(budget) = &self.input_budget{
+            budget.close();
+        }
+        let deadline = grace.map(|grace| {
+            self.cancellation.cancel();
+            Instant::now() + grace
+        });
+        // Without this sender, the feeder stops waiting if every worker is gone.
+        self.tx_deliverer = None;
+        self.closing.store(true, Ordering::SeqCst);
+        let (abandoned_inputs, drained_messages) = self.feeder.close(deadline);
+        // Results set aside by iter_batch are lost too.
+        let drained_messages = drained_messages + self.held_results.len();
+        self.held_results.clear();
+
+        #[cfg(feature = "remote")]
+        self.thread_vec.append(&mut self.remote_vec);
+        for handle in self.thread_vec.drain(..){
+            let joined = match deadline{
+                Some(deadline) => handle.join_until(deadline),
+                None => Some(handle.join()),
+            };
+            match joined{
+                Some(true) => self.joined_workers += 1,
+                Some(false) => {
+                    self.joined_workers += 1;
+                    self.panicked_workers += 1;
+                },
+                None => (),
+            }
+        }
+        let report = ShutdownReport::new(self.name.clone(), abandoned_inputs, persisted_inputs, drained_messages, self.joined_workers, self.panicked_workers);
+        self.joined_workers = 0;
+        self.panicked_workers = 0;
+        report
+    }
+
+    /// Spawn every worker now instead of on the first iteration that has something to work, so that the first result doesn't wait for the threads.
+    /// Returns *KikError::SpawnFailed* if a thread couldn't be spawned, the next iteration tries again. Inline channels have no workers, it does nothing.
+    pub fn warm_up(&mut self) -> Result<(), KikError>{
+        self.build_workers()
+    }
+
+    /// Add workers to the channel while it's running. The package number grows by one for each worker added.
+    /// The channel sizes are fixed at construction, so growing it any further could leave the feeder and the workers waiting on each other.
+    /// 
+    /// Workers still waiting to be removed by *remove_workers* are kept instead of replaced.
+    /// 
+    /// Returns *KikError::SpawnFailed* if a thread couldn't be spawned. The workers still count, the next iteration tries to spawn them again.
+    pub fn add_workers(&mut self, worker_number: usize) -> Result<(), KikError>{
+        self.worker_number += worker_number;
+        let package_number = self.feeder.get_package_number();
+        self.feeder.set_package_number(package_number + worker_number);
+        // Cancel removals first, those workers are still running.
+        let mut kept = 0;
+        while kept < worker_number && self.retiring.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |retiring| retiring.checked_sub(1)).is_ok(){
+            kept += 1;
+        }
+        // Workers are only built once there's something to work.
+        if !self.thread_vec.is_empty(){
+            self.build_workers()?;
+        }
+        Ok(())
+    }
+
+    /// Remove workers from the channel while it's running. There's always at least one worker left. Returns how many workers will be removed.
+    /// The package number shrinks by one for each worker removed.
+    /// 
+    /// Each removed worker leaves after finishing its current message. An idle worker leaves after its next one.
+    pub fn remove_workers(&mut self, worker_number: usize) -> usize{
+        let removed = worker_number.min(self.worker_number - 1);
+        self.worker_number -= removed;
+        let package_number = self.feeder.get_package_number();
+        self.feeder.set_package_number(package_number - removed);
+        self.join_finished_workers();
+        let running = self.thread_vec.len().saturating_sub(self.retiring.load(Ordering::SeqCst));
+        self.retiring.fetch_add(running.saturating_sub(self.worker_number), Ordering::SeqCst);
+        removed
+    }
+
+    /// How many messages the feeder allows in flight right now. Same as the package number, unless a memory pressure probe set in *ChannelConfig* reports pressure.
+    /// With *ChannelConfig::set_adaptive_packages*, the package number itself moves within its band as the channel runs.
+    pub fn get_package_limit(&self) -> usize{
+        self.feeder.get_package_limit()
+    }
+
+    /// The band the package number moves in, as (min, max), moved along with any workers added or removed. None unless *ChannelConfig::set_adaptive_packages* was used.
+    pub fn get_adaptive_band(&self) -> Option<(usize, usize)>{
+        self.feeder.get_adaptive_band()
+    }
+
+    /// How many workers the channel is set to have. Workers being removed aren't counted.
+    pub fn get_worker_number(&self) -> usize{
+        self.worker_number
+    }
+
+    /// Connect to a *RemoteAgent* and add a worker that works its messages through it. The package number grows by one, like with *add_workers*.
+    /// See the remote module for how messages travel. Only messages **S** and errors **E** that implement *Serialize* and *DeserializeOwned* can be sent.
+    ///
+    /// The worker stops if the connection is lost, its message then gives back *WorkError::Lost*. It isn't removed by *remove_workers*.
+    /// Returns the error of the connection or of the thread spawn. Inline channels have no workers, they return *ErrorKind::Unsupported*.
+    #[cfg(feature = "remote")]
+    pub fn add_remote_worker<A>(&mut self, address: A) -> io::Result<()> where A: ToSocketAddrs, S: Serialize + DeserializeOwned, E: Serialize + DeserializeOwned{
+        if self.inline{
+            return Err(io::Error::new(io::ErrorKind::Unsupported, "inline channels have no workers"));
+        }
+        let new_tx_deliverer = match &self.tx_deliverer{
+            Some(tx_deliverer) => SyncSender::clone(tx_deliverer),
+            None => return Err(io::Error::new(io::ErrorKind::NotConnected, "the channel was shut down")),
+        };
+        let call = RemoteLink::<S, E>::connect(address)?.into_call::<T, R>();
+        self.last_id += 1;
+        let new_id = self.last_id;
+        let new_name = match &self.name{
+            Some(name) => format!("{} remote worker {}", name, new_id),
+            None => format!("Remote worker {}", new_id),
+        };
+        let new_rx_inserter = self.rx_inserter.downgrade();
+        let new_wait_strategy = self.wait_strategy;
+        let new_cancellation = self.cancellation.clone();
+        let new_shared_context = self.shared_context.clone();
+        let new_progress_board = self.progress_board.clone();
+        let new_retiring = self.retiring.clone();
+        let new_events = self.events.clone();
+        let new_heartbeat = self.feeder.get_heartbeat(new_id);
+        let new_pause_gate = self.pause_gate.clone();
+        let handle = self.spawn_worker(new_id, new_name,
+            move || {
+                let mut new_worker: Worker<T, R, S, E> = Worker::new(new_id, new_rx_inserter, new_tx_deliverer, None, new_retiring, new_events);
+                new_worker.set_wait_strategy(new_wait_strategy);
+                new_worker.set_pause_gate(Some(new_pause_gate));
+                let mut context = WorkContext::for_worker(new_id, new_cancellation, new_shared_context, None, None, new_progress_board);
+                context.set_heartbeat(new_heartbeat);
+                new_worker.run_remote(context, call, false);
+                drop(new_worker);
+            }
+        )?;
+        self.remote_vec.push(handle);
+        let package_number = self.feeder.get_package_number();
+        self.feeder.set_package_number(package_number + 1);
+        Ok(())
+    }
+
+    /// How many remote workers are still connected. See *add_remote_worker*.
+    #[cfg(feature = "remote")]
+    pub fn get_remote_worker_number(&self) -> usize{
+        self.remote_vec.iter().filter(|handle| !handle.is_finished()).count()
+    }
+
+    /// Take the errors of the thread priorities and core affinities the workers couldn't apply so far, with the id of each worker.
+    /// Those workers run with the system's defaults instead. See the os module.
+    #[cfg(feature = "os")]
+    pub fn take_os_errors(&mut self) -> Vec<(usize, io::Error)>{
+        std::mem::take(&mut *self.os_errors.lock().unwrap_or_else(PoisonError::into_inner))
+    }
+
+    /// Hand a worker to the thread spawner, see kik_spawner. Returns the handle for joining it.
+    fn spawn_worker<F>(&self, worker_id: usize, name: String, work: F) -> io::Result<WorkerHandle> where F: FnOnce() + Send + 'static{
+        let (thread, handle) = WorkerThread::new(worker_id, name, self.stack_size, work);
+        self.thread_spawner.spawn(thread)?;
+        Ok(handle)
+    }
+
+    /// Join the workers that already left.
+    fn join_finished_workers(&mut self){
+        let mut index = 0;
+        while index < self.thread_vec.len(){
+            if self.thread_vec[index].is_finished(){
+                self.joined_workers += 1;
+                let handle = self.thread_vec.swap_remove(index);
+                let worker_id = handle.get_worker_id();
+                if handle.join(){
+                    self.ended_workers.insert(worker_id, WorkerStatus::Exited);
+                } else {
+                    self.panicked_workers += 1;
+                    self.ended_workers.insert(worker_id, WorkerStatus::Panicked);
+                }
+            } else {
+                index += 1;
+            }
+        }
+    }
+
+    /// Build the missing workers before iterating. If none is running afterwards, the iteration ends with *KikError::SpawnFailed* and this returns false.
+    /// The inputs stay queued, so the next iteration tries again.
+    fn start_workers(&mut self) -> bool{
+        match self.build_workers(){
+            Err(error) if self.thread_vec.is_empty() => {
+                self.feeder.fail(error);
+                self.finish_batch();
+                false
+            },
+            // Fewer workers than asked, but the run can go on.
+            _ => true,
+        }
+    }
+
+    /// Builds and append new workers until the max set value is reached. Only called once there is something to work. Inline channels have no workers.
+    /// Stops at the first thread that couldn't be spawned.
+    fn build_workers(&mut self) -> Result<(), KikError>{
+        if self.inline{
+            return Ok(());
+        }
+        self.join_finished_workers();
+        // Workers that are about to leave aren't counted.
+        let running = self.thread_vec.len().saturating_sub(self.retiring.load(Ordering::SeqCst));
+        for _ in running..(self.worker_number){
+            self.last_id += 1;
+            let new_id = self.last_id;
+            
+            // let new_worker: Worker<'a, T, R, S> = Worker::new(self.last_id, new_rx_inserter, new_tx_deliverer);
+            let new_name = match &self.name{
+                Some(name) => format!("{} worker {}", name, new_id),
+                None => format!("Worker {}", new_id),
+            };
+
+            // Creating a weak reference so that it gets disconnected when the main reference (in this struct) is dropped.
+            let new_rx_inserter = self.rx_inserter.downgrade();
+            let new_tx_deliverer = match &self.tx_deliverer{
+                Some(tx_deliverer) => SyncSender::clone(tx_deliverer),
+                // Channel was shut down. No more workers.
+                None => return Ok(()),
+            };
+            let new_idle_hook = self.idle_hook.clone();
+            let new_wait_strategy = self.wait_strategy;
+            let new_idle_timeout = (self.idle_timeout, self.running_workers.clone());
+            let new_throttle = self.throttle;
+            let new_closing = self.closing.clone();
+            let new_pause_gate = self.pause_gate.clone();
+            let new_heartbeat = self.feeder.get_heartbeat(new_id);
+            #[cfg(feature = "os")]
+            let new_os_settings = (self.thread_priority, self.core_affinity.clone(), self.os_errors.clone());
+            let new_cancellation = self.cancellation.clone();
+            let new_worker_context = self.worker_context.clone();
+            let new_shared_context = self.shared_context.clone();
+            let new_stack_peaks = self.stack_peaks.clone();
+            let new_progress_board = self.progress_board.clone();
+            let new_retiring = self.retiring.clone();
+            let new_events = self.events.clone();
+            #[cfg(feature = "process")]
+            let new_process = self.process.clone();
+            
+            let spawned = self.spawn_worker(new_id, new_name,
+                move || {
+                    let mut new_worker: Worker<T, R, S, E> = Worker::new(new_id, new_rx_inserter, new_tx_deliverer, new_idle_hook, new_retiring, new_events);
+                    new_worker.set_wait_strategy(new_wait_strategy);
+                    new_worker.set_idle_timeout(new_idle_timeout.0, new_idle_timeout.1);
+                    new_worker.set_throttle(new_throttle, new_closing);
+                    new_worker.set_pause_gate(Some(new_pause_gate));
+                    #[cfg(feature = "os")]
+                    apply_settings(new_id, new_os_settings.0, new_os_settings.1.as_ref(), &new_os_settings.2);
+                    // Built here so that the worker's state is created in its own thread.
+                    let mut context = WorkContext::for_worker(new_id, new_cancellation, new_shared_context, new_worker_context.as_ref(), new_stack_peaks, new_progress_board);
+                    context.set_heartbeat(new_heartbeat);
+                    #[cfg(feature = "process")]
+                    if let Some((command, connect)) = new_process{
+                        new_worker.run_remote(context, connect(command), true);
+                        return;
+                    }
+                    new_worker.run(context);
+                    drop(new_worker);
+                }
+            );
+            match spawned{
+                Ok(handle) => self.thread_vec.push(handle),
+                Err(error) => return Err(KikError::SpawnFailed{worker_id: new_id, kind: error.kind()}),
+            }
+        }
+        Ok(())
+    }
+
+}
+
+/// Creates new DeliveryService with default values. Useful for those in a hurry.
+impl<T, R, S, E> Default for DeliveryService<T, R, S, E> where 
+T: MessageData + 'static,
+R: MessageInput<T> + 'static,
+S: Message<T, R, E> + Default + Sync + Send + 'static,
+E: Send + 'static,
+{
+    fn default() -> Self{
+        match DeliveryService::build(ChannelConfig::default(), S::new){
+            Ok(channel) => channel,
+            Err(_) => unreachable!("The default config has no backend to check"),
+        }
+    }
+}
+
+/// Not a *FusedIterator*: an iteration that ended because the channel was paused, stalled or a message failed goes on where it stopped. The one of *results* is.
+impl<T, R, S, E> Iterator for &mut DeliveryService<T, R, S, E>  where 
+T: MessageData + 'static,
+R: MessageInput<T> + 'static,
+S: Message<T, R, E> + Sync + Send + 'static,
+E: Send + 'static,
+{
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let result = self.results().next()?;
+        self.take_data(result)
+    }
+
+    /// The upper bound of *DeliveryService::result_bounds*. There's no lower bound, any message can fail and end the iteration early.
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, self.result_bounds().1)
+    }
+}
+
+/// Iterator returned by *DeliveryService::results*. Yields the outcome of each message, successful or not.
+/// Once it returns None it keeps returning None, even if inputs arrive through a weak sender meanwhile. Call *results* again to go on.
+pub struct Results<'a, T, R, S, E> where 
+T: MessageData + 'static,
+R: MessageInput<T> + 'static,
+S: Message<T, R, E> + Sync + Send + 'static,
+E: Send + 'static,
+{
+    channel: &'a mut DeliveryService<T, R, S, E>,
+    // Set once next returned None, so that it keeps returning None.
+    ended: bool,
+}
+
+impl<T, R, S, E> Iterator for Results<'_, T, R, S, E> where 
+T: MessageData + 'static,
+R: MessageInput<T> + 'static,
+S: Message<T, R, E> + Sync + Send + 'static,
+E: Send + 'static,
+{
+    type Item = Result<T, WorkError<E>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.ended{
+            return None;
+        }
+        let next = self.channel.next_result();
+        self.ended = next.is_none();
+        next
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        if self.ended{
+            return (0, Some(0));
+        }
+        self.channel.result_bounds()
+    }
+}
+
+impl<T, R, S, E> FusedIterator for Results<'_, T, R, S, E> where 
+T: MessageData + 'static,
+R: MessageInput<T> + 'static,
+S: Message<T, R, E> + Sync + Send + 'static,
+E: Send + 'static,
+{
+}
+
+/// Iterator returned by *DeliveryService::iter_batch*. Yields the results of one batch, and ends once they've all been returned.
+pub struct BatchIter<'a, T, R, S, E> where 
+T: MessageData + 'static,
+R: MessageInput<T> + 'static,
+S: Message<T, R, E> + Sync + Send + 'static,
+E: Send + 'static,
+{
+    channel: &'a mut DeliveryService<T, R, S, E>,
+    batch: BatchId,
+    ended: bool,
+}
+
+impl<T, R, S, E> Iterator for BatchIter<'_, T, R, S, E> where 
+T: MessageData + 'static,
+R: MessageInput<T> + 'static,
+S: Message<T, R, E> + Sync + Send + 'static,
+E: Send + 'static,
+{
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.ended{
+            return None;
+        }
+        let data = self.channel.next_batch_result(self.batch).and_then(|result| self.channel.take_data(result));
+        self.ended = data.is_none();
+        data
+    }
+}
+
+impl<T, R, S, E> FusedIterator for BatchIter<'_, T, R, S, E> where 
+T: MessageData + 'static,
+R: MessageInput<T> + 'static,
+S: Message<T, R, E> + Sync + Send + 'static,
+E: Send + 'static,
+{
+}
+
+/// Iterator returned by *DeliveryService::try_iter*. Yields the results already waiting, and ends instead of blocking.
+pub struct TryIter<'a, T, R, S, E> where 
+T: MessageData + 'static,
+R: MessageInput<T> + 'static,
+S: Message<T, R, E> + Sync + Send + 'static,
+E: Send + 'static,
+{
+    channel: &'a mut DeliveryService<T, R, S, E>,
+    // Set once next returned None, so that it keeps returning None.
+    ended: bool,
+}
+
+impl<T, R, S, E> Iterator for TryIter<'_, T, R, S, E> where 
+T: MessageData + 'static,
+R: MessageInput<T> + 'static,
+S: Message<T, R, E> + Sync + Send + 'static,
+E: Send + 'static,
+{
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.ended{
+            return None;
+        }
+        let next = self.channel.try_next();
+        self.ended = next.is_none();
+        next
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        if self.ended{
+            return (0, Some(0));
+        }
+        // Ends as soon as no result is waiting.
+        (0, self.channel.result_bounds().1)
+    }
+}
+
+impl<T, R, S, E> FusedIterator for TryIter<'_, T, R, S, E> where 
+T: MessageData + 'static,
+R: MessageInput<T> + 'static,
+S: Message<T, R, E> + Sync + Send + 'static,
+E: Send + 'static,
+{
+}
+
+/// Iterator of an owned *DeliveryService*, for one-shot jobs: "*for data in delivery_service{}*". Yields every result left, like iterating
+/// through a mutable reference does. Dropping it, once done or halfway, shuts the channel down like *DeliveryService::shutdown*, which
+/// joins every worker, without the grace period of dropping the channel itself. Call *shutdown* on it instead to get the *ShutdownReport*.
+pub struct IntoIter<T, R, S, E> where 
+T: MessageData + 'static,
+R: MessageInput<T> + 'static,
+S: Message<T, R, E> + Sync + Send + 'static,
+E: Send + 'static,
+{
+    channel: DeliveryService<T, R, S, E>,
+}
+
+impl<T, R, S, E> IntoIter<T, R, S, E> where 
+T: MessageData + 'static,
+R: MessageInput<T> + 'static,
+S: Message<T, R, E> + Sync + Send + 'static,
+E: Send + 'static,
+{
+    /// Stop the channel and wait for every worker, like *DeliveryService::shutdown*.
+    pub fn shutdown(mut self) -> ShutdownReport{
+        // The drop that follows finds the channel closed already.
+        self.channel.close(None)
+    }
+}
+
+impl<T, R, S, E> Drop for IntoIter<T, R, S, E> where 
+T: MessageData + 'static,
+R: MessageInput<T> + 'static,
+S: Message<T, R, E> + Sync + Send + 'static,
+E: Send + 'static,
+{
+    fn drop(&mut self){
+        // Unlike dropping the channel itself, the workers are joined without a grace period.
+        self.channel.close(None);
+    }
+}
+
+impl<T, R, S, E> Iterator for IntoIter<T, R, S, E> where 
+T: MessageData + 'static,
+R: MessageInput<T> + 'static,
+S: Message<T, R, E> + Sync + Send + 'static,
+E: Send + 'static,
+{
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        (&mut self.channel).next()
+    }
+
+    /// Same as the iterator of *DeliveryService*.
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, self.channel.result_bounds().1)
+    }
+}
+
+impl<T, R, S, E> IntoIterator for DeliveryService<T, R, S, E> where 
+T: MessageData + 'static,
+R: MessageInput<T> + 'static,
+S: Message<T, R, E> + Sync + Send + 'static,
+E: Send + 'static,
+{
+    type Item = T;
+    type IntoIter = IntoIter<T, R, S, E>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter{
+            channel: self,
+        }
+    }
+}
+
+impl<T, R, S, E> Drop for DeliveryService<T, R, S, E> where 
+T: MessageData + 'static,
+R: MessageInput<T> + 'static,
+S: Message<T, R, E> + Sync + Send + 'static,
+E: Send + 'static,
+{
+    fn drop(&mut self){
+        // Best effort. Workers still holding messages are waited for up to the drop grace, so that a stuck one can't hang the dropping thread.
+        let grace = self.drop_grace;
+        self.close(Some(grace));
     }
 }
\ No newline at end of file
@@ -0,0 +1,390 @@
+//! # Context
+//!
+//! What a *Worker* hands to *Message::work_with_context* besides the message itself. *Message::work_with_info* also gets a *WorkInfo*,
+//! telling which worker took the message, on which attempt, and when it was dispatched.
+//!
+//! Each *Worker* owns one *WorkContext* for its whole life and passes it by mutable reference into every message it works.
+//! It gives access to the *CancellationToken* of the channel, so long *work* loops can check if they should give up early.
+//!
+//! It also holds the worker's own state, built once when the worker starts by the closure given to *ChannelConfig::set_worker_context*.
+//! Things like file handles, connections, random number generators or scratch buffers can live there instead of inside every *Message*.
+//!
+//! Read-only data that every worker needs (lookup tables, palettes, configuration) can be set once with *DeliveryService::set_shared_context*
+//! instead of being cloned into every input. Each *WorkContext* of the channel reaches the same value.
+//!
+//! With *ChannelConfig::set_stack_probe*, the context also measures how deep each worker's stack goes. The address of a local variable is taken
+//! when the worker starts, and compared with the address of another one every time the stack is probed. The worker probes before each message.
+//! Deep code inside *work* should call *WorkContext::probe_stack* itself, at the deepest points, since there's no portable way of seeing into it from outside.
+//! Assumes the stack grows downwards, which it does on every platform Rust commonly runs on.
+//!
+//! Long *work* loops can get a *WorkPacer* from *WorkContext::pacer* and call *WorkPacer::checkpoint* once per step. In one call it publishes the progress of the
+//! message (read it with *DeliveryService::get_work_progress*), yields the thread if the current time slice is over, and tells whether the run was cancelled:
+//!
+//! ```ignore
+//! let mut pacer = context.pacer();
+//! pacer.set_total(Some(rows));
+//! for row in 0..rows{
+//!     if !pacer.checkpoint() { return Ok(()); }
+//!     // Work the row.
+//! }
+//! ```
+//!
+//!
+
+use std::any::Any;
+use std::collections::BTreeMap;
+use std::hint::black_box;
+use std::sync::{Arc, Mutex, RwLock, PoisonError};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::thread::yield_now;
+use std::time::{Duration, Instant};
+
+use crate::kik_heartbeat::{Heartbeat, Pulse};
+use crate::kik_report::WorkProgress;
+
+/// Flag shared between a *DeliveryService*, its workers and any handle the user cloned from it. Once cancelled, workers skip the messages
+/// they receive and the iterator returns None as soon as the messages in flight come back. The channel resets it after the cancelled run is cleaned up.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken{
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken{
+    /// Create a token that isn't cancelled.
+    pub fn new() -> Self{
+        Self::default()
+    }
+
+    /// Request the cancellation of the current run. Can be called from any thread.
+    pub fn cancel(&self){
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    /// True if a cancellation was requested and the channel hasn't finished cleaning up after it.
+    pub fn is_cancelled(&self) -> bool{
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Clear the cancellation. Used by kik_feeder once the cancelled run is cleaned up.
+    pub fn reset(&self){
+        self.cancelled.store(false, Ordering::SeqCst);
+    }
+}
+
+
+/// Called once by each worker with its id when it starts. The value returned is kept in the worker's *WorkContext*. Set with *ChannelConfig::set_worker_context*.
+pub type WorkerInit = Arc<dyn Fn(usize) -> Box<dyn Any + Send> + Send + Sync>;
+
+/// Slot for the read-only value shared by every worker of a channel. Set with *DeliveryService::set_shared_context*.
+pub type SharedContext = Arc<RwLock<Option<Arc<dyn Any + Send + Sync>>>>;
+
+/// Deepest stack use probed in each worker, indexed by worker id. Shared between *DeliveryService* and its workers.
+pub type StackPeaks = Arc<Mutex<BTreeMap<usize, usize>>>;
+
+/// Progress of the message each worker is working, indexed by worker id. Shared between *DeliveryService* and its workers.
+pub type ProgressBoard = Arc<Mutex<BTreeMap<usize, Arc<ProgressSlot>>>>;
+
+// Total of a message that didn't tell its total.
+const UNKNOWN_TOTAL: usize = usize::MAX;
+
+/// Progress of the message a single worker is working. Written by *WorkPacer*, read by *DeliveryService::get_work_progress*.
+#[derive(Debug)]
+pub struct ProgressSlot{
+    done: AtomicUsize,
+    total: AtomicUsize,
+    // True while the worker has a message in hand, for DeliveryService::health.
+    working: AtomicBool,
+}
+
+impl ProgressSlot{
+    /// A slot with nothing done and no total.
+    pub fn new() -> Self{
+        ProgressSlot{
+            done: AtomicUsize::new(0),
+            total: AtomicUsize::new(UNKNOWN_TOTAL),
+            working: AtomicBool::new(false),
+        }
+    }
+
+    /// Start over for a new message.
+    fn reset(&self){
+        self.done.store(0, Ordering::Relaxed);
+        self.total.store(UNKNOWN_TOTAL, Ordering::Relaxed);
+    }
+
+    /// Copy the current values.
+    pub fn get_progress(&self) -> WorkProgress{
+        let total = self.total.load(Ordering::Relaxed);
+        WorkProgress::new(self.done.load(Ordering::Relaxed), if total == UNKNOWN_TOTAL { None } else { Some(total) })
+    }
+
+    /// True if the worker is working a message, false if it's waiting for one.
+    pub fn is_working(&self) -> bool{
+        self.working.load(Ordering::Relaxed)
+    }
+}
+
+impl Default for ProgressSlot{
+    fn default() -> Self{
+        Self::new()
+    }
+}
+
+// The progress slot of a worker, listed in the board of its channel while the worker lives.
+struct ProgressEntry{
+    worker_id: usize,
+    slot: Arc<ProgressSlot>,
+    // None for contexts that don't belong to a worker.
+    board: Option<ProgressBoard>,
+}
+
+impl ProgressEntry{
+    fn new(worker_id: usize, board: Option<ProgressBoard>) -> Self{
+        let slot = Arc::new(ProgressSlot::new());
+        if let Some(board) = &board{
+            board.lock().unwrap_or_else(PoisonError::into_inner).insert(worker_id, slot.clone());
+        }
+        ProgressEntry{
+            worker_id,
+            slot,
+            board,
+        }
+    }
+}
+
+impl Drop for ProgressEntry{
+    fn drop(&mut self){
+        // The worker is leaving. Its last message is no longer being worked.
+        if let Some(board) = &self.board{
+            board.lock().unwrap_or_else(PoisonError::into_inner).remove(&self.worker_id);
+        }
+    }
+}
+
+impl Drop for WorkContext{
+    fn drop(&mut self){
+        // The worker is leaving. The feeder stops listening for it.
+        if let Some(heartbeat) = &mut self.heartbeat{
+            heartbeat.send(Pulse::Left);
+        }
+    }
+}
+
+/// Helper for long *work* loops, obtained from *WorkContext::pacer*. See the module documentation.
+/// 
+/// Holds its own handles to the cancellation token and the progress of the worker, so the context can still be used while it's alive.
+pub struct WorkPacer{
+    cancellation: CancellationToken,
+    progress: Arc<ProgressSlot>,
+    // Only Some if the heartbeat is enabled.
+    heartbeat: Option<Heartbeat>,
+    time_slice: Option<Duration>,
+    slice_start: Instant,
+}
+
+impl WorkPacer{
+    /// How many checkpoints the message is expected to go through, for telling how far along it is. Default None (unknown).
+    pub fn set_total(&mut self, total: Option<usize>){
+        self.progress.total.store(total.unwrap_or(UNKNOWN_TOTAL), Ordering::Relaxed);
+    }
+
+    /// Give the thread away at the first checkpoint after this much time has passed, then start a new slice.
+    /// Useful when there are more workers than cores and other threads (like the one iterating) must keep running. Default None (never yields).
+    pub fn set_time_slice(&mut self, time_slice: Option<Duration>){
+        self.time_slice = time_slice;
+        self.slice_start = Instant::now();
+    }
+
+    /// Call once per step of a long loop. Counts the step as done, tells the feeder the worker isn't hung (see kik_heartbeat), yields the thread if the time slice is over,
+    /// and returns false if the run was cancelled, in which case *work* should return early.
+    pub fn checkpoint(&mut self) -> bool{
+        self.progress.done.fetch_add(1, Ordering::Relaxed);
+        if let Some(heartbeat) = &mut self.heartbeat{
+            heartbeat.beat();
+        }
+        if let Some(time_slice) = self.time_slice{
+            if self.slice_start.elapsed() >= time_slice{
+                yield_now();
+                self.slice_start = Instant::now();
+            }
+        }
+        !self.cancellation.is_cancelled()
+    }
+
+    /// How many checkpoints the current message went through.
+    pub fn get_done(&self) -> usize{
+        self.progress.done.load(Ordering::Relaxed)
+    }
+}
+
+/// Address of a local variable of the caller's frame.
+#[inline(never)]
+fn stack_address() -> usize{
+    let marker: u8 = 0;
+    black_box(&marker) as *const u8 as usize
+}
+
+// Tracks the stack depth of a single worker.
+struct StackProbe{
+    worker_id: usize,
+    // Address taken when the worker started.
+    base: usize,
+    // Deepest this worker has gone. The shared map is only locked when it grows.
+    peak: usize,
+    peaks: StackPeaks,
+}
+
+impl StackProbe{
+    fn new(worker_id: usize, peaks: StackPeaks) -> Self{
+        StackProbe{
+            worker_id,
+            base: stack_address(),
+            peak: 0,
+            peaks,
+        }
+    }
+
+    fn sample(&mut self, address: usize){
+        let depth = self.base.saturating_sub(address);
+        if depth > self.peak{
+            self.peak = depth;
+            self.peaks.lock().unwrap_or_else(PoisonError::into_inner).insert(self.worker_id, depth);
+        }
+    }
+}
+
+/// Where and when a message is being worked, passed into *Message::work_with_info*.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WorkInfo{
+    /// Id of the worker working the message, the same one *WorkError* reports. Starts at 1, zero when the channel works inline.
+    pub worker_id: usize,
+    /// How many times a worker started this dispatch of the message, this one included. 1 on the first attempt,
+    /// more when it failed before and was sent again (see *ChannelConfig::set_quarantine*).
+    pub attempt: usize,
+    /// When the feeder sent the message to the workers. The time until now is how long it waited in the queue.
+    pub dispatched_at: Instant,
+    /// Deadline the input was fed with, see *DeliveryService::feed_with_deadline*. None if it has none.
+    /// A message that can't finish in time can check it and give up early, its result would come too late anyway.
+    pub deadline: Option<Instant>,
+}
+
+impl WorkInfo{
+    /// How long the message waited between being dispatched and this moment.
+    pub fn queue_delay(&self) -> Duration{
+        self.dispatched_at.elapsed()
+    }
+
+    /// True if the message has a deadline and it's gone.
+    pub fn is_past_deadline(&self) -> bool{
+        self.deadline.is_some_and(|deadline| deadline <= Instant::now())
+    }
+
+    /// Time left until the deadline, zero once it's gone. None if the message has no deadline.
+    pub fn time_left(&self) -> Option<Duration>{
+        self.deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()))
+    }
+}
+
+/// Per-worker context passed into *Message::work_with_context*.
+pub struct WorkContext{
+    cancellation: CancellationToken,
+    // Shared with the channel and every other worker.
+    shared_context: SharedContext,
+    // Built by the WorkerInit of the channel, if there's one.
+    worker_context: Option<Box<dyn Any + Send>>,
+    // Only Some if the stack probe is enabled.
+    stack_probe: Option<StackProbe>,
+    progress: ProgressEntry,
+    // Only Some if the heartbeat is enabled.
+    heartbeat: Option<Heartbeat>,
+}
+
+impl WorkContext{
+    /// Construct a new context without worker state.
+    pub fn new(cancellation: CancellationToken, shared_context: SharedContext) -> Self{
+        WorkContext{
+            cancellation,
+            shared_context,
+            worker_context: None,
+            stack_probe: None,
+            progress: ProgressEntry::new(0, None),
+            heartbeat: None,
+        }
+    }
+
+    /// Construct the context of the given worker, calling worker_init for its state if there's one. Used by kik_channel in each worker thread, and by kik_feeder when running inline.
+    /// If stack_peaks is given, the stack is measured from here, so it must be called at the top of the worker's thread.
+    /// The progress of the worker is listed in the board until the context is dropped.
+    pub fn for_worker(worker_id: usize, cancellation: CancellationToken, shared_context: SharedContext, worker_init: Option<&WorkerInit>, stack_peaks: Option<StackPeaks>, progress_board: ProgressBoard) -> Self{
+        WorkContext{
+            cancellation,
+            shared_context,
+            worker_context: worker_init.map(|worker_init| worker_init(worker_id)),
+            stack_probe: stack_peaks.map(|peaks| StackProbe::new(worker_id, peaks)),
+            progress: ProgressEntry::new(worker_id, Some(progress_board)),
+            heartbeat: None,
+        }
+    }
+
+    /// A *WorkPacer* for the message being worked. Its progress is the one read by *DeliveryService::get_work_progress*.
+    pub fn pacer(&self) -> WorkPacer{
+        WorkPacer{
+            cancellation: self.cancellation.clone(),
+            progress: self.progress.slot.clone(),
+            heartbeat: self.heartbeat.clone(),
+            time_slice: None,
+            slice_start: Instant::now(),
+        }
+    }
+
+    /// Clear the progress left by the last message. Used by kik_worker before each message.
+    pub fn reset_progress(&self){
+        self.progress.slot.reset();
+    }
+
+    /// Tell *DeliveryService::health* whether the worker is working a message, and the feeder too if the heartbeat is enabled.
+    /// Used by kik_worker around each message.
+    pub fn set_working(&mut self, working: bool){
+        self.progress.slot.working.store(working, Ordering::Relaxed);
+        if let Some(heartbeat) = &mut self.heartbeat{
+            heartbeat.send(if working { Pulse::Working } else { Pulse::Idle });
+        }
+    }
+
+    /// Send beats to the feeder through heartbeat, see kik_heartbeat. Used by kik_channel in each worker thread.
+    pub fn set_heartbeat(&mut self, heartbeat: Option<Heartbeat>){
+        self.heartbeat = heartbeat;
+    }
+
+    /// Record how deep the stack is at this point, if *ChannelConfig::set_stack_probe* is enabled. Does nothing otherwise.
+    /// The worker calls it before each message. Call it from the deepest points of *work* to catch what happens inside it.
+    #[inline(never)]
+    pub fn probe_stack(&mut self){
+        if let Some(stack_probe) = &mut self.stack_probe{
+            stack_probe.sample(stack_address());
+        }
+    }
+
+    /// The value set with *DeliveryService::set_shared_context*. None if there's none, or if **C** isn't its type.
+    /// If it's replaced while the channel runs, messages started afterwards see the new one.
+    pub fn get_shared_context<C>(&self) -> Option<Arc<C>> where C: Send + Sync + 'static{
+        let shared_context = self.shared_context.read().unwrap_or_else(PoisonError::into_inner);
+        Arc::clone(shared_context.as_ref()?).downcast::<C>().ok()
+    }
+
+    /// The state built for this worker by the closure given to *ChannelConfig::set_worker_context*.
+    /// None if the channel has no such closure, or if **C** isn't the type it returns.
+    pub fn get_worker_context<C>(&mut self) -> Option<&mut C> where C: 'static{
+        self.worker_context.as_mut()?.downcast_mut::<C>()
+    }
+
+    /// True if the current run was cancelled. Long *work* loops should check it often and return early.
+    pub fn is_cancelled(&self) -> bool{
+        self.cancellation.is_cancelled()
+    }
+
+    /// The cancellation token of the channel.
+    pub fn get_cancellation_token(&self) -> &CancellationToken{
+        &self.cancellation
+    }
+}
@@ -0,0 +1,261 @@
+//! # Errors
+//!
+//! Error types that the channel hands to the user instead of panicking.
+//!
+//! *KikError* is for failures of the channel itself: workers that disconnected, stalled or couldn't be spawned, and invalid settings given to
+//! *DeliveryService::try_new*. *StopReason* tells why the last iteration ended. *ConfigError* is returned by *ChannelConfigBuilder::build*.
+//! 
+//! *WorkError* is yielded by *DeliveryService::results* when a *Message* failed to be worked, either because *Message::try_work*
+//! returned an error or because the *Worker* panicked while working it. The *Worker* survives both cases and keeps working other *Message*s.
+//! With the "remote" feature, it can also be a remote worker whose connection was lost, that one stops.
+//! With a quarantine set, inputs that keep failing are set aside instead, and *FailureReason* tells how the last attempt went.
+//!
+//!
+
+use std::fmt;
+use std::error::Error;
+use std::time::Duration;
+use std::io;
+
+/// Reason why a single *Message* couldn't produce its *MessageData*. **E** is the error type returned by *Message::try_work*.
+#[derive(Debug)]
+pub enum WorkError<E>{
+    /// *Message::try_work* returned an error.
+    Failed{
+        /// Id of the worker that worked the message.
+        worker_id: usize,
+        /// Error returned by the message.
+        error: E,
+    },
+    /// The worker panicked inside *Message::try_work*. The panic was caught and the worker is still running.
+    Panicked{
+        /// Id of the worker that worked the message.
+        worker_id: usize,
+    },
+    /// The connection to a remote worker was lost while it had the message. The remote worker stopped. See *DeliveryService::add_remote_worker*.
+    #[cfg(feature = "remote")]
+    Lost{
+        /// Id of the remote worker that had the message.
+        worker_id: usize,
+    },
+}
+
+impl<E> WorkError<E>{
+    /// Id of the worker where the failure happened.
+    pub fn get_worker_id(&self) -> usize{
+        match self{
+            WorkError::Failed{worker_id, ..} => *worker_id,
+            WorkError::Panicked{worker_id} => *worker_id,
+            #[cfg(feature = "remote")]
+            WorkError::Lost{worker_id} => *worker_id,
+        }
+    }
+}
+
+impl<E> fmt::Display for WorkError<E> where E: fmt::Display{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        match self{
+            WorkError::Failed{worker_id, error} => write!(f, "Message failed in worker {}: {}", worker_id, error),
+            WorkError::Panicked{worker_id} => write!(f, "Worker {} panicked while working a message", worker_id),
+            #[cfg(feature = "remote")]
+            WorkError::Lost{worker_id} => write!(f, "Lost the connection to remote worker {} while it had a message", worker_id),
+        }
+    }
+}
+
+impl<E> Error for WorkError<E> where E: fmt::Debug + fmt::Display{}
+
+/// Why an input was set aside by the quarantine, see *ChannelConfig::set_quarantine*. Given back with the input by *DeliveryService::take_failed*.
+#[derive(Debug)]
+pub struct FailureReason<E>{
+    error: WorkError<E>,
+    attempts: usize,
+}
+
+impl<E> FailureReason<E>{
+    /// Wrap the error of the last attempt. Used by kik_feeder.
+    pub fn new(error: WorkError<E>, attempts: usize) -> Self{
+        FailureReason{
+            error,
+            attempts,
+        }
+    }
+
+    /// How the last attempt failed.
+    pub fn get_error(&self) -> &WorkError<E>{
+        &self.error
+    }
+
+    /// How many times the input was worked before it was set aside.
+    pub fn get_attempts(&self) -> usize{
+        self.attempts
+    }
+
+    /// Take the error of the last attempt.
+    pub fn into_error(self) -> WorkError<E>{
+        self.error
+    }
+}
+
+impl<E> fmt::Display for FailureReason<E> where E: fmt::Display{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        write!(f, "Set aside after {} attempts. {}", self.attempts, self.error)
+    }
+}
+
+impl<E> Error for FailureReason<E> where E: fmt::Debug + fmt::Display{}
+
+/// Returned when feeding a channel that has already been dropped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Closed;
+
+impl fmt::Display for Closed{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        write!(f, "The channel has been closed")
+    }
+}
+
+impl Error for Closed{}
+
+/// Returned by *DeliveryService::next_timeout* when no result arrived before the deadline. The run goes on, results still come later.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Timeout;
+
+impl fmt::Display for Timeout{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        write!(f, "No result arrived before the deadline")
+    }
+}
+
+impl Error for Timeout{}
+
+
+/// Failures of the channel itself, as opposed to failures of a single message.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum KikError{
+    /// The channel between the feeder and the workers was disconnected while results were still expected. Every worker is gone.
+    Disconnected,
+    /// No result arrived within the stall timeout set in *ChannelConfig::set_stall_timeout*, while messages were in flight.
+    /// A worker may be stuck, or a message lost. The run isn't over: iterating again waits for another timeout. Cancel it or shut down to give up.
+    Stalled{
+        /// How long the feeder waited.
+        timeout: Duration,
+        /// Messages that were still with the workers.
+        in_flight: usize,
+    },
+    /// The thread of a worker couldn't be spawned. Returned by *DeliveryService::add_workers*, and ends the iteration if no worker is running at all.
+    SpawnFailed{
+        /// Id the worker would have had.
+        worker_id: usize,
+        /// What the operating system said.
+        kind: io::ErrorKind,
+    },
+    /// The *ChannelConfig* given to *DeliveryService::try_new* is invalid.
+    Config(ConfigError),
+    /// A message failed while iterating with something that only yields **T**, like the iterator of *DeliveryService*. Its result is dropped,
+    /// and the iteration ends. Iterating again carries on with the other results. *DeliveryService::results* gives the *WorkError* instead.
+    MessageFailed{
+        /// Worker where the message failed.
+        worker_id: usize,
+    },
+}
+
+impl From<ConfigError> for KikError{
+    fn from(error: ConfigError) -> Self{
+        KikError::Config(error)
+    }
+}
+
+impl fmt::Display for KikError{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        match self{
+            KikError::Disconnected => write!(f, "The workers disconnected while results were still expected"),
+            KikError::Stalled{timeout, in_flight} => write!(f, "No result arrived for {:?} while {} messages were in flight", timeout, in_flight),
+            KikError::SpawnFailed{worker_id, kind} => write!(f, "Couldn't spawn the thread of worker {}: {}", worker_id, io::Error::from(*kind)),
+            KikError::Config(error) => write!(f, "Invalid config: {}", error),
+            KikError::MessageFailed{worker_id} => write!(f, "A message failed in worker {}", worker_id),
+        }
+    }
+}
+
+impl Error for KikError{
+    fn source(&self) -> Option<&(dyn Error + 'static)>{
+        match self{
+            KikError::Config(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+
+/// Invalid combination of settings, returned by *ChannelConfigBuilder::build*.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ConfigError{
+    /// There must be at least one worker thread.
+    NoWorkers,
+    /// The channels must hold at least one package.
+    NoChannelSize,
+    /// There must be more packages than workers, or some workers would never get one.
+    NotEnoughPackages{
+        /// Packages asked for.
+        package_number: usize,
+        /// Workers asked for.
+        worker_number: usize,
+    },
+    /// With more packages than both channels and every worker can hold at once, the feeder and the workers can end up waiting on each other forever.
+    TooManyPackages{
+        /// Packages asked for.
+        package_number: usize,
+        /// Most packages allowed: twice the channel size, plus the worker number.
+        max_package_number: usize,
+    },
+    /// The stack size is below *MIN_STACK_SIZE*.
+    StackTooSmall{
+        /// Stack size asked for.
+        stack_size: usize,
+    },
+    /// *Backend::Process* was made for other message or error types than the channel's.
+    #[cfg(feature = "process")]
+    BackendTypes,
+}
+
+impl fmt::Display for ConfigError{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        match self{
+            ConfigError::NoWorkers => write!(f, "There must be at least one worker thread"),
+            ConfigError::NoChannelSize => write!(f, "The channel size must be at least one"),
+            ConfigError::NotEnoughPackages{package_number, worker_number} => write!(f, "There's not enough packages ({}) for every worker ({}) to use", package_number, worker_number),
+            ConfigError::TooManyPackages{package_number, max_package_number} => write!(f, "Too many packages ({}) for the channels and workers to hold, at most {}", package_number, max_package_number),
+            ConfigError::StackTooSmall{stack_size} => write!(f, "Stack size {} is too small for a worker thread", stack_size),
+            #[cfg(feature = "process")]
+            ConfigError::BackendTypes => write!(f, "The process backend was made for other message types than the channel's"),
+        }
+    }
+}
+
+impl Error for ConfigError{}
+
+
+/// Why the last iteration through a *DeliveryService* returned None. Read it with *DeliveryService::last_stop_reason*.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum StopReason{
+    /// Every input fed was worked and returned.
+    Completed,
+    /// The run was cancelled before every input was worked.
+    Cancelled,
+    /// The channel is paused. The run goes on where it stopped after *DeliveryService::resume*.
+    Paused,
+    /// The channel failed. Results still expected are lost.
+    Error(KikError),
+}
+
+impl fmt::Display for StopReason{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        match self{
+            StopReason::Completed => write!(f, "Every input was worked"),
+            StopReason::Cancelled => write!(f, "The run was cancelled"),
+            StopReason::Paused => write!(f, "The channel is paused"),
+            StopReason::Error(error) => write!(f, "{}", error),
+        }
+    }
+}
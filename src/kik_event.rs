@@ -0,0 +1,229 @@
+//! # Event
+//!
+//! Notifications about the life of a *DeliveryService*, for supervisors that would rather react than poll.
+//!
+//! Each call to *DeliveryService::events* creates a new subscription. Every subscription receives every *PoolEvent* sent after it was created.
+//! The channels are unbounded, so a subscription that isn't read keeps its events in memory. Drop the *Receiver* to unsubscribe.
+//!
+//! The same events can be written to a log through *ChannelConfig::set_log_hook*. Nothing is printed otherwise, the workers start and stop silently.
+//!
+//! A *ChannelObserver* is the synchronous alternative, for wiring the channel into a logging or metrics stack. It's called right where things
+//! happen, including once for every message dispatched and worked, which events don't cover.
+//!
+//! With the **tracing** feature, the channel reports to *tracing* itself. Each worker thread runs inside a *worker* span, each message worked
+//! inside a *work* span with its message and worker ids, and an event is emitted when a queue stays full or a channel disconnects.
+//! An observer is still the way to bridge any other stack.
+//!
+//! *DeliveryService::subscribe_results* works the same way for the results themselves. Every result the channel yields is cloned for each
+//! subscription, so that a preview window and a file writer can both watch the same run while the owner iterates it.
+//!
+//!
+
+use std::sync::{Arc, Mutex, RwLock, PoisonError};
+use std::sync::mpsc::{Receiver, Sender, channel};
+use std::time::Duration;
+use std::fmt;
+
+use crate::kik_error::StopReason;
+
+/// Something that happened in a *DeliveryService*.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PoolEvent{
+    /// A worker thread started.
+    WorkerStarted{
+        /// Id of the worker.
+        worker_id: usize,
+    },
+    /// A worker thread finished, either because the channel was shut down or because it was removed with *DeliveryService::remove_workers*.
+    WorkerExited{
+        /// Id of the worker.
+        worker_id: usize,
+    },
+    /// A worker thread died from a panic. Panics inside *Message::try_work* are caught and don't end the thread, they're yielded by *DeliveryService::results* instead.
+    WorkerPanicked{
+        /// Id of the worker.
+        worker_id: usize,
+    },
+    /// A worker has been inside a message for longer than the heartbeat timeout without a beat. See *ChannelConfig::set_heartbeat_timeout*.
+    WorkerHung{
+        /// Id of the worker.
+        worker_id: usize,
+        /// How long since its last beat, when it was flagged.
+        silent_for: Duration,
+    },
+    /// A worker reported as hung beat again.
+    WorkerRecovered{
+        /// Id of the worker.
+        worker_id: usize,
+    },
+    /// An iteration that had work to do returned None.
+    BatchCompleted{
+        /// Why it ended. Same as *DeliveryService::last_stop_reason*.
+        stop_reason: StopReason,
+    },
+}
+
+impl fmt::Display for PoolEvent{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        match self{
+            PoolEvent::WorkerStarted{worker_id} => write!(f, "Worker {} started", worker_id),
+            PoolEvent::WorkerExited{worker_id} => write!(f, "Worker {} exited", worker_id),
+            PoolEvent::WorkerPanicked{worker_id} => write!(f, "Worker {} panicked", worker_id),
+            PoolEvent::WorkerHung{worker_id, silent_for} => write!(f, "Worker {} hung, silent for {:?}", worker_id, silent_for),
+            PoolEvent::WorkerRecovered{worker_id} => write!(f, "Worker {} recovered", worker_id),
+            PoolEvent::BatchCompleted{stop_reason} => write!(f, "Batch completed: {}", stop_reason),
+        }
+    }
+}
+
+/// Called with a line describing each *PoolEvent*, for writing it to a log. Set it with *ChannelConfig::set_log_hook*.
+pub type LogHook = Arc<dyn Fn(&str) + Send + Sync>;
+
+/// Callbacks for the life of the messages and workers of a *DeliveryService*. Register one with *DeliveryService::add_observer*.
+///
+/// Every method does nothing by default, so only the interesting ones need implementing. They're called on the thread where it happened:
+/// *on_dispatch*, *on_worker_hung* and *on_worker_recover* on the feeder's, the others on the worker's. Keep them short, the message or worker waits for them to return.
+///
+/// A message is identified by its dispatch order, which is unique for the life of the channel. A recycled message gets a new id for each input.
+pub trait ChannelObserver: Send + Sync{
+    /// The feeder sent a message to the workers.
+    fn on_dispatch(&self, _message_id: usize){}
+
+    /// A worker is about to work a message. Not called for cancelled messages, which are given back without being worked.
+    fn on_work_start(&self, _message_id: usize, _worker_id: usize){}
+
+    /// A worker finished a message, successfully or not, after spending the given time inside *Message::work*. Always follows *on_work_start*, on the same thread.
+    fn on_complete(&self, _message_id: usize, _work_time: Duration){}
+
+    /// The queue towards the workers, or back to the feeder, stayed full through every retry (see kik_backoff). The sender goes to sleep until there's room.
+    fn on_queue_full(&self, _message_id: usize){}
+
+    /// The feeder or a worker found the other side of its channel gone. The run ends with *KikError::Disconnected*, or the worker panics.
+    fn on_disconnect(&self){}
+
+    /// A worker thread started.
+    fn on_worker_spawn(&self, _worker_id: usize){}
+
+    /// A worker thread finished, because the channel was shut down or the worker was removed.
+    fn on_worker_exit(&self, _worker_id: usize){}
+
+    /// A worker thread died from a panic.
+    fn on_worker_panic(&self, _worker_id: usize){}
+
+    /// A worker went silent inside a message for longer than the heartbeat timeout. Called on the feeder's thread.
+    fn on_worker_hung(&self, _worker_id: usize, _silent_for: Duration){}
+
+    /// A worker reported as hung beat again. Called on the feeder's thread.
+    fn on_worker_recover(&self, _worker_id: usize){}
+}
+
+/// Senders for every subscription of a channel, and its observers. Shared between *DeliveryService*, its feeder and its workers.
+#[derive(Clone, Default)]
+pub struct EventSenders{
+    senders: Arc<Mutex<Vec<Sender<PoolEvent>>>>,
+    observers: Arc<RwLock<Vec<Arc<dyn ChannelObserver>>>>,
+    // Name of the channel and where to write its events, if anywhere.
+    name: Option<String>,
+    log_hook: Option<LogHook>,
+}
+
+impl EventSenders{
+    /// Create a list with no subscriptions. Every event is also written to the log hook, if there's one, naming the channel it came from.
+    pub fn new(name: Option<String>, log_hook: Option<LogHook>) -> Self{
+        EventSenders{
+            name,
+            log_hook,
+            ..Self::default()
+        }
+    }
+
+    /// Create a new subscription. It receives every event sent from now on.
+    pub fn subscribe(&self) -> Receiver<PoolEvent>{
+        let (tx, rx) = channel();
+        self.senders.lock().unwrap_or_else(PoisonError::into_inner).push(tx);
+        rx
+    }
+
+    /// Register an observer. It's called for everything that happens from now on.
+    pub fn observe(&self, observer: Arc<dyn ChannelObserver>){
+        self.observers.write().unwrap_or_else(PoisonError::into_inner).push(observer);
+    }
+
+    /// Send the event to every subscription, forgetting the ones whose receiver was dropped. Events about workers are passed to the observers too.
+    pub fn send(&self, event: PoolEvent){
+        match event{
+            PoolEvent::WorkerStarted{worker_id} => self.notify(|observer| observer.on_worker_spawn(worker_id)),
+            PoolEvent::WorkerExited{worker_id} => self.notify(|observer| observer.on_worker_exit(worker_id)),
+            PoolEvent::WorkerPanicked{worker_id} => self.notify(|observer| observer.on_worker_panic(worker_id)),
+            PoolEvent::WorkerHung{worker_id, silent_for} => self.notify(|observer| observer.on_worker_hung(worker_id, silent_for)),
+            PoolEvent::WorkerRecovered{worker_id} => self.notify(|observer| observer.on_worker_recover(worker_id)),
+            _ => {},
+        }
+        if let Some(log_hook) = &self.log_hook{
+            log_hook(&format!("{} (pool: {})", event, self.name.as_deref().unwrap_or("unnamed")));
+        }
+        let mut senders = self.senders.lock().unwrap_or_else(PoisonError::into_inner);
+        senders.retain(|sender| sender.send(event.clone()).is_ok());
+    }
+
+    /// Tell the observers a message was sent to the workers.
+    pub fn dispatched(&self, message_id: usize){
+        self.notify(|observer| observer.on_dispatch(message_id));
+    }
+
+    /// Tell the observers a worker is about to work a message.
+    pub fn started(&self, message_id: usize, worker_id: usize){
+        self.notify(|observer| observer.on_work_start(message_id, worker_id));
+    }
+
+    /// Tell the observers a message was worked.
+    pub fn completed(&self, message_id: usize, work_time: Duration){
+        self.notify(|observer| observer.on_complete(message_id, work_time));
+    }
+
+    /// Tell the observers a message couldn't be sent right away and its sender is going to sleep.
+    pub fn queue_full(&self, message_id: usize){
+        self.notify(|observer| observer.on_queue_full(message_id));
+    }
+
+    /// Tell the observers a channel was found disconnected.
+    pub fn disconnected(&self){
+        #[cfg(feature = "tracing")]
+        tracing::warn!("channel disconnected");
+        self.notify(|observer| observer.on_disconnect());
+    }
+
+    // Call every observer. Only takes a read lock, so workers don't wait on each other.
+    fn notify(&self, call: impl Fn(&dyn ChannelObserver)){
+        let observers = self.observers.read().unwrap_or_else(PoisonError::into_inner);
+        for observer in observers.iter(){
+            call(observer.as_ref());
+        }
+    }
+}
+
+/// Senders for every result subscription of a channel. Only the channel sends, from the thread iterating it.
+pub struct ResultSenders<T>{
+    senders: Vec<Sender<T>>,
+}
+
+impl<T> ResultSenders<T> where T: Clone{
+    /// Create a list with no subscriptions.
+    pub fn new() -> Self{
+        ResultSenders{
+            senders: Vec::new(),
+        }
+    }
+
+    /// Create a new subscription. It receives a clone of every result yielded from now on.
+    pub fn subscribe(&mut self) -> Receiver<T>{
+        let (tx, rx) = channel();
+        self.senders.push(tx);
+        rx
+    }
+
+    /// Send a clone of the result to every subscription, forgetting the ones whose receiver was dropped.
+    pub fn send(&mut self, data: &T){
+        self.senders.retain(|sender| sender.send(data.clone()).is_ok());
+    }
+}
@@ -8,11 +8,11 @@
 //! *kik_feeder* will check how many *Messages* are roaming through it's system (counted based on how many "gets" and "sends" were successful). If there are not enough *Messages*,
 //! it will send more in the system. If there are no *Message*s to send and no *Message*s to retrieve, return None.
 //! 
-//! If there are *Message*s to retrieve, it will block until a *Worker* leave it in the *deliverer* channel. A deadlock might occur if the thread panics while working.
+//! If there are *Message*s to retrieve, it will block (sleeping, not spinning) until a *Worker* leave it in the *deliverer* channel. A deadlock might occur if the thread panics while working.
 //! So be aware that the implementation of the *Message* relies completely on the user.
 //! 
 //! Once it retrieves a *Message* from the *deliverer*. The feeder will call the *Message*'s implementation of *clone_message_data* to get a copy of the *MessageData* to send back 
-//! to the iterator. Before returning the *MessageData*, it will try to reset the *Message* that it's holding with the next input waiting to be sent back to the system. 
+//! to the iterator. If the *Message* won't be recycled, *take_message_data* is called instead, moving the *MessageData* out. Before returning the *MessageData*, it will try to reset the *Message* that it's holding with the next input waiting to be sent back to the system. 
 //! This is done to reduce calls to memory management in the system.
 //! 
 //! 
@@ -26,223 +26,1624 @@
 //! saving stack space. Maybe the code would become so complex that it should be used in another crate entirely. Not sure yet.
 //! 
 //! 
-//! # Panics!
-//! Will panic if it tries to send a *Message* to *inserter* but receive a *std::sync::mpsc::TrySendError::Disconnected* error. The order for drop is *DeliveryService* then *FeederRecycler* then *Worker*.
+//! # Disconnects
+//! If it tries to send a *Message* to *inserter* but the workers are gone, the *Message* is dropped and the iteration ends with *KikError::Disconnected*. The order for drop is *DeliveryService* then *FeederRecycler* then *Worker*.
 //! When *DeliveryService* drops, all the others will do the same without panicking. But if channel is disconnected, then some unexpected event happened.
 //! 
 //! 
 
-use std::thread::{yield_now};
-use std::sync::mpsc::{Receiver, SyncSender, TrySendError, TryRecvError};
+use std::sync::Arc;
+use std::sync::mpsc::{Receiver, RecvTimeoutError, TryRecvError, TrySendError};
 use std::marker::PhantomData;
+use std::collections::{BTreeMap, VecDeque};
+use std::time::{Duration, Instant};
 use crate::kik_message::{MessageData, MessageInput, Message};
+use crate::kik_package::{MergeFn, Output, Package, Retrieved};
+use crate::kik_queue::WorkSender;
+use crate::kik_backoff::{Backoff, WaitStrategy};
+use crate::kik_adaptive::AdaptiveWindow;
+use crate::kik_pool::MessagePool;
+use crate::kik_report::{AllocStats, BatchStats, BatchReport, ChannelStats, MemoryStats, MetricsCollector, Throughput, ThroughputHistory};
+use crate::kik_alloc::CountingAllocator;
+use crate::kik_error::{WorkError, FailureReason, KikError, StopReason, Timeout};
+use crate::kik_scheduler::{Scheduler, LifoScheduler, FifoScheduler, DispatchOrder, Priority};
+use crate::kik_context::{CancellationToken, ProgressBoard, SharedContext, WorkContext};
+use crate::kik_worker::work_package;
+use crate::kik_event::EventSenders;
+use crate::kik_heartbeat::{Heartbeat, HeartbeatMonitor};
+use crate::kik_channel::ChannelConfig;
+use crate::kik_sender::{BatchId, FeedReceipt};
+
+/// Called by the feeder for the next input whenever a message is free to be sent. Returning None means it's exhausted.
+pub type InputGenerator<R> = Box<dyn FnMut() -> Option<R> + Send>;
+
+/// Called by the feeder before retrieving each result, returning how much memory pressure the host application is under, from 0.0 (none) to 1.0 (the most).
+/// Set with *ChannelConfig::set_memory_pressure*.
+pub type PressureProbe = Arc<dyn Fn() -> f64 + Send + Sync>;
+
+/// Called by the feeder whenever a result comes back, with how many results the run returned so far and how many it has in total.
+/// Set by *DeliveryService::on_progress*.
+pub type ProgressCallback = Box<dyn FnMut(usize, usize) + Send>;
+
+/// Builds each new message instead of *Message::new*. Set by *DeliveryService::set_message_template*, which clones the template in it.
+pub type MessageFactory<S> = Box<dyn Fn() -> S + Send + Sync>;
+
+/// Builds each new message when there's no template. *Message::new* for messages with *Default*, or the one given to *DeliveryService::try_with_builder*.
+pub type MessageBuilder<S> = fn() -> S;
 
 /// Used by kik_channel for inserting/retrieving messages. It's public, but not meant to be used directly.
-pub struct FeederRecycler<T, R, S>  where 
+pub struct FeederRecycler<T, R, S, E>  where 
 T: MessageData + 'static,
 R: MessageInput<T> + 'static,
-S: Message<T, R> + Sync + Send + Clone + 'static,
+S: Message<T, R, E> + Sync + Send + 'static,
+E: Send + 'static,
 {
-    id: usize,
+    // Name of the channel, attached to reports.
+    name: Option<String>,
     // counts how many messages are to be recovered from the system
     messages: usize,
     // Holds how many max messages should be in the system
     package_number: usize,
-    input_vec: Vec<R>,
+    // Messages allowed in flight right now. Lower than package_number while under memory pressure.
+    package_limit: usize,
+    // Moves package_number within a band, if set.
+    adaptive: Option<AdaptiveWindow>,
+    // Only Some if a memory pressure probe is set.
+    pressure: Option<PressureProbe>,
+    // While true, no new messages are built if there are others to recycle.
+    under_pressure: bool,
+    // Holds the inputs waiting to be sent and decides which one goes next.
+    scheduler: Box<dyn Scheduler<R>>,
+    // Batch of every input in the scheduler, if they're all from the same one. Used when the scheduler doesn't keep the batches itself.
+    scheduled_batch: Option<BatchId>,
+    // Reused for pushing a batch into the scheduler.
+    staging: Vec<R>,
+    // Inputs fed with Priority::High and Priority::Low, with their batch. Dispatched before and after the scheduler's, in the order they were fed.
+    high_lane: VecDeque<(R, BatchId)>,
+    low_lane: VecDeque<(R, BatchId)>,
+    // How many results each batch still has to give, oldest first. Forgotten once they reach zero.
+    batch_remaining: VecDeque<(BatchId, usize)>,
+    // Batch of the last result returned by next or try_next.
+    last_batch: Option<BatchId>,
+    // Outputs of a message that fanned out, with their batch. Returned before anything else.
+    fanned_out: VecDeque<(Option<BatchId>, T)>,
+    // Sequence number of the last result returned by next or try_next.
+    last_sequence: usize,
+    // Called for each new message instead of the builder, if set.
+    template: Option<MessageFactory<S>>,
+    // Builds each new message when there's no template.
+    builder: MessageBuilder<S>,
+    // Messages built ahead of time, loaned instead of building new ones. Only Some if enabled in the config.
+    pool: Option<MessagePool<S>>,
+    // Pulled from, in order, once the scheduler is empty. Dropped once they return None.
+    generators: VecDeque<InputGenerator<R>>,
+    // Inputs fed with a receipt. They skip the scheduler and go first, in the order they were fed.
+    acked_inputs: VecDeque<(R, FeedReceipt)>,
+    // Input taken from the scheduler but held back because it was too heavy to be sent yet.
+    held_input: Option<R>,
+    // Receipt of the held input, if it came with one.
+    held_receipt: Option<FeedReceipt>,
+    // Receipt of the input taken by next_input, told once the message carrying it is sent.
+    taken_receipt: Option<FeedReceipt>,
+    // Batches of the held input and of the input taken by next_input. The last one is stamped on the package carrying it.
+    held_batch: Option<BatchId>,
+    taken_batch: Option<BatchId>,
+    // Routing key of the input taken by next_input.
+    taken_key: Option<u64>,
+    // Deadline of the batches fed with one. Their inputs still queued past it are dropped instead of sent.
+    deadlines: BTreeMap<BatchId, Instant>,
+    // Deadline of the input taken by next_input, stamped on the package carrying it.
+    taken_deadline: Option<Instant>,
+    // How many inputs were dropped for being past their deadline.
+    expired: usize,
+    // Results that came back since the run started.
+    run_done: usize,
+    // The last run ended. The next result starts a new one.
+    run_over: bool,
+    // Told of every result that comes back. Only Some if set by the channel.
+    progress_callback: Option<ProgressCallback>,
+    // A message fanned out, so a message may no longer give a single result.
+    fanned: bool,
+    // Batch whose inputs are packed together, how many per message and how the worker merges them. Set by map_reduce.
+    merging: Option<(BatchId, usize, MergeFn<S>)>,
+    // Limit for the total weight of the messages away with the workers.
+    max_weight: Option<usize>,
+    // How many times a failing message is tried before its input is set aside. None sends errors back right away.
+    max_attempts: Option<usize>,
+    // Inputs set aside after their last attempt, until take_failed.
+    quarantine: Vec<(R, FailureReason<E>)>,
+    // Total weight of the messages away with the workers.
+    outstanding_weight: usize,
+    // Buffers given back by the consumer, swapped into recycled messages instead of cloning their data. At most package_number.
+    spare_buffers: Vec<T>,
+    // Bytes the messages away with the workers may hold.
+    memory_budget: Option<usize>,
+    // Largest size of a message seen so far, counting its result. None until the first result comes back.
+    message_size: Option<usize>,
+    // Only Some while a report is being collected.
+    stats: Option<BatchStats>,
+
+    // If true, results are given back in the same order that the inputs were fed.
+    ordered: bool,
+    // Sequence number for the next message sent to the workers.
+    next_sequence: usize,
+    // Sequence number of the next result to be returned in ordered mode.
+    next_expected: usize,
+    // Results that arrived before the ones that were sent earlier. Only used in ordered mode.
+    reorder_buffer: BTreeMap<usize, Retrieved<T, E>>,
+    // If true, the first result of a run is returned as soon as it arrives, instead of after the package window is filled.
+    fast_first_result: bool,
+    // Successful results older than this are discarded instead of returned.
+    result_ttl: Option<Duration>,
+    // How many results were discarded because of result_ttl.
+    discarded: usize,
+    // When the last message retrieved was completed by its worker. Cancelled ones don't count.
+    last_completed_at: Option<Instant>,
+    // Shared with the workers. When cancelled, the current run is thrown away.
+    cancellation: CancellationToken,
+    // When inline, messages are worked on this thread as they are sent, instead of going through the channels.
+    inline: bool,
+    // Messages worked inline, waiting to be retrieved as if they came from the workers.
+    inline_done: VecDeque<Package<S, E>>,
+    // A push found no workers. Nothing sent after it will come back.
+    workers_gone: bool,
+    inline_context: WorkContext,
+    // Why the last iteration ended. None until the first one ends.
+    stop_reason: Option<StopReason>,
+    // Slot given to the next message built.
+    next_slot: usize,
+    // Only Some if memory tracking is enabled.
+    memory: Option<MemoryStats>,
+    // Only Some if throughput history is enabled.
+    throughput: Option<ThroughputHistory>,
+    // Only Some if allocation tracking is enabled.
+    alloc: Option<AllocStats>,
+    // Only Some if metrics are enabled.
+    metrics: Option<MetricsCollector>,
+    // How to wait for results, and for room to send messages.
+    wait_strategy: WaitStrategy,
+    // How long to wait for a result before giving up on the workers. None waits forever.
+    stall_timeout: Option<Duration>,
+    // Listens to the beats of the workers. Only Some if a heartbeat timeout was set, and not inline.
+    heartbeat: Option<HeartbeatMonitor>,
+    // Subscriptions and observers of the channel. Handed to it by get_events.
+    events: EventSenders,
 
-    tx_inserter: SyncSender<S>,
-    rx_deliverer: Receiver<S>,
+    // None after the feeder is closed, which disconnects the workers.
+    tx_inserter: Option<WorkSender<Package<S, E>>>,
+    rx_deliverer: Receiver<Package<S, E>>,
+    // Packages taken out of rx_deliverer by wait_for_package or get_ready_results, handed out by the next receives in the same order.
+    delivered: VecDeque<Package<S, E>>,
 
     // PhantomData is to tell the compiler that generics T and R exist in the implementation but are not stored in the struct
     resource_type: PhantomData<T>,
     resource_type2: PhantomData<R>,
 }
 
-impl<T, R, S> FeederRecycler<T, R, S> where 
+impl<T, R, S, E> FeederRecycler<T, R, S, E> where 
 T: MessageData + 'static,
 R: MessageInput<T> + 'static,
-S: Message<T, R> + Sync + Send + Clone + 'static,
+S: Message<T, R, E> + Sync + Send + 'static,
+E: Send + 'static,
 {
-    /// Constructs a new instance of feeder with default values.
-    pub fn new(id: usize, package_number: usize, tx_inserter: SyncSender<S>, rx_deliverer: Receiver<S>)->Self{
+    /// Constructs a new instance of feeder with the settings in config. If config is ordered, results will be returned in the same order as the inputs were fed.
+    /// The shared context and the progress board are only used when running inline. builder builds each message when there's no template.
+    pub fn new(config: &ChannelConfig, cancellation: CancellationToken, shared_context: SharedContext, progress_board: ProgressBoard, tx_inserter: WorkSender<Package<S, E>>, rx_deliverer: Receiver<Package<S, E>>, builder: MessageBuilder<S>)->Self{
+        let ordered = config.get_ordered();
+        let adaptive = config.get_adaptive_packages().map(|(min, max)| AdaptiveWindow::new(min, max));
+        let package_number = adaptive.as_ref().map_or(config.get_package_number(), |adaptive| adaptive.clamp(config.get_package_number()));
+        // Enough for the most messages that can be in flight.
+        let pool_size = adaptive.as_ref().map_or(package_number, |adaptive| adaptive.get_band().1);
         FeederRecycler{
-            id,
-            input_vec: Vec::new(),
+            name: config.get_name().map(String::from),
+            scheduler: match config.get_dispatch_order(){
+                DispatchOrder::Fifo => Box::new(FifoScheduler::new()),
+                DispatchOrder::Lifo => Box::new(LifoScheduler::new()),
+            },
+            scheduled_batch: None,
+            staging: Vec::new(),
+            high_lane: VecDeque::new(),
+            low_lane: VecDeque::new(),
+            batch_remaining: VecDeque::new(),
+            last_batch: None,
+            fanned_out: VecDeque::new(),
+            last_sequence: 0,
+            template: None,
+            builder,
+            pool: if config.get_message_pool() { Some(Self::filled_pool(pool_size, &None, builder)) } else { None },
+            generators: VecDeque::new(),
+            acked_inputs: VecDeque::new(),
+            held_input: None,
+            held_receipt: None,
+            taken_receipt: None,
+            held_batch: None,
+            taken_batch: None,
+            taken_key: None,
+            deadlines: BTreeMap::new(),
+            taken_deadline: None,
+            expired: 0,
+            merging: None,
+            max_weight: config.get_max_weight(),
+            max_attempts: config.get_quarantine(),
+            quarantine: Vec::new(),
+            outstanding_weight: 0,
+            spare_buffers: Vec::new(),
+            memory_budget: config.get_memory_budget(),
+            message_size: None,
+            stats: None,
             package_number,
+            package_limit: package_number,
+            adaptive,
+            pressure: config.get_memory_pressure().cloned(),
+            under_pressure: false,
+
+            ordered,
+            next_sequence: 0,
+            next_expected: 0,
+            reorder_buffer: BTreeMap::new(),
+            fast_first_result: config.get_fast_first_result(),
+            result_ttl: config.get_result_ttl(),
+            discarded: 0,
+            last_completed_at: None,
+            inline: config.get_inline(),
+            inline_done: VecDeque::new(),
+            workers_gone: false,
+            // Only the inline worker needs its state.
+            inline_context: if config.get_inline() { WorkContext::for_worker(0, cancellation.clone(), shared_context, config.get_worker_context(), None, progress_board) } else { WorkContext::new(cancellation.clone(), shared_context) },
+            cancellation,
+            stop_reason: None,
+            next_slot: 0,
+            memory: if config.get_memory_tracking() { Some(MemoryStats::new(config.get_name().map(String::from))) } else { None },
+            throughput: config.get_throughput_history().map(|history| ThroughputHistory::new(config.get_name().map(String::from), history)),
+            alloc: if config.get_alloc_tracking() { Some(AllocStats::new(config.get_name().map(String::from))) } else { None },
+            wait_strategy: config.get_wait_strategy(),
+            stall_timeout: config.get_stall_timeout(),
+            heartbeat: config.get_heartbeat_timeout().filter(|_| !config.get_inline()).map(HeartbeatMonitor::new),
+            events: EventSenders::new(config.get_name().map(String::from), config.get_log_hook().cloned()),
+            metrics: if config.get_metrics() { Some(MetricsCollector::new(config.get_name().map(String::from))) } else { None },
 
             messages: 0,
-            tx_inserter,
+            run_done: 0,
+            run_over: false,
+            progress_callback: None,
+            fanned: false,
+            tx_inserter: Some(tx_inserter),
             rx_deliverer,
+            delivered: VecDeque::new(),
 
             // ::< used to specify type of const arguments
             resource_type: PhantomData::<T>,
             resource_type2: PhantomData::<R>,            
         }
     }
-    /// Append a new vec of input values to iterate later on.
-    pub fn append_input(&mut self, input_vec: &mut Vec<R>){
-        self.input_vec.append(input_vec);
+    /// Append the inputs of a batch to iterate later on, into the lane of their priority.
+    pub fn append_batch<I>(&mut self, batch: BatchId, priority: Priority, inputs: I) where I: Iterator<Item = R>{
+        let mark = self.alloc_mark();
+        let lane = match priority{
+            Priority::High => &mut self.high_lane,
+            Priority::Low => &mut self.low_lane,
+            Priority::Normal => {
+                self.staging.extend(inputs);
+                self.batch_remaining.push_back((batch, self.staging.len()));
+                self.schedule_staging(Some(batch));
+                self.record_alloc(mark, AllocStats::record_feed);
+                return;
+            },
+        };
+        let before = lane.len();
+        lane.extend(inputs.map(|input| (input, batch)));
+        let count = lane.len() - before;
+        self.batch_remaining.push_back((batch, count));
+        self.record_alloc(mark, AllocStats::record_feed);
+    }
+
+    /// Push the staged inputs into the scheduler, keeping track of whether every input in it comes from the same batch.
+    fn schedule_staging(&mut self, batch: Option<BatchId>){
+        if self.staging.is_empty(){
+            return;
+        }
+        self.scheduled_batch = match batch{
+            Some(batch) if self.scheduler.is_empty() || self.scheduled_batch == Some(batch) => Some(batch),
+            _ => None,
+        };
+        match batch{
+            Some(batch) => self.scheduler.push_batch(&mut self.staging, batch),
+            None => self.scheduler.push(&mut self.staging),
+        }
+    }
+
+    /// Take the next queued input, with its batch if it's known: the high lane first, then the scheduler, then the low lane.
+    fn next_queued(&mut self) -> Option<(R, Option<BatchId>)>{
+        if let Some((input, batch)) = self.high_lane.pop_front(){
+            return Some((input, Some(batch)));
+        }
+        if let Some((input, batch)) = self.scheduler.next_batch(){
+            return Some((input, batch.or(self.scheduled_batch)));
+        }
+        self.low_lane.pop_front().map(|(input, batch)| (input, Some(batch)))
+    }
+
+    /// How many results of the batch haven't been returned yet. Zero once the run it was fed in is over, even if some of its inputs were shed.
+    pub fn get_batch_remaining(&self, batch: BatchId) -> usize{
+        match self.batch_remaining.binary_search_by_key(&batch, |(id, _)| *id){
+            Ok(index) => self.batch_remaining[index].1,
+            Err(_) => 0,
+        }
+    }
+
+    /// Batch of the last result returned by *next* or *try_next*. None if it had none.
+    pub fn get_last_batch(&self) -> Option<BatchId>{
+        self.last_batch
+    }
+
+    /// Sequence number of the last result returned by *next* or *try_next*. Inputs are numbered in the order they're sent to the workers.
+    pub fn get_last_sequence(&self) -> usize{
+        self.last_sequence
+    }
+
+    /// Count a result of the batch as returned or discarded.
+    fn count_batch_result(&mut self, batch: Option<BatchId>){
+        self.last_batch = batch;
+        if let Some(batch) = batch{
+            self.count_batch_inputs(batch, 1);
+        }
+        if self.run_over{
+            self.run_over = false;
+            self.run_done = 0;
+        }
+        self.run_done += 1;
+        if self.progress_callback.is_some(){
+            let (done, total) = self.get_progress();
+            if let Some(callback) = &mut self.progress_callback{
+                callback(done, total);
+            }
+        }
+    }
+
+    /// How many results the current run returned or discarded, and how many it has in total. The total grows as more is fed, and is only
+    /// an estimate with generators (one each until exhausted) and messages that fan out (one until they come back). Once a run is over,
+    /// both stay the same until the next one is fed.
+    pub fn get_progress(&self) -> (usize, usize){
+        let remaining = self.get_remaining_messages();
+        let done = if self.run_over && remaining > 0 { 0 } else { self.run_done };
+        (done, done + remaining)
+    }
+
+    /// Set the callback told of every result that comes back, or remove it with None.
+    pub fn set_progress_callback(&mut self, callback: Option<ProgressCallback>){
+        self.progress_callback = callback;
+    }
+
+    /// Count inputs of the batch as done, without a result of their own.
+    fn count_batch_inputs(&mut self, batch: BatchId, count: usize){
+        if let Ok(index) = self.batch_remaining.binary_search_by_key(&batch, |(id, _)| *id){
+            let remaining = &mut self.batch_remaining[index].1;
+            *remaining = remaining.saturating_sub(count);
+        }
+        while let Some((_, 0)) = self.batch_remaining.front(){
+            self.batch_remaining.pop_front();
+        }
+        // Deadlines of batches that are done aren't needed anymore.
+        match self.batch_remaining.front(){
+            Some((oldest, _)) => {
+                let oldest = *oldest;
+                self.deadlines.retain(|batch, _| *batch >= oldest);
+            },
+            None => self.deadlines.clear(),
+        }
+    }
+
+    /// Drop the inputs of the batch that are still queued once the deadline passes. See *DeliveryService::feed_with_deadline*.
+    pub fn set_deadline(&mut self, batch: BatchId, deadline: Instant){
+        self.deadlines.insert(batch, deadline);
+    }
+
+    /// True if the input's batch has a deadline and it's gone. Counts the input as done in its batch then.
+    fn expire_input(&mut self, batch: Option<BatchId>) -> bool{
+        let batch = match batch{
+            Some(batch) => batch,
+            None => return false,
+        };
+        match self.deadlines.get(&batch){
+            Some(deadline) if *deadline <= Instant::now() => {
+                self.expired += 1;
+                self.count_batch_inputs(batch, 1);
+                true
+            },
+            _ => false,
+        }
+    }
+
+    /// How many inputs were dropped without being sent because their deadline had passed.
+    pub fn get_expired_inputs(&self) -> usize{
+        self.expired
+    }
+
+    /// Forget every batch that was queued or being worked. Used when a run is thrown away.
+    fn clear_batches(&mut self){
+        self.batch_remaining.clear();
+        self.deadlines.clear();
+        self.held_batch = None;
+    }
+
+    /// Append inputs that skip the scheduler, returning a receipt that tells when they've all been sent. Borrowed vector will become empty.
+    pub fn append_input_ack(&mut self, input_vec: &mut Vec<R>) -> FeedReceipt{
+        let mark = self.alloc_mark();
+        let receipt = FeedReceipt::new(input_vec.len());
+        self.acked_inputs.extend(input_vec.drain(..).map(|input| (input, receipt.clone())));
+        self.record_alloc(mark, AllocStats::record_feed);
+        receipt
+    }
+
+    /// Throw away the inputs fed with a receipt, and the held one if it has a receipt, telling their receipts. Returns how many were thrown away.
+    fn abandon_acked_inputs(&mut self) -> usize{
+        let mut abandoned: usize = 0;
+        if let Some(receipt) = self.held_receipt.take(){
+            self.held_input = None;
+            receipt.abandon_one();
+            abandoned += 1;
+        }
+        for (_, receipt) in self.acked_inputs.drain(..){
+            receipt.abandon_one();
+            abandoned += 1;
+        }
+        abandoned
+    }
+
+    /// Take every input that wasn't sent yet, in the order they would have been sent. Generators are left alone.
+    /// Receipts are told that their inputs were abandoned, since they'll never be sent by this feeder.
+    pub fn take_queued_inputs(&mut self) -> Vec<R>{
+        let mut input_vec: Vec<R> = Vec::with_capacity(self.acked_inputs.len() + self.high_lane.len() + self.scheduler.len() + self.low_lane.len() + 1);
+        if let Some(receipt) = self.held_receipt.take(){
+            receipt.abandon_one();
+        }
+        input_vec.extend(self.held_input.take());
+        for (input, receipt) in self.acked_inputs.drain(..){
+            receipt.abandon_one();
+            input_vec.push(input);
+        }
+        while let Some((input, _)) = self.next_queued(){
+            input_vec.push(input);
+        }
+        self.clear_batches();
+        input_vec
+    }
+
+    /// Change how many messages can be in the system at once. Used by kik_channel when workers are added or removed.
+    /// If there are more messages than that, the extra ones are dropped as they come back instead of being recycled.
+    /// An adaptive band moves by as many packages as the package number did.
+    pub fn set_package_number(&mut self, package_number: usize){
+        if let Some(adaptive) = &mut self.adaptive{
+            adaptive.shift(package_number as isize - self.package_number as isize);
+        }
+        self.package_number = package_number;
+        // The messages away come back to the pool, only the ones missing are built.
+        let pool_size = self.get_pool_size();
+        if let Some(pool) = &mut self.pool{
+            pool.set_capacity(pool_size);
+            let (template, builder) = (&self.template, self.builder);
+            pool.fill(pool_size.saturating_sub(self.messages), || Self::build_from(template, builder));
+        }
+        self.refresh_package_limit();
+    }
+
+    /// The band the package number moves in, as (min, max). None if it's fixed.
+    pub fn get_adaptive_band(&self) -> Option<(usize, usize)>{
+        self.adaptive.as_ref().map(AdaptiveWindow::get_band)
+    }
+
+    /// Returns how many messages can be in the system at once.
+    pub fn get_package_number(&self) -> usize{
+        self.package_number
+    }
+
+    /// Returns how many messages are allowed in the system right now. Same as the package number unless under memory pressure.
+    pub fn get_package_limit(&self) -> usize{
+        self.package_limit
+    }
+
+    /// Ask the memory pressure probe, if there's one, how many messages are allowed in the system now. Always at least one, so the run can go on.
+    fn refresh_package_limit(&mut self){
+        let pressure = match &self.pressure{
+            Some(probe) => probe().clamp(0.0, 1.0),
+            None => 0.0,
+        };
+        // NaN becomes 0 here, same as no pressure.
+        let shed = (self.package_number as f64 * pressure) as usize;
+        self.package_limit = self.package_number.saturating_sub(shed).max(1);
+        self.under_pressure = pressure > 0.0;
+    }
+
+    /// Append a generator to pull inputs from once the queued inputs run out.
+    pub fn append_generator(&mut self, generator: InputGenerator<R>){
+        let mark = self.alloc_mark();
+        self.generators.push_back(generator);
+        self.record_alloc(mark, AllocStats::record_feed);
+    }
+
+    /// Pull the next input from the generators, dropping the ones that are exhausted.
+    fn generate_input(&mut self) -> Option<R>{
+        while let Some(generator) = self.generators.front_mut(){
+            match generator(){
+                Some(input) => return Some(input),
+                None => {
+                    self.generators.pop_front();
+                },
+            }
+        }
+        None
+    }
+
+    /// Replace the scheduler. Inputs queued in the old one are moved to the new one, in the order the old one would dispatch them, keeping their batches.
+    pub fn set_scheduler(&mut self, scheduler: Box<dyn Scheduler<R>>){
+        let mut old_scheduler = std::mem::replace(&mut self.scheduler, scheduler);
+        let mut inputs: Vec<(R, Option<BatchId>)> = Vec::with_capacity(old_scheduler.len() + 1);
+        // A held input with a receipt stays where it is, or its receipt would be lost.
+        if self.held_receipt.is_none(){
+            if let Some(input) = self.held_input.take(){
+                inputs.push((input, self.held_batch.take()));
+            }
+        }
+        while let Some((input, batch)) = old_scheduler.next_batch(){
+            inputs.push((input, batch.or(self.scheduled_batch)));
+        }
+        // Consecutive inputs of the same batch are pushed together.
+        let mut inputs = inputs.into_iter().peekable();
+        while let Some((input, batch)) = inputs.next(){
+            self.staging.push(input);
+            while let Some((input, _)) = inputs.next_if(|(_, next_batch)| *next_batch == batch){
+                self.staging.push(input);
+            }
+            self.schedule_staging(batch);
+        }
+    }
+
+    /// Set what builds each new message, or None to go back to the builder. Messages already built are kept.
+    /// The pool, if there's one, is filled again from the new template.
+    pub fn set_message_template(&mut self, template: Option<MessageFactory<S>>){
+        self.template = template;
+        let pool_size = self.get_pool_size();
+        if let Some(pool) = &mut self.pool{
+            pool.clear();
+            let (template, builder) = (&self.template, self.builder);
+            pool.fill(pool_size, || Self::build_from(template, builder));
+        }
+    }
+
+    /// A message from the template if there's one, or from the builder.
+    pub fn build_message(&self) -> S{
+        Self::build_from(&self.template, self.builder)
+    }
+
+    fn build_from(template: &Option<MessageFactory<S>>, builder: MessageBuilder<S>) -> S{
+        match template{
+            Some(template) => template(),
+            None => builder(),
+        }
+    }
+
+    // A pool holding pool_size messages, ready to be loaned.
+    fn filled_pool(pool_size: usize, template: &Option<MessageFactory<S>>, builder: MessageBuilder<S>) -> MessagePool<S>{
+        let mut pool = MessagePool::new(pool_size);
+        pool.fill(pool_size, || Self::build_from(template, builder));
+        pool
+    }
+
+    // The most messages that can be in flight: the package number, or the top of the adaptive band.
+    fn get_pool_size(&self) -> usize{
+        self.adaptive.as_ref().map_or(self.package_number, |adaptive| adaptive.get_band().1)
+    }
+
+    /// How many messages are waiting in the pool. Zero without a pool.
+    pub fn get_pooled_messages(&self) -> usize{
+        self.pool.as_ref().map_or(0, MessagePool::len)
+    }
+
+    /// How many messages had to be built because the pool was empty. Zero without a pool.
+    pub fn get_pool_misses(&self) -> usize{
+        self.pool.as_ref().map_or(0, MessagePool::get_misses)
+    }
+
+    /// Start collecting data for a new *BatchReport*. Anything collected before is discarded.
+    pub fn start_report(&mut self){
+        self.stats = Some(BatchStats::new(self.name.clone()));
+    }
+
+    /// Stop collecting data and build the report. Returns None if *start_report* wasn't called.
+    pub fn finish_report(&mut self) -> Option<BatchReport>{
+        self.stats.take().map(BatchStats::finish)
+    }
+
+    /// Memory observed for each message slot. None if memory tracking is disabled.
+    pub fn get_memory_stats(&self) -> Option<&MemoryStats>{
+        self.memory.as_ref()
+    }
+
+    /// Allocations counted on each path. None if allocation tracking is disabled.
+    pub fn get_alloc_stats(&self) -> Option<&AllocStats>{
+        self.alloc.as_ref()
+    }
+
+    /// Set the allocation counts back to zero. Does nothing if allocation tracking is disabled.
+    pub fn reset_alloc_stats(&mut self){
+        if let Some(alloc) = &mut self.alloc{
+            alloc.reset();
+        }
+    }
+
+    /// Allocations made by this thread so far, and how many of them were already given a path. None if allocation tracking is disabled.
+    pub fn alloc_mark(&self) -> Option<(usize, usize)>{
+        let alloc = self.alloc.as_ref()?;
+        Some((CountingAllocator::get_thread_allocations(), alloc.get_total_allocations()))
+    }
+
+    /// Give the allocations made since the mark to a path. The ones already given to another path in between are left out.
+    pub fn record_alloc(&mut self, mark: Option<(usize, usize)>, record: fn(&mut AllocStats, usize)){
+        let (allocations, counted) = match mark{
+            Some(mark) => mark,
+            None => return,
+        };
+        if let Some(alloc) = &mut self.alloc{
+            let new_allocations = CountingAllocator::get_thread_allocations().saturating_sub(allocations);
+            let new_counted = alloc.get_total_allocations() - counted;
+            record(alloc, new_allocations.saturating_sub(new_counted));
+        }
+    }
+
+    /// The subscriptions and observers the feeder reports to. Clones share them, for the channel and its workers.
+    pub fn get_events(&self) -> EventSenders{
+        self.events.clone()
+    }
+
+    /// Timings recorded so far. None if metrics are disabled.
+    pub fn get_stats(&self) -> Option<ChannelStats>{
+        self.metrics.as_ref().map(MetricsCollector::snapshot)
+    }
+
+    /// Forget the timings recorded so far. Does nothing if metrics are disabled.
+    pub fn reset_stats(&mut self){
+        if let Some(metrics) = &mut self.metrics{
+            metrics.reset();
+        }
+    }
+
+    /// Rates over the last window. None if throughput history is disabled.
+    pub fn get_throughput(&self, window: Duration) -> Option<Throughput>{
+        self.throughput.as_ref().map(|throughput| throughput.measure(window))
+    }
+
+    /// Loans a message from the pool, or builds a new one from the template if there's one, and gives it the input. Returns it together with its new slot.
+    fn new_message(&mut self, input: R) -> (S, usize){
+        let mark = self.alloc_mark();
+        let mut new_message: S = match self.pool.as_mut().and_then(MessagePool::loan){
+            Some(message) => message,
+            None => {
+                if let Some(stats) = &mut self.stats{
+                    stats.record_allocated();
+                }
+                self.build_message()
+            },
+        };
+        new_message.set_input(input);
+        self.next_slot += 1;
+        self.record_alloc(mark, AllocStats::record_dispatch);
+        (new_message, self.next_slot)
+    }
+
+    /// Take the next input to be dispatched, together with its weight. Returns None if there's none, or if the next one would go over the max weight.
+    /// In the last case the input is held back until enough messages are retrieved. With no weight away, any input is allowed, so a heavy one can't block the run.
+    /// Inputs past their deadline are dropped on the way.
+    fn next_input(&mut self) -> Option<(R, usize)>{
+        let (input, receipt, batch) = loop{
+            let (input, receipt, batch): (R, Option<FeedReceipt>, Option<BatchId>) = match self.held_input.take(){
+                Some(input) => (input, self.held_receipt.take(), self.held_batch.take()),
+                None => match self.acked_inputs.pop_front(){
+                    Some((input, receipt)) => (input, Some(receipt), None),
+                    None => match self.next_queued(){
+                        Some((input, batch)) => (input, None, batch),
+                        None => (self.generate_input()?, None, None),
+                    },
+                },
+            };
+            if !self.expire_input(batch){
+                break (input, receipt, batch);
+            }
+        };
+        let weight = input.weight();
+        let over_weight = match self.max_weight{
+            Some(max_weight) => self.outstanding_weight > 0 && self.outstanding_weight + weight > max_weight,
+            None => false,
+        };
+        if over_weight || self.over_memory_budget(){
+            self.held_input = Some(input);
+            self.held_receipt = receipt;
+            self.held_batch = batch;
+            return None;
+        }
+        // Counted right away, since the input is always sent after being taken.
+        self.outstanding_weight += weight;
+        self.taken_receipt = receipt;
+        self.taken_batch = batch;
+        self.taken_deadline = batch.and_then(|batch| self.deadlines.get(&batch).copied());
+        self.taken_key = input.routing_key();
+        Some((input, weight))
+    }
+
+    /// Total weight of the messages away with the workers.
+    pub fn get_outstanding_weight(&self) -> usize{
+        self.outstanding_weight
+    }
+
+    /// Keep a buffer for the next message recycled, see *Message::swap_message_data*. Dropped if package_number buffers are already waiting.
+    pub fn return_buffer(&mut self, data: T){
+        if self.spare_buffers.len() < self.package_number{
+            self.spare_buffers.push(data);
+        }
+    }
+
+    /// How many buffers given back are waiting to be swapped into a message.
+    pub fn get_spare_buffers(&self) -> usize{
+        self.spare_buffers.len()
+    }
+
+    /// True if one more message in flight would go past the memory budget. There's always room for one.
+    fn over_memory_budget(&self) -> bool{
+        match (self.memory_budget, self.message_size){
+            (None, _) => false,
+            _ if self.messages == 0 => false,
+            (Some(_), None) => true,
+            (Some(budget), Some(size)) => (self.messages + 1).saturating_mul(size) > budget,
+        }
+    }
+
+    /// Estimated bytes held by the messages away with the workers. Zero without a memory budget.
+    pub fn get_in_flight_memory(&self) -> usize{
+        match self.memory_budget{
+            Some(_) => self.messages.saturating_mul(self.message_size.unwrap_or(0)),
+            None => 0,
+        }
+    }
+
+    /// Remember the size of a message that came back, if it's the largest so far. Only needed for the memory budget.
+    fn record_message_size(&mut self, payload_size: usize, result: &Result<Output<T>, WorkError<E>>){
+        if self.memory_budget.is_none(){
+            return;
+        }
+        let data_size = match result{
+            Ok(Output::FannedOut(outputs)) => outputs.iter().map(MessageData::size_hint).sum(),
+            Ok(Output::Data(data)) => data.size_hint(),
+            Err(_) => 0,
+        };
+        let size = payload_size.max(data_size);
+        self.message_size = Some(self.message_size.map_or(size, |largest| largest.max(size)));
     }
 
     /// Send a 'work' message to all the workers.
-    fn send_message(&mut self, message: S){
+    fn send_message(&mut self, message: S, slot: usize, weight: usize){
+        let mark = self.alloc_mark();
+        // The message is moved into the package once. Each failed attempt gives the same package back.
+        let mut package = Package::new(message, self.next_sequence, slot, weight, self.taken_batch.take());
+        package.key = self.taken_key.take();
+        package.deadline = self.taken_deadline.take();
+        if let Some((batch, per_message, merge)) = self.merging{
+            if package.batch == Some(batch){
+                package.merge = Some(merge);
+                self.take_merged(&mut package, per_message);
+            }
+        }
+        // No threads and no channels. Work it right now and keep it for get_message.
+        self.events.dispatched(package.sequence);
+        if self.inline{
+            work_package(0, &mut package, &mut self.inline_context, &self.events);
+            self.inline_done.push_back(package);
+        } else if !self.push_package(package){
+            // Nobody will work it. The next receive ends the iteration instead of waiting for it.
+            self.events.disconnected();
+            self.workers_gone = true;
+            self.record_alloc(mark, AllocStats::record_dispatch);
+            return;
+        }
+        self.messages += 1;
+        self.next_sequence += 1;
+        self.dispatch_taken_receipt();
+        self.record_alloc(mark, AllocStats::record_dispatch);
+    }
+
+    /// Pack more inputs of the package's batch into it, up to per_message in total. Stops at the first input of another batch,
+    /// which is held back for the next message.
+    fn take_merged(&mut self, package: &mut Package<S, E>, per_message: usize){
+        while package.merged_inputs + 1 < per_message && self.held_input.is_none(){
+            let (input, weight) = match self.next_input(){
+                Some(x) => x,
+                None => break,
+            };
+            if self.taken_batch != package.batch{
+                self.outstanding_weight -= weight;
+                self.held_input = Some(input);
+                self.held_receipt = self.taken_receipt.take();
+                self.held_batch = self.taken_batch.take();
+                break;
+            }
+            self.taken_batch = None;
+            self.taken_deadline = None;
+            let mut message = self.build_message();
+            message.set_input(input);
+            package.merged.push(message);
+            package.merged_inputs += 1;
+            package.weight += weight;
+        }
+    }
+
+    /// Pack the inputs of the batch together, up to per_message inputs in each message sent to the workers, merged by the given function. None stops it.
+    /// Used by *DeliveryService::map_reduce*.
+    pub fn set_merging(&mut self, merging: Option<(BatchId, usize, MergeFn<S>)>){
+        self.merging = merging;
+    }
+
+    /// Send the package to the workers. While the channel is full, it's retried a few times (see kik_backoff), then the feeder sleeps until a worker makes room.
+    /// 
+    /// Returns false if the feeder was closed or every worker is gone.
+    fn push_package(&self, mut package: Package<S, E>) -> bool{
+        let tx_inserter = match &self.tx_inserter{
+            Some(tx_inserter) => tx_inserter,
+            None => return false,
+        };
+        let mut backoff = Backoff::with_strategy(self.wait_strategy);
         // attempt to send the message until it succeeds or the channel is closed.
         loop{
-            let message_copy = message.clone();
-            yield_now();
-            // println!("Sending message.");
-            match self.tx_inserter.try_send(message_copy){
-                Ok(_) => {
-                    // println!("Succesfully sent.");
-                    self.messages = self.messages + 1;
-                    break;
-                },
-                Err(err) => {
-                    match err{
-                        TrySendError::Full(_) => {
-                            continue;
-                        },
-                        TrySendError::Disconnected(_) => {
-                            panic!("Feeder Error(id: {}): Channel disconnected.", self.id);
-                        }
+            let key = package.key;
+            match tx_inserter.try_send(package, key){
+                Ok(_) => return true,
+                // Workers are busy. Give them the cpu and try again, a few times.
+                Err(TrySendError::Full(returned)) => {
+                    package = returned;
+                    if backoff.retry(){
+                        continue;
                     }
-                }
+                    // Out of retries. Sleep until a worker takes something.
+                    self.events.queue_full(package.sequence);
+                    #[cfg(feature = "tracing")]
+                    tracing::debug!(message_id = package.sequence, "work queue full, waiting for a worker");
+                    match tx_inserter.send(package, key){
+                        Ok(_) => return true,
+                        Err(_) => return false,
+                    }
+                },
+                Err(TrySendError::Disconnected(_)) => return false,
             }
         }
     }
 
+    /// Tell the receipt of the input just sent, if it came with one.
+    fn dispatch_taken_receipt(&mut self){
+        if let Some(receipt) = self.taken_receipt.take(){
+            receipt.dispatch_one();
+        }
+    }
+
     // get a result message from workers
-    /// Retrieve a result message from the workers.
-    fn get_message(&mut self) -> S{
-        let message: S;
+    /// Retrieve a result package from the workers. The data is still inside, take it out with *recycle_package* or *consume_package*.
+    /// Returns None if the workers are gone, or stalled past the stall timeout, which ends the iteration with *StopReason::Error*.
+    fn get_message(&mut self) -> Option<Package<S, E>>{
         loop{
-            yield_now();
-            // Try to retrieve a message from workers
-            // println!("Retrieving message");
-            let get_message = self.rx_deliverer.try_recv();
-            match get_message{
-                Ok(new_message) => {
-                    // successful retrieval
-                    // println!("Successful retrieval.");
-                    message = new_message;
-                    self.messages = self.messages -1;
-                    break;
+            // Sleep until a worker delivers a message.
+            let message: Package<S, E> = match self.receive_package(){
+                Ok(new_message) => new_message,
+                // This thread is supposed to exit before the workers. Else something wrong went with them.
+                Err(error) => {
+                    if error == KikError::Disconnected{
+                        self.events.disconnected();
+                    }
+                    self.stop_reason = Some(StopReason::Error(error));
+                    return None;
                 },
-                Err(err) => match err{
-                    // If it's empty, wait and try again until all the counters were used
-                    TryRecvError::Empty => {
-                        continue;
-                    },
-                    // This thread is supposed to exit before the workers. Else something wrong went with them.
-                    TryRecvError::Disconnected => panic!("Error feeder id {}: behave_inserter_deliverer can't pull messages because channel is disconnected.", self.id),
+            };
+            let message = self.unpack_package(message);
+            // None if it failed and was sent again. Wait for the next one then.
+            if let Some(message) = self.settle_failure(message){
+                return Some(message);
+            }
+        }
+    }
+
+    /// Get the first result of a run, sending more messages only while it hasn't arrived. Used when fast_first_result is set.
+    fn get_first_message(&mut self) -> Option<Package<S, E>>{
+        while self.messages < self.package_limit && !self.growth_paused(){
+            if let Some(message) = self.try_receive_package(){
+                let message = self.unpack_package(message);
+                if let Some(message) = self.settle_failure(message){
+                    return Some(message);
                 }
+                continue;
+            }
+            let (new_input, weight) = match self.next_input(){
+                Some(x) => x,
+                None => break,
+            };
+            let (new_message, slot) = self.new_message(new_input);
+            self.send_message(new_message, slot, weight);
+        }
+        // The window is full or there is nothing else to send. Sleep until the first result arrives.
+        self.get_message()
+    }
+
+    /// Record a package retrieved from the workers in the stats.
+    fn unpack_package(&mut self, message: Package<S, E>) -> Package<S, E>{
+        self.messages -= 1;
+        if !message.cancelled{
+            self.last_completed_at = self.last_completed_at.max(Some(message.completed_at));
+        }
+        self.outstanding_weight -= message.weight;
+        // Inputs merged by the worker have no result of their own. They're done now.
+        if let (Some(batch), true) = (message.batch, message.merged_inputs > 0){
+            self.count_batch_inputs(batch, message.merged_inputs);
+        }
+        if let Some(stats) = &mut self.stats{
+            stats.record_package(&message);
+        }
+        if let Some(memory) = &mut self.memory{
+            memory.record(message.slot, message.message.payload_size());
+        }
+        if let Some(throughput) = &mut self.throughput{
+            throughput.record(&message, message.message.payload_size());
+        }
+        if let Some(metrics) = &mut self.metrics{
+            metrics.record(&message);
+        }
+        // Worked inline, nothing ever waits.
+        if let (Some(adaptive), false) = (&mut self.adaptive, self.inline){
+            if let Some(package_number) = adaptive.record(self.package_number, message.dispatched_at, message.completed_at, message.work_time){
+                self.package_number = package_number;
+                self.refresh_package_limit();
             }
         }
         message
     }
 
+    /// Give a failed package another attempt, or set its input aside once it's out of them. See *ChannelConfig::set_quarantine*.
+    /// Returns None if the package was sent again. Anything else is given back as it is.
+    fn settle_failure(&mut self, mut message: Package<S, E>) -> Option<Package<S, E>>{
+        let max_attempts = match self.max_attempts{
+            Some(max_attempts) => max_attempts,
+            None => return Some(message),
+        };
+        // The inputs merged by map_reduce were drained while being worked, so they can't be tried again. A cancelled run is thrown away anyway.
+        if message.error.is_none() || message.merged_inputs > 0 || self.cancellation.is_cancelled(){
+            return Some(message);
+        }
+        if message.attempt < max_attempts{
+            message.error = None;
+            self.resend_package(message);
+            return None;
+        }
+        if let Some(input) = message.message.take_input(){
+            if let Some(error) = message.error.take(){
+                self.quarantine.push((input, FailureReason::new(error, message.attempt)));
+                message.quarantined = true;
+            }
+        }
+        Some(message)
+    }
+
+    /// Send a package that came back from the workers once more, keeping its sequence. Counted as being away again.
+    fn resend_package(&mut self, mut package: Package<S, E>){
+        package.dispatched_at = Instant::now();
+        let weight = package.weight;
+        self.outstanding_weight += weight;
+        self.events.dispatched(package.sequence);
+        if self.inline{
+            work_package(0, &mut package, &mut self.inline_context, &self.events);
+            self.inline_done.push_back(package);
+        } else if !self.push_package(package){
+            self.events.disconnected();
+            self.workers_gone = true;
+            self.outstanding_weight -= weight;
+            return;
+        }
+        self.messages += 1;
+    }
+
+    /// Take the result out of a package whose message will be sent again. The data is cloned, since the message keeps its buffers.
+    fn recycle_package(&mut self, mut message: Package<S, E>) -> (S, Retrieved<T, E>){
+        let mark = self.alloc_mark();
+        // A failed message has no valid data to clone. One whose input was set aside gives nothing, like an empty fan out.
+        let result = match message.error{
+            Some(error) => Err(error),
+            None if message.quarantined => Ok(Output::FannedOut(Vec::new())),
+            None => match message.message.fan_out(){
+                Some(outputs) => Ok(Output::FannedOut(outputs)),
+                None => match self.spare_buffers.pop(){
+                    Some(spare) => Ok(Output::Data(message.message.swap_message_data(spare))),
+                    None => Ok(Output::Data(message.message.clone_message_data())),
+                },
+            },
+        };
+        self.record_message_size(message.message.payload_size(), &result);
+        let retrieved = Retrieved{
+            sequence: message.sequence,
+            slot: message.slot,
+            batch: message.batch,
+            completed_at: message.completed_at,
+            result,
+        };
+        self.record_alloc(mark, AllocStats::record_recycle);
+        (message.message, retrieved)
+    }
+
+    /// Take the result out of a package whose message won't be used again. The data is moved out of the message instead of cloned.
+    fn consume_package(&mut self, mut message: Package<S, E>) -> Retrieved<T, E>{
+        // A pooled message goes back with its buffers, so its data is cloned or swapped out like when recycling.
+        if self.pool.is_some(){
+            let (message, retrieved) = self.recycle_package(message);
+            if let Some(pool) = &mut self.pool{
+                pool.give_back(message);
+            }
+            return retrieved;
+        }
+        let mark = self.alloc_mark();
+        let payload_size = if self.memory_budget.is_some() { message.message.payload_size() } else { 0 };
+        let result = match message.error{
+            Some(error) => Err(error),
+            None if message.quarantined => Ok(Output::FannedOut(Vec::new())),
+            None => match message.message.fan_out(){
+                Some(outputs) => Ok(Output::FannedOut(outputs)),
+                None => Ok(Output::Data(message.message.take_message_data())),
+            },
+        };
+        self.record_message_size(payload_size, &result);
+        self.record_alloc(mark, AllocStats::record_recycle);
+        Retrieved{
+            sequence: message.sequence,
+            slot: message.slot,
+            batch: message.batch,
+            completed_at: message.completed_at,
+            result,
+        }
+    }
+
+    /// Queue every output of a message that fanned out, to be returned one per call. The batch waits for all of them.
+    fn queue_fanned_out(&mut self, batch: Option<BatchId>, outputs: Vec<T>){
+        self.fanned = true;
+        if let Some(batch) = batch{
+            if let Ok(index) = self.batch_remaining.binary_search_by_key(&batch, |(id, _)| *id){
+                self.batch_remaining[index].1 += outputs.len();
+            } else if !outputs.is_empty(){
+                // The message was its last input. Batches are kept sorted, and this one is older than any left.
+                self.batch_remaining.push_front((batch, outputs.len()));
+            }
+        }
+        self.fanned_out.extend(outputs.into_iter().map(|data| (batch, data)));
+    }
+
+    /// Why the last iteration ended. None if no iteration has ended yet.
+    pub fn get_stop_reason(&self) -> Option<&StopReason>{
+        self.stop_reason.as_ref()
+    }
+
+    /// Throw away the current run: queued inputs are dropped, messages in flight are waited for and dropped, and the token is reset so that the channel can be fed again.
+    /// Returns how many inputs and messages were thrown away.
+    pub fn cancel_run(&mut self) -> usize{
+        let mut cancelled: usize = self.reorder_buffer.len() + self.fanned_out.len();
+        self.reorder_buffer.clear();
+        self.fanned_out.clear();
+        cancelled += self.abandon_acked_inputs();
+        if self.held_input.take().is_some(){
+            cancelled += 1;
+        }
+        self.generators.clear();
+        while self.next_queued().is_some(){
+            cancelled += 1;
+        }
+        self.clear_batches();
+        // Workers give messages back without working them while the token is cancelled.
+        while self.messages > 0{
+            match self.receive_package(){
+                Ok(package) => {
+                    if let Some(pool) = &mut self.pool{
+                        pool.give_back(package.message);
+                    }
+                },
+                Err(_) => break,
+            }
+            self.messages -= 1;
+            cancelled += 1;
+        }
+        self.messages = 0;
+        // Nothing sent before this point comes back anymore. In ordered mode the next result to yield is the next one sent.
+        self.next_expected = self.next_sequence;
+        self.outstanding_weight = 0;
+        self.cancellation.reset();
+        self.stop_reason = Some(StopReason::Cancelled);
+        self.run_over = true;
+        cancelled
+    }
+
+    /// Same as *cancel_run*, but the inputs are given back instead of dropped. First the ones of the messages in flight, recovered with
+    /// *Message::take_input*, then the queued ones. Messages that can't give their input back lose it, and so do results already worked
+    /// but not yet yielded in order. Used by *DeliveryService::checkpoint_all*.
+    #[cfg(feature = "serde")]
+    pub fn cancel_run_inputs(&mut self) -> Vec<R>{
+        let queued = self.take_queued_inputs();
+        let mut inputs: Vec<R> = Vec::with_capacity(self.messages + queued.len());
+        while self.messages > 0{
+            match self.receive_package(){
+                Ok(mut package) => {
+                    inputs.extend(package.message.take_input());
+                    for mut other in package.merged.drain(..){
+                        inputs.extend(other.take_input());
+                    }
+                    if let Some(pool) = &mut self.pool{
+                        pool.give_back(package.message);
+                    }
+                },
+                Err(_) => break,
+            }
+            self.messages -= 1;
+        }
+        inputs.extend(queued);
+        self.cancel_run();
+        inputs
+    }
+
+    /// End the iteration with the error. Nothing is thrown away, the next iteration carries on.
+    pub fn fail(&mut self, error: KikError){
+        self.stop_reason = Some(StopReason::Error(error));
+    }
+
+    /// Record that an iteration ended because the channel is paused.
+    pub fn set_paused(&mut self){
+        self.stop_reason = Some(StopReason::Paused);
+    }
+
+    /// Record that an iteration ended with nothing left to do.
+    pub fn set_completed(&mut self){
+        self.stop_reason = Some(StopReason::Completed);
+        self.run_over = true;
+        // Only inputs shed by the scheduler can be left here.
+        self.batch_remaining.clear();
+    }
+
+    /// Wait for the next package from the workers, or take the next one worked inline. Disconnected if the workers are gone, Stalled if none arrives within the stall timeout.
+    fn receive_package(&mut self) -> Result<Package<S, E>, KikError>{
+        if self.inline{
+            return self.inline_done.pop_front().ok_or(KikError::Disconnected);
+        }
+        self.check_heartbeats();
+        if let Some(package) = self.delivered.pop_front(){
+            return Ok(package);
+        }
+        if self.workers_gone{
+            return Err(KikError::Disconnected);
+        }
+        let waiting_since = Instant::now();
+        let mut backoff = Backoff::with_strategy(self.wait_strategy);
+        loop{
+            match self.rx_deliverer.try_recv(){
+                Ok(package) => return Ok(package),
+                Err(TryRecvError::Disconnected) => return Err(KikError::Disconnected),
+                Err(TryRecvError::Empty) => {},
+            }
+            if let Some(timeout) = self.stall_timeout{
+                if waiting_since.elapsed() >= timeout{
+                    return Err(KikError::Stalled{timeout, in_flight: self.messages});
+                }
+            }
+            if !backoff.retry(){
+                break;
+            }
+        }
+        if self.stall_timeout.is_none() && self.heartbeat.is_none(){
+            return self.rx_deliverer.recv().map_err(|_| KikError::Disconnected);
+        }
+        // Wake up now and then to look for hung workers, until the stall timeout if there's one.
+        loop{
+            let mut wait = self.heartbeat.as_ref().map_or(Duration::MAX, HeartbeatMonitor::check_interval);
+            if let Some(timeout) = self.stall_timeout{
+                let stall_left = timeout.saturating_sub(waiting_since.elapsed());
+                if stall_left == Duration::from_secs(0){
+                    return Err(KikError::Stalled{timeout, in_flight: self.messages});
+                }
+                wait = wait.min(stall_left);
+            }
+            match self.rx_deliverer.recv_timeout(wait){
+                Ok(package) => return Ok(package),
+                Err(RecvTimeoutError::Disconnected) => return Err(KikError::Disconnected),
+                Err(RecvTimeoutError::Timeout) => self.check_heartbeats(),
+            }
+        }
+    }
+
+    /// Take the next package if one is already waiting. Doesn't block.
+    fn try_receive_package(&mut self) -> Option<Package<S, E>>{
+        if self.inline{
+            return self.inline_done.pop_front();
+        }
+        self.check_heartbeats();
+        if let Some(package) = self.delivered.pop_front(){
+            return Some(package);
+        }
+        self.rx_deliverer.try_recv().ok()
+    }
+
+    /// Sleep until a worker delivers a package or the deadline passes. The package is kept for the next receive, so *try_next* finds it.
+    /// Returns right away if nothing is in flight, or if a package is already waiting, unless the deadline passed. Returns false if the workers are gone.
+    pub fn wait_for_package(&mut self, deadline: Instant) -> Result<bool, Timeout>{
+        if self.inline || self.messages == 0 || !self.delivered.is_empty(){
+            // The caller tries again right away. Don't let it do that past the deadline.
+            if Instant::now() >= deadline{
+                return Err(Timeout);
+            }
+            return Ok(true);
+        }
+        match self.rx_deliverer.recv_timeout(deadline.saturating_duration_since(Instant::now())){
+            Ok(package) => {
+                self.delivered.push_back(package);
+                Ok(true)
+            },
+            Err(RecvTimeoutError::Timeout) => Err(Timeout),
+            Err(RecvTimeoutError::Disconnected) => {
+                self.events.disconnected();
+                self.stop_reason = Some(StopReason::Error(KikError::Disconnected));
+                Ok(false)
+            },
+        }
+    }
+
+    /// How many inputs are queued and not dispatched yet. Generators aren't counted, there's no telling how many inputs they still have.
+    pub fn get_pending_inputs(&self) -> usize{
+        self.held_input.iter().count() + self.acked_inputs.len() + self.high_lane.len() + self.scheduler.len() + self.low_lane.len()
+    }
+
+    /// When the last message delivered back was completed, counting the ones not retrieved yet. None if no message was completed so far.
+    /// Call *get_ready_results* first for an up to date value.
+    pub fn get_last_completed_at(&self) -> Option<Instant>{
+        let waiting = self.delivered.iter().chain(self.inline_done.iter()).filter(|package| !package.cancelled).map(|package| package.completed_at).max();
+        self.last_completed_at.max(waiting)
+    }
+
+    /// The end of the control channel for the given worker. None unless *ChannelConfig::set_heartbeat_timeout* was used, or if running inline.
+    pub fn get_heartbeat(&self, worker_id: usize) -> Option<Heartbeat>{
+        self.heartbeat.as_ref().map(|heartbeat| heartbeat.heartbeat(worker_id))
+    }
+
+    /// Read the beats of the workers, flagging the ones that went silent inside a message. See kik_heartbeat.
+    pub fn check_heartbeats(&mut self){
+        if let Some(heartbeat) = &mut self.heartbeat{
+            heartbeat.check(&self.events);
+        }
+    }
+
+    /// True if the worker was flagged as hung by the last check.
+    pub fn is_hung(&self, worker_id: usize) -> bool{
+        self.heartbeat.as_ref().is_some_and(|heartbeat| heartbeat.is_hung(worker_id))
+    }
+
+    /// How many messages were dispatched and haven't been delivered back by the workers yet. Call *get_ready_results* first for an up to date count.
+    pub fn get_in_flight(&self) -> usize{
+        self.messages - self.delivered.len() - self.inline_done.len()
+    }
+
+    /// How many results are waiting to be returned: delivered by the workers, or held back in ordered mode.
+    pub fn get_ready_results(&mut self) -> usize{
+        if !self.inline{
+            while let Ok(package) = self.rx_deliverer.try_recv(){
+                self.delivered.push_back(package);
+            }
+        }
+        self.delivered.len() + self.inline_done.len() + self.reorder_buffer.len() + self.fanned_out.len()
+    }
+
+    /// Take every input set aside by the quarantine so far, with why it failed, in the order they were set aside.
+    pub fn take_failed(&mut self) -> Vec<(R, FailureReason<E>)>{
+        std::mem::take(&mut self.quarantine)
+    }
+
+    /// How many inputs the quarantine is holding.
+    pub fn get_failed_inputs(&self) -> usize{
+        self.quarantine.len()
+    }
+
+    /// Returns how many results were discarded for being older than the result TTL.
+    pub fn get_discarded_results(&self) -> usize{
+        self.discarded
+    }
+
+    /// True if the result is a successful one that has been waiting for longer than the result TTL.
+    fn is_expired(&self, retrieved: &Retrieved<T, E>) -> bool{
+        match self.result_ttl{
+            Some(ttl) => retrieved.result.is_ok() && retrieved.completed_at.elapsed() > ttl,
+            None => false,
+        }
+    }
+
+    /// Stop dispatching and disconnect the workers. Inputs still queued are dropped, messages still in the system are waited for and dropped.
+    /// Messages are only waited for until the deadline, if there's one. Returns how many inputs were abandoned and how many worked messages were discarded.
+    /// Calling it again does nothing.
+    pub fn close(&mut self, deadline: Option<Instant>) -> (usize, usize){
+        let mut abandoned: usize = self.abandon_acked_inputs();
+        if self.held_input.take().is_some(){
+            abandoned += 1;
+        }
+        self.generators.clear();
+        while self.next_queued().is_some(){
+            abandoned += 1;
+        }
+        self.clear_batches();
+        // Workers close once the channel is empty and disconnected.
+        self.tx_inserter = None;
+
+        let mut drained = self.reorder_buffer.len() + self.fanned_out.len();
+        self.reorder_buffer.clear();
+        self.fanned_out.clear();
+        while self.messages > 0{
+            // Past the deadline, whatever the workers are still holding is lost.
+            if let Some(deadline) = deadline{
+                if self.wait_for_package(deadline) != Ok(true){
+                    break;
+                }
+            }
+            match self.receive_package(){
+                Ok(_) => drained += 1,
+                // Every worker is gone, or stuck. Whatever they were holding is lost.
+                Err(_) => break,
+            }
+            self.messages -= 1;
+        }
+        self.messages = 0;
+        self.outstanding_weight = 0;
+        (abandoned, drained)
+    }
+
     /// Returns how many messages are still to be processed and recovered. This doesn't tell how many are results waiting to be recovered and how many are still waiting for the workers. Just how many iterations might remain.
-    pub fn get_remaining_messages(&mut self) -> usize{
-        self.messages + self.input_vec.len()
+    /// Each generator that isn't exhausted yet counts as one, since there's no telling how many inputs it still has.
+    pub fn get_remaining_messages(&self) -> usize{
+        self.messages + self.fanned_out.len() + self.acked_inputs.len() + self.high_lane.len() + self.scheduler.len() + self.low_lane.len() + self.reorder_buffer.len() + self.held_input.iter().count() + self.generators.len()
+    }
+
+    /// Lower and upper bound of how many results are left, like *Iterator::size_hint*. Exact unless something can make a message give other than one result:
+    /// generators and fanned out messages can give more, quarantine, result_ttl and deadlines can give fewer.
+    /// Inputs dropped by a shedding scheduler can't be told apart, so they aren't accounted for.
+    pub fn get_result_bounds(&self) -> (usize, Option<usize>){
+        let known = self.get_remaining_messages() - self.generators.len();
+        let may_drop = self.fanned || self.max_attempts.is_some() || self.result_ttl.is_some() || !self.deadlines.is_empty();
+        let may_grow = self.fanned || !self.generators.is_empty();
+        (if may_drop { 0 } else { known }, if may_grow { None } else { Some(known) })
+    }
+
+    /// True if no new message should be built: under memory pressure, only the messages already in the system are recycled.
+    /// A message is still built when there's none, so the run can go on.
+    fn growth_paused(&self) -> bool{
+        self.under_pressure && self.messages > 0
     }
 
     /// Feed messages for the workers until the max number set has been achieved.
     fn feed_initial_messages(&mut self){
-        for _ in (self.messages)..(self.package_number){
-            // It will stop sending messages if there is no input remaining.
-            let new_input: R = match self.input_vec.pop(){
+        for _ in (self.messages)..(self.package_limit){
+            if self.growth_paused(){
+                break;
+            }
+            // It will stop sending messages if there is no input remaining, or if the next one is too heavy for now.
+            let (new_input, weight) = match self.next_input(){
                 Some(x) => x,
                 // No more messages to send.
                 None => break,
             };
-            let mut new_message: S = S::new();
-            new_message.set_input(new_input);
+            let (new_message, slot) = self.new_message(new_input);
+            self.send_message(new_message, slot, weight);
+        }
+    }
 
-            self.send_message(new_message);
+    /// Return the result for the oldest input still waiting, holding back any results that arrive before it.
+    fn retrieve_ordered(&mut self) -> Option<Retrieved<T, E>>{
+        loop{
+            if let Some(retrieved) = self.reorder_buffer.remove(&self.next_expected){
+                self.next_expected += 1;
+                return Some(retrieved);
+            }
+            // Every message sent is eventually retrieved, so the buffer will be empty once this returns None.
+            let retrieved = self.retrieve_data()?;
+            self.reorder_buffer.insert(retrieved.sequence, retrieved);
         }
     }
 
+    /// Put an input taken with *next_input* back, to be the next one sent.
+    fn hold_input(&mut self, input: R, weight: usize){
+        self.outstanding_weight -= weight;
+        self.held_input = Some(input);
+        self.held_receipt = self.taken_receipt.take();
+        self.held_batch = self.taken_batch.take();
+    }
+
     /// Get a message from the workers and pull a copy of the MessageData inside. If there are more messages to sent, it will recycle the acquired message for the workers. Saving time.
-    fn retrieve_data(&mut self)-> Option<T>{
-        let new_data: T;
-        match self.input_vec.pop(){
+    fn retrieve_data(&mut self)-> Option<Retrieved<T, E>>{
+        self.refresh_package_limit();
+        match self.next_input(){
             // This means that there are no more messages to send
             None => {
                 // This means that there are no more messages to get
                 if self.messages == 0{
                     // ending function or iteration
+                    self.set_completed();
                     return None;
                 }
                 
                 // This means that there are no messages to send, but there are messages to retrieve.
-                let new_message = self.get_message();
-                new_data = new_message.clone_message_data();
-                // There's no need to recycle more messages, therefore new_message will be dropped. This needs to be done, since each message lifetime is 'static. Or else memory will only be freed when program ends (I think).
-                std::mem::drop(new_message);
-                return Some(new_data);
+                let new_package = self.get_message()?;
+                // There's no need to recycle more messages, therefore the message is consumed. Its data is moved out and the rest is dropped.
+                Some(self.consume_package(new_package))
             },
 
             //This means that there are still messages to send
-            Some(new_input) => {                
+            Some((new_input, weight)) => {                
                 // Considering the special case where there is only one input remaining (the one currently held in 'new_input') no more messages to get, no more messages to send. 
                 // In this case, a message will be created, sent, and consumed, instead of recycled.
                 if self.messages == 0{
-                    let mut new_message = S::new();
-                    new_message.set_input(new_input);
-                    self.send_message(new_message);
+                    let (new_message, slot) = self.new_message(new_input);
+                    self.send_message(new_message, slot, weight);
+                    if self.fast_first_result{
+                        let new_package = self.get_first_message()?;
+                        // Keep the message in the system if there's still work for it.
+                        return match self.next_input(){
+                            Some((next_input, next_weight)) => {
+                                let (mut new_message, new_data) = self.recycle_package(new_package);
+                                new_message.set_input(next_input);
+                                if let Some(stats) = &mut self.stats{
+                                    stats.record_recycled();
+                                }
+                                self.send_message(new_message, new_data.slot, next_weight);
+                                Some(new_data)
+                            },
+                            None => Some(self.consume_package(new_package)),
+                        };
+                    }
                     // checks to send a few input messages if possible. While worker process the first message.
                     self.feed_initial_messages();
-                    let new_message = self.get_message();
-                    new_data = new_message.clone_message_data();
-                    std::mem::drop(new_message);
+                    let new_package = self.get_message()?;
+                    let new_data = self.consume_package(new_package);
                     // checks to send another message for the workers since this one had to be deleted.
                     self.feed_initial_messages();
                     return Some(new_data);
                 }
 
                 // This means that there are less messages in the delivery system than there should be.
-                if self.messages < self.package_number {
+                if self.messages < self.package_limit {
                     // Send new messages until the system max has been reached.
                     self.feed_initial_messages();
                 }
 
                 // There are messages to feed and there are messages to get. Therefore recycle messages.
-                let mut new_message = self.get_message();
-                new_data = new_message.clone_message_data();
+                let new_package = match self.get_message(){
+                    Some(new_package) => new_package,
+                    // Keep the input for when the workers come back, if they do.
+                    None => {
+                        self.hold_input(new_input, weight);
+                        return None;
+                    },
+                };
+                // Workers were removed, or memory is tight, and there are still more messages than the system allows. Hold the input back and let this message go.
+                if self.messages >= self.package_limit{
+                    self.hold_input(new_input, weight);
+                    return Some(self.consume_package(new_package));
+                }
+                let (mut new_message, new_data) = self.recycle_package(new_package);
                 
                 // Data will be replaced by the workers. Only thing they need is the input.
                 new_message.set_input(new_input);
-                self.send_message(new_message);
+                if let Some(stats) = &mut self.stats{
+                    stats.record_recycled();
+                }
+                self.send_message(new_message, new_data.slot, weight);
+                Some(new_data)
+            }
+        }
+    }
+
+    /// Like *retrieve_data*, but only takes a result that the workers already delivered. Keeps the package window full while polling.
+    /// Returns None if no result is waiting yet. When inline, sending a message works it right away, so this never waits on a worker.
+    fn try_retrieve_data(&mut self) -> Option<Retrieved<T, E>>{
+        self.refresh_package_limit();
+        if self.messages < self.package_limit{
+            self.feed_initial_messages();
+        }
+        let new_package = self.try_receive_package()?;
+        let new_package = self.unpack_package(new_package);
+        let new_package = self.settle_failure(new_package)?;
+        // Recycle the message if there's more work for it and the window has room, like retrieve_data does.
+        if self.messages < self.package_limit{
+            if let Some((new_input, weight)) = self.next_input(){
+                let (mut new_message, new_data) = self.recycle_package(new_package);
+                new_message.set_input(new_input);
+                if let Some(stats) = &mut self.stats{
+                    stats.record_recycled();
+                }
+                self.send_message(new_message, new_data.slot, weight);
                 return Some(new_data);
             }
         }
+        Some(self.consume_package(new_package))
+    }
+
+    /// Return the result for the oldest input still waiting if it already arrived. Results that arrive before it are held back, as in *retrieve_ordered*.
+    fn try_retrieve_ordered(&mut self) -> Option<Retrieved<T, E>>{
+        loop{
+            if let Some(retrieved) = self.reorder_buffer.remove(&self.next_expected){
+                self.next_expected += 1;
+                return Some(retrieved);
+            }
+            let retrieved = self.try_retrieve_data()?;
+            self.reorder_buffer.insert(retrieved.sequence, retrieved);
+        }
+    }
+
+    /// Non-blocking version of *next*. Returns a result only if one is already waiting, None otherwise. None doesn't mean the run is over.
+    /// A cancelled run isn't thrown away here, since that waits for the messages in flight. The next call to *next* does it.
+    pub fn try_next(&mut self) -> Option<Result<T, WorkError<E>>>{
+        loop{
+            if self.cancellation.is_cancelled(){
+                return None;
+            }
+            if let Some((batch, data)) = self.fanned_out.pop_front(){
+                self.count_batch_result(batch);
+                return Some(Ok(data));
+            }
+            let retrieved = if self.ordered{
+                self.try_retrieve_ordered()?
+            } else {
+                self.try_retrieve_data()?
+            };
+            self.last_sequence = retrieved.sequence;
+            self.count_batch_result(retrieved.batch);
+            if self.is_expired(&retrieved){
+                self.discarded += 1;
+                continue;
+            }
+            match retrieved.result{
+                Ok(Output::Data(data)) => return Some(Ok(data)),
+                Ok(Output::FannedOut(outputs)) => self.queue_fanned_out(retrieved.batch, outputs),
+                Err(error) => return Some(Err(error)),
+            }
+        }
     }
 }
 
 // This will be used by the channel that handles the feeder. Call kik_channel's iterator instead.
-impl<T, R, S> Iterator for FeederRecycler<T, R, S>  where 
+impl<T, R, S, E> Iterator for FeederRecycler<T, R, S, E>  where 
 T: MessageData + 'static,
 R: MessageInput<T> + 'static,
-S: Message<T, R> + Sync + Send + Clone + 'static,
+S: Message<T, R, E> + Sync + Send + 'static,
+E: Send + 'static,
 // S: Message<T, R> + Sync + Send + Copy + 'static,
 {
-    type Item = T;
+    type Item = Result<T, WorkError<E>>;
 
     fn next(&mut self) -> Option<Self::Item> {
         // Returns None if there are no messages to retrieve, ending the iteration.
         // Unless the entire object goes out of scope, we can keep feeding more input to use in other iterations later on.
-        self.retrieve_data()
+        loop{
+            if self.cancellation.is_cancelled(){
+                self.cancel_run();
+                return None;
+            }
+            if let Some((batch, data)) = self.fanned_out.pop_front(){
+                self.count_batch_result(batch);
+                return Some(Ok(data));
+            }
+            let retrieved = if self.ordered{
+                self.retrieve_ordered()?
+            } else {
+                self.retrieve_data()?
+            };
+            self.last_sequence = retrieved.sequence;
+            self.count_batch_result(retrieved.batch);
+            if self.is_expired(&retrieved){
+                self.discarded += 1;
+                continue;
+            }
+            match retrieved.result{
+                Ok(Output::Data(data)) => return Some(Ok(data)),
+                Ok(Output::FannedOut(outputs)) => self.queue_fanned_out(retrieved.batch, outputs),
+                Err(error) => return Some(Err(error)),
+            }
+        }
     }
 }
-
-impl<T, R, S> Drop for FeederRecycler<T, R, S> where
-T: MessageData + 'static,
-R: MessageInput<T> + 'static,
-S: Message<T, R> + Sync + Send + Clone + 'static,
-{
-    fn drop(&mut self){
-        std::mem::drop(&self.input_vec);
-        std::mem::drop(&self.rx_deliverer);
-        std::mem::drop(&self.tx_inserter);
-    }
-}
\ No newline at end of file
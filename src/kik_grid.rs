@@ -0,0 +1,102 @@
+//! # Grid
+//!
+//! Inputs for splitting an image, or any other 2D area, between the workers. Every image and fractal user ends up writing the same
+//! nested loops, and they're easy to get wrong on the edges when the area isn't a multiple of the tile size.
+//!
+//! *cells* gives rectangles clipped to the area, so the ones on the right and bottom edges are smaller. *tiles* gives the corners of
+//! fixed size tiles instead, for *TileMessage*, and those on the edges go past the area. *rows* and *columns* split it in bands.
+//!
+//! ```
+//! use kik_sync_service::util::grid::{self, GridCell};
+//!
+//! // 100 by 70 in cells of 32 by 32: 4 columns and 3 rows, the last ones narrower.
+//! let cells = grid::cells(100, 70, 32, 32);
+//! assert_eq!(cells.len(), 12);
+//! assert_eq!(cells[11], GridCell{ x0: 96, y0: 64, x1: 100, y1: 70 });
+//! assert_eq!(cells.iter().map(GridCell::area).sum::<usize>(), 100 * 70);
+//! assert_eq!(grid::rows(70, 32), vec![0..32, 32..64, 64..70]);
+//! ```
+//!
+//! Sizes of zero are treated as one, like *ChunkInput::split* does, so that an empty tile can't loop forever.
+//!
+//!
+
+use std::ops::Range;
+
+use crate::kik_message::{MessageInput, MessageData};
+use crate::kik_buffer::TileInput;
+
+/// A rectangle of the area, from (x0, y0) included to (x1, y1) excluded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GridCell{
+    pub x0: usize,
+    pub y0: usize,
+    pub x1: usize,
+    pub y1: usize,
+}
+
+impl GridCell{
+    /// Width of the cell.
+    pub fn width(&self) -> usize{
+        self.x1 - self.x0
+    }
+
+    /// Height of the cell.
+    pub fn height(&self) -> usize{
+        self.y1 - self.y0
+    }
+
+    /// How many values the cell covers.
+    pub fn area(&self) -> usize{
+        self.width() * self.height()
+    }
+}
+
+impl<T> MessageInput<T> for GridCell where T: MessageData{
+    fn new() -> Self{
+        GridCell{
+            x0: 0,
+            y0: 0,
+            x1: 0,
+            y1: 0,
+        }
+    }
+
+    /// The area of the cell, so that the smaller ones on the edges weigh less.
+    fn weight(&self) -> usize{
+        self.area()
+    }
+}
+
+/// Split the length in bands of band_len, from zero. The last one is shorter if band_len doesn't divide len.
+fn bands(len: usize, band_len: usize) -> impl Iterator<Item = Range<usize>> + Clone{
+    let band_len = band_len.max(1);
+    (0..len).step_by(band_len).map(move |start| start..(start + band_len).min(len))
+}
+
+/// Cells of cell_width by cell_height covering an area of width by height, row by row. The cells on the right and bottom edges are clipped to the area.
+pub fn cells(width: usize, height: usize, cell_width: usize, cell_height: usize) -> Vec<GridCell>{
+    let columns = bands(width, cell_width);
+    bands(height, cell_height).flat_map(|rows| columns.clone().map(move |columns| GridCell{
+        x0: columns.start,
+        y0: rows.start,
+        x1: columns.end,
+        y1: rows.end,
+    })).collect()
+}
+
+/// The corners of every tile of tile_width by tile_height covering an area of width by height, row by row.
+/// Tiles on the right and bottom edges go past the area when the sizes don't divide it, the values past it are for the caller to skip.
+pub fn tiles(width: usize, height: usize, tile_width: usize, tile_height: usize) -> Vec<TileInput>{
+    cells(width, height, tile_width, tile_height).into_iter().map(|cell| TileInput{ x: cell.x0, y: cell.y0 }).collect()
+}
+
+/// Split a height in bands of rows_per_band rows, top to bottom. The last one is shorter if rows_per_band doesn't divide height.
+pub fn rows(height: usize, rows_per_band: usize) -> Vec<Range<usize>>{
+    bands(height, rows_per_band).collect()
+}
+
+/// Split a width in bands of columns_per_band columns, left to right. The last one is narrower if columns_per_band doesn't divide width.
+pub fn columns(width: usize, columns_per_band: usize) -> Vec<Range<usize>>{
+    bands(width, columns_per_band).collect()
+}
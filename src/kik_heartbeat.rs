@@ -0,0 +1,136 @@
+//! # Heartbeat
+//!
+//! Tells a worker stuck inside *Message::work* (an endless loop, a deadlock in user code) apart from one that is simply busy.
+//! Enabled with *ChannelConfig::set_heartbeat_timeout*. Used by kik_worker and kik_feeder, not meant to be used directly.
+//!
+//! Each worker sends a *Beat* on a control channel when it starts a message and when it's done with it. Long messages keep beating
+//! through *WorkPacer::checkpoint*, at most four times per timeout. The feeder reads the beats whenever it looks for results, and flags
+//! the workers that have been inside a message for longer than the timeout without a beat. Waiting for work, for room to send a result
+//! or for the channel to be resumed isn't being inside a message, so idle workers are never flagged.
+//!
+//! A flagged worker is reported once with *PoolEvent::WorkerHung*, and listed as *WorkerStatus::Hung* by *DeliveryService::health*.
+//! If it beats again, it's reported with *PoolEvent::WorkerRecovered*. Messages that take longer than the timeout without calling
+//! *WorkPacer::checkpoint* are flagged too, so keep the timeout above the slowest of them.
+//!
+//!
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::time::{Duration, Instant};
+
+use crate::kik_event::{EventSenders, PoolEvent};
+
+// Beats sent by the pacer per timeout, so that a late one doesn't get the worker flagged.
+const BEATS_PER_TIMEOUT: u32 = 4;
+
+/// What a worker says with a *Beat*.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Pulse{
+    /// Inside a message.
+    Working,
+    /// Done with its message.
+    Idle,
+    /// Leaving the channel.
+    Left,
+}
+
+/// Sent by a worker on the control channel.
+#[derive(Clone, Copy, Debug)]
+pub struct Beat{
+    worker_id: usize,
+    pulse: Pulse,
+    sent_at: Instant,
+}
+
+/// The worker's end of the control channel.
+#[derive(Clone)]
+pub struct Heartbeat{
+    worker_id: usize,
+    tx: Sender<Beat>,
+    interval: Duration,
+    last_beat: Instant,
+}
+
+impl Heartbeat{
+    /// Beats of the given worker, sent on tx. *beat* sends at most one every interval.
+    pub fn new(worker_id: usize, tx: Sender<Beat>, interval: Duration) -> Self{
+        Heartbeat{
+            worker_id,
+            tx,
+            interval,
+            last_beat: Instant::now(),
+        }
+    }
+
+    /// Send the pulse right away. A feeder that's gone doesn't need it.
+    pub fn send(&mut self, pulse: Pulse){
+        self.last_beat = Instant::now();
+        let _ = self.tx.send(Beat{ worker_id: self.worker_id, pulse, sent_at: self.last_beat });
+    }
+
+    /// Tell the feeder the worker is still making progress, if it wasn't told for an interval.
+    pub fn beat(&mut self){
+        if self.last_beat.elapsed() >= self.interval{
+            self.send(Pulse::Working);
+        }
+    }
+}
+
+/// The feeder's end of the control channel.
+pub struct HeartbeatMonitor{
+    rx: Receiver<Beat>,
+    tx: Sender<Beat>,
+    timeout: Duration,
+    // Last beat of each worker that sent one and didn't leave.
+    last_beats: BTreeMap<usize, (Pulse, Instant)>,
+    // Workers flagged and not heard from since.
+    hung: BTreeSet<usize>,
+}
+
+impl HeartbeatMonitor{
+    /// Flag the workers that go silent inside a message for longer than timeout.
+    pub fn new(timeout: Duration) -> Self{
+        let (tx, rx) = channel();
+        HeartbeatMonitor{
+            rx,
+            tx,
+            timeout,
+            last_beats: BTreeMap::new(),
+            hung: BTreeSet::new(),
+        }
+    }
+
+    /// A heartbeat for the given worker, sending on this monitor's control channel.
+    pub fn heartbeat(&self, worker_id: usize) -> Heartbeat{
+        Heartbeat::new(worker_id, self.tx.clone(), self.check_interval())
+    }
+
+    /// How often the pacer beats, and how long the feeder waits for results before checking again.
+    pub fn check_interval(&self) -> Duration{
+        (self.timeout / BEATS_PER_TIMEOUT).max(Duration::from_millis(1))
+    }
+
+    /// Read the beats sent so far, and flag or clear workers. Changes are sent to events.
+    pub fn check(&mut self, events: &EventSenders){
+        while let Ok(beat) = self.rx.try_recv(){
+            if self.hung.remove(&beat.worker_id){
+                events.send(PoolEvent::WorkerRecovered{worker_id: beat.worker_id});
+            }
+            match beat.pulse{
+                Pulse::Left => { self.last_beats.remove(&beat.worker_id); },
+                pulse => { self.last_beats.insert(beat.worker_id, (pulse, beat.sent_at)); },
+            }
+        }
+        for (worker_id, (pulse, sent_at)) in &self.last_beats{
+            let silent_for = sent_at.elapsed();
+            if *pulse == Pulse::Working && silent_for > self.timeout && self.hung.insert(*worker_id){
+                events.send(PoolEvent::WorkerHung{worker_id: *worker_id, silent_for});
+            }
+        }
+    }
+
+    /// True if the worker was flagged by the last check.
+    pub fn is_hung(&self, worker_id: usize) -> bool{
+        self.hung.contains(&worker_id)
+    }
+}
@@ -8,6 +8,10 @@
 //! *Message* is what the channels share. *MessageInput* is what *FeederRecycler* sets in each *Message*.
 //! *MessageData* is what the channel returns when the user iterates through it.
 //! 
+//! *MessageData*, *MessageInput* and *Message* don't need their own *new* if they have *Default*. For the first two, an empty impl does.
+//! Numbers, *String*, *Vec*s, arrays and *Option*s are already *MessageData*. Integers, *Range<usize>* and pairs or triples of values are already *MessageInput*.
+//! With those, only the *Message* has to be written.
+//! 
 //! The *MessageData* shared must be *Sync* and *Send*. Must have *'static* lifetimes, must have *Clone* trait, 
 //! but doesn't need to be *Copy*. I haven't tested if being *Copy* will break *Drop* behaviors.
 //! But if it did, compiler would probably notice.
@@ -15,25 +19,63 @@
 //! 
 
 use std::marker::{Send, Sync};
+use std::convert::Infallible;
+use std::ops::Range;
+use std::time::Duration;
+
+use crate::kik_context::{WorkContext, WorkInfo};
 
 // Making sure that this trait only applies to objects that have Clone
 /// MessageData holds the resource type that will be returned by the worker-threads. Must implement Sync, Send, Clone and have lifetime 'static.
+/// 
+/// With derive(Default), an empty impl is enough. Numbers, *String*, *Vec*, *Option*, *Box*, arrays and pairs or triples of data already have one.
 pub trait MessageData: Sync + Send + Clone + 'static{
-    fn new() -> Self;
+    /// Not used by the channel, data is always generated by the messages. Kept so that older code that calls it still builds.
+    /// By default it calls *Default::default*. Data without *Default* doesn't need it, older impls that write their own still build.
+    fn new() -> Self where Self: Default{
+        Self::default()
+    }
+
+    /// Roughly how many bytes this data takes, heap buffers included. Used by kik_feeder when a memory budget is set in *ChannelConfig*.
+    /// By default only the size of the type itself, override it for types that hold buffers. *Vec* and *String* count their capacity.
+    fn size_hint(&self) -> usize{
+        std::mem::size_of::<Self>()
+    }
 }
 
 // This is the trait input that can only be applied to ojbects with MessageData trait
-/// MessageInput will have the input arguments for generating each MessageData. Must implement Sync, Send, Clone and have lifetime 'static.
-pub trait MessageInput<T> : Sync + Send + Clone + 'static where T: MessageData
+/// MessageInput will have the input arguments for generating each MessageData. Must implement Sync, Send and have lifetime 'static.
+/// 
+/// It doesn't need *Clone*. Each input is moved from the feeder into *Message::set_input* and never copied, so it can own things like a *File* or a socket.
+pub trait MessageInput<T> : Sized + Sync + Send + 'static where T: MessageData
 {
-    fn new() -> Self;
+    /// Not used by the channel, inputs are always given by the user. Kept so that older code that calls it still builds.
+    /// By default it calls *Default::default*. Inputs without *Default* don't need it, and don't have it.
+    fn new() -> Self where Self: Default{
+        Self::default()
+    }
+
+    /// How costly this input is to work, in any unit the user chooses (pixels, bytes...). Used by kik_feeder when a max weight is set in *ChannelConfig*. Default 1.
+    fn weight(&self) -> usize{
+        1
+    }
+
+    /// Key for keyed dispatch, see *ChannelConfig::set_keyed_dispatch*. Inputs with the same key are worked by the same worker, in the order they were sent.
+    /// Hash other keys (a connection id, a file name) into a u64 first. Default None, the input goes to any worker. Used by kik_feeder.
+    fn routing_key(&self) -> Option<u64>{
+        None
+    }
 }
 
 // This is the Message Trait that holds the data and the value type that changes it
-/// Message has the tools to generate each MessageData T, based on each MessageInput R. Must implement Sync, Send, Clone and have lifetime 'static.
-pub trait Message<T, R> : Sync + Send + Clone + 'static where
+/// Message has the tools to generate each MessageData T, based on each MessageInput R. Must implement Sync, Send and have lifetime 'static.
+/// Only *DeliveryService::set_message_template* needs it to be *Clone*.
+/// 
+/// E is the error returned by *try_work*. It defaults to *Infallible* for messages that can't fail.
+pub trait Message<T, R, E = Infallible> : Sized + Sync + Send + 'static where
                                                 R: MessageInput<T>,
                                                 T: MessageData,
+                                                E: Send + 'static,
 {
     /// Behavior for storing a given input MessageInput, before a worker can use it for generating MessageData. Used by kik_feeder.
     fn set_input(&mut self, message_input: R);
@@ -41,20 +83,196 @@ pub trait Message<T, R> : Sync + Send + Clone + 'static where
     /// Workers will call this to use the stored MessageInput (R<T>) to generate and replace the existing MessageData stored. Used by kik_worker.
     fn work(&mut self);
 
-    /// This will call MessageInput::new() method. No need to implement this. Used by kik_feeder.
-    fn new_message_input() -> R{
+    /// Fallible version of *work*. By default it calls *work* and always succeeds. 
+    /// Override it for messages that can fail, the error will reach the user through *DeliveryService::results* instead of stopping the worker.
+    fn try_work(&mut self) -> Result<(), E>{
+        self.work();
+        Ok(())
+    }
+
+    /// Version of *try_work* that receives the worker's *WorkContext*. By default it calls *try_work*.
+    /// Override it when work needs the context, for example to check for cancellation in long loops.
+    fn work_with_context(&mut self, context: &mut WorkContext) -> Result<(), E>{
+        let _ = context;
+        self.try_work()
+    }
+
+    /// Version of *work_with_context* that also receives a *WorkInfo*. This is what the workers actually call. By default it calls *work_with_context*.
+    /// Override it for logging which worker handled the message, for logic that depends on the attempt, or for measuring how long it waited in the queue.
+    fn work_with_info(&mut self, context: &mut WorkContext, info: &WorkInfo) -> Result<(), E>{
+        let _ = info;
+        self.work_with_context(context)
+    }
+
+    /// Give back the input stored by *set_input*, for setting it aside when the quarantine gives up on it (see *ChannelConfig::set_quarantine*).
+    /// Called after the last failed attempt. By default None: the input can't be recovered, and the error reaches *DeliveryService::results* as usual. Used by kik_feeder.
+    fn take_input(&mut self) -> Option<R>{
+        None
+    }
+
+    /// This will call MessageInput::new() method. No need to implement this. Not used by the channel anymore.
+    fn new_message_input() -> R where R: Default{
         R::new()
     }
 
-    /// This will call MessageData::new() method. No need to implement this. Used by kik_feeder.
-    fn new_message_data() -> T{
+    /// This will call MessageData::new() method. No need to implement this. Not used by the channel anymore.
+    fn new_message_data() -> T where T: Default{
         T::new()
     }
 
     /// This method is used when retrieving MessageData for the iterator. Clone the MessageData stored and return it. Used by kik_feeder.
     fn clone_message_data(&self) -> T;
+
+    /// Used instead of *clone_message_data* when the message will be recycled and a buffer given back with *DeliveryService::return_buffer* is waiting.
+    /// Return the MessageData stored and keep spare in its place, for the next work to fill. The spare holds whatever the consumer left in it.
+    /// By default it clones and drops the spare, like before. Override it with *std::mem::replace* so the same allocations go around between
+    /// the consumer and the workers instead of being cloned for every result. Used by kik_feeder.
+    fn swap_message_data(&mut self, spare: T) -> T{
+        drop(spare);
+        self.clone_message_data()
+    }
+
+    /// Used instead of *clone_message_data* when the message won't be recycled, because there are no inputs left for it. By default it clones.
+    /// Override it to move the MessageData out (for example with *std::mem::take*), so large buffers aren't copied right before being dropped. Used by kik_feeder.
+    fn take_message_data(self) -> T{
+        self.clone_message_data()
+    }
     
-    /// Construct a new message with default values. Used by kik_feeder.
-    fn new() -> Self;
+    /// For inputs that generate several results, like a file decoded into many frames. Return every *MessageData* the last work generated,
+    /// and the iterator yields each of them in order instead of the one from *clone_message_data*. An empty vector yields nothing for this input.
+    /// Called right after the message comes back from the worker. By default None, one input gives one result. Used by kik_feeder.
+    fn fan_out(&mut self) -> Option<Vec<T>>{
+        None
+    }
+
+    /// Construct a new message with default values. Used by kik_feeder, through the builder given to the channel (see *DeliveryService::try_new*).
+    /// By default it calls *Default::default*, so messages with *Default* don't need it. Messages without it are built by *DeliveryService::try_with_builder* instead.
+    fn new() -> Self where Self: Default{
+        Self::default()
+    }
+
+    /// How many bytes this message is holding. Used by kik_feeder when memory tracking is enabled in *ChannelConfig*.
+    /// By default only counts the size of the type itself, override it to include heap buffers (like the capacity of a Vec).
+    fn payload_size(&self) -> usize{
+        std::mem::size_of::<Self>()
+    }
+
+}
+
+
+/// Combines the data generated from another input into the data of this message, for *DeliveryService::map_reduce*, which needs it.
+/// The worker calls it after working each input packed with this one, so a single message goes back for all of them.
+/// Must be associative and commutative, like a sum, a maximum or a histogram. Used by kik_worker.
+pub trait Merge<T> where T: MessageData{
+    fn merge(&mut self, other_data: T);
+}
+
+/// Work other's data into message. Handed to the workers in each package packed by map_reduce, which is the only place where *Merge* is known.
+pub fn merge_message<T, R, E, S>(message: &mut S, other: S) where
+T: MessageData,
+R: MessageInput<T>,
+E: Send + 'static,
+S: Message<T, R, E> + Merge<T>,
+{
+    message.merge(other.take_message_data());
+}
+
+// Integers used as an index, a seed or an id.
+macro_rules! integer_message_input{
+    ($($input:ty),*) => {
+        $(
+            impl<T> MessageInput<T> for $input where T: MessageData{
+                fn new() -> Self{
+                    0
+                }
+            }
+        )*
+    };
+}
 
-}
\ No newline at end of file
+integer_message_input!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize);
+
+/// A range of indexes, like the part of a buffer a message should fill. Weighs its length.
+impl<T> MessageInput<T> for Range<usize> where T: MessageData{
+    fn new() -> Self{
+        0..0
+    }
+
+    fn weight(&self) -> usize{
+        self.len()
+    }
+}
+
+/// Coordinates, or an id with a parameter.
+impl<T, A, B> MessageInput<T> for (A, B) where T: MessageData, A: Default + Sync + Send + 'static, B: Default + Sync + Send + 'static{
+    fn new() -> Self{
+        Default::default()
+    }
+}
+
+impl<T, A, B, C> MessageInput<T> for (A, B, C) where T: MessageData, A: Default + Sync + Send + 'static, B: Default + Sync + Send + 'static,
+C: Default + Sync + Send + 'static{
+    fn new() -> Self{
+        Default::default()
+    }
+}
+
+
+// Numbers and other plain values, like a sum, a count, a flag or a measured time.
+macro_rules! plain_message_data{
+    ($($data:ty),*) => {
+        $(
+            impl MessageData for $data{}
+        )*
+    };
+}
+
+plain_message_data!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64, bool, char, (), Duration);
+
+/// Counts the bytes it holds, spare capacity included.
+impl MessageData for String{
+    fn size_hint(&self) -> usize{
+        std::mem::size_of::<Self>() + self.capacity()
+    }
+}
+
+/// Counts the values it holds, spare capacity included. Heap buffers inside each value aren't counted, wrap the *Vec* in a type of your own for that.
+impl<V> MessageData for Vec<V> where V: Sync + Send + Clone + 'static{
+    fn size_hint(&self) -> usize{
+        std::mem::size_of::<Self>() + self.capacity() * std::mem::size_of::<V>()
+    }
+}
+
+impl<V, const N: usize> MessageData for [V; N] where V: MessageData{
+    fn size_hint(&self) -> usize{
+        self.iter().map(MessageData::size_hint).sum()
+    }
+}
+
+impl<V> MessageData for Option<V> where V: MessageData{
+    fn size_hint(&self) -> usize{
+        match self{
+            Some(data) => std::mem::size_of::<Self>() - std::mem::size_of::<V>() + data.size_hint(),
+            None => std::mem::size_of::<Self>(),
+        }
+    }
+}
+
+impl<V> MessageData for Box<V> where V: MessageData{
+    fn size_hint(&self) -> usize{
+        std::mem::size_of::<Self>() + (**self).size_hint()
+    }
+}
+
+impl<A, B> MessageData for (A, B) where A: MessageData, B: MessageData{
+    fn size_hint(&self) -> usize{
+        std::mem::size_of::<Self>() - std::mem::size_of::<A>() - std::mem::size_of::<B>() + self.0.size_hint() + self.1.size_hint()
+    }
+}
+
+impl<A, B, C> MessageData for (A, B, C) where A: MessageData, B: MessageData, C: MessageData{
+    fn size_hint(&self) -> usize{
+        std::mem::size_of::<Self>() - std::mem::size_of::<A>() - std::mem::size_of::<B>() - std::mem::size_of::<C>()
+            + self.0.size_hint() + self.1.size_hint() + self.2.size_hint()
+    }
+}
@@ -12,14 +12,27 @@
 
 #[cfg(test)]
 mod tests{
-    use crate::message::{Message, MessageData, MessageInput};
-    use crate::channel::{DeliveryService};
+    use crate::message::{Message, MessageData, MessageInput, Merge};
+    use crate::channel::{ChannelConfig, DeliveryService};
+    use crate::error::{WorkError, Closed, StopReason};
+    use crate::scheduler::Scheduler;
+    use crate::context::WorkContext;
+    use std::time::Instant;
+    use std::time::Duration;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    // Passes everything to System. Only counts, for test_alloc_stats.
+    #[global_allocator]
+    static ALLOCATOR: crate::report::CountingAllocator = crate::report::CountingAllocator;
 
     // What type of data should be returned.
     pub struct MessageArray{
         data: [u32; 1024],
     }
 
+    // The example is kept as it was first written, clippy would rewrite it.
+    #[allow(clippy::manual_memcpy)]
     impl Clone for MessageArray{
         fn clone(&self) -> Self{
             let mut new_array: [u32; 1024] = [0; 1024];
@@ -32,15 +45,18 @@ mod tests{
         }
     }
 
-    // with this trait it can be used as data for a Message.
-    impl MessageData for MessageArray{
-        fn new() -> Self{
+    // Arrays this large have no derive(Default).
+    impl Default for MessageArray{
+        fn default() -> Self{
             MessageArray{
                 data: [0; 1024],
             }
         }
     }
 
+    // with this trait it can be used as data for a Message.
+    impl MessageData for MessageArray{}
+
     impl MessageArray{
         pub fn get(&mut self) -> &mut [u32; 1024]{
             &mut self.data
@@ -49,6 +65,7 @@ mod tests{
 
 
     // What kind of input it needs.
+    #[derive(Default)]
     pub struct Coordinates{
         pub x0: usize,
         pub y0: usize,
@@ -70,20 +87,13 @@ mod tests{
 
     // This implementation tells the compiler that this object can be 
     // used as input for the worker threads, and it can only work with MessageArray.
-    impl MessageInput<MessageArray> for Coordinates{
-        fn new() -> Self{
-            Coordinates{
-                x0: 0,
-                y0: 0,
-                x1: 0,
-                y1: 0,
-            }
-        }
-    }
+    // Its new comes from Default.
+    impl MessageInput<MessageArray> for Coordinates{}
 
 
     // This is the message that holds both the data and input. 
     // Feel free to add anything else you might need to work with it.
+    #[derive(Default)]
     pub struct ThreadMessage{
         pub array: MessageArray,
         pub current_input: Coordinates,
@@ -100,6 +110,7 @@ mod tests{
 
     // ThreadMessage uses MessageArray as data,
     // ThreadMessage uses Coordinates as input to change the data.
+    #[allow(clippy::assign_op_pattern)]
     impl Message<MessageArray, Coordinates> for ThreadMessage {
 
         fn set_input(&mut self, message_input: Coordinates){
@@ -151,6 +162,7 @@ mod tests{
 
     // Finally, Now that all the data structure is set, time to use the channel.
     #[test]
+    #[allow(clippy::redundant_field_names, clippy::explicit_counter_loop)]
     fn test(){
         let width: usize = 1024;
         let height: usize = 768;
@@ -233,4 +245,3985 @@ mod tests{
             counter += 1;
         }
     }
-}
\ No newline at end of file
+
+    // Builds one Coordinates input for each 32x32 tile of a width x height image.
+    fn tile_coordinates(width: usize, height: usize) -> Vec<Coordinates>{
+        let mut coordinates: Vec<Coordinates> = Vec::new();
+        for y in 0..(height / 32){
+            for x in 0..(width / 32){
+                let (x0, y0) = (32 * x, 32 * y);
+                coordinates.push(Coordinates{x0, y0, x1: x0 + 32, y1: y0 + 32});
+            }
+        }
+        coordinates
+    }
+
+    #[test]
+    fn test_drain_report(){
+        let mut coordinates = tile_coordinates(256, 256);
+        let total = coordinates.len();
+
+        let mut kiki_channel: DeliveryService<MessageArray,Coordinates,ThreadMessage> = DeliveryService::default();
+        kiki_channel.feed_feeder(&mut coordinates);
+        let (results, report) = kiki_channel.drain();
+
+        assert_eq!(results.len(), total);
+        assert_eq!(report.get_message_count(), total);
+        assert_eq!(report.get_worker_messages().values().sum::<usize>(), total);
+        // Every input is either in a fresh message or in a recycled one.
+        assert_eq!(report.get_allocated_messages() + report.get_recycled_messages(), total);
+        assert!(report.get_work_time_percentile(50.0) <= report.get_work_time_percentile(100.0));
+        assert!(kiki_channel.is_empty());
+    }
+
+    // Small data and input for testing the channel's behavior instead of the work itself.
+    #[derive(Clone, Default)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    pub struct Number(pub u64);
+
+    impl MessageData for Number{}
+
+    impl MessageInput<Number> for Number{
+        fn new() -> Self{
+            Number(0)
+        }
+
+        fn weight(&self) -> usize{
+            self.0 as usize
+        }
+    }
+
+    // Squares the input. Fails for inputs that are multiples of 10 and panics for 13.
+    #[derive(Clone, Default)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    pub struct SquareMessage{
+        pub input: Number,
+        pub output: Number,
+    }
+
+    impl Message<Number, Number, String> for SquareMessage{
+        fn set_input(&mut self, message_input: Number){
+            self.input = message_input;
+        }
+
+        fn work(&mut self){
+            self.output = Number(self.input.0 * self.input.0);
+        }
+
+        fn try_work(&mut self) -> Result<(), String>{
+            if self.input.0 == 13{
+                panic!("Unlucky number");
+            }
+            if self.input.0.is_multiple_of(10){
+                return Err(format!("Can't square {}", self.input.0));
+            }
+            self.work();
+            Ok(())
+        }
+
+        fn clone_message_data(&self) -> Number{
+            self.output.clone()
+        }
+
+        fn new() -> Self{
+            SquareMessage{
+                input: Number(0),
+                output: Number(0),
+            }
+        }
+    }
+
+    #[test]
+    fn test_fallible_results(){
+        let mut inputs: Vec<Number> = (1..=30).map(Number).collect();
+        let mut kiki_channel: DeliveryService<Number, Number, SquareMessage, String> = DeliveryService::default();
+        kiki_channel.feed_feeder(&mut inputs);
+
+        let (mut squares, mut failed, mut panicked) = (0, 0, 0);
+        for result in kiki_channel.results(){
+            match result{
+                Ok(Number(value)) => {
+                    assert_eq!((value as f64).sqrt().fract(), 0.0);
+                    squares += 1;
+                },
+                Err(WorkError::Failed{error, ..}) => {
+                    assert!(error.starts_with("Can't square"));
+                    failed += 1;
+                },
+                Err(WorkError::Panicked{..}) => panicked += 1,
+                #[cfg(feature = "remote")]
+                Err(WorkError::Lost{..}) => unreachable!(),
+            }
+        }
+        assert_eq!((squares, failed, panicked), (26, 3, 1));
+
+        // Workers survive failures and keep working.
+        let mut inputs: Vec<Number> = vec![Number(2), Number(3)];
+        kiki_channel.feed_feeder(&mut inputs);
+        assert_eq!(kiki_channel.results().filter(|result| result.is_ok()).count(), 2);
+    }
+
+    #[test]
+    fn test_iterator_stops_on_failure(){
+        let mut config = ChannelConfig::new();
+        config.set_ordered(true);
+        let mut kiki_channel: DeliveryService<Number, Number, SquareMessage, String> = DeliveryService::try_new(config).unwrap();
+        kiki_channel.feed_feeder(&mut vec![Number(2), Number(10), Number(3)]);
+        let squares: Vec<u64> = (&mut kiki_channel).map(|number| number.0).collect();
+        assert_eq!(squares, vec![4]);
+        assert!(matches!(kiki_channel.last_stop_reason(), Some(StopReason::Error(crate::error::KikError::MessageFailed{..}))));
+
+        // The failed result is gone, the others are still there.
+        let squares: Vec<u64> = (&mut kiki_channel).map(|number| number.0).collect();
+        assert_eq!(squares, vec![9]);
+        assert_eq!(kiki_channel.last_stop_reason(), Some(&StopReason::Completed));
+    }
+
+    #[test]
+    fn test_weak_sender(){
+        let mut kiki_channel: DeliveryService<Number, Number, SquareMessage, String> = DeliveryService::default();
+        let sender = kiki_channel.weak_sender();
+        let producer = sender.clone();
+        std::thread::spawn(move || {
+            producer.feed(&mut vec![Number(2), Number(3)]).unwrap();
+        }).join().unwrap();
+        sender.send(Number(4)).unwrap();
+
+        let total: u64 = kiki_channel.results().map(|result| result.unwrap().0).sum();
+        assert_eq!(total, 4 + 9 + 16);
+
+        // Senders don't keep the channel alive.
+        drop(kiki_channel);
+        assert!(sender.is_closed());
+        assert_eq!(sender.send(Number(5)), Err(Closed));
+    }
+
+    #[test]
+    fn test_ordered(){
+        let mut config = ChannelConfig::new();
+        config.set_worker_number(4);
+        config.set_ordered(true);
+        let mut kiki_channel: DeliveryService<Number, Number, SquareMessage, String> = DeliveryService::try_new(config).unwrap();
+
+        // Feeding twice before iterating, the second batch must come after the first.
+        let inputs: Vec<u64> = (1..500).filter(|x| x % 10 != 0 && *x != 13).collect();
+        let (first, second) = inputs.split_at(200);
+        kiki_channel.feed_feeder(&mut first.iter().copied().map(Number).collect());
+        kiki_channel.feed_feeder(&mut second.iter().copied().map(Number).collect());
+
+        let results: Vec<u64> = kiki_channel.results().map(|result| result.unwrap().0).collect();
+        let expected: Vec<u64> = inputs.iter().map(|x| x * x).collect();
+        assert_eq!(results, expected);
+    }
+
+    #[test]
+    fn test_result_ttl(){
+        let mut config = ChannelConfig::new();
+        config.set_result_ttl(Some(Duration::from_millis(5)));
+        let mut kiki_channel: DeliveryService<Number, Number, SquareMessage, String> = DeliveryService::try_new(config).unwrap();
+        kiki_channel.feed_feeder(&mut (1..10).map(Number).collect());
+
+        // A slow consumer lets the results waiting in the channel get old.
+        let mut delivered = 0;
+        for result in kiki_channel.results(){
+            assert!(result.is_ok());
+            delivered += 1;
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        assert!(kiki_channel.get_discarded_results() > 0);
+        assert_eq!(delivered + kiki_channel.get_discarded_results(), 9);
+    }
+
+    // Needs worker threads.
+    #[cfg(not(any(miri, feature = "inline")))]
+    #[test]
+    fn test_idle_hook(){
+        let idle_calls = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&idle_calls);
+        let mut config = ChannelConfig::new();
+        config.set_worker_number(2);
+        config.set_idle_hook(Duration::from_millis(5), move |worker_id, idle_for| {
+            assert!(worker_id > 0);
+            assert!(idle_for >= Duration::from_millis(5));
+            counter.fetch_add(1, Ordering::SeqCst);
+        });
+        let mut kiki_channel: DeliveryService<Number, Number, SquareMessage, String> = DeliveryService::try_new(config).unwrap();
+        kiki_channel.feed_feeder(&mut vec![Number(3)]);
+        assert_eq!(kiki_channel.results().count(), 1);
+
+        // Workers have nothing left to do.
+        std::thread::sleep(Duration::from_millis(50));
+        assert!(idle_calls.load(Ordering::SeqCst) > 0);
+    }
+
+    #[test]
+    fn test_empty_iteration(){
+        let idle_calls = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&idle_calls);
+        let mut config = ChannelConfig::new();
+        config.set_idle_hook(Duration::from_millis(1), move |_, _| {
+            counter.fetch_add(1, Ordering::SeqCst);
+        });
+        let mut kiki_channel: DeliveryService<Number, Number, SquareMessage, String> = DeliveryService::try_new(config).unwrap();
+
+        assert!(kiki_channel.results().next().is_none());
+        assert!((&mut kiki_channel).next().is_none());
+        let (results, report) = kiki_channel.drain();
+        assert!(results.is_empty());
+        assert_eq!(report.get_message_count(), 0);
+
+        // No worker was spawned, so no worker could report being idle.
+        std::thread::sleep(Duration::from_millis(20));
+        assert_eq!(idle_calls.load(Ordering::SeqCst), 0);
+    }
+
+    // Returns how many messages its worker has worked so far, counted in the worker's own state.
+    #[derive(Clone, Default)]
+    pub struct TallyMessage{
+        pub output: Number,
+    }
+
+    impl Message<Number, Number> for TallyMessage{
+        fn set_input(&mut self, _message_input: Number){}
+
+        fn work(&mut self){}
+
+        fn work_with_context(&mut self, context: &mut WorkContext) -> Result<(), std::convert::Infallible>{
+            let tally: &mut u64 = context.get_worker_context().unwrap();
+            *tally += 1;
+            self.output = Number(*tally);
+            Ok(())
+        }
+
+        fn clone_message_data(&self) -> Number{
+            self.output.clone()
+        }
+
+        fn new() -> Self{
+            TallyMessage{
+                output: Number(0),
+            }
+        }
+    }
+
+    #[test]
+    fn test_worker_context(){
+        let init_calls = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&init_calls);
+        let mut config = ChannelConfig::new();
+        config.set_worker_number(2);
+        config.set_worker_context(move |_worker_id| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            0_u64
+        });
+        let expected_calls = if config.get_inline() { 1 } else { 2 };
+        let mut kiki_channel: DeliveryService<Number, Number, TallyMessage> = DeliveryService::try_new(config).unwrap();
+        kiki_channel.feed_feeder(&mut vec![Number(0); 20]);
+        let tallies: Vec<u64> = (&mut kiki_channel).map(|Number(tally)| tally).collect();
+        assert_eq!(tallies.len(), 20);
+        // The state outlives each message. At least one of the two workers did half of them.
+        assert!(tallies.iter().max().unwrap() >= &10);
+
+        kiki_channel.shutdown();
+        assert_eq!(init_calls.load(Ordering::SeqCst), expected_calls);
+    }
+
+    // Looks the input up in a table shared by every worker.
+    #[derive(Clone, Default)]
+    pub struct LookupMessage{
+        pub input: Number,
+        pub output: Number,
+    }
+
+    impl Message<Number, Number> for LookupMessage{
+        fn set_input(&mut self, message_input: Number){
+            self.input = message_input;
+        }
+
+        fn work(&mut self){}
+
+        fn work_with_context(&mut self, context: &mut WorkContext) -> Result<(), std::convert::Infallible>{
+            let table: Arc<Vec<u64>> = context.get_shared_context().unwrap();
+            self.output = Number(table[self.input.0 as usize]);
+            Ok(())
+        }
+
+        fn clone_message_data(&self) -> Number{
+            self.output.clone()
+        }
+
+        fn new() -> Self{
+            LookupMessage{
+                input: Number(0),
+                output: Number(0),
+            }
+        }
+    }
+
+    #[test]
+    fn test_shared_context(){
+        let mut config = ChannelConfig::new();
+        config.set_worker_number(2);
+        let mut kiki_channel: DeliveryService<Number, Number, LookupMessage> = DeliveryService::try_new(config).unwrap();
+        kiki_channel.set_shared_context(Arc::new((0..10).map(|x| x * x).collect::<Vec<u64>>()));
+        kiki_channel.feed_feeder(&mut (0..10).map(Number).collect());
+        let mut results: Vec<u64> = (&mut kiki_channel).map(|Number(x)| x).collect();
+        results.sort_unstable();
+        assert_eq!(results, (0..10).map(|x| x * x).collect::<Vec<u64>>());
+
+        // Replaced for the next run, without rebuilding the workers.
+        kiki_channel.set_shared_context(Arc::new(vec![7_u64; 10]));
+        kiki_channel.feed_feeder(&mut (0..10).map(Number).collect());
+        assert!((&mut kiki_channel).all(|Number(x)| x == 7));
+    }
+
+    // Looks the input up in its own table, which is empty unless the message was cloned from a template.
+    #[derive(Clone, Default)]
+    pub struct TableMessage{
+        pub table: Vec<u64>,
+        pub input: Number,
+        pub output: Number,
+    }
+
+    impl Message<Number, Number> for TableMessage{
+        fn set_input(&mut self, message_input: Number){
+            self.input = message_input;
+        }
+
+        fn work(&mut self){
+            self.output = Number(self.table.get(self.input.0 as usize).copied().unwrap_or(0));
+        }
+
+        fn clone_message_data(&self) -> Number{
+            self.output.clone()
+        }
+
+        fn new() -> Self{
+            TableMessage{
+                table: Vec::new(),
+                input: Number(0),
+                output: Number(0),
+            }
+        }
+    }
+
+    #[test]
+    fn test_message_template(){
+        let mut config = ChannelConfig::new();
+        config.set_worker_number(2);
+        let mut kiki_channel: DeliveryService<Number, Number, TableMessage> = DeliveryService::try_new(config).unwrap();
+        let mut template = TableMessage::new();
+        template.table = (0..20).map(|x| x * x * x).collect();
+        kiki_channel.set_message_template(template);
+        kiki_channel.feed_feeder(&mut (0..20).map(Number).collect());
+        let mut results: Vec<u64> = (&mut kiki_channel).map(|number| number.0).collect();
+        results.sort_unstable();
+        assert_eq!(results, (0..20).map(|x| x * x * x).collect::<Vec<u64>>());
+
+        // Messages from the last run are gone, so the new ones are built with Message::new.
+        kiki_channel.clear_message_template();
+        kiki_channel.feed_feeder(&mut (0..20).map(Number).collect());
+        assert!((&mut kiki_channel).all(|number| number.0 == 0));
+    }
+
+    // Only counted by test_no_message_clones.
+    static MESSAGE_CLONES: AtomicUsize = AtomicUsize::new(0);
+
+    // Doubles the input, counting every time it's cloned.
+    #[derive(Default)]
+    pub struct CloneCountingMessage{
+        pub output: Number,
+    }
+
+    impl Clone for CloneCountingMessage{
+        fn clone(&self) -> Self{
+            MESSAGE_CLONES.fetch_add(1, Ordering::SeqCst);
+            CloneCountingMessage{
+                output: self.output.clone(),
+            }
+        }
+    }
+
+    impl Message<Number, Number> for CloneCountingMessage{
+        fn set_input(&mut self, message_input: Number){
+            self.output = Number(message_input.0 * 2);
+        }
+
+        fn work(&mut self){}
+
+        fn clone_message_data(&self) -> Number{
+            self.output.clone()
+        }
+
+        fn new() -> Self{
+            CloneCountingMessage{
+                output: Number(0),
+            }
+        }
+    }
+
+    #[test]
+    fn test_no_message_clones(){
+        // Small channels, so that sends keep finding them full.
+        let mut config = ChannelConfig::new();
+        config.set_worker_number(1);
+        config.set_package_number(3);
+        let mut kiki_channel: DeliveryService<Number, Number, CloneCountingMessage> = DeliveryService::try_new(config).unwrap();
+        kiki_channel.feed_feeder(&mut (0..200).map(Number).collect());
+        assert_eq!((&mut kiki_channel).count(), 200);
+        assert_eq!(MESSAGE_CLONES.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn test_throughput(){
+        let mut config = ChannelConfig::new();
+        config.set_worker_number(2);
+        config.set_throughput_history(Some(Duration::from_secs(60)));
+        let mut kiki_channel: DeliveryService<Number, Number, SquareMessage, String> = DeliveryService::try_new(config).unwrap();
+        kiki_channel.feed_feeder(&mut (0..50).map(Number).collect());
+        assert_eq!(kiki_channel.results().count(), 50);
+
+        let throughput = kiki_channel.throughput(Duration::from_secs(10)).unwrap();
+        assert_eq!(throughput.get_messages(), 50);
+        assert_eq!(throughput.get_bytes(), 50 * std::mem::size_of::<SquareMessage>());
+        // The channel is younger than the window asked for.
+        assert!(throughput.get_window() < Duration::from_secs(10));
+        assert!(throughput.get_messages_per_second() > 5.0);
+        assert!(throughput.get_bytes_per_second() > throughput.get_messages_per_second());
+
+        // Nothing was finished in an empty window.
+        assert_eq!(kiki_channel.throughput(Duration::ZERO).unwrap().get_messages_per_second(), 0.0);
+
+        let untracked: DeliveryService<Number, Number, SquareMessage, String> = DeliveryService::default();
+        assert!(untracked.throughput(Duration::from_secs(10)).is_none());
+    }
+
+    #[test]
+    fn test_stats(){
+        let mut config = ChannelConfig::new();
+        config.set_worker_number(2);
+        config.set_metrics(true);
+        let mut kiki_channel: DeliveryService<Number, Number, SquareMessage, String> = DeliveryService::try_new(config).unwrap();
+        kiki_channel.feed_feeder(&mut (1..31).map(Number).collect());
+        assert_eq!(kiki_channel.results().count(), 30);
+
+        let stats = kiki_channel.stats().unwrap();
+        assert_eq!(stats.get_message_count(), 30);
+        // 10, 20, 30 and the panic on 13.
+        assert_eq!(stats.get_failed_count(), 4);
+        assert_eq!(stats.get_worker_messages().values().sum::<usize>(), 30);
+        assert!(stats.get_work_time_percentile(50.0) <= stats.get_work_time_percentile(99.0));
+        // Latency includes the work itself.
+        assert!(stats.get_average_latency() >= stats.get_average_work_time());
+        assert!(stats.get_latency_percentile(100.0) >= stats.get_work_time_percentile(100.0));
+        for (worker_id, idle) in stats.get_worker_idle_time(){
+            assert_eq!(idle + stats.get_worker_busy_time()[&worker_id], stats.get_elapsed());
+        }
+        assert_eq!(stats.get_worker_throughput().len(), stats.get_worker_messages().len());
+
+        kiki_channel.reset_stats();
+        assert_eq!(kiki_channel.stats().unwrap().get_message_count(), 0);
+
+        let untracked: DeliveryService<Number, Number, SquareMessage, String> = DeliveryService::default();
+        assert!(untracked.stats().is_none());
+    }
+
+    #[test]
+    fn test_config_builder(){
+        use crate::channel::MIN_STACK_SIZE;
+        use crate::error::ConfigError;
+
+        // Channel size and package number follow the worker number unless set.
+        let config = ChannelConfig::builder().worker_number(3).build().unwrap();
+        assert_eq!((config.get_worker_number(), config.get_channel_size(), config.get_package_number()), (3, 3, 6));
+        let config = ChannelConfig::builder().package_number(5).channel_size(2).worker_number(4).stack_size(MIN_STACK_SIZE).build().unwrap();
+        assert_eq!((config.get_worker_number(), config.get_channel_size(), config.get_package_number()), (4, 2, 5));
+        assert_eq!(config.get_stack_size(), MIN_STACK_SIZE);
+
+        assert_eq!(ChannelConfig::builder().worker_number(0).build().err(), Some(ConfigError::NoWorkers));
+        assert_eq!(ChannelConfig::builder().channel_size(0).build().err(), Some(ConfigError::NoChannelSize));
+        assert_eq!(ChannelConfig::builder().worker_number(4).channel_size(1).build().err(), Some(ConfigError::NotEnoughPackages{ package_number: 2, worker_number: 4 }));
+        assert_eq!(ChannelConfig::builder().worker_number(2).channel_size(1).package_number(5).build().err(), Some(ConfigError::TooManyPackages{ package_number: 5, max_package_number: 4 }));
+        assert_eq!(ChannelConfig::builder().stack_size(1024).build().err(), Some(ConfigError::StackTooSmall{ stack_size: 1024 }));
+
+        // A channel built from the tightest valid config still finishes.
+        let config = ChannelConfig::builder().worker_number(2).channel_size(1).package_number(4).build().unwrap();
+        let mut kiki_channel: DeliveryService<Number, Number, SquareMessage, String> = DeliveryService::try_new(config).unwrap();
+        kiki_channel.feed_feeder(&mut (0..50).map(Number).collect());
+        assert_eq!(kiki_channel.results().count(), 50);
+    }
+
+    // Zero is interactive, one is soon, anything else is background.
+    impl crate::scheduler::DeadlineInput for Number{
+        fn deadline_class(&self) -> crate::scheduler::DeadlineClass{
+            use crate::scheduler::DeadlineClass;
+            match self.0{
+                0 => DeadlineClass::Interactive,
+                1 => DeadlineClass::Soon,
+                _ => DeadlineClass::Background,
+            }
+        }
+    }
+
+    #[test]
+    fn test_deadline_scheduler(){
+        use crate::scheduler::DeadlineScheduler;
+        use std::sync::Mutex;
+
+        let mut scheduler: DeadlineScheduler<Number> = DeadlineScheduler::new();
+        scheduler.push(&mut vec![Number(2), Number(1), Number(0), Number(3), Number(0)]);
+        assert_eq!(scheduler.len(), 5);
+        let order: Vec<u64> = std::iter::from_fn(|| scheduler.next()).map(|number| number.0).collect();
+        assert_eq!(order, vec![0, 0, 1, 2, 3]);
+
+        // Late interactive inputs are shed, background ones never are.
+        let shed: Arc<Mutex<Vec<u64>>> = Arc::new(Mutex::new(Vec::new()));
+        let sink = shed.clone();
+        scheduler.set_shedding(move |number: Number| sink.lock().unwrap().push(number.0));
+        scheduler.push(&mut vec![Number(0), Number(2)]);
+        std::thread::sleep(Duration::from_millis(20));
+        scheduler.push(&mut vec![Number(0)]);
+        assert_eq!(scheduler.next().unwrap().0, 0);
+        assert_eq!(scheduler.next().unwrap().0, 2);
+        assert!(scheduler.next().is_none());
+        assert_eq!(*shed.lock().unwrap(), vec![0]);
+
+        // Works as the channel's scheduler like any other.
+        let mut kiki_channel: DeliveryService<Number, Number, SquareMessage, String> = DeliveryService::default();
+        kiki_channel.set_scheduler(DeadlineScheduler::new());
+        kiki_channel.feed_feeder(&mut (0..30).map(|x| Number(x % 4)).collect());
+        assert_eq!(kiki_channel.results().count(), 30);
+    }
+
+    // Only counted by test_take_message_data.
+    static DATA_CLONES: AtomicUsize = AtomicUsize::new(0);
+    static DATA_TAKES: AtomicUsize = AtomicUsize::new(0);
+
+    // Doubles the input, counting how its data was taken out.
+    #[derive(Clone, Default)]
+    pub struct TakingMessage{
+        pub output: Number,
+    }
+
+    impl Message<Number, Number> for TakingMessage{
+        fn set_input(&mut self, message_input: Number){
+            self.output = Number(message_input.0 * 2);
+        }
+
+        fn work(&mut self){}
+
+        fn clone_message_data(&self) -> Number{
+            DATA_CLONES.fetch_add(1, Ordering::SeqCst);
+            self.output.clone()
+        }
+
+        fn take_message_data(self) -> Number{
+            DATA_TAKES.fetch_add(1, Ordering::SeqCst);
+            self.output
+        }
+
+        fn new() -> Self{
+            TakingMessage{
+                output: Number(0),
+            }
+        }
+    }
+
+    #[test]
+    fn test_take_message_data(){
+        let mut config = ChannelConfig::new();
+        config.set_worker_number(2);
+        config.set_package_number(4);
+        let mut kiki_channel: DeliveryService<Number, Number, TakingMessage> = DeliveryService::try_new(config).unwrap();
+        kiki_channel.feed_feeder(&mut (0..100).map(Number).collect());
+        let mut results: Vec<u64> = (&mut kiki_channel).map(|number| number.0).collect();
+        results.sort_unstable();
+        assert_eq!(results, (0..100).map(|x| x * 2).collect::<Vec<u64>>());
+
+        // Each message built is taken from once, when it isn't needed anymore. Every other result is cloned.
+        // The first message of a run is never recycled, so there can be one more than the package number.
+        let takes = DATA_TAKES.load(Ordering::SeqCst);
+        assert!((1..=5).contains(&takes));
+        assert_eq!(DATA_CLONES.load(Ordering::SeqCst) + takes, 100);
+    }
+
+    #[test]
+    fn test_work_queue(){
+        use std::sync::mpsc::{TrySendError, RecvTimeoutError};
+        use crate::kik_queue::work_queue;
+
+        let (tx, rx) = work_queue::<usize>(2);
+        assert!(tx.try_send(1, None).is_ok());
+        assert!(tx.try_send(2, None).is_ok());
+        assert!(matches!(tx.try_send(3, None), Err(TrySendError::Full(3))));
+        assert_eq!(rx.recv(), Some(1));
+        assert!(tx.try_send(3, None).is_ok());
+        assert_eq!(rx.recv_timeout(Duration::from_millis(10)), Ok(2));
+        assert_eq!(rx.recv(), Some(3));
+        assert_eq!(rx.recv_timeout(Duration::from_millis(10)), Err(RecvTimeoutError::Timeout));
+
+        // Weak receivers only work while the strong one is alive.
+        let weak = rx.downgrade();
+        assert!(weak.upgrade().is_some());
+        drop(rx);
+        assert!(weak.upgrade().is_none());
+        assert!(matches!(tx.try_send(4, None), Err(TrySendError::Disconnected(4))));
+
+        // Packages still queued are received before the disconnection.
+        let (tx, rx) = work_queue::<usize>(2);
+        assert!(tx.try_send(5, None).is_ok());
+        drop(tx);
+        assert_eq!(rx.recv(), Some(5));
+        assert_eq!(rx.recv(), None);
+        assert_eq!(rx.recv_timeout(Duration::from_millis(10)), Err(RecvTimeoutError::Disconnected));
+
+        // A single slot is full after one package.
+        let (tx, rx) = work_queue::<usize>(1);
+        assert!(tx.try_send(6, None).is_ok());
+        assert!(matches!(tx.try_send(7, None), Err(TrySendError::Full(7))));
+        assert_eq!(rx.recv(), Some(6));
+        assert!(tx.try_send(7, None).is_ok());
+        assert_eq!(rx.recv(), Some(7));
+    }
+
+    #[cfg(not(miri))]
+    #[test]
+    fn test_work_queue_threads(){
+        use std::sync::mpsc::TrySendError;
+        use crate::kik_queue::work_queue;
+
+        // Several receivers sleeping and waking on a small queue. Every package is received exactly once.
+        let (tx, rx) = work_queue::<usize>(3);
+        let receivers: Vec<_> = (0..4).map(|_| {
+            let weak = rx.downgrade();
+            std::thread::spawn(move || {
+                let mut received: Vec<usize> = Vec::new();
+                while let Some(package) = weak.upgrade().and_then(|rx| rx.recv()){
+                    received.push(package);
+                }
+                received
+            })
+        }).collect();
+        for mut package in 0..10_000{
+            loop{
+                match tx.try_send(package, None){
+                    Ok(_) => break,
+                    Err(TrySendError::Full(returned)) => {
+                        package = returned;
+                        std::thread::yield_now();
+                    },
+                    Err(TrySendError::Disconnected(_)) => panic!("Receiver dropped while sending."),
+                }
+            }
+        }
+        drop(tx);
+        let mut received: Vec<usize> = receivers.into_iter().flat_map(|receiver| receiver.join().unwrap()).collect();
+        received.sort_unstable();
+        assert_eq!(received, (0..10_000).collect::<Vec<usize>>());
+    }
+
+    #[test]
+    fn test_backoff(){
+        use crate::kik_backoff::{Backoff, SEND_RETRIES, WaitStrategy};
+
+        let mut backoff = Backoff::new();
+        for _ in 0..SEND_RETRIES{
+            assert!(backoff.retry());
+        }
+        // Budget spent, the sender should block now.
+        assert!(!backoff.retry());
+
+        // Busy strategies never run out, parking has no budget at all.
+        for strategy in [WaitStrategy::SpinHint, WaitStrategy::Yield].iter(){
+            let mut backoff = Backoff::with_strategy(*strategy);
+            assert!((0..SEND_RETRIES * 4).all(|_| backoff.retry()));
+        }
+        assert!(!Backoff::with_strategy(WaitStrategy::Park).retry());
+    }
+
+    // Needs worker threads.
+    #[cfg(not(any(miri, feature = "inline")))]
+    #[test]
+    fn test_stall_timeout(){
+        use crate::error::KikError;
+
+        let mut config = ChannelConfig::new();
+        config.set_worker_number(1);
+        config.set_stall_timeout(Some(Duration::from_millis(50)));
+        let mut kiki_channel: DeliveryService<Number, Number, SlowMessage> = DeliveryService::try_new(config).unwrap();
+        // The slow message goes first and holds the only worker.
+        kiki_channel.feed_feeder(&mut vec![Number(300), Number(1), Number(1), Number(1)]);
+        assert!((&mut kiki_channel).next().is_none());
+        assert_eq!(kiki_channel.last_stop_reason(), Some(&StopReason::Error(KikError::Stalled{timeout: Duration::from_millis(50), in_flight: 2})));
+
+        // The run goes on once the slow message comes back, nothing was lost.
+        let mut returned = 0;
+        for _ in 0..100{
+            if (&mut kiki_channel).next().is_some(){
+                returned += 1;
+            } else if kiki_channel.last_stop_reason() == Some(&StopReason::Completed){
+                break;
+            }
+        }
+        assert_eq!(returned, 4);
+    }
+
+    #[test]
+    fn test_try_new(){
+        use crate::error::{KikError, ConfigError};
+
+        // One worker and a channel of one can't hold ten packages.
+        let mut config = ChannelConfig::new();
+        config.set_worker_number(1);
+        config.set_package_number(10);
+        let result: Result<DeliveryService<Number, Number, SquareMessage, String>, KikError> = DeliveryService::try_new(config);
+        assert_eq!(result.err(), Some(KikError::Config(ConfigError::TooManyPackages{package_number: 10, max_package_number: 3})));
+
+        let mut kiki_channel: DeliveryService<Number, Number, SquareMessage, String> = DeliveryService::try_new(ChannelConfig::new()).unwrap();
+        kiki_channel.feed_feeder(&mut (1..=10).map(Number).collect());
+        assert_eq!(kiki_channel.results().count(), 10);
+    }
+
+    // Needs worker threads.
+    #[cfg(not(any(miri, feature = "inline")))]
+    #[test]
+    fn test_spawn_failed(){
+        use crate::error::KikError;
+
+        // No system gives out a stack this big.
+        let mut config = ChannelConfig::new();
+        config.set_worker_number(2);
+        config.set_stack_size(1usize << 46);
+        let mut kiki_channel: DeliveryService<Number, Number, SquareMessage, String> = DeliveryService::try_new(config).unwrap();
+        kiki_channel.feed_feeder(&mut (1..=10).map(Number).collect());
+        assert!((&mut kiki_channel).next().is_none());
+        assert!(matches!(kiki_channel.last_stop_reason(), Some(StopReason::Error(KikError::SpawnFailed{worker_id: 1, ..}))));
+    }
+
+    #[cfg(not(any(miri, feature = "inline")))]
+    #[test]
+    fn test_warm_up(){
+        // Lazy by default, nothing was fed so no worker was spawned.
+        let kiki_channel: DeliveryService<Number, Number, SquareMessage, String> = DeliveryService::default();
+        assert_eq!(kiki_channel.shutdown().get_joined_workers(), 0);
+
+        let mut kiki_channel: DeliveryService<Number, Number, SquareMessage, String> = DeliveryService::default();
+        kiki_channel.warm_up().unwrap();
+        let workers = kiki_channel.get_worker_number();
+        assert_eq!(kiki_channel.shutdown().get_joined_workers(), workers);
+
+        let mut config = ChannelConfig::builder().worker_number(3).build().unwrap();
+        config.set_spawn_eagerly(true);
+        let mut kiki_channel: DeliveryService<Number, Number, SquareMessage, String> = DeliveryService::try_new(config).unwrap();
+        // Spawning again finds every worker running.
+        kiki_channel.warm_up().unwrap();
+        kiki_channel.feed_feeder(&mut (1..=9).map(Number).collect());
+        assert_eq!(kiki_channel.results().count(), 9);
+        assert_eq!(kiki_channel.shutdown().get_joined_workers(), 3);
+    }
+
+    #[cfg(not(any(miri, feature = "inline")))]
+    #[test]
+    fn test_idle_timeout(){
+        use crate::event::PoolEvent;
+
+        let mut config = ChannelConfig::builder().worker_number(3).build().unwrap();
+        config.set_idle_timeout(Some(Duration::from_millis(100)));
+        let mut kiki_channel: DeliveryService<Number, Number, SquareMessage, String> = DeliveryService::try_new(config).unwrap();
+        let events = kiki_channel.events();
+        let count_events = |started: &mut usize, exited: &mut usize| {
+            for event in events.try_iter(){
+                match event{
+                    PoolEvent::WorkerStarted{..} => *started += 1,
+                    PoolEvent::WorkerExited{..} => *exited += 1,
+                    _ => {},
+                }
+            }
+        };
+        let (mut started, mut exited) = (0, 0);
+        for _ in 0..2{
+            kiki_channel.feed_feeder(&mut (1..=9).map(Number).collect());
+            assert_eq!(kiki_channel.results().count(), 9);
+            std::thread::sleep(Duration::from_millis(500));
+            // Every worker but the last one left.
+            count_events(&mut started, &mut exited);
+            assert!(started >= 3);
+            assert_eq!(exited, started - 1);
+        }
+        // The second run spawned the ones that left.
+        assert!(started >= 5);
+        assert_eq!(kiki_channel.shutdown().get_joined_workers(), started);
+    }
+
+    #[cfg(not(any(miri, feature = "inline")))]
+    #[test]
+    fn test_thread_spawner(){
+        use crate::channel::WorkerThread;
+        use crate::error::KikError;
+
+        // Two threads stand in for an existing pool, each one runs the workers it's handed.
+        let spawned = Arc::new(AtomicUsize::new(0));
+        let counter = spawned.clone();
+        let mut config = ChannelConfig::builder().worker_number(2).build().unwrap();
+        config.set_name(Some(String::from("spawned")));
+        config.set_thread_spawner(move |thread: WorkerThread| {
+            assert_eq!(thread.get_name(), format!("spawned worker {}", thread.get_worker_id()));
+            counter.fetch_add(1, Ordering::SeqCst);
+            std::thread::Builder::new()
+                .name(format!("pool thread {}", thread.get_worker_id()))
+                .spawn(move || thread.run())
+                .map(|_| ())
+        });
+        let mut kiki_channel: DeliveryService<Number, Number, SquareMessage, String> = DeliveryService::try_new(config).unwrap();
+        kiki_channel.feed_feeder(&mut (1..=9).map(Number).collect());
+        assert_eq!(kiki_channel.results().count(), 9);
+        assert_eq!(spawned.load(Ordering::SeqCst), 2);
+        assert_eq!(kiki_channel.shutdown().get_joined_workers(), 2);
+
+        // A spawner that gives up fails the run like a thread that couldn't be spawned.
+        let mut config = ChannelConfig::new();
+        config.set_thread_spawner(|_thread: WorkerThread| Err(std::io::Error::other("no threads left")));
+        let mut kiki_channel: DeliveryService<Number, Number, SquareMessage, String> = DeliveryService::try_new(config).unwrap();
+        kiki_channel.feed_feeder(&mut (1..=9).map(Number).collect());
+        assert!((&mut kiki_channel).next().is_none());
+        assert!(matches!(kiki_channel.last_stop_reason(), Some(StopReason::Error(KikError::SpawnFailed{worker_id: 1, ..}))));
+    }
+
+    // Needs worker threads.
+    #[cfg(not(any(miri, feature = "inline")))]
+    #[test]
+    fn test_wait_strategy(){
+        use crate::channel::WaitStrategy;
+
+        assert_eq!(ChannelConfig::new().get_wait_strategy(), WaitStrategy::ExponentialBackoff);
+        for strategy in [WaitStrategy::SpinHint, WaitStrategy::Yield, WaitStrategy::ExponentialBackoff, WaitStrategy::Park].iter(){
+            // Small channels, so that both sides keep finding them full.
+            let mut config = ChannelConfig::builder().worker_number(2).channel_size(1).package_number(3).build().unwrap();
+            config.set_wait_strategy(*strategy);
+            let mut kiki_channel: DeliveryService<Number, Number, SquareMessage, String> = DeliveryService::try_new(config).unwrap();
+            kiki_channel.feed_feeder(&mut (1..=50).map(Number).collect());
+            assert_eq!(kiki_channel.results().count(), 50);
+            // Spinning workers still leave when the channel is dropped.
+            kiki_channel.shutdown();
+        }
+    }
+
+    #[cfg(not(miri))]
+    #[test]
+    fn test_blocking_send(){
+        use crate::kik_queue::{WorkSender, WorkReceiver, work_queue, stealing_queue};
+
+        let check = |(tx, rx): (WorkSender<usize>, WorkReceiver<usize>)| {
+            assert!(tx.send(1, None).is_ok());
+            // Full. Sleeps until the other thread takes the first one.
+            let receiver = std::thread::spawn(move || {
+                std::thread::sleep(Duration::from_millis(20));
+                let first = rx.recv();
+                (first, rx)
+            });
+            assert!(tx.send(2, None).is_ok());
+            let (first, rx) = receiver.join().unwrap();
+            assert_eq!(first, Some(1));
+            assert_eq!(rx.recv(), Some(2));
+            // Nobody left to take it.
+            drop(rx);
+            assert_eq!(tx.send(3, None), Err(3));
+        };
+        check(work_queue(1));
+        check(stealing_queue(1, 1));
+    }
+
+    #[test]
+    fn test_stealing_queue(){
+        use std::sync::mpsc::TrySendError;
+        use crate::kik_queue::stealing_queue;
+
+        // Two lanes of two. Packages go around the lanes, the first weak receiver prefers lane 0.
+        let (tx, rx) = stealing_queue::<usize>(4, 2);
+        let first = rx.downgrade().upgrade().unwrap();
+        for package in 1..=4{
+            assert!(tx.try_send(package, None).is_ok());
+        }
+        assert!(matches!(tx.try_send(5, None), Err(TrySendError::Full(5))));
+
+        // Oldest first from its own lane, then newest first from the other.
+        drop(tx);
+        let received: Vec<usize> = std::iter::from_fn(|| first.recv()).collect();
+        assert_eq!(received, vec![1, 3, 4, 2]);
+    }
+
+    #[cfg(not(any(miri, feature = "inline")))]
+    #[test]
+    fn test_work_stealing(){
+        // Some inputs take much longer than others. Every one is still worked once.
+        let mut config = ChannelConfig::new();
+        config.set_worker_number(4);
+        config.set_work_stealing(true);
+        let mut kiki_channel: DeliveryService<Number, Number, SlowMessage> = DeliveryService::try_new(config).unwrap();
+        kiki_channel.feed_feeder(&mut (0..40).map(|x| Number(if x % 8 == 0 { 20 } else { x % 3 })).collect());
+        let mut results: Vec<u64> = (&mut kiki_channel).map(|number| number.0).collect();
+        results.sort_unstable();
+        let mut expected: Vec<u64> = (0..40).map(|x| if x % 8 == 0 { 20 } else { x % 3 }).collect();
+        expected.sort_unstable();
+        assert_eq!(results, expected);
+
+        // Workers added later share the lanes.
+        kiki_channel.add_workers(2).unwrap();
+        kiki_channel.feed_feeder(&mut vec![Number(1); 30]);
+        assert_eq!((&mut kiki_channel).count(), 30);
+    }
+
+    #[test]
+    fn test_keyed_queue(){
+        use crate::kik_queue::keyed_queue;
+
+        // Two lanes. Each weak receiver claims one, and only takes from its own.
+        let (tx, rx) = keyed_queue::<u64>(2);
+        let first = rx.downgrade();
+        let second = rx.downgrade();
+        for package in [10, 11, 20, 12]{
+            assert!(tx.try_send(package, Some(package / 10 - 1)).is_ok());
+        }
+        let second_lane: Vec<u64> = std::iter::from_fn(|| second.upgrade().unwrap().try_recv().ok()).collect();
+        assert_eq!(second_lane, vec![20]);
+        assert_eq!(first.upgrade().unwrap().try_recv().ok(), Some(10));
+
+        // Once the first receiver is gone, the second one adopts its lane, in order.
+        drop(first);
+        let adopted: Vec<u64> = std::iter::from_fn(|| second.upgrade().unwrap().try_recv().ok()).collect();
+        assert_eq!(adopted, vec![11, 12]);
+    }
+
+    // Input with a routing key. Number n of its key.
+    #[derive(Clone, Default)]
+    pub struct KeyedInput{
+        pub key: u64,
+        pub n: u64,
+    }
+
+    impl MessageInput<Numbers> for KeyedInput{
+        fn routing_key(&self) -> Option<u64>{
+            Some(self.key)
+        }
+    }
+
+    // Gives back the key, n, and how many inputs of that key its worker has seen, counted in the worker's own state.
+    #[derive(Clone, Default)]
+    pub struct KeyedMessage{
+        pub input: KeyedInput,
+        pub output: Numbers,
+    }
+
+    impl Message<Numbers, KeyedInput> for KeyedMessage{
+        fn set_input(&mut self, message_input: KeyedInput){
+            self.input = message_input;
+        }
+
+        fn work(&mut self){}
+
+        fn work_with_context(&mut self, context: &mut WorkContext) -> Result<(), std::convert::Infallible>{
+            let seen: &mut std::collections::BTreeMap<u64, u64> = context.get_worker_context().unwrap();
+            let count = seen.entry(self.input.key).or_insert(0);
+            *count += 1;
+            self.output = Numbers(vec![self.input.key, self.input.n, *count]);
+            Ok(())
+        }
+
+        fn clone_message_data(&self) -> Numbers{
+            self.output.clone()
+        }
+
+        fn new() -> Self{
+            KeyedMessage{
+                input: KeyedInput::new(),
+                output: Numbers(Vec::new()),
+            }
+        }
+    }
+
+    #[test]
+    fn test_keyed_dispatch(){
+        let mut config = ChannelConfig::new();
+        config.set_worker_number(3);
+        config.set_keyed_dispatch(true);
+        config.set_worker_context(|_worker_id| std::collections::BTreeMap::<u64, u64>::new());
+        let mut kiki_channel: DeliveryService<Numbers, KeyedInput, KeyedMessage> = DeliveryService::try_new(config).unwrap();
+        kiki_channel.feed_feeder(&mut (0..60).map(|i| KeyedInput{ key: i % 4, n: i / 4 }).collect());
+        let results: Vec<Numbers> = (&mut kiki_channel).collect();
+        assert_eq!(results.len(), 60);
+        // The same worker saw every input of the key, in order.
+        for Numbers(result) in results{
+            assert_eq!(result[2], result[1] + 1);
+        }
+
+        // The keys of a removed worker move to another one.
+        kiki_channel.remove_workers(2);
+        kiki_channel.feed_feeder(&mut (0..30).map(|i| KeyedInput{ key: i % 4, n: i / 4 }).collect());
+        assert_eq!((&mut kiki_channel).count(), 30);
+    }
+
+    // Recurses as many levels as the input, with a kilobyte on the stack for each, and probes the stack at the bottom.
+    #[derive(Clone, Default)]
+    pub struct DeepMessage{
+        pub input: Number,
+        pub output: Number,
+    }
+
+    fn recurse(levels: u64, context: &mut WorkContext) -> u64{
+        let buffer = std::hint::black_box([levels as u8; 1024]);
+        if levels == 0{
+            context.probe_stack();
+            return 0;
+        }
+        recurse(levels - 1, context) + buffer[0] as u64
+    }
+
+    impl Message<Number, Number> for DeepMessage{
+        fn set_input(&mut self, message_input: Number){
+            self.input = message_input;
+        }
+
+        fn work(&mut self){}
+
+        fn work_with_context(&mut self, context: &mut WorkContext) -> Result<(), std::convert::Infallible>{
+            self.output = Number(recurse(self.input.0, context));
+            Ok(())
+        }
+
+        fn clone_message_data(&self) -> Number{
+            self.output.clone()
+        }
+
+        fn new() -> Self{
+            DeepMessage{
+                input: Number(0),
+                output: Number(0),
+            }
+        }
+    }
+
+    #[test]
+    fn test_stack_probe(){
+        let mut config = ChannelConfig::new();
+        config.set_worker_number(2);
+        config.set_stack_probe(true);
+        let inline = config.get_inline();
+        let mut kiki_channel: DeliveryService<Number, Number, DeepMessage> = DeliveryService::try_new(config).unwrap();
+        kiki_channel.feed_feeder(&mut vec![Number(64); 4]);
+        assert_eq!((&mut kiki_channel).count(), 4);
+
+        match kiki_channel.get_stack_usage(){
+            Some(usage) => {
+                assert!(usage.get_peak() >= 64 * 1024);
+                assert!(usage.get_headroom() < usage.get_stack_size() - 64 * 1024);
+                assert!(!usage.get_worker_peaks().is_empty());
+            },
+            // No worker threads to measure.
+            None => assert!(inline),
+        }
+
+        let untracked: DeliveryService<Number, Number, DeepMessage> = DeliveryService::default();
+        assert!(untracked.get_stack_usage().is_none());
+    }
+
+    // A growable buffer as data, for tests that care about memory.
+    #[derive(Clone, Default)]
+    pub struct Numbers(pub Vec<u64>);
+
+    impl MessageData for Numbers{}
+
+    impl MessageInput<Numbers> for Number{
+        fn new() -> Self{
+            Number(0)
+        }
+    }
+
+    // Fills the buffer with the numbers from 0 to the input.
+    #[derive(Clone, Default)]
+    pub struct CountMessage{
+        pub input: Number,
+        pub output: Numbers,
+    }
+
+    impl Message<Numbers, Number> for CountMessage{
+        fn set_input(&mut self, message_input: Number){
+            self.input = message_input;
+        }
+
+        fn work(&mut self){
+            self.output.0.clear();
+            self.output.0.extend(0..self.input.0);
+        }
+
+        fn clone_message_data(&self) -> Numbers{
+            self.output.clone()
+        }
+
+        fn new() -> Self{
+            CountMessage{
+                input: Number(0),
+                output: Numbers::new(),
+            }
+        }
+
+        fn payload_size(&self) -> usize{
+            self.output.0.capacity() * std::mem::size_of::<u64>()
+        }
+    }
+
+    #[test]
+    fn test_memory_tracking(){
+        let mut config = ChannelConfig::new();
+        config.set_worker_number(1);
+        config.set_package_number(2);
+        config.set_ordered(true);
+        config.set_memory_tracking(true);
+        let mut kiki_channel: DeliveryService<Numbers, Number, CountMessage> = DeliveryService::try_new(config).unwrap();
+        // Each input needs a bigger buffer than the one before.
+        kiki_channel.feed_feeder(&mut (1..=64).map(|x| Number(x * 16)).collect());
+        assert_eq!(kiki_channel.drain().0.len(), 64);
+
+        let memory = kiki_channel.get_memory_stats().unwrap();
+        assert_eq!(memory.get_slots().values().map(|slot| slot.get_samples()).sum::<usize>(), 64);
+        assert!(memory.get_peak() >= 64 * 16 * std::mem::size_of::<u64>());
+        assert!(memory.get_growths() > 0);
+
+        let untracked: DeliveryService<Numbers, Number, CountMessage> = DeliveryService::default();
+        assert!(untracked.get_memory_stats().is_none());
+    }
+
+    // Dispatches the smallest number first.
+    pub struct SmallestFirst(Vec<Number>);
+
+    impl Scheduler<Number> for SmallestFirst{
+        fn push(&mut self, input_vec: &mut Vec<Number>){
+            self.0.append(input_vec);
+            self.0.sort_by_key(|number| std::cmp::Reverse(number.0));
+        }
+
+        fn next(&mut self) -> Option<Number>{
+            self.0.pop()
+        }
+
+        fn len(&self) -> usize{
+            self.0.len()
+        }
+    }
+
+    #[test]
+    fn test_custom_scheduler(){
+        let mut config = ChannelConfig::new();
+        config.set_ordered(true);
+        let mut kiki_channel: DeliveryService<Number, Number, SquareMessage, String> = DeliveryService::try_new(config).unwrap();
+        kiki_channel.feed_feeder(&mut vec![Number(9), Number(2), Number(7)]);
+        // Inputs fed before the scheduler is set are moved into it.
+        kiki_channel.set_scheduler(SmallestFirst(Vec::new()));
+        kiki_channel.feed_feeder(&mut vec![Number(5), Number(1)]);
+
+        let results: Vec<u64> = kiki_channel.results().map(|result| result.unwrap().0).collect();
+        assert_eq!(results, vec![1, 4, 25, 49, 81]);
+    }
+
+    #[test]
+    fn test_work_counts(){
+        use crate::channel::SequentialDeliveryService;
+
+        // Messages are worked as soon as they're sent, so the counts are exact.
+        let mut config = ChannelConfig::new();
+        config.set_worker_number(1);
+        config.set_package_number(3);
+        let mut sequential: SequentialDeliveryService<Number, Number, SquareMessage, String> = SequentialDeliveryService::try_new(config).unwrap();
+        sequential.feed_feeder(&mut (1..=5).map(Number).collect());
+        assert_eq!((sequential.pending_inputs(), sequential.in_flight(), sequential.ready_results()), (5, 0, 0));
+        assert!((&mut sequential).next().is_some());
+        // Three were sent, one came back and was replaced by the next input.
+        assert_eq!((sequential.pending_inputs(), sequential.in_flight(), sequential.ready_results()), (1, 0, 3));
+
+        // With workers, the results move between the counts, but none is lost.
+        let mut kiki_channel: DeliveryService<Number, Number, SlowMessage> = DeliveryService::default();
+        kiki_channel.feed_feeder(&mut vec![Number(1); 40]);
+        assert_eq!(kiki_channel.pending_inputs(), 40);
+        for returned in 1..=40{
+            assert!((&mut kiki_channel).next().is_some());
+            // Results keep arriving while counting. Counting in flight first, one may be counted as ready too. Counting ready first, one may be missed.
+            let pending = kiki_channel.pending_inputs();
+            let over = kiki_channel.in_flight() + kiki_channel.ready_results();
+            let under = kiki_channel.ready_results() + kiki_channel.in_flight();
+            assert!(pending + under <= 40 - returned && 40 - returned <= pending + over);
+        }
+    }
+
+    #[test]
+    fn test_dispatch_order(){
+        use crate::scheduler::DispatchOrder;
+
+        // In ordered mode the results come in dispatch order.
+        for (dispatch_order, expected) in [(DispatchOrder::Fifo, vec![1, 4, 9, 16]), (DispatchOrder::Lifo, vec![16, 9, 4, 1])].iter(){
+            let mut config = ChannelConfig::new();
+            config.set_ordered(true);
+            config.set_dispatch_order(*dispatch_order);
+            let mut kiki_channel: DeliveryService<Number, Number, SquareMessage, String> = DeliveryService::try_new(config).unwrap();
+            kiki_channel.feed_feeder(&mut (1..=4).map(Number).collect());
+            let results: Vec<u64> = kiki_channel.results().map(|result| result.unwrap().0).collect();
+            assert_eq!(&results, expected);
+        }
+        assert_eq!(ChannelConfig::new().get_dispatch_order(), DispatchOrder::Fifo);
+    }
+
+    #[test]
+    fn test_priority(){
+        use crate::scheduler::Priority;
+
+        // In ordered mode the results come in dispatch order.
+        let mut config = ChannelConfig::new();
+        config.set_ordered(true);
+        let mut kiki_channel: DeliveryService<Number, Number, SquareMessage, String> = DeliveryService::try_new(config).unwrap();
+        kiki_channel.feed_feeder(&mut vec![Number(1), Number(2)]);
+        kiki_channel.feed_feeder_with_priority(&mut vec![Number(3)], Priority::Low);
+        kiki_channel.feed_feeder_with_priority(&mut vec![Number(4), Number(5)], Priority::High);
+        kiki_channel.feed_feeder_with_priority(&mut vec![Number(6)], Priority::High);
+        assert_eq!(kiki_channel.len(), 6);
+        let results: Vec<u64> = kiki_channel.results().map(|result| result.unwrap().0).collect();
+        assert_eq!(results, vec![16, 25, 36, 1, 4, 9]);
+
+        // Lanes keep their batches.
+        kiki_channel.feed_feeder(&mut vec![Number(7)]);
+        let near = kiki_channel.feed_feeder_with_priority(&mut vec![Number(8), Number(9)], Priority::High);
+        assert_eq!(kiki_channel.iter_batch(near).map(|n| n.0).collect::<Vec<u64>>(), vec![64, 81]);
+        assert_eq!(kiki_channel.len(), 1);
+    }
+
+    #[test]
+    fn test_iter_batch(){
+        let mut config = ChannelConfig::new();
+        config.set_worker_number(2);
+        let mut kiki_channel: DeliveryService<Number, Number, SquareMessage, String> = DeliveryService::try_new(config).unwrap();
+        let first = kiki_channel.feed_feeder(&mut vec![Number(1), Number(2), Number(3)]);
+        let second = kiki_channel.feed_feeder(&mut vec![Number(4), Number(5), Number(6)]);
+        assert_ne!(first, second);
+
+        // The second batch first. Results of the first one that arrive meanwhile wait for later.
+        let mut received: Vec<u64> = kiki_channel.iter_batch(second).map(|n| n.0).collect();
+        received.sort_unstable();
+        assert_eq!(received, vec![16, 25, 36]);
+        assert_eq!(kiki_channel.len(), 3);
+        let mut received: Vec<u64> = kiki_channel.iter_batch(first).map(|n| n.0).collect();
+        received.sort_unstable();
+        assert_eq!(received, vec![1, 4, 9]);
+        assert!(kiki_channel.is_empty());
+        assert_eq!(kiki_channel.iter_batch(first).count(), 0);
+
+        // A custom scheduler doesn't keep batches, but one fed after the other was dispatched is still told apart.
+        kiki_channel.set_scheduler(SmallestFirst(Vec::new()));
+        let third = kiki_channel.feed_feeder(&mut vec![Number(7), Number(8)]);
+        let mut received: Vec<u64> = kiki_channel.iter_batch(third).map(|n| n.0).collect();
+        received.sort_unstable();
+        assert_eq!(received, vec![49, 64]);
+    }
+
+    #[test]
+    fn test_process(){
+        let mut config = ChannelConfig::new();
+        config.set_worker_number(4);
+        let mut kiki_channel: DeliveryService<Number, Number, SlowMessage> = DeliveryService::try_new(config).unwrap();
+        // The first inputs take the longest, so they finish last.
+        let ordered: Vec<u64> = kiki_channel.process(&mut vec![Number(40), Number(30), Number(20), Number(10)], true).iter().map(|n| n.0).collect();
+        assert_eq!(ordered, vec![40, 30, 20, 10]);
+
+        // Results of other inputs are kept for later.
+        kiki_channel.feed_feeder(&mut vec![Number(1), Number(2)]);
+        let mut unordered: Vec<u64> = kiki_channel.process(&mut vec![Number(5), Number(6), Number(7)], false).iter().map(|n| n.0).collect();
+        unordered.sort_unstable();
+        assert_eq!(unordered, vec![5, 6, 7]);
+        let mut rest: Vec<u64> = (&mut kiki_channel).map(|n| n.0).collect();
+        rest.sort_unstable();
+        assert_eq!(rest, vec![1, 2]);
+        assert!(kiki_channel.process(&mut Vec::new(), true).is_empty());
+    }
+
+    #[test]
+    fn test_submit(){
+        let mut kiki_channel: DeliveryService<Number, Number, SquareMessage, String> = DeliveryService::default();
+        kiki_channel.feed_feeder(&mut (1..=5).map(Number).collect());
+        let mut job = kiki_channel.submit(Number(7));
+        let failed = kiki_channel.submit(Number(10));
+        assert!(!job.is_ready());
+        assert_eq!(kiki_channel.len(), 7);
+
+        // The iterator only yields the inputs that were fed.
+        let mut received: Vec<u64> = (&mut kiki_channel).map(|n| n.0).collect();
+        received.sort_unstable();
+        assert_eq!(received, vec![1, 4, 9, 16, 25]);
+        assert!(job.is_ready());
+        assert_eq!(job.wait().map(|result| result.unwrap().0), Some(49));
+        assert!(failed.wait().unwrap().is_err());
+
+        // Waited on another thread while this one iterates.
+        let job = kiki_channel.submit(Number(3));
+        let waiter = std::thread::spawn(move || job.wait().map(|result| result.unwrap().0));
+        assert_eq!((&mut kiki_channel).count(), 0);
+        assert_eq!(waiter.join().unwrap(), Some(9));
+
+        // A cancelled job never comes.
+        let job = kiki_channel.submit(Number(4));
+        kiki_channel.cancel();
+        assert!(job.wait().is_none());
+    }
+
+    // Splits input n into n results, n * 10 + i for each i.
+    #[derive(Clone, Default)]
+    pub struct FanOutMessage{
+        pub input: Number,
+        pub outputs: Vec<Number>,
+    }
+
+    impl Message<Number, Number> for FanOutMessage{
+        fn set_input(&mut self, message_input: Number){
+            self.input = message_input;
+        }
+
+        fn work(&mut self){
+            let n = self.input.0;
+            self.outputs = (0..n).map(|i| Number(n * 10 + i)).collect();
+        }
+
+        fn fan_out(&mut self) -> Option<Vec<Number>>{
+            Some(std::mem::take(&mut self.outputs))
+        }
+
+        fn clone_message_data(&self) -> Number{
+            unreachable!("Every result is fanned out")
+        }
+
+        fn new() -> Self{
+            FanOutMessage{
+                input: Number(0),
+                outputs: Vec::new(),
+            }
+        }
+    }
+
+    #[test]
+    fn test_fan_out(){
+        let mut config = ChannelConfig::new();
+        config.set_worker_number(2);
+        let mut kiki_channel: DeliveryService<Number, Number, FanOutMessage> = DeliveryService::try_new(config).unwrap();
+        kiki_channel.feed_feeder(&mut vec![Number(3), Number(0), Number(2)]);
+        let mut received: Vec<u64> = (&mut kiki_channel).map(|n| n.0).collect();
+        received.sort_unstable();
+        assert_eq!(received, vec![20, 21, 30, 31, 32]);
+
+        // Each output in order, and the batch waits for all of them.
+        assert_eq!(kiki_channel.process(&mut vec![Number(3), Number(0), Number(2)], true).iter().map(|n| n.0).collect::<Vec<u64>>(), vec![30, 31, 32, 20, 21]);
+        let first = kiki_channel.feed_feeder(&mut vec![Number(4)]);
+        kiki_channel.feed_feeder(&mut vec![Number(1)]);
+        assert_eq!(kiki_channel.iter_batch(first).count(), 4);
+        assert_eq!(kiki_channel.results().count(), 1);
+    }
+
+    // Sum of the squares of every input merged into it.
+    #[derive(Clone, Default)]
+    pub struct SumSquaresMessage{
+        pub input: Number,
+        pub data: Number,
+    }
+
+    impl Message<Number, Number> for SumSquaresMessage{
+        fn set_input(&mut self, message_input: Number){
+            self.input = message_input;
+        }
+
+        fn work(&mut self){
+            self.data = Number(self.input.0 * self.input.0);
+        }
+
+        fn clone_message_data(&self) -> Number{
+            self.data.clone()
+        }
+
+        fn new() -> Self{
+            SumSquaresMessage{
+                input: Number(0),
+                data: Number(0),
+            }
+        }
+    }
+
+    // Without it, map_reduce doesn't compile for this message.
+    impl Merge<Number> for SumSquaresMessage{
+        fn merge(&mut self, other_data: Number){
+            self.data.0 += other_data.0;
+        }
+    }
+
+    #[test]
+    fn test_map_reduce(){
+        let mut config = ChannelConfig::new();
+        config.set_worker_number(2);
+        let mut kiki_channel: DeliveryService<Number, Number, SumSquaresMessage> = DeliveryService::try_new(config).unwrap();
+        assert_eq!(kiki_channel.map_reduce(&mut (1..=100).map(Number).collect(), 8).0, 338350);
+        assert_eq!(kiki_channel.map_reduce(&mut vec![Number(3)], 8).0, 9);
+        assert_eq!(kiki_channel.map_reduce(&mut Vec::new(), 8).0, 0);
+
+        // Inputs fed before aren't merged, their results are set aside.
+        kiki_channel.feed_feeder(&mut vec![Number(2), Number(4)]);
+        assert_eq!(kiki_channel.map_reduce(&mut (1..=10).map(Number).collect(), 3).0, 385);
+        let mut received: Vec<u64> = (&mut kiki_channel).map(|n| n.0).collect();
+        received.sort_unstable();
+        assert_eq!(received, vec![4, 16]);
+    }
+
+    #[test]
+    fn test_vec_message(){
+        use crate::buffer::{ChunkInput, Chunk, VecMessage};
+
+        assert_eq!(ChunkInput::split(10, 4), vec![ChunkInput{ offset: 0, len: 4 }, ChunkInput{ offset: 4, len: 4 }, ChunkInput{ offset: 8, len: 2 }]);
+        let mut kiki_channel: DeliveryService<Chunk<u64>, ChunkInput, VecMessage<u64>> = DeliveryService::default();
+        kiki_channel.set_message_template(VecMessage::with_fill(|offset, buffer| {
+            for (index, value) in buffer.iter_mut().enumerate(){
+                *value = ((offset + index) * (offset + index)) as u64;
+            }
+        }));
+        kiki_channel.feed_feeder(&mut ChunkInput::split(100, 7));
+        let mut squares = vec![0; 100];
+        for chunk in &mut kiki_channel{
+            squares[chunk.offset..chunk.offset + chunk.data.len()].copy_from_slice(&chunk.data);
+        }
+        assert_eq!(squares, (0..100).map(|x| x * x).collect::<Vec<u64>>());
+
+        // Without a fill function, each message fails instead.
+        let mut config = ChannelConfig::new();
+        config.set_worker_number(1);
+        let mut kiki_channel: DeliveryService<Chunk<u64>, ChunkInput, VecMessage<u64>> = DeliveryService::try_new(config).unwrap();
+        kiki_channel.feed_feeder(&mut ChunkInput::split(10, 5));
+        assert!(kiki_channel.results().all(|result| matches!(result, Err(WorkError::Panicked{..}))));
+    }
+
+    #[test]
+    fn test_tile_message(){
+        use crate::buffer::{TileInput, Tile, TileMessage};
+
+        // Tiles of 4 by 2 over an area of 10 by 4. The last column of tiles goes past it.
+        let corners = TileMessage::<u32, 4, 2>::grid(10, 4);
+        assert_eq!(corners.len(), 6);
+        assert_eq!(corners[5], TileInput{ x: 8, y: 2 });
+        let mut kiki_channel: DeliveryService<Tile<u32, 4, 2>, TileInput, TileMessage<u32, 4, 2>> = DeliveryService::default();
+        kiki_channel.set_message_template(TileMessage::with_fill(|x, y, rows| {
+            for (row, values) in rows.iter_mut().enumerate(){
+                for (column, value) in values.iter_mut().enumerate(){
+                    *value = ((y + row) * 100 + x + column) as u32;
+                }
+            }
+        }));
+        kiki_channel.feed_feeder(&mut corners.clone());
+        let mut tiles: Vec<Tile<u32, 4, 2>> = (&mut kiki_channel).collect();
+        tiles.sort_by_key(|tile| (tile.y, tile.x));
+        assert_eq!(tiles.len(), 6);
+        assert_eq!(*tiles[5].rows, [[208, 209, 210, 211], [308, 309, 310, 311]]);
+    }
+
+    #[test]
+    fn test_grid(){
+        use crate::buffer::{TileInput, TileMessage};
+        use crate::util::grid::{self, GridCell};
+
+        // 10 by 4 in cells of 4 by 3: the last column is 2 wide and the last row 1 high.
+        let cells = grid::cells(10, 4, 4, 3);
+        assert_eq!(cells.len(), 6);
+        assert_eq!(cells[2], GridCell{ x0: 8, y0: 0, x1: 10, y1: 3 });
+        assert_eq!(cells[5], GridCell{ x0: 8, y0: 3, x1: 10, y1: 4 });
+        assert_eq!(cells.iter().map(GridCell::area).sum::<usize>(), 40);
+        assert_eq!(<GridCell as MessageInput<u64>>::weight(&cells[5]), 2);
+
+        // Tiles keep their size, only the corners are given.
+        assert_eq!(grid::tiles(10, 4, 4, 2), TileMessage::<u32, 4, 2>::grid(10, 4));
+        assert_eq!(grid::tiles(10, 4, 4, 3)[5], TileInput{ x: 8, y: 3 });
+        assert_eq!(grid::rows(7, 3), vec![0..3, 3..6, 6..7]);
+        assert_eq!(grid::columns(6, 3), vec![0..3, 3..6]);
+
+        // Nothing to cover, and sizes of zero counted as one.
+        assert!(grid::cells(0, 5, 2, 2).is_empty());
+        assert_eq!(grid::columns(3, 0), vec![0..1, 1..2, 2..3]);
+    }
+
+    // Squares each index of the range. Only the message is written, input and data are std types.
+    #[derive(Clone, Default)]
+    pub struct RangeSquares{
+        pub input: std::ops::Range<usize>,
+        pub output: Vec<u64>,
+    }
+
+    impl Message<Vec<u64>, std::ops::Range<usize>> for RangeSquares{
+        fn set_input(&mut self, message_input: std::ops::Range<usize>){
+            self.input = message_input;
+        }
+
+        fn work(&mut self){
+            self.output = self.input.clone().map(|x| (x * x) as u64).collect();
+        }
+
+        fn clone_message_data(&self) -> Vec<u64>{
+            self.output.clone()
+        }
+    }
+
+    // Multiplies a pair of integers.
+    #[derive(Clone, Default)]
+    pub struct PairProduct{
+        pub input: (u32, u32),
+        pub output: u64,
+    }
+
+    impl Message<u64, (u32, u32)> for PairProduct{
+        fn set_input(&mut self, message_input: (u32, u32)){
+            self.input = message_input;
+        }
+
+        fn work(&mut self){
+            self.output = self.input.0 as u64 * self.input.1 as u64;
+        }
+
+        fn clone_message_data(&self) -> u64{
+            self.output
+        }
+    }
+
+    #[test]
+    fn test_multi_channel(){
+        use crate::channel::MultiChannel;
+
+        enum Media{
+            Audio(Number),
+            Video(Numbers),
+        }
+
+        let mut audio: DeliveryService<Number, Number, SlowMessage> = DeliveryService::default();
+        let mut video: DeliveryService<Numbers, Number, CountMessage> = DeliveryService::default();
+        audio.feed_feeder(&mut vec![Number(1); 10]);
+        video.feed_feeder(&mut (0..5).map(Number).collect());
+
+        let mut results = MultiChannel::new().with(&mut audio, Media::Audio).with(&mut video, Media::Video);
+        assert_eq!(results.len(), 2);
+        assert_eq!(results.get_last_source(), None);
+        let (mut samples, mut lengths) = (0, Vec::new());
+        while let Some(media) = results.next(){
+            match media{
+                Media::Audio(Number(1)) => {
+                    samples += 1;
+                    assert_eq!(results.get_last_source(), Some(0));
+                },
+                Media::Audio(_) => panic!("wrong audio result"),
+                Media::Video(Numbers(numbers)) => {
+                    lengths.push(numbers.len());
+                    assert_eq!(results.get_last_source(), Some(1));
+                },
+            }
+        }
+        lengths.sort();
+        assert_eq!((samples, lengths), (10, vec![0, 1, 2, 3, 4]));
+        assert!(results.try_next().is_none());
+        drop(results);
+        assert!(audio.is_empty() && video.is_empty());
+
+        assert!(MultiChannel::<Number>::new().next().is_none());
+    }
+
+    #[test]
+    fn test_tee(){
+        let kiki_channel: DeliveryService<u64, (u32, u32), PairProduct> = DeliveryService::default();
+        kiki_channel.feed_feeder(&mut (0..50).map(|number| (number, number)).collect());
+        let mut tees = kiki_channel.tee(3, Some(4)).into_iter();
+        let (display, mut disk, dropped) = (tees.next().unwrap(), tees.next().unwrap(), tees.next().unwrap());
+        // A consumer that leaves doesn't hold the others up.
+        drop(dropped);
+
+        assert!(disk.next().is_some());
+        assert_eq!(display.queued(), 1);
+        let writer = std::thread::spawn(move || {
+            let mut written = 1;
+            for _ in disk{
+                std::thread::sleep(Duration::from_micros(200));
+                written += 1;
+            }
+            written
+        });
+        let mut squares: Vec<u64> = display.collect();
+        squares.sort();
+        assert_eq!(squares, (0..50).map(|number| number * number).collect::<Vec<u64>>());
+        assert_eq!(writer.join().unwrap(), 50);
+
+        // Failed messages are skipped, the results after them still reach every consumer.
+        let kiki_channel: DeliveryService<Number, Number, SquareMessage, String> = DeliveryService::default();
+        kiki_channel.feed_feeder(&mut (1..=12).map(Number).collect());
+        let mut tees = kiki_channel.tee(2, None).into_iter();
+        let (first, second) = (tees.next().unwrap(), tees.next().unwrap());
+        let expected: Vec<u64> = (1..=12).filter(|n| *n != 10).map(|n| n * n).collect();
+        for tee in [first, second]{
+            let mut squares: Vec<u64> = tee.map(|n| n.0).collect();
+            squares.sort_unstable();
+            assert_eq!(squares, expected);
+        }
+    }
+
+    #[test]
+    fn test_size_hint(){
+        let mut kiki_channel: DeliveryService<u64, (u32, u32), PairProduct> = DeliveryService::default();
+        kiki_channel.feed_feeder(&mut (0..20).map(|number| (number, number)).collect());
+        assert_eq!(kiki_channel.result_bounds(), (20, Some(20)));
+        let mut results = &mut kiki_channel;
+        assert!(results.next().is_some());
+        assert_eq!(results.size_hint(), (0, Some(19)));
+        assert_eq!(results.results().size_hint(), (19, Some(19)));
+        assert_eq!(results.collect::<Vec<u64>>().len(), 19);
+        assert_eq!(kiki_channel.results().size_hint(), (0, Some(0)));
+
+        // A weak sender can add inputs anytime.
+        let sender = kiki_channel.weak_sender();
+        sender.send((1, 1)).unwrap();
+        assert_eq!(kiki_channel.result_bounds(), (1, None));
+        drop(sender);
+        assert_eq!(kiki_channel.try_iter().size_hint().1, Some(1));
+        assert_eq!((&mut kiki_channel).count(), 1);
+
+        // Messages that fan out can give any number of results.
+        let mut kiki_channel: DeliveryService<Number, Number, FanOutMessage> = DeliveryService::default();
+        kiki_channel.feed_feeder(&mut vec![Number(3), Number(2)]);
+        assert_eq!(kiki_channel.result_bounds(), (2, Some(2)));
+        assert_eq!((&mut kiki_channel).count(), 5);
+        assert_eq!(kiki_channel.result_bounds(), (0, None));
+    }
+
+    #[test]
+    fn test_into_iter(){
+        let kiki_channel: DeliveryService<u64, (u32, u32), PairProduct> = DeliveryService::default();
+        kiki_channel.feed_feeder(&mut (1..=10).map(|x| (x, x + 1)).collect());
+        let sender = kiki_channel.weak_sender();
+        let mut sum = 0;
+        for product in kiki_channel{
+            sum += product;
+        }
+        assert_eq!(sum, 440);
+        // The channel was shut down along with its iterator.
+        assert!(sender.is_closed());
+
+        // Stopping halfway, the results left are dropped.
+        let kiki_channel: DeliveryService<u64, (u32, u32), PairProduct> = DeliveryService::default();
+        kiki_channel.feed_feeder(&mut (1..=10).map(|x| (x, x + 1)).collect());
+        let mut results = kiki_channel.into_iter();
+        assert_eq!(results.size_hint(), (0, Some(10)));
+        assert!(results.next().is_some());
+        let report = results.shutdown();
+        assert_eq!(report.get_abandoned_inputs() + report.get_drained_messages(), 9);
+    }
+
+    #[test]
+    fn test_progress(){
+        let mut kiki_channel: DeliveryService<u64, (u32, u32), PairProduct> = DeliveryService::default();
+        assert_eq!(kiki_channel.progress(), (0, 0));
+        kiki_channel.feed_feeder(&mut (0..10).map(|number| (number, number)).collect());
+        assert_eq!(kiki_channel.progress(), (0, 10));
+        let (tx, rx) = std::sync::mpsc::channel();
+        kiki_channel.on_progress(move |done, total| tx.send((done, total)).unwrap());
+        assert_eq!((&mut kiki_channel).take(4).count(), 4);
+        assert_eq!(kiki_channel.progress(), (4, 10));
+
+        // Feeding during the run adds to its total.
+        kiki_channel.feed_feeder(&mut (0..5).map(|number| (number, number)).collect());
+        assert_eq!(kiki_channel.progress(), (4, 15));
+        assert_eq!((&mut kiki_channel).count(), 11);
+        assert_eq!(kiki_channel.progress(), (15, 15));
+        let told: Vec<(usize, usize)> = rx.try_iter().collect();
+        assert_eq!(told.len(), 15);
+        assert_eq!(told[3], (4, 10));
+        assert_eq!(told[14], (15, 15));
+
+        // The next feed starts a new run.
+        kiki_channel.clear_on_progress();
+        kiki_channel.feed_feeder(&mut vec![(1, 1), (2, 2)]);
+        assert_eq!(kiki_channel.progress(), (0, 2));
+        assert_eq!((&mut kiki_channel).count(), 2);
+        assert_eq!(kiki_channel.progress(), (2, 2));
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_fused(){
+        let mut kiki_channel: DeliveryService<u64, (u32, u32), PairProduct> = DeliveryService::default();
+        kiki_channel.feed_feeder(&mut vec![(2, 3), (4, 5)]);
+        assert_eq!((&mut kiki_channel).count(), 2);
+        assert!(!kiki_channel.has_pending());
+        // Done until fed again, then it resumes.
+        assert!((&mut kiki_channel).next().is_none());
+        assert!((&mut kiki_channel).next().is_none());
+        kiki_channel.feed_feeder(&mut vec![(6, 7)]);
+        assert!(kiki_channel.has_pending());
+        assert_eq!((&mut kiki_channel).next(), Some(42));
+        assert!((&mut kiki_channel).next().is_none());
+
+        // An ended iterator stays ended, a new one picks up what arrived meanwhile.
+        let sender = kiki_channel.weak_sender();
+        assert!(kiki_channel.has_pending());
+        let mut results = kiki_channel.results();
+        assert!(results.next().is_none());
+        sender.send((1, 8)).unwrap();
+        assert!(results.next().is_none());
+        assert_eq!(results.size_hint(), (0, Some(0)));
+        assert_eq!(kiki_channel.results().map(Result::unwrap).collect::<Vec<u64>>(), vec![8]);
+        drop(sender);
+        assert!(!kiki_channel.has_pending());
+    }
+
+    #[test]
+    fn test_std_types(){
+        let mut kiki_channel: DeliveryService<Vec<u64>, std::ops::Range<usize>, RangeSquares> = DeliveryService::default();
+        assert_eq!(kiki_channel.process(&mut vec![0..3, 3..5, 5..5], true), vec![vec![0, 1, 4], vec![9, 16], vec![]]);
+
+        let mut kiki_channel: DeliveryService<u64, (u32, u32), PairProduct> = DeliveryService::default();
+        kiki_channel.feed_feeder(&mut (1..=10).map(|x| (x, x + 1)).collect());
+        assert_eq!(kiki_channel.fold(0, |sum, product| sum + product), 440);
+
+        assert_eq!(<[f32; 3] as MessageData>::new(), [0.0; 3]);
+        assert_eq!(<String as MessageData>::new(), "");
+        assert_eq!(<std::ops::Range<usize> as MessageInput<u64>>::weight(&(2..7)), 5);
+    }
+
+    // Data written before Default was enough, with its own new and no Default.
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct Celsius(i64);
+
+    impl MessageData for Celsius{
+        fn new() -> Self{
+            Celsius(0)
+        }
+    }
+
+    // Converts with the function it's built with, so it has no Default.
+    #[derive(Clone)]
+    pub struct ConvertMessage{
+        pub input: u64,
+        pub output: Celsius,
+        pub convert: fn(u64) -> i64,
+    }
+
+    impl ConvertMessage{
+        fn from_fahrenheit() -> Self{
+            ConvertMessage{
+                input: 0,
+                output: Celsius(0),
+                convert: |fahrenheit| (fahrenheit as i64 - 32) * 5 / 9,
+            }
+        }
+    }
+
+    impl Message<Celsius, u64> for ConvertMessage{
+        fn set_input(&mut self, message_input: u64){
+            self.input = message_input;
+        }
+
+        fn work(&mut self){
+            self.output = Celsius((self.convert)(self.input));
+        }
+
+        fn clone_message_data(&self) -> Celsius{
+            self.output.clone()
+        }
+    }
+
+    #[test]
+    fn test_builder(){
+        use crate::channel::SequentialDeliveryService;
+
+        let mut kiki_channel: DeliveryService<Celsius, u64, ConvertMessage> = DeliveryService::try_with_builder(ChannelConfig::new(), ConvertMessage::from_fahrenheit).unwrap();
+        assert_eq!(kiki_channel.process(&mut vec![32, 212, 50], true), vec![Celsius(0), Celsius(100), Celsius(10)]);
+
+        let mut kiki_channel: SequentialDeliveryService<Celsius, u64, ConvertMessage> = SequentialDeliveryService::try_with_builder(ChannelConfig::new(), ConvertMessage::from_fahrenheit).unwrap();
+        assert_eq!(kiki_channel.process(&mut vec![104, 41], true), vec![Celsius(40), Celsius(5)]);
+    }
+
+    // Only work is written, the rest comes from the derive.
+    #[cfg(feature = "derive")]
+    #[derive(Clone, Default, Message)]
+    pub struct DerivedMessage{
+        #[input]
+        pub input: (u64, u64),
+        #[data]
+        pub output: Vec<u64>,
+        pub step: u64,
+    }
+
+    #[cfg(feature = "derive")]
+    impl DerivedMessage{
+        fn work(&mut self){
+            self.step += 1;
+            self.output = (self.input.0..self.input.1).map(|x| x * self.step).collect();
+        }
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn test_derive_message(){
+        let mut config = ChannelConfig::new();
+        config.set_worker_number(1);
+        let mut kiki_channel: DeliveryService<Vec<u64>, (u64, u64), DerivedMessage> = DeliveryService::try_new(config).unwrap();
+        assert_eq!(kiki_channel.process(&mut vec![(0, 3), (5, 6)], true), vec![vec![0, 1, 2], vec![5]]);
+        let message = <DerivedMessage as Message<Vec<u64>, (u64, u64)>>::new();
+        assert_eq!((message.input, message.output, message.step), ((0, 0), Vec::new(), 0));
+    }
+
+    // Nothing to write for the data, new comes from Default.
+    #[derive(Clone, Default, Debug, PartialEq)]
+    pub struct Stats{
+        pub sum: u64,
+        pub count: u64,
+    }
+
+    impl MessageData for Stats{}
+
+    #[derive(Clone, Default)]
+    pub struct Samples{
+        pub values: Vec<u64>,
+    }
+
+    // Nothing to write for the input either.
+    impl MessageInput<Stats> for Samples{}
+
+    #[derive(Clone, Default)]
+    pub struct StatsMessage{
+        pub input: Samples,
+        pub output: Stats,
+    }
+
+    impl Message<Stats, Samples> for StatsMessage{
+        fn set_input(&mut self, message_input: Samples){
+            self.input = message_input;
+        }
+
+        fn work(&mut self){
+            self.output = Stats{
+                sum: self.input.values.iter().sum(),
+                count: self.input.values.len() as u64,
+            };
+        }
+
+        fn clone_message_data(&self) -> Stats{
+            self.output.clone()
+        }
+
+        fn new() -> Self{
+            Self::default()
+        }
+    }
+
+    #[test]
+    fn test_default_types(){
+        let mut kiki_channel: DeliveryService<Stats, Samples, StatsMessage> = DeliveryService::default();
+        let results = kiki_channel.process(&mut vec![Samples{ values: vec![1, 2, 3] }, Samples::default()], true);
+        assert_eq!(results, vec![Stats{ sum: 6, count: 3 }, Stats::default()]);
+        assert_eq!(<Stats as MessageData>::new(), Stats::default());
+    }
+
+    #[test]
+    fn test_fold(){
+        let mut kiki_channel: DeliveryService<Number, Number, SquareMessage, String> = DeliveryService::default();
+        kiki_channel.feed_feeder(&mut (1..=9).map(Number).collect());
+        assert_eq!(kiki_channel.fold(0, |sum, n| sum + n.0), 285);
+        assert!(kiki_channel.is_empty());
+
+        // Histogram of the last digit.
+        kiki_channel.feed_feeder(&mut (1..=9).chain(1..=9).map(Number).collect());
+        let histogram = kiki_channel.fold([0usize; 10], |mut histogram, n| {
+            histogram[(n.0 % 10) as usize] += 1;
+            histogram
+        });
+        assert_eq!(histogram, [0, 4, 0, 0, 4, 2, 4, 0, 0, 4]);
+        assert_eq!(kiki_channel.fold(7, |sum, n| sum + n.0), 7);
+    }
+
+    // Owns an open file, so it can't be cloned.
+    pub struct FileInput{
+        pub file: std::fs::File,
+    }
+
+    impl MessageInput<u64> for FileInput{}
+
+    // Not Clone either.
+    #[derive(Default)]
+    pub struct FileLenMessage{
+        pub input: Option<std::fs::File>,
+        pub output: u64,
+    }
+
+    impl Message<u64, FileInput> for FileLenMessage{
+        fn set_input(&mut self, message_input: FileInput){
+            self.input = Some(message_input.file);
+        }
+
+        fn work(&mut self){
+            use std::io::Read;
+
+            let mut contents = Vec::new();
+            if let Some(mut file) = self.input.take(){
+                file.read_to_end(&mut contents).unwrap();
+            }
+            self.output = contents.len() as u64;
+        }
+
+        fn clone_message_data(&self) -> u64{
+            self.output
+        }
+
+        fn new() -> Self{
+            Self::default()
+        }
+    }
+
+    #[test]
+    fn test_move_only_input(){
+        let directory = std::env::temp_dir().join(format!("kik_move_only_{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let mut inputs: Vec<FileInput> = (1..=5).map(|len| {
+            let path = directory.join(format!("{}.txt", len));
+            std::fs::write(&path, vec![b'x'; len]).unwrap();
+            FileInput{
+                file: std::fs::File::open(path).unwrap(),
+            }
+        }).collect();
+        let mut kiki_channel: DeliveryService<u64, FileInput, FileLenMessage> = DeliveryService::default();
+        assert_eq!(kiki_channel.process(&mut inputs, true), vec![1, 2, 3, 4, 5]);
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    // Gives back what the worker told it: (worker id, attempt, whether dispatched_at came before the work).
+    #[derive(Default)]
+    pub struct InfoMessage{
+        pub output: (usize, usize, bool),
+    }
+
+    impl Message<(usize, usize, bool), u64> for InfoMessage{
+        fn set_input(&mut self, _message_input: u64){}
+
+        fn work(&mut self){
+            panic!("The workers call work_with_info");
+        }
+
+        fn work_with_info(&mut self, _context: &mut WorkContext, info: &crate::context::WorkInfo) -> Result<(), std::convert::Infallible>{
+            self.output = (info.worker_id, info.attempt, info.dispatched_at <= Instant::now() && info.queue_delay() < Duration::from_secs(60));
+            Ok(())
+        }
+
+        fn clone_message_data(&self) -> (usize, usize, bool){
+            self.output
+        }
+
+        fn new() -> Self{
+            Self::default()
+        }
+    }
+
+    #[test]
+    fn test_work_info(){
+        let mut kiki_channel: DeliveryService<(usize, usize, bool), u64, InfoMessage> = DeliveryService::default();
+        let workers = kiki_channel.get_worker_number();
+        let results = kiki_channel.process(&mut (0..20).collect(), false);
+        assert_eq!(results.len(), 20);
+        for (worker_id, attempt, dispatched_before) in results{
+            if cfg!(any(miri, feature = "inline")){
+                assert_eq!(worker_id, 0);
+            } else {
+                assert!((1..=workers).contains(&worker_id));
+            }
+            assert_eq!(attempt, 1);
+            assert!(dispatched_before);
+        }
+    }
+
+    // Gives back its input, and the time it had left before its deadline. None without one.
+    #[derive(Default)]
+    pub struct DeadlineMessage{
+        pub output: (u64, Option<Duration>),
+    }
+
+    impl Message<(u64, Option<Duration>), u64> for DeadlineMessage{
+        fn set_input(&mut self, message_input: u64){
+            self.output.0 = message_input;
+        }
+
+        fn work(&mut self){
+            panic!("The workers call work_with_info");
+        }
+
+        fn work_with_info(&mut self, _context: &mut WorkContext, info: &crate::context::WorkInfo) -> Result<(), std::convert::Infallible>{
+            assert!(!info.is_past_deadline());
+            self.output.1 = info.time_left();
+            Ok(())
+        }
+
+        fn clone_message_data(&self) -> (u64, Option<Duration>){
+            self.output
+        }
+
+        fn new() -> Self{
+            Self::default()
+        }
+    }
+
+    #[test]
+    fn test_feed_with_deadline(){
+        let mut kiki_channel: DeliveryService<(u64, Option<Duration>), u64, DeadlineMessage> = DeliveryService::default();
+        // Already late, none of these are sent.
+        kiki_channel.feed_with_deadline(&mut (0..10).collect(), Instant::now());
+        let late_batch = kiki_channel.feed_with_deadline(&mut (10..20).collect(), Instant::now() - Duration::from_millis(1));
+        let batch = kiki_channel.feed_with_deadline(&mut (20..30).collect(), Instant::now() + Duration::from_secs(60));
+        kiki_channel.feed_feeder(&mut (30..40).collect());
+        assert_eq!(kiki_channel.iter_batch(late_batch).count(), 0);
+        let mut results: Vec<(u64, Option<Duration>)> = kiki_channel.iter_batch(batch).collect();
+        results.extend(&mut kiki_channel);
+        results.sort_by_key(|(input, _)| *input);
+        assert_eq!(results.len(), 20);
+        assert_eq!(kiki_channel.get_expired_inputs(), 20);
+        for (input, time_left) in results{
+            match input{
+                20..=29 => assert!(time_left.is_some_and(|left| left > Duration::ZERO)),
+                30..=39 => assert_eq!(time_left, None),
+                _ => panic!("{} was past its deadline", input),
+            }
+        }
+    }
+
+    // Fails for good on 7, panics on 13, and fails only on the first attempt for multiples of 5.
+    #[derive(Default)]
+    pub struct FlakyMessage{
+        pub input: u64,
+        pub output: u64,
+    }
+
+    impl Message<u64, u64, String> for FlakyMessage{
+        fn set_input(&mut self, message_input: u64){
+            self.input = message_input;
+        }
+
+        fn work(&mut self){
+            panic!("The workers call work_with_info");
+        }
+
+        fn work_with_info(&mut self, _context: &mut WorkContext, info: &crate::context::WorkInfo) -> Result<(), String>{
+            match self.input{
+                7 => Err(String::from("poison")),
+                13 => panic!("Bad luck"),
+                n if n % 5 == 0 && info.attempt == 1 => Err(String::from("flaky")),
+                n => {
+                    self.output = n * n;
+                    Ok(())
+                },
+            }
+        }
+
+        fn take_input(&mut self) -> Option<u64>{
+            Some(self.input)
+        }
+
+        fn clone_message_data(&self) -> u64{
+            self.output
+        }
+
+        fn new() -> Self{
+            Self::default()
+        }
+    }
+
+    #[test]
+    fn test_quarantine(){
+        let mut config = ChannelConfig::new();
+        config.set_quarantine(Some(3));
+        config.set_ordered(true);
+        assert_eq!(config.get_quarantine(), Some(3));
+        let mut kiki_channel: DeliveryService<u64, u64, FlakyMessage, String> = DeliveryService::try_new(config).unwrap();
+        kiki_channel.feed_feeder(&mut (1..=15).collect());
+        let results: Vec<u64> = kiki_channel.results().map(|result| result.unwrap()).collect();
+        // Multiples of 5 made it on their second attempt. 7 and 13 were set aside.
+        let expected: Vec<u64> = (1..=15).filter(|n| *n != 7 && *n != 13).map(|n| n * n).collect();
+        assert_eq!(results, expected);
+        assert_eq!(kiki_channel.get_failed_inputs(), 2);
+        let mut failed = kiki_channel.take_failed();
+        failed.sort_by_key(|(input, _)| *input);
+        assert_eq!(failed.len(), 2);
+        assert_eq!(failed[0].0, 7);
+        assert_eq!(failed[0].1.get_attempts(), 3);
+        assert!(matches!(failed[0].1.get_error(), WorkError::Failed{error, ..} if error == "poison"));
+        assert_eq!(failed[1].0, 13);
+        assert!(matches!(failed[1].1.get_error(), WorkError::Panicked{..}));
+        assert!(kiki_channel.take_failed().is_empty());
+
+        // Without a quarantine, every error is yielded right away.
+        let mut kiki_channel: DeliveryService<u64, u64, FlakyMessage, String> = DeliveryService::default();
+        kiki_channel.feed_feeder(&mut (1..=15).collect());
+        assert_eq!(kiki_channel.results().filter(|result| result.is_err()).count(), 5);
+        assert!(kiki_channel.take_failed().is_empty());
+    }
+
+    #[cfg(feature = "spill")]
+    #[test]
+    fn test_feed_spilling(){
+        let spill_dir = std::env::temp_dir().join(format!("kik_sync_service-test-spill-{}", std::process::id()));
+        std::fs::create_dir_all(&spill_dir).unwrap();
+        let spill_files = || std::fs::read_dir(&spill_dir).unwrap().count();
+
+        let mut config = ChannelConfig::builder().worker_number(1).package_number(2).build().unwrap();
+        config.set_ordered(true);
+        config.set_spill_threshold(Some(4));
+        config.set_spill_dir(Some(spill_dir.clone()));
+        let mut kiki_channel: DeliveryService<u64, u64, FlakyMessage, String> = DeliveryService::try_new(config).unwrap();
+        // FlakyMessage fails on 7, 13 and the first try of multiples of 5.
+        let inputs: Vec<u64> = (1..=200).filter(|n| *n != 7 && *n != 13 && n % 5 != 0).collect();
+        let (first, second) = inputs.split_at(50);
+
+        assert_eq!(kiki_channel.feed_spilling(&mut first.to_vec()).unwrap(), 46);
+        // The file isn't drained yet, so these go behind it.
+        assert_eq!(kiki_channel.feed_spilling(&mut second.to_vec()).unwrap(), second.len());
+        assert_eq!(kiki_channel.get_spilled_inputs(), inputs.len() - 4);
+        assert_eq!(spill_files(), 1);
+
+        let results: Vec<u64> = (&mut kiki_channel).collect();
+        assert_eq!(results, inputs.iter().map(|n| n * n).collect::<Vec<u64>>());
+        assert_eq!(kiki_channel.get_spilled_inputs(), 0);
+        assert!(kiki_channel.take_spill_error().is_none());
+        // Drained files are deleted.
+        assert_eq!(spill_files(), 0);
+
+        // Under the threshold, nothing touches the disk.
+        assert_eq!(kiki_channel.feed_spilling(&mut vec![1, 2, 3]).unwrap(), 0);
+        assert_eq!(spill_files(), 0);
+        assert_eq!((&mut kiki_channel).count(), 3);
+        std::fs::remove_dir(&spill_dir).unwrap();
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_checkpoint(){
+        let inputs: Vec<u64> = vec![1, 2, 3, 4, 6, 8, 9, 11, 12, 14];
+        let squares = |inputs: &[u64]| -> Vec<u64> { inputs.iter().map(|n| n * n).collect() };
+        let new_channel = || -> DeliveryService<u64, u64, FlakyMessage, String> {
+            DeliveryService::try_new(ChannelConfig::builder().worker_number(1).package_number(2).build().unwrap()).unwrap()
+        };
+
+        // Stop after two results. What was in flight still comes out, the rest is worked after resuming.
+        let mut kiki_channel = new_channel();
+        kiki_channel.feed_feeder(&mut inputs.clone());
+        let mut results: Vec<u64> = (&mut kiki_channel).take(2).collect();
+        let mut checkpoint: Vec<u8> = Vec::new();
+        let written = kiki_channel.checkpoint(&mut checkpoint).unwrap();
+        assert!(written >= inputs.len() - 4);
+        results.extend(&mut kiki_channel);
+        assert_eq!(results.len(), inputs.len() - written);
+        drop(kiki_channel);
+
+        let mut kiki_channel = new_channel();
+        assert_eq!(kiki_channel.restore(&checkpoint[..]).unwrap(), written);
+        results.extend(&mut kiki_channel);
+        results.sort_unstable();
+        assert_eq!(results, squares(&inputs));
+
+        // With checkpoint_all, the messages in flight give their inputs back too, nothing is left to iterate.
+        let mut kiki_channel = new_channel();
+        kiki_channel.feed_feeder(&mut inputs.clone());
+        let mut results: Vec<u64> = (&mut kiki_channel).take(2).collect();
+        let mut checkpoint: Vec<u8> = Vec::new();
+        assert_eq!(kiki_channel.checkpoint_all(&mut checkpoint).unwrap(), inputs.len() - 2);
+        assert_eq!(kiki_channel.results().count(), 0);
+
+        // A damaged checkpoint feeds nothing.
+        assert!(kiki_channel.restore(&checkpoint[..checkpoint.len() - 1]).is_err());
+        assert!(kiki_channel.restore(&b"not a checkpoint at all"[..]).is_err());
+        assert_eq!(kiki_channel.len(), 0);
+        assert_eq!(kiki_channel.restore(&checkpoint[..]).unwrap(), inputs.len() - 2);
+        results.extend(&mut kiki_channel);
+        results.sort_unstable();
+        assert_eq!(results, squares(&inputs));
+    }
+
+    // Accepts a few writes, then fails.
+    struct FailingWriter{
+        writes_left: usize,
+    }
+
+    impl std::io::Write for FailingWriter{
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize>{
+            if self.writes_left == 0{
+                return Err(std::io::Error::other("disk full"));
+            }
+            self.writes_left -= 1;
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()>{
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_drain_to_writer(){
+        let mut config = ChannelConfig::new();
+        config.set_ordered(true);
+        let mut kiki_channel: DeliveryService<Number, Number, SquareMessage, String> = DeliveryService::try_new(config).unwrap();
+        kiki_channel.feed_feeder(&mut (1..=5).map(Number).collect());
+        let mut sink: Vec<u8> = Vec::new();
+        let written = kiki_channel.drain_to_writer(&mut sink, |n, writer| writeln!(writer, "{}", n.0)).unwrap();
+        assert_eq!(written, 5);
+        assert_eq!(String::from_utf8(sink).unwrap(), "1\n4\n9\n16\n25\n");
+
+        // The error stops the run. Results after the one that failed are still there.
+        kiki_channel.feed_feeder(&mut (1..=6).map(Number).collect());
+        let error = kiki_channel.drain_to_writer(FailingWriter{ writes_left: 2 }, |n, writer| writer.write_all(&n.0.to_le_bytes())).unwrap_err();
+        assert_eq!(error.to_string(), "disk full");
+        assert_eq!(kiki_channel.results().count(), 3);
+    }
+
+    // Hands out a few bytes at a time, then fails.
+    struct FailingReader{
+        bytes_left: usize,
+    }
+
+    impl std::io::Read for FailingReader{
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize>{
+            if self.bytes_left == 0{
+                return Err(std::io::Error::other("connection reset"));
+            }
+            let read = buf.len().min(self.bytes_left).min(3);
+            buf[..read].fill(1);
+            self.bytes_left -= read;
+            Ok(read)
+        }
+    }
+
+    #[test]
+    fn test_feed_reader(){
+        let mut config = ChannelConfig::new();
+        config.set_ordered(true);
+        let mut kiki_channel: DeliveryService<Number, Number, SquareMessage, String> = DeliveryService::try_new(config).unwrap();
+        let bytes: Vec<u8> = (1..=9).collect();
+        let handle = kiki_channel.feed_reader(std::io::Cursor::new(bytes), 4, |chunk| Number(*chunk.last().unwrap() as u64));
+        let results: Vec<u64> = (&mut kiki_channel).map(|n| n.0).collect();
+        // Chunks 1..=4, 5..=8 and the short 9..=9.
+        assert_eq!(results, vec![16, 64, 81]);
+        assert!(handle.is_finished());
+        assert!(!handle.has_error());
+        assert_eq!(handle.get_bytes_read(), 9);
+        assert_eq!(handle.get_chunks(), 3);
+
+        // Partial reads still fill whole chunks, the error ends the inputs.
+        let handle = kiki_channel.feed_reader(FailingReader{ bytes_left: 11 }, 5, |chunk| Number(chunk.len() as u64));
+        let results: Vec<u64> = (&mut kiki_channel).map(|n| n.0).collect();
+        assert_eq!(results, vec![25, 25]);
+        assert!(handle.is_finished());
+        assert_eq!(handle.get_bytes_read(), 10);
+        assert_eq!(handle.take_error().unwrap().to_string(), "connection reset");
+        assert!(handle.take_error().is_none());
+    }
+
+    // Inline channels have no workers, remote or not.
+    #[cfg(all(feature = "remote", not(any(miri, feature = "inline"))))]
+    #[test]
+    fn test_remote_worker(){
+        use std::net::TcpListener;
+        use crate::kik_package::Package;
+        use crate::kik_remote::RemoteLink;
+        use crate::remote::RemoteAgent;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let agent = std::thread::spawn(move || {
+            let agent: RemoteAgent<Number, Number, SquareMessage, String> = RemoteAgent::new();
+            let mut worked = Vec::new();
+            for _ in 0..2{
+                let (stream, _) = listener.accept().unwrap();
+                worked.push(agent.serve_connection(stream).unwrap());
+            }
+            worked
+        });
+
+        // The message goes through the socket and comes back worked, failures come back as errors.
+        let mut call = RemoteLink::<SquareMessage, String>::connect(address).unwrap().into_call::<Number, Number>();
+        let mut package = Package::new(SquareMessage{ input: Number(7), output: Number(0) }, 0, 0, 1, None);
+        call(&mut package).unwrap();
+        assert_eq!(package.message.output.0, 49);
+        let mut package = Package::new(SquareMessage{ input: Number(20), output: Number(0) }, 0, 0, 1, None);
+        call(&mut package).unwrap();
+        assert!(matches!(package.error, Some(WorkError::Failed{error, ..}) if error == "Can't square 20"));
+        drop(call);
+
+        // Results of the remote worker come out of the same iterator as the local ones.
+        let mut config = ChannelConfig::new();
+        config.set_ordered(true);
+        let mut kiki_channel: DeliveryService<Number, Number, SquareMessage, String> = DeliveryService::try_new(config).unwrap();
+        kiki_channel.add_remote_worker(address).unwrap();
+        assert_eq!(kiki_channel.get_remote_worker_number(), 1);
+        kiki_channel.feed_feeder(&mut (1..=12).map(Number).collect());
+        let results: Vec<Result<u64, String>> = kiki_channel.results().map(|result| match result{
+            Ok(n) => Ok(n.0),
+            Err(WorkError::Failed{error, ..}) => Err(error),
+            Err(error) => panic!("{}", error),
+        }).collect();
+        let expected: Vec<Result<u64, String>> = (1..=12).map(|n| if n == 10 { Err("Can't square 10".to_string()) } else { Ok(n * n) }).collect();
+        assert_eq!(results, expected);
+        drop(kiki_channel);
+
+        let worked = agent.join().unwrap();
+        assert_eq!(worked[0], 2);
+        assert!(worked[1] <= 12);
+    }
+
+    // Doubles the input. Aborts the whole process for 0.
+    #[cfg(all(feature = "process", not(any(miri, feature = "inline"))))]
+    #[derive(serde::Serialize, serde::Deserialize)]
+    #[derive(Default)]
+    pub struct AbortMessage{
+        pub input: Number,
+        pub output: Number,
+    }
+
+    #[cfg(all(feature = "process", not(any(miri, feature = "inline"))))]
+    impl Message<Number, Number, String> for AbortMessage{
+        fn set_input(&mut self, message_input: Number){
+            self.input = message_input;
+        }
+
+        fn work(&mut self){
+            if self.input.0 == 0{
+                std::process::abort();
+            }
+            self.output = Number(self.input.0 * 2);
+        }
+
+        fn clone_message_data(&self) -> Number{
+            self.output.clone()
+        }
+
+        fn new() -> Self{
+            AbortMessage{
+                input: Number(0),
+                output: Number(0),
+            }
+        }
+    }
+
+
+    // The children run this same test binary, filtered down to this test. It serves the parent there instead.
+    #[cfg(all(feature = "process", not(any(miri, feature = "inline"))))]
+    #[test]
+    fn test_process_backend(){
+        use crate::channel::{Backend, ProcessBackend};
+        use crate::remote::RemoteAgent;
+
+        RemoteAgent::<Number, Number, AbortMessage, String>::new().serve_if_child();
+
+        let backend = ProcessBackend::new::<Number, Number, AbortMessage, String>()
+            .with_arg("--exact")
+            .with_arg("kik_message_example::tests::test_process_backend")
+            .with_arg("--test-threads=1");
+        let mut config = ChannelConfig::builder().worker_number(2).build().unwrap();
+        config.set_ordered(true);
+        config.set_backend(Backend::Process(backend.clone()));
+        let mut kiki_channel: DeliveryService<Number, Number, AbortMessage, String> = DeliveryService::try_new(config).unwrap();
+        kiki_channel.feed_feeder(&mut vec![Number(1), Number(2), Number(0), Number(3), Number(4)]);
+        let results: Vec<Option<u64>> = kiki_channel.results().map(|result| match result{
+            Ok(n) => Some(n.0),
+            Err(WorkError::Lost{..}) => None,
+            Err(error) => panic!("{}", error),
+        }).collect();
+        // The child that aborted was replaced, the host never noticed.
+        assert_eq!(results, vec![Some(2), Some(4), None, Some(6), Some(8)]);
+
+        // A backend made for other types is refused.
+        let mut config = ChannelConfig::default();
+        config.set_backend(Backend::Process(backend));
+        let refused = DeliveryService::<Number, Number, SquareMessage, String>::try_new(config);
+        assert!(matches!(refused, Err(crate::error::KikError::Config(crate::error::ConfigError::BackendTypes))));
+    }
+
+    #[test]
+    fn test_on_result(){
+        use std::sync::mpsc::channel;
+
+        let mut kiki_channel: DeliveryService<Number, Number, SquareMessage, String> = DeliveryService::default();
+        let (tx, rx) = channel();
+        kiki_channel.on_result(move |n: Number| tx.send(n.0).unwrap());
+        kiki_channel.feed_feeder(&mut (1..=5).map(Number).collect());
+        assert_eq!(kiki_channel.run_until_empty(), 5);
+        let mut received: Vec<u64> = rx.try_iter().collect();
+        received.sort_unstable();
+        assert_eq!(received, vec![1, 4, 9, 16, 25]);
+
+        // The callback is kept for the next run.
+        kiki_channel.feed_feeder(&mut vec![Number(6)]);
+        assert_eq!(kiki_channel.run_until_empty(), 1);
+        assert_eq!(rx.try_iter().collect::<Vec<u64>>(), vec![36]);
+
+        kiki_channel.clear_on_result();
+        kiki_channel.feed_feeder(&mut vec![Number(7)]);
+        assert_eq!(kiki_channel.run_until_empty(), 1);
+        assert_eq!(rx.try_iter().count(), 0);
+    }
+
+    // Wakes the thread that is blocked on a future.
+    #[cfg(feature = "async")]
+    struct ThreadWaker(std::thread::Thread);
+
+    #[cfg(feature = "async")]
+    impl std::task::Wake for ThreadWaker{
+        fn wake(self: Arc<Self>){
+            self.0.unpark();
+        }
+    }
+
+    // Smallest executor there is, so that the tests don't need an async runtime.
+    #[cfg(feature = "async")]
+    pub fn block_on<F: std::future::Future>(future: F) -> F::Output{
+        use std::task::{Context, Poll, Waker};
+
+        let waker = Waker::from(Arc::new(ThreadWaker(std::thread::current())));
+        let mut context = Context::from_waker(&waker);
+        let mut future = Box::pin(future);
+        loop{
+            match future.as_mut().poll(&mut context){
+                Poll::Ready(output) => return output,
+                Poll::Pending => std::thread::park(),
+            }
+        }
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_into_stream(){
+        let kiki_channel: DeliveryService<Number, Number, SquareMessage, String> = DeliveryService::default();
+        kiki_channel.feed_feeder(&mut (1..=9).map(Number).collect());
+        let mut stream = kiki_channel.into_stream();
+        let mut received = block_on(async {
+            let mut received = Vec::new();
+            while let Some(n) = stream.next_result().await{
+                received.push(n.0);
+            }
+            received
+        });
+        received.sort_unstable();
+        assert_eq!(received, vec![1, 4, 9, 16, 25, 36, 49, 64, 81]);
+
+        // Kept alive, it goes on until the input is closed.
+        let mut config = ChannelConfig::new();
+        config.set_keep_alive(true);
+        let kiki_channel: DeliveryService<Number, Number, SquareMessage, String> = DeliveryService::try_new(config).unwrap();
+        let sender = kiki_channel.weak_sender();
+        let mut stream = kiki_channel.into_stream();
+        sender.send(Number(3)).unwrap();
+        assert_eq!(block_on(stream.next_result()).map(|n| n.0), Some(9));
+        sender.close_input();
+        assert!(block_on(stream.next_result()).is_none());
+
+        // Dropping the stream stops the thread, which closes the input.
+        let mut config = ChannelConfig::new();
+        config.set_keep_alive(true);
+        let kiki_channel: DeliveryService<Number, Number, SquareMessage, String> = DeliveryService::try_new(config).unwrap();
+        let sender = kiki_channel.weak_sender();
+        drop(kiki_channel.into_stream());
+        assert!(sender.is_closed());
+    }
+
+    // Takes the next item the way a stream combinator does, through the trait only.
+    #[cfg(feature = "async")]
+    fn next_item<St: futures_core::Stream + Unpin>(stream: &mut St) -> Option<St::Item>{
+        block_on(std::future::poll_fn(|cx| std::pin::Pin::new(&mut *stream).poll_next(cx)))
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_result_stream_trait(){
+        let kiki_channel: DeliveryService<Number, Number, SquareMessage, String> = DeliveryService::default();
+        kiki_channel.feed_feeder(&mut (1..=4).map(Number).collect());
+        let mut stream = kiki_channel.into_stream();
+        assert_eq!(futures_core::Stream::size_hint(&stream).1, None);
+        let mut received = Vec::new();
+        while let Some(n) = next_item(&mut stream){
+            received.push(n.0);
+        }
+        received.sort_unstable();
+        assert_eq!(received, vec![1, 4, 9, 16]);
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_async_feeder(){
+        use std::future::Future;
+        use std::task::{Context, Poll, Waker};
+        use crate::error::Closed;
+
+        let mut config = ChannelConfig::new();
+        config.set_worker_number(2);
+        config.set_keep_alive(true);
+        let mut kiki_channel: DeliveryService<Number, Number, SquareMessage, String> = DeliveryService::try_new(config).unwrap();
+        let feeder = kiki_channel.async_feeder(2);
+        block_on(feeder.feed(Number(1))).unwrap();
+        block_on(feeder.feed(Number(2))).unwrap();
+
+        // The channel holds two inputs already, the third waits until a result is taken.
+        let waker = Waker::from(Arc::new(ThreadWaker(std::thread::current())));
+        let mut context = Context::from_waker(&waker);
+        let mut third = Box::pin(feeder.feed(Number(3)));
+        assert!(third.as_mut().poll(&mut context).is_pending());
+        assert!((&mut kiki_channel).next().is_some());
+        assert_eq!(third.as_mut().poll(&mut context), Poll::Ready(Ok(())));
+
+        kiki_channel.close_input();
+        assert_eq!((&mut kiki_channel).count(), 2);
+        assert_eq!(block_on(feeder.feed(Number(4))), Err(Closed));
+    }
+
+    // Needs worker threads.
+    #[cfg(not(any(miri, feature = "inline")))]
+    #[test]
+    fn test_shutdown(){
+        let mut config = ChannelConfig::new();
+        config.set_worker_number(3);
+        let mut kiki_channel: DeliveryService<Number, Number, SquareMessage, String> = DeliveryService::try_new(config).unwrap();
+        kiki_channel.feed_feeder(&mut (1..=9).chain(101..=190).map(Number).collect());
+        assert_eq!(kiki_channel.results().take(3).count(), 3);
+
+        let report = kiki_channel.shutdown();
+        assert_eq!(report.get_abandoned_inputs() + report.get_drained_messages(), 96);
+        assert!(report.get_drained_messages() > 0);
+        assert_eq!(report.get_joined_workers(), 3);
+        assert_eq!(report.get_panicked_workers(), 0);
+
+        // Nothing was ever sent, so nothing is lost and there's nothing to join.
+        let kiki_channel: DeliveryService<Number, Number, SquareMessage, String> = DeliveryService::default();
+        let report = kiki_channel.shutdown();
+        assert_eq!(report.get_joined_workers(), 0);
+        assert_eq!(report.get_abandoned_inputs() + report.get_drained_messages(), 0);
+    }
+
+    #[test]
+    fn test_stop_reason(){
+        let mut kiki_channel: DeliveryService<Number, Number, SquareMessage, String> = DeliveryService::default();
+        assert_eq!(kiki_channel.last_stop_reason(), None);
+
+        kiki_channel.feed_feeder(&mut (1..=5).map(Number).collect());
+        assert_eq!(kiki_channel.results().count(), 5);
+        assert_eq!(kiki_channel.last_stop_reason(), Some(&StopReason::Completed));
+    }
+
+    // Takes input milliseconds to finish, giving up early if the run is cancelled.
+    #[derive(Clone, Default)]
+    pub struct SlowMessage{
+        pub input: Number,
+        pub output: Number,
+    }
+
+    impl Message<Number, Number> for SlowMessage{
+        fn set_input(&mut self, message_input: Number){
+            self.input = message_input;
+        }
+
+        fn work(&mut self){
+            std::thread::sleep(Duration::from_millis(self.input.0));
+            self.output = self.input.clone();
+        }
+
+        fn work_with_context(&mut self, context: &mut WorkContext) -> Result<(), std::convert::Infallible>{
+            for _ in 0..self.input.0{
+                if context.is_cancelled(){
+                    return Ok(());
+                }
+                std::thread::sleep(Duration::from_millis(1));
+            }
+            self.output = self.input.clone();
+            Ok(())
+        }
+
+        fn clone_message_data(&self) -> Number{
+            self.output.clone()
+        }
+
+        fn new() -> Self{
+            SlowMessage{
+                input: Number(0),
+                output: Number(0),
+            }
+        }
+    }
+
+    #[test]
+    fn test_cancel(){
+        let mut config = ChannelConfig::new();
+        config.set_worker_number(2);
+        let mut kiki_channel: DeliveryService<Number, Number, SlowMessage> = DeliveryService::try_new(config).unwrap();
+        kiki_channel.feed_feeder(&mut vec![Number(20); 100]);
+
+        let token = kiki_channel.cancellation_token();
+        let start = Instant::now();
+        let mut received = 0;
+        for _ in &mut kiki_channel{
+            received += 1;
+            token.cancel();
+        }
+        // 100 inputs of 20ms with 2 workers would take a second.
+        assert!(start.elapsed() < Duration::from_millis(500));
+        assert_eq!(received, 1);
+        assert_eq!(kiki_channel.last_stop_reason(), Some(&StopReason::Cancelled));
+        assert!(kiki_channel.is_empty());
+
+        // The channel is still usable.
+        kiki_channel.feed_feeder(&mut vec![Number(1); 4]);
+        assert_eq!((&mut kiki_channel).count(), 4);
+
+        kiki_channel.feed_feeder(&mut vec![Number(1000); 10]);
+        assert_eq!(kiki_channel.cancel(), 10);
+        assert_eq!((&mut kiki_channel).count(), 0);
+    }
+
+    #[test]
+    fn test_cancel_ordered(){
+        let mut config = ChannelConfig::new();
+        config.set_worker_number(2);
+        config.set_ordered(true);
+        let mut kiki_channel: DeliveryService<Number, Number, SlowMessage> = DeliveryService::try_new(config).unwrap();
+        kiki_channel.feed_feeder(&mut (20..40).map(Number).collect());
+
+        let token = kiki_channel.cancellation_token();
+        for _ in &mut kiki_channel{
+            token.cancel();
+        }
+        assert_eq!(kiki_channel.last_stop_reason(), Some(&StopReason::Cancelled));
+
+        // Results sent after the cancel come out in order, instead of waiting behind the ones thrown away.
+        kiki_channel.feed_feeder(&mut (1..=4).map(Number).collect());
+        let results: Vec<u64> = (&mut kiki_channel).map(|n| n.0).collect();
+        assert_eq!(results, vec![1, 2, 3, 4]);
+        assert!(kiki_channel.is_empty());
+
+        // Same after cancel, with nothing iterated in between.
+        kiki_channel.feed_feeder(&mut vec![Number(1000); 6]);
+        kiki_channel.cancel();
+        kiki_channel.feed_feeder(&mut (5..=8).map(Number).collect());
+        let results: Vec<u64> = (&mut kiki_channel).map(|n| n.0).collect();
+        assert_eq!(results, vec![5, 6, 7, 8]);
+    }
+
+    #[cfg(not(any(miri, feature = "inline")))]
+    #[test]
+    fn test_throttle(){
+        use crate::channel::Throttle;
+
+        let mut config = ChannelConfig::builder().worker_number(1).channel_size(1).package_number(2).build().unwrap();
+        assert_eq!(config.get_throttle(), None);
+        config.set_throttle(Some(Throttle::DutyCycle(-1.0)));
+        assert_eq!(config.get_throttle(), Some(Throttle::DutyCycle(0.01)));
+
+        // Busy a quarter of the time: each 5ms message is followed by 15ms of rest.
+        config.set_throttle(Some(Throttle::DutyCycle(0.25)));
+        let mut kiki_channel: DeliveryService<Number, Number, SlowMessage> = DeliveryService::try_new(config).unwrap();
+        kiki_channel.feed_feeder(&mut vec![Number(5); 10]);
+        let start = Instant::now();
+        assert_eq!((&mut kiki_channel).count(), 10);
+        assert!(start.elapsed() >= Duration::from_millis(150));
+
+        let mut config = ChannelConfig::builder().worker_number(1).channel_size(1).package_number(2).build().unwrap();
+        config.set_throttle(Some(Throttle::Pause(Duration::from_millis(20))));
+        let mut kiki_channel: DeliveryService<Number, Number, SlowMessage> = DeliveryService::try_new(config).unwrap();
+        kiki_channel.feed_feeder(&mut vec![Number(0); 5]);
+        let start = Instant::now();
+        assert_eq!((&mut kiki_channel).count(), 5);
+        assert!(start.elapsed() >= Duration::from_millis(80));
+
+        // A long rest doesn't hold the channel up once it's dropped.
+        let mut config = ChannelConfig::builder().worker_number(1).channel_size(1).package_number(2).build().unwrap();
+        config.set_throttle(Some(Throttle::Pause(Duration::from_secs(60))));
+        let mut kiki_channel: DeliveryService<Number, Number, SlowMessage> = DeliveryService::try_new(config).unwrap();
+        kiki_channel.feed_feeder(&mut vec![Number(0); 1]);
+        let start = Instant::now();
+        assert_eq!((&mut kiki_channel).count(), 1);
+        drop(kiki_channel);
+        assert!(start.elapsed() < Duration::from_secs(10));
+    }
+
+    #[cfg(not(any(miri, feature = "inline")))]
+    #[test]
+    fn test_pause(){
+        let config = ChannelConfig::builder().worker_number(2).channel_size(2).package_number(4).build().unwrap();
+        let mut kiki_channel: DeliveryService<Number, Number, SlowMessage> = DeliveryService::try_new(config).unwrap();
+        kiki_channel.feed_feeder(&mut vec![Number(5); 20]);
+        assert_eq!((&mut kiki_channel).take(2).count(), 2);
+
+        kiki_channel.pause();
+        assert!(kiki_channel.is_paused());
+        assert!((&mut kiki_channel).next().is_none());
+        assert_eq!(kiki_channel.last_stop_reason(), Some(&StopReason::Paused));
+        assert!(kiki_channel.try_next().is_none());
+        assert!(kiki_channel.has_pending());
+
+        // Only the messages already being worked come back.
+        std::thread::sleep(Duration::from_millis(50));
+        let worked = kiki_channel.ready_results();
+        std::thread::sleep(Duration::from_millis(100));
+        assert_eq!(kiki_channel.ready_results(), worked);
+        assert_eq!(kiki_channel.len(), 18);
+
+        kiki_channel.resume();
+        assert!(!kiki_channel.is_paused());
+        assert_eq!((&mut kiki_channel).count(), 18);
+        assert_eq!(kiki_channel.last_stop_reason(), Some(&StopReason::Completed));
+
+        // Dropping a paused channel doesn't wait forever for its workers.
+        kiki_channel.feed_feeder(&mut vec![Number(5); 20]);
+        assert_eq!((&mut kiki_channel).take(1).count(), 1);
+        kiki_channel.pause();
+        drop(kiki_channel);
+    }
+
+    #[cfg(not(any(miri, feature = "inline")))]
+    #[test]
+    fn test_health(){
+        use crate::report::WorkerStatus;
+
+        let mut config = ChannelConfig::builder().worker_number(2).package_number(4).build().unwrap();
+        // The first worker dies before working anything.
+        config.set_worker_context(|worker_id| if worker_id == 1 { panic!("worker {} failed to start", worker_id) });
+        let mut kiki_channel: DeliveryService<Number, Number, SlowMessage> = DeliveryService::try_new(config).unwrap();
+        let health = kiki_channel.health();
+        assert!(health.get_workers().is_empty());
+        assert_eq!(health.get_since_last_completed(), None);
+
+        kiki_channel.feed_feeder(&mut vec![Number(50); 4]);
+        assert_eq!((&mut kiki_channel).take(1).count(), 1);
+        std::thread::sleep(Duration::from_millis(10));
+        let health = kiki_channel.health();
+        assert_eq!(health.get_status(1), Some(WorkerStatus::Panicked));
+        assert_eq!(health.get_status(2), Some(WorkerStatus::Running));
+        assert_eq!(health.get_in_flight() + health.get_ready_results(), 3);
+        assert!(health.get_since_last_completed().is_some());
+
+        // Dead workers are only listed once, and replaced.
+        assert_eq!(kiki_channel.health().get_status(1), None);
+        assert_eq!((&mut kiki_channel).count(), 3);
+        kiki_channel.pause();
+        let health = kiki_channel.health();
+        assert_eq!(health.count(WorkerStatus::Idle), 2);
+        assert_eq!(health.get_status(3), Some(WorkerStatus::Idle));
+        assert_eq!(health.get_pending_inputs() + health.get_in_flight() + health.get_ready_results(), 0);
+        assert!(health.is_paused());
+    }
+
+    #[cfg(not(any(miri, feature = "inline")))]
+    #[test]
+    fn test_heartbeat(){
+        use crate::event::PoolEvent;
+        use crate::report::WorkerStatus;
+
+        let mut config = ChannelConfig::builder().worker_number(1).build().unwrap();
+        assert_eq!(config.get_heartbeat_timeout(), None);
+        config.set_heartbeat_timeout(Some(Duration::from_millis(50)));
+        let mut kiki_channel: DeliveryService<Number, Number, SlowMessage> = DeliveryService::try_new(config).unwrap();
+        let events = kiki_channel.events();
+
+        // Quick messages never go silent for long.
+        kiki_channel.feed_feeder(&mut vec![Number(5); 10]);
+        assert_eq!((&mut kiki_channel).count(), 10);
+
+        // This one does, without a pacer to beat through.
+        kiki_channel.feed_feeder(&mut vec![Number(300)]);
+        assert!(kiki_channel.try_next().is_none());
+        std::thread::sleep(Duration::from_millis(150));
+        assert_eq!(kiki_channel.health().get_status(1), Some(WorkerStatus::Hung));
+        assert_eq!((&mut kiki_channel).count(), 1);
+        assert_eq!(kiki_channel.health().get_status(1), Some(WorkerStatus::Idle));
+
+        let beats: Vec<PoolEvent> = events.try_iter().filter(|event| matches!(event, PoolEvent::WorkerHung{..} | PoolEvent::WorkerRecovered{..})).collect();
+        assert_eq!(beats.len(), 2);
+        assert!(matches!(beats[0], PoolEvent::WorkerHung{worker_id: 1, silent_for} if silent_for > Duration::from_millis(50)));
+        assert_eq!(beats[1], PoolEvent::WorkerRecovered{worker_id: 1});
+    }
+
+    // Gives back the nice value of the thread that worked it, read from /proc.
+    #[cfg(all(feature = "os", target_os = "linux", not(any(miri, feature = "inline"))))]
+    #[derive(Default)]
+    pub struct NiceMessage{
+        pub nice: i64,
+    }
+
+    #[cfg(all(feature = "os", target_os = "linux", not(any(miri, feature = "inline"))))]
+    impl Message<i64, u64> for NiceMessage{
+        fn set_input(&mut self, _message_input: u64){}
+
+        fn work(&mut self){
+            let stat = std::fs::read_to_string("/proc/thread-self/stat").unwrap();
+            // Fields after the command name start at the third one, the nice value is the nineteenth.
+            let fields: Vec<&str> = stat[stat.rfind(')').unwrap() + 2..].split(' ').collect();
+            self.nice = fields[16].parse().unwrap();
+        }
+
+        fn clone_message_data(&self) -> i64{
+            self.nice
+        }
+
+        fn new() -> Self{
+            Self::default()
+        }
+    }
+
+    #[cfg(all(feature = "os", target_os = "linux", not(any(miri, feature = "inline"))))]
+    #[test]
+    fn test_os_settings(){
+        use crate::channel::{CoreSet, ThreadPriority};
+
+        // Lowering the priority and staying on the first core need no privileges.
+        let mut config = ChannelConfig::builder().worker_number(2).build().unwrap();
+        config.set_thread_priority(Some(ThreadPriority::Low));
+        config.set_core_affinity(Some(CoreSet::new().with(0).pinned()));
+        assert_eq!(config.get_core_affinity().map(CoreSet::len), Some(1));
+        let mut kiki_channel: DeliveryService<i64, u64, NiceMessage> = DeliveryService::try_new(config).unwrap();
+        kiki_channel.feed_feeder(&mut (0..10).collect());
+        assert!((&mut kiki_channel).all(|nice| nice == 10));
+        assert!(kiki_channel.take_os_errors().is_empty());
+
+        // A setting that can't be applied leaves the worker running with the defaults. A single worker, so that it surely started.
+        let mut config = ChannelConfig::builder().worker_number(1).build().unwrap();
+        config.set_thread_priority(Some(ThreadPriority::Realtime(0)));
+        config.set_core_affinity(Some(CoreSet::new().with(1 << 20)));
+        let mut kiki_channel: DeliveryService<i64, u64, NiceMessage> = DeliveryService::try_new(config).unwrap();
+        kiki_channel.feed_feeder(&mut (0..10).collect());
+        assert_eq!((&mut kiki_channel).count(), 10);
+        let errors = kiki_channel.take_os_errors();
+        assert_eq!(errors.len(), 2);
+        assert!(errors.iter().all(|(worker_id, error)| *worker_id == 1 && error.kind() == std::io::ErrorKind::InvalidInput));
+    }
+
+    // Same as SlowMessage, but goes through a WorkPacer instead of checking the token itself.
+    #[derive(Clone, Default)]
+    pub struct PacedMessage{
+        pub input: Number,
+        pub output: Number,
+    }
+
+    impl Message<Number, Number> for PacedMessage{
+        fn set_input(&mut self, message_input: Number){
+            self.input = message_input;
+        }
+
+        fn work(&mut self){
+            self.output = self.input.clone();
+        }
+
+        fn work_with_context(&mut self, context: &mut WorkContext) -> Result<(), std::convert::Infallible>{
+            let mut pacer = context.pacer();
+            pacer.set_total(Some(self.input.0 as usize));
+            pacer.set_time_slice(Some(Duration::from_millis(5)));
+            for _ in 0..self.input.0{
+                if !pacer.checkpoint(){
+                    return Ok(());
+                }
+                std::thread::sleep(Duration::from_millis(1));
+            }
+            self.output = self.input.clone();
+            Ok(())
+        }
+
+        fn clone_message_data(&self) -> Number{
+            self.output.clone()
+        }
+
+        fn new() -> Self{
+            PacedMessage{
+                input: Number(0),
+                output: Number(0),
+            }
+        }
+    }
+
+    #[test]
+    fn test_work_pacer(){
+        use crate::channel::SequentialDeliveryService;
+        use crate::report::WorkProgress;
+
+        let mut kiki_channel: SequentialDeliveryService<Number, Number, PacedMessage> = SequentialDeliveryService::default();
+        kiki_channel.feed_feeder(&mut vec![Number(3)]);
+        assert_eq!((&mut kiki_channel).map(|n| n.0).collect::<Vec<u64>>(), vec![3]);
+        // The caller's thread is worker 0. Its progress is kept until the next message.
+        let progress = kiki_channel.get_work_progress()[&0];
+        assert_eq!(progress, WorkProgress::new(3, Some(3)));
+        assert_eq!(progress.get_fraction(), Some(1.0));
+    }
+
+    #[cfg(not(any(miri, feature = "inline")))]
+    #[test]
+    fn test_work_pacer_cancel(){
+        let mut config = ChannelConfig::new();
+        config.set_worker_number(1);
+        let mut kiki_channel: DeliveryService<Number, Number, PacedMessage> = DeliveryService::try_new(config).unwrap();
+        assert!(kiki_channel.get_work_progress().is_empty());
+        kiki_channel.feed_feeder(&mut vec![Number(2000)]);
+
+        let token = kiki_channel.cancellation_token();
+        let start = Instant::now();
+        // Poll until the only worker is a few steps in.
+        let progress = loop{
+            assert!(kiki_channel.try_next().is_none());
+            if let Some(progress) = kiki_channel.get_work_progress().get(&1).copied(){
+                if progress.get_done() >= 5{
+                    break progress;
+                }
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        };
+        assert_eq!(progress.get_total(), Some(2000));
+        assert!(progress.get_fraction().unwrap() < 0.5);
+
+        // The next checkpoint tells the message to give up.
+        token.cancel();
+        assert!((&mut kiki_channel).next().is_none());
+        assert!(start.elapsed() < Duration::from_secs(1));
+        assert_eq!(kiki_channel.last_stop_reason(), Some(&StopReason::Cancelled));
+        assert!(kiki_channel.get_work_progress()[&1].get_done() < 2000);
+    }
+
+    #[test]
+    fn test_next_timeout(){
+        let mut kiki_channel: DeliveryService<Number, Number, SquareMessage, String> = DeliveryService::default();
+        assert!(matches!(kiki_channel.next_timeout(Duration::from_millis(1)), Ok(None)));
+        kiki_channel.feed_feeder(&mut (1..=9).map(Number).collect());
+        let mut results: Vec<u64> = Vec::new();
+        while let Some(data) = kiki_channel.next_timeout(Duration::from_secs(5)).unwrap(){
+            results.push(data.0);
+        }
+        results.sort_unstable();
+        assert_eq!(results, (1..=9).map(|n| n * n).collect::<Vec<u64>>());
+        assert_eq!(kiki_channel.last_stop_reason(), Some(&StopReason::Completed));
+
+        // Inline channels work the message before looking at the deadline.
+        #[cfg(not(any(miri, feature = "inline")))]
+        {
+            use crate::error::Timeout;
+
+            let mut config = ChannelConfig::new();
+            config.set_worker_number(1);
+            let mut kiki_channel: DeliveryService<Number, Number, SlowMessage> = DeliveryService::try_new(config).unwrap();
+            kiki_channel.feed_feeder(&mut vec![Number(200)]);
+            let start = Instant::now();
+            assert_eq!(kiki_channel.next_timeout(Duration::from_millis(20)).map(|data| data.map(|n| n.0)), Err(Timeout));
+            assert!(start.elapsed() < Duration::from_millis(150));
+            // The run went on after the timeout.
+            assert_eq!(kiki_channel.next_timeout(Duration::from_secs(5)).map(|data| data.map(|n| n.0)), Ok(Some(200)));
+            assert_eq!(kiki_channel.next_timeout(Duration::from_millis(20)).map(|data| data.map(|n| n.0)), Ok(None));
+        }
+    }
+
+    #[test]
+    fn test_name(){
+        let mut config = ChannelConfig::new();
+        config.set_worker_number(2);
+        config.set_memory_tracking(true);
+        config.set_name(Some(String::from("squares")));
+        let mut kiki_channel: DeliveryService<Number, Number, SquareMessage, String> = DeliveryService::try_new(config).unwrap();
+        assert_eq!(kiki_channel.get_name(), Some("squares"));
+
+        kiki_channel.feed_feeder(&mut (1..=5).map(Number).collect());
+        let (results, report) = kiki_channel.drain();
+        assert_eq!(results.len(), 5);
+        assert_eq!(report.get_name(), Some("squares"));
+        assert_eq!(kiki_channel.get_memory_stats().unwrap().get_name(), Some("squares"));
+        assert_eq!(kiki_channel.shutdown().get_name(), Some("squares"));
+
+        let kiki_channel: DeliveryService<Number, Number, SquareMessage, String> = DeliveryService::default();
+        assert_eq!(kiki_channel.shutdown().get_name(), None);
+    }
+
+    #[test]
+    fn test_fast_first_result(){
+        let mut config = ChannelConfig::new();
+        config.set_worker_number(4);
+        config.set_fast_first_result(true);
+        let mut kiki_channel: DeliveryService<Number, Number, SquareMessage, String> = DeliveryService::try_new(config).unwrap();
+        // Skipping the inputs that fail.
+        let inputs: Vec<u64> = (1..=60u64).filter(|n| !n.is_multiple_of(10) && *n != 13).collect();
+        for _ in 0..2{
+            kiki_channel.feed_feeder(&mut inputs.iter().copied().map(Number).collect());
+            let (mut results, report) = kiki_channel.drain();
+            results.sort_unstable_by_key(|n| n.0);
+            let expected: Vec<u64> = inputs.iter().map(|n| n * n).collect();
+            assert_eq!(results.iter().map(|n| n.0).collect::<Vec<u64>>(), expected);
+            assert_eq!(report.get_allocated_messages() + report.get_recycled_messages(), inputs.len());
+        }
+    }
+
+    #[test]
+    fn test_try_next(){
+        let mut kiki_channel: DeliveryService<Number, Number, SquareMessage, String> = DeliveryService::default();
+        assert!(kiki_channel.try_next().is_none());
+        // Skipping the inputs that fail.
+        let inputs: Vec<u64> = (1..=60u64).filter(|n| !n.is_multiple_of(10) && *n != 13).collect();
+        kiki_channel.feed_feeder(&mut inputs.iter().copied().map(Number).collect());
+        // Poll like a main loop would between frames, until everything was retrieved.
+        let mut results: Vec<u64> = Vec::new();
+        while !kiki_channel.is_empty(){
+            results.extend(kiki_channel.try_iter().map(|n| n.0));
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        results.sort_unstable();
+        assert_eq!(results, inputs.iter().map(|n| n * n).collect::<Vec<u64>>());
+        assert_eq!(kiki_channel.last_stop_reason(), Some(&StopReason::Completed));
+
+        // Ordered channels still yield in order.
+        let mut config = ChannelConfig::new();
+        config.set_ordered(true);
+        let mut kiki_channel: DeliveryService<Number, Number, SquareMessage, String> = DeliveryService::try_new(config).unwrap();
+        kiki_channel.feed_feeder(&mut inputs.iter().copied().map(Number).collect());
+        let mut results: Vec<u64> = Vec::new();
+        while !kiki_channel.is_empty(){
+            while let Some(data) = kiki_channel.try_next(){
+                results.push(data.0);
+            }
+        }
+        assert_eq!(results, inputs.iter().map(|n| n * n).collect::<Vec<u64>>());
+    }
+
+    #[test]
+    fn test_alloc_stats(){
+        let mut config = ChannelConfig::new();
+        config.set_worker_number(4);
+        config.set_alloc_tracking(true);
+        let mut kiki_channel: DeliveryService<Number, Number, SquareMessage, String> = DeliveryService::try_new(config).unwrap();
+        // Skipping the inputs that fail, since failures allocate their error.
+        let inputs: Vec<u64> = (1..=200u64).filter(|n| !n.is_multiple_of(10) && *n != 13).collect();
+        let mut results: Vec<u64> = Vec::with_capacity(inputs.len());
+
+        // The first run fills the queues and spawns the workers.
+        kiki_channel.feed_feeder(&mut inputs.iter().copied().map(Number).collect());
+        results.extend((&mut kiki_channel).map(|n| n.0));
+        assert!(kiki_channel.alloc_stats().unwrap().get_feed_allocations() > 0);
+        kiki_channel.reset_alloc_stats();
+        assert_eq!(kiki_channel.alloc_stats().unwrap().get_total_allocations(), 0);
+
+        // The steady-state run shouldn't allocate at all.
+        for _ in 0..3{
+            let mut input_vec: Vec<Number> = inputs.iter().copied().map(Number).collect();
+            results.clear();
+            kiki_channel.feed_feeder(&mut input_vec);
+            for data in &mut kiki_channel{
+                results.push(data.0);
+            }
+            assert_eq!(results.len(), inputs.len());
+            let alloc = kiki_channel.alloc_stats().unwrap();
+            assert_eq!(alloc.get_feed_allocations(), 0);
+            assert_eq!(alloc.get_dispatch_allocations(), 0);
+            assert_eq!(alloc.get_recycle_allocations(), 0);
+            assert_eq!(alloc.get_yield_allocations(), 0);
+        }
+
+        let kiki_channel: DeliveryService<Number, Number, SquareMessage, String> = DeliveryService::default();
+        assert!(kiki_channel.alloc_stats().is_none());
+    }
+
+    #[test]
+    fn test_memory_pressure(){
+        // Pressure in percent, so it can be shared through an atomic.
+        let pressure = Arc::new(AtomicUsize::new(0));
+        let probe_pressure = pressure.clone();
+        let mut config = ChannelConfig::new();
+        config.set_worker_number(4);
+        config.set_memory_pressure(move || probe_pressure.load(Ordering::SeqCst) as f64 / 100.0);
+        let package_number = config.get_package_number();
+        let mut kiki_channel: DeliveryService<Number, Number, SquareMessage, String> = DeliveryService::try_new(config).unwrap();
+        assert_eq!(kiki_channel.get_package_limit(), package_number);
+
+        // Each input weighs 1, so the outstanding weight is how many messages are in flight.
+        kiki_channel.feed_feeder(&mut vec![Number(1); 80]);
+        let mut results: Vec<u64> = Vec::new();
+        let mut index = 0;
+        while let Some(result) = kiki_channel.results().next(){
+            results.push(result.unwrap().0);
+            match index{
+                10 => pressure.store(100, Ordering::SeqCst),
+                40 => pressure.store(50, Ordering::SeqCst),
+                55 => pressure.store(0, Ordering::SeqCst),
+                _ => (),
+            }
+            // Once the extra messages came back, only one is left in flight. It stays that way while there's any pressure.
+            if (30..=40).contains(&index){
+                assert_eq!(kiki_channel.get_package_limit(), 1);
+                assert!(kiki_channel.get_outstanding_weight() <= 1);
+            }
+            if (42..=55).contains(&index){
+                assert_eq!(kiki_channel.get_package_limit(), package_number / 2);
+                assert!(kiki_channel.get_outstanding_weight() <= 1);
+            }
+            index += 1;
+        }
+        assert_eq!(kiki_channel.get_package_limit(), package_number);
+        assert_eq!(results, vec![1; 80]);
+    }
+
+    #[test]
+    fn test_adaptive_window(){
+        use crate::kik_adaptive::AdaptiveWindow;
+
+        let mut window = AdaptiveWindow::new(5, 3);
+        assert_eq!(window.get_band(), (3, 5));
+        assert_eq!(window.clamp(10), 5);
+        let work = Duration::from_millis(10);
+        // Taken right away and collected right away. Workers might run out, so it grows, up to the top of the band.
+        let now = Instant::now();
+        assert_eq!(window.record(3, now - work, now, work), None);
+        assert_eq!(window.record(3, now - work, now, work), None);
+        assert_eq!(window.record(3, now - work, now, work), Some(4));
+        for _ in 0..4{
+            window.record(4, now - work, now, work);
+        }
+        for _ in 0..5{
+            assert!(matches!(window.record(5, now - work, now, work), None | Some(5)));
+        }
+        // Results waiting longer than they took to work.
+        let completed = Instant::now() - Duration::from_millis(50);
+        for _ in 0..4{
+            window.record(5, completed - work, completed, work);
+        }
+        assert_eq!(window.record(5, completed - work, completed, work), Some(4));
+        // Messages waiting longer in the queue than they took to work. Never below the band.
+        let now = Instant::now();
+        for _ in 0..3{
+            window.record(3, now - work * 3, now, work);
+        }
+        assert_eq!(window.record(3, now - work * 3, now, work), None);
+        let mut last = None;
+        for _ in 0..4{
+            last = window.record(4, now - work * 3, now, work).or(last);
+        }
+        assert_eq!(last, Some(3));
+        window.shift(-2);
+        assert_eq!(window.get_band(), (1, 3));
+    }
+
+    #[test]
+    fn test_adaptive_packages(){
+        use crate::error::ConfigError;
+
+        let mut config = ChannelConfig::new();
+        config.set_worker_number(2);
+        config.set_adaptive_packages(Some((6, 3)));
+        assert_eq!(config.get_adaptive_packages(), Some((3, 6)));
+        assert!(config.validate().is_ok());
+        config.set_adaptive_packages(Some((2, 6)));
+        assert_eq!(config.validate(), Err(ConfigError::NotEnoughPackages{ package_number: 2, worker_number: 2 }));
+        config.set_adaptive_packages(Some((3, 7)));
+        assert_eq!(config.validate(), Err(ConfigError::TooManyPackages{ package_number: 7, max_package_number: 6 }));
+
+        // A consumer much slower than the work. The messages only hold results, so the window shrinks to the bottom of the band.
+        config.set_adaptive_packages(Some((3, 6)));
+        let mut kiki_channel: DeliveryService<Number, Number, SlowMessage> = DeliveryService::try_new(config).unwrap();
+        assert_eq!(kiki_channel.get_adaptive_band(), Some((3, 6)));
+        kiki_channel.feed_feeder(&mut vec![Number(1); 40]);
+        let mut results = 0;
+        while let Some(number) = (&mut kiki_channel).next(){
+            assert_eq!(number.0, 1);
+            let limit = kiki_channel.get_package_limit();
+            assert!((3..=6).contains(&limit));
+            std::thread::sleep(Duration::from_millis(10));
+            results += 1;
+        }
+        assert_eq!(results, 40);
+        if !cfg!(any(miri, feature = "inline")){
+            assert_eq!(kiki_channel.get_package_limit(), 3);
+        }
+        // The band follows the workers.
+        kiki_channel.add_workers(1).unwrap();
+        assert_eq!(kiki_channel.get_adaptive_band(), Some((4, 7)));
+    }
+
+    // A big buffer that tells its own size.
+    #[derive(Clone, Default)]
+    pub struct Frame{
+        pub pixels: Vec<u8>,
+    }
+
+    impl MessageData for Frame{
+        fn size_hint(&self) -> usize{
+            std::mem::size_of::<Self>() + self.pixels.len()
+        }
+    }
+
+    #[derive(Default)]
+    pub struct FrameMessage{
+        pub input: u8,
+        pub output: Frame,
+    }
+
+    impl Message<Frame, u8> for FrameMessage{
+        fn set_input(&mut self, message_input: u8){
+            self.input = message_input;
+        }
+
+        fn work(&mut self){
+            self.output.pixels = vec![self.input; 1 << 20];
+        }
+
+        fn clone_message_data(&self) -> Frame{
+            self.output.clone()
+        }
+
+        fn take_message_data(self) -> Frame{
+            self.output
+        }
+
+        fn new() -> Self{
+            FrameMessage{
+                input: 0,
+                output: Frame::new(),
+            }
+        }
+    }
+
+    #[test]
+    fn test_memory_budget(){
+        let frame_size = std::mem::size_of::<Frame>() + (1 << 20);
+        let mut config = ChannelConfig::new();
+        config.set_worker_number(4);
+        config.set_memory_budget(Some(frame_size * 7 / 2));
+        assert_eq!(config.get_memory_budget(), Some(frame_size * 7 / 2));
+        let mut kiki_channel: DeliveryService<Frame, u8, FrameMessage> = DeliveryService::try_new(config).unwrap();
+        kiki_channel.feed_feeder(&mut (0..24).collect());
+        let mut seen: Vec<u8> = Vec::new();
+        while let Some(frame) = (&mut kiki_channel).next(){
+            assert_eq!(frame.pixels.len(), 1 << 20);
+            seen.push(frame.pixels[0]);
+            // Three frames fit in the budget, a fourth wouldn't.
+            assert!(kiki_channel.get_outstanding_weight() <= 3);
+            assert!(kiki_channel.get_in_flight_memory() <= frame_size * 3);
+        }
+        seen.sort_unstable();
+        assert_eq!(seen, (0..24).collect::<Vec<u8>>());
+        assert_eq!(kiki_channel.get_in_flight_memory(), 0);
+    }
+
+    // The same frames as a plain Vec<u8>, which counts its own capacity.
+    #[derive(Default)]
+    pub struct PixelsMessage{
+        pub input: u8,
+        pub output: Vec<u8>,
+    }
+
+    impl Message<Vec<u8>, u8> for PixelsMessage{
+        fn set_input(&mut self, message_input: u8){
+            self.input = message_input;
+        }
+
+        fn work(&mut self){
+            self.output = vec![self.input; 1 << 20];
+        }
+
+        fn clone_message_data(&self) -> Vec<u8>{
+            self.output.clone()
+        }
+
+        fn take_message_data(self) -> Vec<u8>{
+            self.output
+        }
+
+        fn new() -> Self{
+            Self::default()
+        }
+    }
+
+    #[test]
+    fn test_memory_budget_vec(){
+        let frame_size = std::mem::size_of::<Vec<u8>>() + (1 << 20);
+        assert_eq!(vec![0u8; 1 << 20].size_hint(), frame_size);
+        let mut config = ChannelConfig::new();
+        config.set_worker_number(4);
+        config.set_memory_budget(Some(frame_size * 5 / 2));
+        let mut kiki_channel: DeliveryService<Vec<u8>, u8, PixelsMessage> = DeliveryService::try_new(config).unwrap();
+        kiki_channel.feed_feeder(&mut (0..16).collect());
+        let mut seen: Vec<u8> = Vec::new();
+        while let Some(pixels) = (&mut kiki_channel).next(){
+            assert_eq!(pixels.len(), 1 << 20);
+            seen.push(pixels[0]);
+            // Two frames fit in the budget, a third wouldn't.
+            assert!(kiki_channel.get_outstanding_weight() <= 2);
+            assert!(kiki_channel.get_in_flight_memory() <= frame_size * 2);
+        }
+        seen.sort_unstable();
+        assert_eq!(seen, (0..16).collect::<Vec<u8>>());
+    }
+
+    // Counts its clones, so a test can tell whether results were cloned or swapped.
+    static BUFFER_CLONES: AtomicUsize = AtomicUsize::new(0);
+
+    #[derive(Default, Debug)]
+    pub struct CountedBuffer{
+        pub values: Vec<u64>,
+    }
+
+    impl Clone for CountedBuffer{
+        fn clone(&self) -> Self{
+            BUFFER_CLONES.fetch_add(1, Ordering::SeqCst);
+            CountedBuffer{
+                values: self.values.clone(),
+            }
+        }
+    }
+
+    impl MessageData for CountedBuffer{}
+
+    #[derive(Default)]
+    pub struct BufferMessage{
+        pub input: u64,
+        pub output: CountedBuffer,
+    }
+
+    impl Message<CountedBuffer, u64> for BufferMessage{
+        fn set_input(&mut self, message_input: u64){
+            self.input = message_input;
+        }
+
+        fn work(&mut self){
+            self.output.values.clear();
+            self.output.values.resize(64, self.input);
+        }
+
+        fn clone_message_data(&self) -> CountedBuffer{
+            self.output.clone()
+        }
+
+        fn swap_message_data(&mut self, spare: CountedBuffer) -> CountedBuffer{
+            std::mem::replace(&mut self.output, spare)
+        }
+
+        fn take_message_data(self) -> CountedBuffer{
+            self.output
+        }
+
+        fn new() -> Self{
+            Self::default()
+        }
+    }
+
+    #[test]
+    fn test_return_buffer(){
+        let mut kiki_channel: DeliveryService<CountedBuffer, u64, BufferMessage> = DeliveryService::default();
+        // Results dropped by the consumer. Every recycled message clones its data.
+        BUFFER_CLONES.store(0, Ordering::SeqCst);
+        kiki_channel.feed_feeder(&mut (0..100).collect());
+        assert_eq!((&mut kiki_channel).count(), 100);
+        assert!(BUFFER_CLONES.load(Ordering::SeqCst) >= 50);
+
+        // Results given back. Only the first recycled message finds no spare.
+        BUFFER_CLONES.store(0, Ordering::SeqCst);
+        kiki_channel.feed_feeder(&mut (0..100).collect());
+        let mut sum = 0;
+        while let Some(buffer) = (&mut kiki_channel).next(){
+            assert_eq!(buffer.values.len(), 64);
+            sum += buffer.values[0];
+            kiki_channel.return_buffer(buffer);
+        }
+        assert_eq!(sum, (0..100).sum());
+        assert!(BUFFER_CLONES.load(Ordering::SeqCst) <= 2);
+        assert!(kiki_channel.get_spare_buffers() >= 1);
+    }
+
+    // Counts how many were built.
+    static POOLED_BUILT: AtomicUsize = AtomicUsize::new(0);
+
+    #[derive(Default)]
+    pub struct PooledMessage{
+        pub input: u64,
+        pub output: Vec<f32>,
+    }
+
+    impl Message<Vec<f32>, u64> for PooledMessage{
+        fn set_input(&mut self, message_input: u64){
+            self.input = message_input;
+        }
+
+        fn work(&mut self){
+            for (index, sample) in self.output.iter_mut().enumerate(){
+                *sample = (self.input as usize + index) as f32;
+            }
+        }
+
+        fn clone_message_data(&self) -> Vec<f32>{
+            self.output.clone()
+        }
+
+        fn new() -> Self{
+            POOLED_BUILT.fetch_add(1, Ordering::SeqCst);
+            PooledMessage{
+                input: 0,
+                output: vec![0.0; 256],
+            }
+        }
+    }
+
+    #[test]
+    fn test_message_pool(){
+        use crate::pool::MessagePool;
+
+        let mut pool: MessagePool<u32> = MessagePool::new(2);
+        pool.fill(5, || 7);
+        assert_eq!((pool.len(), pool.get_capacity()), (2, 2));
+        assert_eq!(pool.loan(), Some(7));
+        assert_eq!(pool.loan(), Some(7));
+        assert_eq!(pool.loan(), None);
+        assert_eq!(pool.get_misses(), 1);
+        pool.give_back(1);
+        pool.give_back(2);
+        pool.give_back(3);
+        assert_eq!(pool.len(), 2);
+
+        let mut config = ChannelConfig::new();
+        config.set_worker_number(2);
+        config.set_message_pool(true);
+        assert!(config.get_message_pool());
+        let package_number = config.get_package_number();
+        let mut kiki_channel: DeliveryService<Vec<f32>, u64, PooledMessage> = DeliveryService::try_new(config).unwrap();
+        // Built up front.
+        assert_eq!(kiki_channel.get_pooled_messages(), package_number);
+        let built = POOLED_BUILT.load(Ordering::SeqCst);
+        assert!(built >= package_number);
+        for _ in 0..3{
+            kiki_channel.feed_feeder(&mut (0..50).collect());
+            let mut count = 0;
+            for samples in &mut kiki_channel{
+                assert_eq!(samples.len(), 256);
+                assert_eq!(samples[1], samples[0] + 1.0);
+                count += 1;
+            }
+            assert_eq!(count, 50);
+            // Every message went back to the pool.
+            assert_eq!(kiki_channel.get_pooled_messages(), package_number);
+        }
+        assert_eq!(kiki_channel.get_pool_misses(), 0);
+        // Nothing was built while running. Other tests don't build PooledMessage.
+        assert_eq!(POOLED_BUILT.load(Ordering::SeqCst), built);
+
+        // More workers need more messages, built right away.
+        kiki_channel.add_workers(1).unwrap();
+        assert_eq!(kiki_channel.get_pooled_messages(), package_number + 1);
+    }
+
+    #[cfg(not(miri))]
+    #[test]
+    fn test_split(){
+        let kiki_channel: DeliveryService<Number, Number, SquareMessage, String> = DeliveryService::default();
+        kiki_channel.feed_feeder(&mut vec![Number(1), Number(2)]);
+        let (feeder, results) = kiki_channel.split();
+        let producer = std::thread::spawn(move || {
+            let second_feeder = feeder.clone();
+            // Skipping the inputs that fail. Pausing now and then so the receiver runs out of work.
+            for n in (3..=60u64).filter(|n| !n.is_multiple_of(10) && *n != 13){
+                feeder.send(Number(n)).unwrap();
+                if n.is_multiple_of(7){
+                    std::thread::sleep(Duration::from_millis(2));
+                }
+            }
+            second_feeder.feed(&mut vec![Number(61), Number(62)]).unwrap();
+        });
+        // Only ends once both handles are dropped.
+        let mut received: Vec<u64> = results.map(|n| n.0).collect();
+        producer.join().unwrap();
+        received.sort_unstable();
+        let expected: Vec<u64> = (1..=62u64).filter(|n| !n.is_multiple_of(10) && *n != 13).map(|n| n * n).collect();
+        assert_eq!(received, expected);
+
+        // Shutting down the receiver closes the handles.
+        let kiki_channel: DeliveryService<Number, Number, SquareMessage, String> = DeliveryService::default();
+        let (feeder, results) = kiki_channel.split();
+        assert!(!feeder.is_closed());
+        assert_eq!(results.shutdown().get_joined_workers(), 0);
+        assert!(feeder.is_closed());
+        assert_eq!(feeder.send(Number(1)), Err(Closed));
+    }
+
+    #[cfg(not(miri))]
+    #[test]
+    fn test_keep_alive(){
+        let mut config = ChannelConfig::new();
+        config.set_worker_number(2);
+        config.set_keep_alive(true);
+        let mut kiki_channel: DeliveryService<Number, Number, SquareMessage, String> = DeliveryService::try_new(config).unwrap();
+        let sender = kiki_channel.weak_sender();
+        kiki_channel.feed_feeder(&mut vec![Number(1), Number(2)]);
+        let producer = std::thread::spawn(move || {
+            // Each feed arrives after the loop ran out of work.
+            for n in 3..=6{
+                std::thread::sleep(Duration::from_millis(5));
+                sender.send(Number(n)).unwrap();
+            }
+            std::thread::sleep(Duration::from_millis(5));
+            sender.close_input();
+            assert_eq!(sender.send(Number(7)), Err(Closed));
+        });
+        let mut received: Vec<u64> = (&mut kiki_channel).map(|n| n.0).collect();
+        producer.join().unwrap();
+        received.sort_unstable();
+        assert_eq!(received, vec![1, 4, 9, 16, 25, 36]);
+        assert!(kiki_channel.is_input_closed());
+
+        // Once closed, it ends like a channel that isn't kept alive.
+        kiki_channel.feed_feeder(&mut vec![Number(8)]);
+        assert_eq!((&mut kiki_channel).map(|n| n.0).collect::<Vec<u64>>(), vec![64]);
+    }
+
+    #[test]
+    fn test_shared_feed(){
+        // Only needs to read the channel to feed it.
+        fn feed_more(channel: &DeliveryService<Number, Number, SquareMessage, String>, from: u64){
+            let mut inputs: Vec<Number> = (from..from + 5).filter(|n| !n.is_multiple_of(10) && *n != 13).map(Number).collect();
+            channel.feed_feeder(&mut inputs);
+        }
+        let mut kiki_channel: DeliveryService<Number, Number, SquareMessage, String> = DeliveryService::default();
+        feed_more(&kiki_channel, 1);
+        let mut received: Vec<u64> = Vec::new();
+        // Feeding halfway through the iteration, the new inputs join the same run.
+        while let Some(n) = (&mut kiki_channel).next(){
+            received.push(n.0);
+            if received.len() == 2{
+                feed_more(&kiki_channel, 21);
+            }
+        }
+        received.sort_unstable();
+        let expected: Vec<u64> = [1, 2, 3, 4, 5, 21, 22, 23, 24, 25].iter().map(|n| n * n).collect();
+        assert_eq!(received, expected);
+    }
+
+    #[test]
+    fn test_max_weight(){
+        let mut config = ChannelConfig::new();
+        config.set_worker_number(4);
+        config.set_max_weight(Some(20));
+        let mut kiki_channel: DeliveryService<Number, Number, SlowMessage> = DeliveryService::try_new(config).unwrap();
+        // 30 is heavier than the limit. It's sent alone instead of blocking the run.
+        let mut inputs: Vec<Number> = (1..=12).map(Number).collect();
+        inputs.push(Number(30));
+        kiki_channel.feed_feeder(&mut inputs);
+
+        let mut received: Vec<u64> = Vec::new();
+        while let Some(result) = kiki_channel.results().next(){
+            received.push(result.unwrap().0);
+            let outstanding = kiki_channel.get_outstanding_weight();
+            assert!(outstanding <= 20 || outstanding == 30, "{} outstanding", outstanding);
+        }
+        received.sort_unstable();
+        assert_eq!(received, (1..=12).chain(Some(30)).collect::<Vec<u64>>());
+        assert_eq!(kiki_channel.get_outstanding_weight(), 0);
+    }
+
+    #[test]
+    fn test_feed_generator(){
+        let mut config = ChannelConfig::new();
+        config.set_worker_number(2);
+        let mut kiki_channel: DeliveryService<Number, Number, SquareMessage, String> = DeliveryService::try_new(config).unwrap();
+        let generated = Arc::new(AtomicUsize::new(0));
+        let counter = generated.clone();
+        let mut next: u64 = 0;
+        kiki_channel.feed_generator(move ||{
+            next += 1;
+            counter.fetch_add(1, Ordering::SeqCst);
+            // Skipping the inputs that fail.
+            while next.is_multiple_of(10) || next == 13{
+                next += 1;
+            }
+            Some(Number(next))
+        });
+        // Queued inputs go first.
+        kiki_channel.feed_feeder(&mut vec![Number(1001)]);
+        assert_eq!(kiki_channel.len(), 2);
+
+        // The generator never ends, only the inputs needed are pulled.
+        let squares: Vec<u64> = kiki_channel.results().take(20).map(|result| result.unwrap().0).collect();
+        assert!(squares.contains(&1_002_001));
+        assert!(generated.load(Ordering::SeqCst) <= 20 + 4);
+
+        let mut finite = vec![Number(2), Number(3)].into_iter();
+        let mut kiki_channel: DeliveryService<Number, Number, SquareMessage, String> = DeliveryService::default();
+        kiki_channel.feed_generator(move || finite.next());
+        let mut squares: Vec<u64> = kiki_channel.results().map(|result| result.unwrap().0).collect();
+        squares.sort_unstable();
+        assert_eq!(squares, vec![4, 9]);
+        assert!(kiki_channel.is_empty());
+    }
+
+    // Needs worker threads.
+    #[cfg(not(any(miri, feature = "inline")))]
+    #[test]
+    fn test_scale_workers(){
+        let mut config = ChannelConfig::new();
+        config.set_worker_number(2);
+        let mut kiki_channel: DeliveryService<Number, Number, SlowMessage> = DeliveryService::try_new(config).unwrap();
+        kiki_channel.feed_feeder(&mut vec![Number(2); 60]);
+        let mut received = kiki_channel.results().take(5).count();
+
+        kiki_channel.add_workers(3).unwrap();
+        assert_eq!(kiki_channel.get_worker_number(), 5);
+        received += kiki_channel.results().take(20).count();
+
+        // Always at least one left.
+        assert_eq!(kiki_channel.remove_workers(10), 4);
+        assert_eq!(kiki_channel.get_worker_number(), 1);
+        received += kiki_channel.results().count();
+        assert_eq!(received, 60);
+
+        // Every worker that was started is joined, the ones removed included.
+        assert_eq!(kiki_channel.shutdown().get_joined_workers(), 5);
+    }
+
+    // Needs worker threads.
+    #[cfg(not(any(miri, feature = "inline")))]
+    #[test]
+    fn test_events(){
+        use crate::event::PoolEvent;
+
+        let mut config = ChannelConfig::new();
+        config.set_worker_number(2);
+        let mut kiki_channel: DeliveryService<Number, Number, SlowMessage> = DeliveryService::try_new(config).unwrap();
+        let events = kiki_channel.events();
+        kiki_channel.feed_feeder(&mut vec![Number(1); 10]);
+        assert_eq!((&mut kiki_channel).count(), 10);
+        kiki_channel.shutdown();
+
+        let events: Vec<PoolEvent> = events.try_iter().collect();
+        let count = |expected: fn(&PoolEvent) -> bool| events.iter().filter(|event| expected(event)).count();
+        assert_eq!(count(|event| matches!(event, PoolEvent::WorkerStarted{..})), 2);
+        assert_eq!(count(|event| matches!(event, PoolEvent::WorkerExited{..})), 2);
+        assert_eq!(count(|event| matches!(event, PoolEvent::WorkerPanicked{..})), 0);
+        assert_eq!(count(|event| *event == PoolEvent::BatchCompleted{stop_reason: StopReason::Completed}), 1);
+    }
+
+    // Needs worker threads.
+    #[cfg(not(any(miri, feature = "inline")))]
+    #[test]
+    fn test_log_hook(){
+        use std::sync::Mutex;
+
+        let lines: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+        let log = lines.clone();
+        let mut config = ChannelConfig::new();
+        config.set_worker_number(2);
+        config.set_name(Some(String::from("logged")));
+        config.set_log_hook(move |line| log.lock().unwrap().push(line.to_string()));
+        let mut kiki_channel: DeliveryService<Number, Number, SquareMessage, String> = DeliveryService::try_new(config).unwrap();
+        kiki_channel.feed_feeder(&mut (1..=4).map(Number).collect());
+        assert_eq!((&mut kiki_channel).count(), 4);
+        kiki_channel.shutdown();
+
+        let lines = lines.lock().unwrap();
+        assert_eq!(lines.iter().filter(|line| line.contains("started")).count(), 2);
+        assert_eq!(lines.iter().filter(|line| line.contains("exited")).count(), 2);
+        assert!(lines.contains(&"Batch completed: Every input was worked (pool: logged)".to_string()));
+    }
+
+    // Needs worker threads.
+    #[cfg(not(any(miri, feature = "inline")))]
+    #[test]
+    fn test_observer(){
+        use crate::event::ChannelObserver;
+        use std::sync::Mutex;
+
+        #[derive(Default)]
+        struct Recorder{
+            dispatched: Mutex<Vec<usize>>,
+            completed: Mutex<Vec<usize>>,
+            started: AtomicUsize,
+            spawned: AtomicUsize,
+            exited: AtomicUsize,
+            panicked: AtomicUsize,
+        }
+
+        impl ChannelObserver for Recorder{
+            fn on_dispatch(&self, message_id: usize){
+                self.dispatched.lock().unwrap().push(message_id);
+            }
+
+            fn on_work_start(&self, _message_id: usize, _worker_id: usize){
+                self.started.fetch_add(1, Ordering::SeqCst);
+            }
+
+            fn on_complete(&self, message_id: usize, work_time: Duration){
+                assert!(work_time >= Duration::from_millis(1));
+                self.completed.lock().unwrap().push(message_id);
+            }
+
+            fn on_worker_spawn(&self, _worker_id: usize){
+                self.spawned.fetch_add(1, Ordering::SeqCst);
+            }
+
+            fn on_worker_exit(&self, _worker_id: usize){
+                self.exited.fetch_add(1, Ordering::SeqCst);
+            }
+
+            fn on_worker_panic(&self, _worker_id: usize){
+                self.panicked.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        let mut config = ChannelConfig::new();
+        config.set_worker_number(2);
+        let mut kiki_channel: DeliveryService<Number, Number, SlowMessage> = DeliveryService::try_new(config).unwrap();
+        let recorder = Arc::new(Recorder::default());
+        kiki_channel.add_observer(recorder.clone());
+        kiki_channel.feed_feeder(&mut vec![Number(1); 10]);
+        assert_eq!((&mut kiki_channel).count(), 10);
+        kiki_channel.shutdown();
+
+        // Ids are the dispatch order.
+        assert_eq!(*recorder.dispatched.lock().unwrap(), (0..10).collect::<Vec<usize>>());
+        let mut completed = recorder.completed.lock().unwrap().clone();
+        completed.sort_unstable();
+        assert_eq!(completed, (0..10).collect::<Vec<usize>>());
+        assert_eq!(recorder.started.load(Ordering::SeqCst), 10);
+        assert_eq!(recorder.spawned.load(Ordering::SeqCst), 2);
+        assert_eq!(recorder.exited.load(Ordering::SeqCst), 2);
+        assert_eq!(recorder.panicked.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn test_subscribe_results(){
+        let mut kiki_channel: DeliveryService<Number, Number, SquareMessage, String> = DeliveryService::default();
+        let preview = kiki_channel.subscribe_results();
+        let writer = kiki_channel.subscribe_results();
+        let dropped = kiki_channel.subscribe_results();
+        drop(dropped);
+        // 10 fails, and failures aren't sent.
+        kiki_channel.feed_feeder(&mut (1..=12).map(Number).collect());
+        let mut received: Vec<u64> = kiki_channel.results().filter_map(Result::ok).map(|n| n.0).collect();
+        received.sort_unstable();
+
+        for subscription in [preview, writer].iter(){
+            let mut watched: Vec<u64> = subscription.try_iter().map(|n| n.0).collect();
+            watched.sort_unstable();
+            assert_eq!(watched, received);
+        }
+        assert_eq!(received.len(), 11);
+    }
+
+    #[test]
+    fn test_registry(){
+        use crate::registry::PoolRegistry;
+
+        let mut registry = PoolRegistry::new();
+        for name in ["squares", "slow"].iter(){
+            let mut config = ChannelConfig::new();
+            config.set_worker_number(2);
+            config.set_name(Some(String::from(*name)));
+            if *name == "squares"{
+                let channel: DeliveryService<Number, Number, SquareMessage, String> = DeliveryService::try_new(config).unwrap();
+                assert!(registry.register(name, channel).is_ok());
+            } else {
+                let channel: DeliveryService<Number, Number, SlowMessage> = DeliveryService::try_new(config).unwrap();
+                assert!(registry.register(name, channel).is_ok());
+            }
+        }
+        let taken: DeliveryService<Number, Number, SlowMessage> = DeliveryService::default();
+        assert!(registry.register("slow", taken).is_err());
+        assert_eq!(registry.names().collect::<Vec<&str>>(), vec!["squares", "slow"]);
+
+        // Found with their full type only.
+        assert!(registry.get::<DeliveryService<Number, Number, SlowMessage>>("squares").is_none());
+        assert!(registry.get::<DeliveryService<Number, Number, SlowMessage>>("missing").is_none());
+        let slow = registry.get_mut::<DeliveryService<Number, Number, SlowMessage>>("slow").unwrap();
+        slow.feed_feeder(&mut vec![Number(1); 4]);
+        assert_eq!(slow.count(), 4);
+
+        // Last registered goes first.
+        let reports = registry.shutdown_all();
+        let names: Vec<&str> = reports.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, vec!["slow", "squares"]);
+        assert_eq!(reports[0].1.get_name(), Some("slow"));
+    }
+
+    #[test]
+    fn test_sequential(){
+        use crate::channel::SequentialDeliveryService;
+
+        let mut config = ChannelConfig::new();
+        config.set_worker_number(2);
+        let mut parallel: DeliveryService<Number, Number, SquareMessage, String> = DeliveryService::try_new(config).unwrap();
+        let mut sequential: SequentialDeliveryService<Number, Number, SquareMessage, String> = SequentialDeliveryService::default();
+        let events = sequential.events();
+
+        // Same calls for both.
+        parallel.feed_feeder(&mut (1..=9).map(Number).collect());
+        sequential.feed_feeder(&mut (1..=9).map(Number).collect());
+        let mut parallel_results: Vec<u64> = (&mut parallel).map(|Number(x)| x).collect();
+        let mut sequential_results: Vec<u64> = (&mut sequential).map(|Number(x)| x).collect();
+        parallel_results.sort_unstable();
+        sequential_results.sort_unstable();
+        assert_eq!(parallel_results, sequential_results);
+        assert!(sequential.is_empty());
+
+        // No worker was ever started.
+        assert!(events.try_iter().all(|event| !matches!(event, crate::event::PoolEvent::WorkerStarted{..})));
+        assert_eq!(sequential.shutdown().get_joined_workers(), 0);
+    }
+
+    #[test]
+    fn test_feed_receipt(){
+        let mut config = ChannelConfig::new();
+        config.set_worker_number(1);
+        config.set_package_number(2);
+        let mut kiki_channel: DeliveryService<Number, Number, SquareMessage, String> = DeliveryService::try_new(config).unwrap();
+        kiki_channel.feed_feeder(&mut vec![Number(1); 3]);
+        let receipt = kiki_channel.feed_feeder_ack(&mut vec![Number(2); 4]);
+        assert_eq!(receipt.get_remaining(), 4);
+        assert!(!receipt.wait_timeout(Duration::from_millis(1)));
+
+        // Sent before the plain inputs, and the receipt is told as soon as the last of them leaves.
+        let mut results = kiki_channel.results();
+        assert_eq!(results.next().unwrap().unwrap().0, 4);
+        let mut received = 1;
+        while !receipt.is_dispatched(){
+            assert!(results.next().is_some());
+            received += 1;
+        }
+        assert!(received < 7);
+        assert!(receipt.wait());
+        assert_eq!(kiki_channel.results().count(), 7 - received);
+
+        // Thrown away before being sent.
+        let receipt = kiki_channel.feed_feeder_ack(&mut vec![Number(2); 4]);
+        assert_eq!(kiki_channel.cancel(), 4);
+        assert!(!receipt.wait());
+        assert_eq!(receipt.get_abandoned(), 4);
+    }
+
+    #[test]
+    fn test_drop_policy(){
+        use crate::channel::DropPolicy;
+        use std::sync::Mutex;
+
+        let new_channel = || {
+            let mut config = ChannelConfig::new();
+            config.set_worker_number(2);
+            let mut kiki_channel: DeliveryService<Number, Number, SlowMessage> = DeliveryService::try_new(config).unwrap();
+            kiki_channel.feed_feeder(&mut vec![Number(40); 20]);
+            assert!((&mut kiki_channel).next().is_some());
+            kiki_channel
+        };
+
+        // Everything left is worked before the channel goes away.
+        let mut kiki_channel = new_channel();
+        kiki_channel.set_drop_policy(DropPolicy::Finish);
+        let report = kiki_channel.shutdown();
+        assert_eq!(report.get_abandoned_inputs() + report.get_drained_messages(), 0);
+
+        // A keep-alive channel stops waiting for more inputs once it's going away, and still works the ones it has.
+        let mut config = ChannelConfig::new();
+        config.set_keep_alive(true);
+        let mut kiki_channel: DeliveryService<Number, Number, SlowMessage> = DeliveryService::try_new(config).unwrap();
+        kiki_channel.feed_feeder(&mut vec![Number(1); 4]);
+        kiki_channel.set_drop_policy(DropPolicy::Finish);
+        let sender = kiki_channel.weak_sender();
+        let (tx_report, rx_report) = std::sync::mpsc::channel();
+        std::thread::spawn(move || tx_report.send(kiki_channel.shutdown()).unwrap());
+        let report = rx_report.recv_timeout(Duration::from_secs(5)).expect("shutdown waited for more inputs");
+        assert_eq!(report.get_abandoned_inputs() + report.get_drained_messages(), 0);
+        assert!(sender.is_closed());
+
+        // The inputs that weren't sent are handed over instead of lost.
+        let persisted: Arc<Mutex<Vec<Number>>> = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&persisted);
+        let mut kiki_channel = new_channel();
+        kiki_channel.set_drop_policy(DropPolicy::Persist(Box::new(move |mut input_vec| sink.lock().unwrap().append(&mut input_vec))));
+        let report = kiki_channel.shutdown();
+        assert_eq!(report.get_abandoned_inputs(), 0);
+        assert_eq!(persisted.lock().unwrap().len(), report.get_persisted_inputs());
+        assert_eq!(1 + report.get_persisted_inputs() + report.get_drained_messages(), 20);
+
+        // The messages with the workers give up early. 19 messages of 40ms with 2 workers would take over 350ms.
+        let mut kiki_channel = new_channel();
+        kiki_channel.set_drop_policy(DropPolicy::Abort);
+        let start = Instant::now();
+        drop(kiki_channel);
+        assert!(start.elapsed() < Duration::from_millis(200));
+    }
+
+    // Sleeps input milliseconds, without checking for cancellation.
+    #[cfg(not(any(miri, feature = "inline")))]
+    #[derive(Default)]
+    pub struct StuckMessage{
+        pub input: u64,
+    }
+
+    #[cfg(not(any(miri, feature = "inline")))]
+    impl Message<u64, u64> for StuckMessage{
+        fn set_input(&mut self, message_input: u64){
+            self.input = message_input;
+        }
+
+        fn work(&mut self){
+            std::thread::sleep(Duration::from_millis(self.input));
+        }
+
+        fn clone_message_data(&self) -> u64{
+            self.input
+        }
+
+        fn new() -> Self{
+            Self::default()
+        }
+    }
+
+    #[cfg(not(any(miri, feature = "inline")))]
+    #[test]
+    fn test_drop_grace(){
+        let mut kiki_channel: DeliveryService<u64, u64, StuckMessage> = DeliveryService::default();
+        kiki_channel.set_drop_grace(Duration::from_millis(50));
+        kiki_channel.feed_feeder(&mut vec![0, 2000]);
+        assert!((&mut kiki_channel).next().is_some());
+        // The worker stuck in the second message is left behind instead of waited for.
+        let start = Instant::now();
+        drop(kiki_channel);
+        assert!(start.elapsed() < Duration::from_millis(1000));
+
+        // Shutdown still waits for it.
+        let mut kiki_channel: DeliveryService<u64, u64, StuckMessage> = DeliveryService::default();
+        kiki_channel.set_drop_grace(Duration::from_millis(50));
+        kiki_channel.feed_feeder(&mut vec![0, 300]);
+        assert!((&mut kiki_channel).next().is_some());
+        let start = Instant::now();
+        let report = kiki_channel.shutdown();
+        assert!(start.elapsed() >= Duration::from_millis(250));
+        assert_eq!(report.get_drained_messages(), 1);
+
+        // So does dropping the iterator of an owned channel.
+        let mut kiki_channel: DeliveryService<u64, u64, StuckMessage> = DeliveryService::default();
+        kiki_channel.set_drop_grace(Duration::from_millis(50));
+        kiki_channel.feed_feeder(&mut vec![0, 300]);
+        let mut results = kiki_channel.into_iter();
+        assert!(results.next().is_some());
+        let start = Instant::now();
+        drop(results);
+        assert!(start.elapsed() >= Duration::from_millis(250));
+    }
+
+    #[cfg(any(miri, feature = "inline"))]
+    #[test]
+    fn test_inline(){
+        let config = ChannelConfig::new();
+        assert!(config.get_inline());
+        let mut kiki_channel: DeliveryService<Number, Number, SquareMessage, String> = DeliveryService::try_new(config).unwrap();
+        kiki_channel.feed_feeder(&mut (1..=5).map(Number).collect());
+        let mut results: Vec<u64> = kiki_channel.results().map(|result| result.unwrap().0).collect();
+        results.sort_unstable();
+        assert_eq!(results, vec![1, 4, 9, 16, 25]);
+        // Everything was worked on this thread.
+        assert_eq!(kiki_channel.shutdown().get_joined_workers(), 0);
+    }
+
+    #[test]
+    fn test_no_workers_is_inline(){
+        let mut config = ChannelConfig::new();
+        config.set_worker_number(0);
+        assert!(config.get_inline());
+        assert_eq!(config.get_worker_number(), 1);
+        let mut kiki_channel: DeliveryService<Number, Number, SquareMessage, String> = DeliveryService::try_new(config).unwrap();
+        kiki_channel.feed_feeder(&mut (1..=5).map(Number).collect());
+        // Nothing is worked before it's asked for, so the results come in input order.
+        let results: Vec<u64> = kiki_channel.results().map(|result| result.unwrap().0).collect();
+        assert_eq!(results, vec![1, 4, 9, 16, 25]);
+        assert_eq!(kiki_channel.shutdown().get_joined_workers(), 0);
+    }
+}
@@ -0,0 +1,204 @@
+//! # Os
+//!
+//! Scheduling settings for the worker threads, behind the "os" feature. Set with *ChannelConfig::set_thread_priority* and
+//! *ChannelConfig::set_core_affinity*, applied by each worker thread to itself as soon as it's spawned.
+//!
+//! The crate has no dependencies, so there's no libc. The few functions needed are declared here and called directly. Only Linux is
+//! supported for now, anywhere else every setting fails with *io::ErrorKind::Unsupported*.
+//!
+//! A setting that can't be applied doesn't stop the worker, it runs with the defaults of the system instead. The error is kept for
+//! *DeliveryService::take_os_errors*. Raising the priority usually needs privileges: *ThreadPriority::High* needs CAP_SYS_NICE and
+//! *ThreadPriority::Realtime* a realtime limit (see *ulimit -r*), so check the errors before relying on them.
+//!
+//!
+
+use std::collections::BTreeSet;
+use std::io;
+use std::sync::{Arc, Mutex, PoisonError};
+
+/// Errors of the settings that couldn't be applied, with the id of the worker. Shared by the workers of a channel.
+pub type OsErrors = Arc<Mutex<Vec<(usize, io::Error)>>>;
+
+/// How the system schedules the worker threads against the other threads of the machine. See *ChannelConfig::set_thread_priority*.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ThreadPriority{
+    /// Nice 10. Background work that should give way to everything else.
+    Low,
+    /// Nice 0, the default of every thread.
+    Normal,
+    /// Nice -10. Needs privileges.
+    High,
+    /// First in, first out realtime scheduling at the given level, between 1 and 99. Ahead of every normal thread, for audio and the like.
+    /// A realtime worker that never waits can freeze the machine, keep their number below the core count.
+    Realtime(u8),
+}
+
+/// Cores the workers may run on. See *ChannelConfig::set_core_affinity*.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CoreSet{
+    cores: BTreeSet<usize>,
+    // Each worker gets a single core of the set instead of all of them.
+    pinned: bool,
+}
+
+impl CoreSet{
+    /// An empty set. A worker given no core at all is left where the system puts it.
+    pub fn new() -> Self{
+        Self::default()
+    }
+
+    /// Add a core, counting from 0.
+    pub fn with(mut self, core: usize) -> Self{
+        self.cores.insert(core);
+        self
+    }
+
+    /// Pin each worker to a single core of the set, in turn by worker id, instead of letting every worker run on any of them.
+    /// With more workers than cores, some share a core.
+    pub fn pinned(mut self) -> Self{
+        self.pinned = true;
+        self
+    }
+
+    /// True if each worker gets a single core. See *pinned*.
+    pub fn is_pinned(&self) -> bool{
+        self.pinned
+    }
+
+    /// True if the core is in the set.
+    pub fn contains(&self, core: usize) -> bool{
+        self.cores.contains(&core)
+    }
+
+    /// How many cores are in the set.
+    pub fn len(&self) -> usize{
+        self.cores.len()
+    }
+
+    /// True if the set has no core.
+    pub fn is_empty(&self) -> bool{
+        self.cores.is_empty()
+    }
+
+    /// The cores in the set, from lowest to highest.
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_{
+        self.cores.iter().copied()
+    }
+
+    /// The cores the worker runs on. Ids start at 1, so worker 1 gets the first core when pinned.
+    fn cores_of(&self, worker_id: usize) -> Vec<usize>{
+        if !self.pinned || self.cores.is_empty(){
+            return self.iter().collect();
+        }
+        let index = worker_id.saturating_sub(1) % self.cores.len();
+        self.iter().skip(index).take(1).collect()
+    }
+}
+
+impl std::iter::FromIterator<usize> for CoreSet{
+    fn from_iter<I>(cores: I) -> Self where I: IntoIterator<Item = usize>{
+        CoreSet{
+            cores: cores.into_iter().collect(),
+            pinned: false,
+        }
+    }
+}
+
+/// Apply the settings to the calling thread, the worker with the given id. Errors are pushed into errors instead of returned.
+pub fn apply_settings(worker_id: usize, priority: Option<ThreadPriority>, affinity: Option<&CoreSet>, errors: &OsErrors){
+    let mut failed = Vec::new();
+    if let Some(priority) = priority{
+        if let Err(error) = set_current_priority(priority){
+            failed.push((worker_id, error));
+        }
+    }
+    if let Some(affinity) = affinity{
+        let cores = affinity.cores_of(worker_id);
+        if !cores.is_empty(){
+            if let Err(error) = set_current_affinity(&cores){
+                failed.push((worker_id, error));
+            }
+        }
+    }
+    if !failed.is_empty(){
+        errors.lock().unwrap_or_else(PoisonError::into_inner).append(&mut failed);
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod sys{
+    use std::os::raw::{c_int, c_uint, c_ulong};
+
+    pub const PRIO_PROCESS: c_int = 0;
+    pub const SCHED_OTHER: c_int = 0;
+    pub const SCHED_FIFO: c_int = 1;
+    // Size of cpu_set_t in glibc and musl, 1024 cores.
+    pub const CPU_SET_BYTES: usize = 128;
+
+    #[repr(C)]
+    pub struct SchedParam{
+        pub sched_priority: c_int,
+    }
+
+    extern "C"{
+        pub fn gettid() -> c_int;
+        pub fn setpriority(which: c_int, who: c_uint, prio: c_int) -> c_int;
+        pub fn sched_setaffinity(pid: c_int, cpusetsize: usize, mask: *const u8) -> c_int;
+        pub fn pthread_self() -> c_ulong;
+        pub fn pthread_setschedparam(thread: c_ulong, policy: c_int, param: *const SchedParam) -> c_int;
+    }
+}
+
+/// Set the priority of the calling thread.
+#[cfg(target_os = "linux")]
+fn set_current_priority(priority: ThreadPriority) -> io::Result<()>{
+    let (policy, level, nice) = match priority{
+        ThreadPriority::Low => (sys::SCHED_OTHER, 0, 10),
+        ThreadPriority::Normal => (sys::SCHED_OTHER, 0, 0),
+        ThreadPriority::High => (sys::SCHED_OTHER, 0, -10),
+        ThreadPriority::Realtime(level) if (1..=99).contains(&level) => (sys::SCHED_FIFO, level, 0),
+        ThreadPriority::Realtime(_) => return Err(io::Error::new(io::ErrorKind::InvalidInput, "realtime priority must be between 1 and 99")),
+    };
+    let param = sys::SchedParam{ sched_priority: level.into() };
+    // Both only read their arguments and act on the calling thread. pthread_setschedparam returns the error instead of setting errno.
+    let result = unsafe { sys::pthread_setschedparam(sys::pthread_self(), policy, &param) };
+    if result != 0{
+        return Err(io::Error::from_raw_os_error(result));
+    }
+    if policy == sys::SCHED_OTHER{
+        // On Linux, the nice value of a thread id belongs to that thread alone.
+        let result = unsafe { sys::setpriority(sys::PRIO_PROCESS, sys::gettid() as u32, nice) };
+        if result != 0{
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+/// Keep the calling thread on the given cores.
+#[cfg(target_os = "linux")]
+fn set_current_affinity(cores: &[usize]) -> io::Result<()>{
+    let mut mask = [0u8; sys::CPU_SET_BYTES];
+    for &core in cores{
+        if core >= sys::CPU_SET_BYTES * 8{
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("core {} is out of range", core)));
+        }
+        mask[core / 8] |= 1 << (core % 8);
+    }
+    // The mask outlives the call, which only reads it. Pid 0 is the calling thread.
+    let result = unsafe { sys::sched_setaffinity(0, mask.len(), mask.as_ptr()) };
+    if result != 0{
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn set_current_priority(_priority: ThreadPriority) -> io::Result<()>{
+    Err(io::Error::new(io::ErrorKind::Unsupported, "thread priorities are only supported on Linux"))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn set_current_affinity(_cores: &[usize]) -> io::Result<()>{
+    Err(io::Error::new(io::ErrorKind::Unsupported, "core affinity is only supported on Linux"))
+}
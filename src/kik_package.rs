@@ -0,0 +1,105 @@
+//! # Package
+//!
+//! Envelope used by kik_feeder and kik_worker for carrying each *Message* through the channels.
+//! Besides the *Message* itself, it holds the bookkeeping that the feeder needs for reports. Not meant to be used directly.
+//!
+//!
+
+use std::time::{Duration, Instant};
+
+use crate::kik_error::WorkError;
+use crate::kik_sender::BatchId;
+
+/// Merges the data of the second message into the first. The workers don't know that S implements *Merge*, so the feeder hands them this.
+pub type MergeFn<S> = fn(&mut S, S);
+
+/// Carries a *Message* **S** between *FeederRecycler* and the *Worker*s. **E** is the error that the message might return when worked.
+pub struct Package<S, E>{
+    /// The message being worked.
+    pub message: S,
+    /// Order in which the feeder dispatched the message. Used for giving back results in the same order as the inputs.
+    pub sequence: usize,
+    /// Identifies the message across recycling. Each message built by the feeder gets a new slot.
+    pub slot: usize,
+    /// *MessageInput::weight* of the input being worked. Counted by the feeder while the package is away.
+    pub weight: usize,
+    /// Batch of the input being worked. None for inputs fed with a receipt or pulled from a generator.
+    pub batch: Option<BatchId>,
+    /// Copies of the message holding more inputs of the same batch. The worker works each of them and merges its data into *message*.
+    /// Only used by *DeliveryService::map_reduce*, see *Merge*.
+    pub merged: Vec<S>,
+    /// Merges each message of *merged* into *message*, set by the feeder along with them. See *kik_message::merge_message*.
+    pub merge: Option<MergeFn<S>>,
+    /// How many inputs were merged into this package besides its own. Their weight is included in *weight*.
+    pub merged_inputs: usize,
+    /// *MessageInput::routing_key* of the input being worked. Used by kik_queue when dispatching by key.
+    pub key: Option<u64>,
+    /// Id of the last worker that worked this message. Zero while it hasn't been worked yet.
+    pub worker_id: usize,
+    /// How many times a worker started working this package. Zero while it hasn't been worked yet. See *WorkInfo::attempt*.
+    pub attempt: usize,
+    /// How long the last worker spent inside *Message::work*.
+    pub work_time: Duration,
+    /// Set by the worker when the message failed to be worked.
+    pub error: Option<WorkError<E>>,
+    /// Set by the feeder when the input was set aside by the quarantine. The package then gives no result.
+    pub quarantined: bool,
+    /// Set by the worker when it skipped the message because the run was cancelled.
+    pub cancelled: bool,
+    /// When the last worker finished working this message.
+    pub completed_at: Instant,
+    /// When the feeder sent this message to the workers.
+    pub dispatched_at: Instant,
+    /// Deadline of the input being worked, if it was fed with one. See *WorkInfo::deadline*.
+    pub deadline: Option<Instant>,
+}
+
+impl<S, E> Package<S, E>{
+    /// Wrap a message that is about to be sent to the workers.
+    pub fn new(message: S, sequence: usize, slot: usize, weight: usize, batch: Option<BatchId>) -> Self{
+        let now = Instant::now();
+        Package{
+            message,
+            sequence,
+            slot,
+            weight,
+            batch,
+            merged: Vec::new(),
+            merge: None,
+            merged_inputs: 0,
+            key: None,
+            worker_id: 0,
+            attempt: 0,
+            work_time: Duration::from_secs(0),
+            error: None,
+            quarantined: false,
+            cancelled: false,
+            completed_at: now,
+            dispatched_at: now,
+            deadline: None,
+        }
+    }
+}
+
+
+/// What the feeder takes out of a *Package* retrieved from the workers.
+pub struct Retrieved<T, E>{
+    /// Sequence number of the package that generated this result.
+    pub sequence: usize,
+    /// Slot of the message that generated this result.
+    pub slot: usize,
+    /// Batch of the input that generated this result.
+    pub batch: Option<BatchId>,
+    /// When the worker finished working the package.
+    pub completed_at: Instant,
+    /// The data generated, or the reason it couldn't be generated.
+    pub result: Result<Output<T>, WorkError<E>>,
+}
+
+/// The data taken out of a message that was worked.
+pub enum Output<T>{
+    /// What *Message::clone_message_data* or the methods like it returned.
+    Data(T),
+    /// Every output of a message that fanned out, see *Message::fan_out*. Empty for a message whose input was set aside.
+    FannedOut(Vec<T>),
+}
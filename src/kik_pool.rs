@@ -0,0 +1,87 @@
+//! # Pool
+//!
+//! *MessagePool* keeps messages built ahead of time, for channels that shouldn't allocate while they run, like real-time audio.
+//! Enabled with *ChannelConfig::set_message_pool*.
+//!
+//! The channel fills its pool with package_number messages when it's built, and again when the message template changes. The feeder loans one out
+//! for each new message it would have built, and takes it back once there's no input left for it, instead of dropping it. Its data is then cloned
+//! out like a recycled message's, or swapped for a buffer given back with *DeliveryService::return_buffer*.
+//! A message is only built on demand when the pool ran dry, after *DeliveryService::add_workers* for instance. *DeliveryService::get_pool_misses* counts those.
+//!
+//!
+
+/// Messages built ahead of time, loaned out and given back. See the module documentation.
+pub struct MessagePool<S>{
+    idle: Vec<S>,
+    // Messages given back beyond this are dropped.
+    capacity: usize,
+    // Loans asked for while the pool was empty.
+    misses: usize,
+}
+
+impl<S> MessagePool<S>{
+    /// An empty pool that keeps up to capacity messages.
+    pub fn new(capacity: usize) -> Self{
+        MessagePool{
+            idle: Vec::with_capacity(capacity),
+            capacity,
+            misses: 0,
+        }
+    }
+
+    /// Build messages until count of them are waiting, never more than the capacity.
+    pub fn fill<F>(&mut self, count: usize, mut build: F) where F: FnMut() -> S{
+        let count = count.min(self.capacity);
+        self.idle.reserve(count.saturating_sub(self.idle.len()));
+        while self.idle.len() < count{
+            self.idle.push(build());
+        }
+    }
+
+    /// Take a message out of the pool. None if it's empty, which is counted as a miss, the caller builds one then.
+    pub fn loan(&mut self) -> Option<S>{
+        let message = self.idle.pop();
+        if message.is_none(){
+            self.misses += 1;
+        }
+        message
+    }
+
+    /// Put a message back in the pool. It's dropped if the pool is already full.
+    pub fn give_back(&mut self, message: S){
+        if self.idle.len() < self.capacity{
+            self.idle.push(message);
+        }
+    }
+
+    /// Change how many messages the pool keeps. Extra messages waiting are dropped.
+    pub fn set_capacity(&mut self, capacity: usize){
+        self.capacity = capacity;
+        self.idle.truncate(capacity);
+    }
+
+    /// Drop every message waiting. The capacity and misses are kept.
+    pub fn clear(&mut self){
+        self.idle.clear();
+    }
+
+    /// How many messages are waiting to be loaned.
+    pub fn len(&self) -> usize{
+        self.idle.len()
+    }
+
+    /// True if no message is waiting.
+    pub fn is_empty(&self) -> bool{
+        self.idle.is_empty()
+    }
+
+    /// How many messages the pool keeps at most.
+    pub fn get_capacity(&self) -> usize{
+        self.capacity
+    }
+
+    /// How many loans found the pool empty.
+    pub fn get_misses(&self) -> usize{
+        self.misses
+    }
+}
@@ -0,0 +1,224 @@
+//! # Process
+//!
+//! Workers that each drive a child process, behind the "process" feature. Selected with *ChannelConfig::set_backend*.
+//!
+//! With *Backend::Process*, every worker thread spawns a child process and sends it each message through its standard input, the same way
+//! a remote worker sends them to a *RemoteAgent* (see the remote module). The child works the message and sends it back through its standard output.
+//! A segfault, an abort or running out of memory inside *Message::work* only kills that child: its message gives back *WorkError::Lost*,
+//! and the worker spawns a new child for the next message. The host keeps running.
+//!
+//! By default the child is the running executable itself, started with the environment variable *PROCESS_WORKER_VAR* set. Its main
+//! must call *RemoteAgent::serve_if_child* before doing anything else, which serves the parent and exits when it's a child, and returns right away otherwise:
+//!
+//! ```ignore
+//! fn main(){
+//!     RemoteAgent::<Tile, Area, TileMessage, String>::new().serve_if_child();
+//!     let mut config = ChannelConfig::default();
+//!     config.set_backend(Backend::Process(ProcessBackend::new::<Tile, Area, TileMessage, String>()));
+//!     // ...
+//! }
+//! ```
+//!
+//! The standard output of the child belongs to the channel, *Message::work* shouldn't print to it. Anything printed before *serve_if_child* is skipped.
+//! The standard error is the parent's.
+//!
+//!
+
+use std::any::Any;
+use std::ffi::OsString;
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+use std::process::{self, Child, Command, Stdio};
+use std::sync::Arc;
+
+use crate::kik_message::{Message, MessageInput, MessageData};
+use crate::kik_package::Package;
+use crate::kik_error::WorkError;
+use crate::kik_remote::{RemoteAgent, RemoteCall, RemoteLink};
+
+use serde::{Serialize, de::DeserializeOwned};
+
+/// Environment variable set in the child processes spawned by *Backend::Process*. *RemoteAgent::serve_if_child* looks for it.
+pub const PROCESS_WORKER_VAR: &str = "KIK_SYNC_SERVICE_PROCESS_WORKER";
+
+/// Builds the call of a worker from the command of its child. Made by *ProcessBackend::new* for the types it was given.
+pub type ProcessConnect<S, E> = fn(ProcessCommand) -> RemoteCall<S, E>;
+
+/// Where the workers of a channel run. See *ChannelConfig::set_backend*.
+#[derive(Clone, Default)]
+pub enum Backend{
+    /// Each worker works its messages in its own thread. The default.
+    #[default]
+    Threads,
+    /// Each worker sends its messages to a child process of its own. See the process module.
+    Process(ProcessBackend),
+}
+
+impl fmt::Debug for Backend{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result{
+        match self{
+            Backend::Threads => write!(f, "Threads"),
+            Backend::Process(backend) => write!(f, "Process({:?})", backend.command),
+        }
+    }
+}
+
+/// The program each worker spawns, and the message types it works. See the process module.
+#[derive(Clone)]
+pub struct ProcessBackend{
+    command: ProcessCommand,
+    // A ProcessConnect for the types given to new.
+    connect: Arc<dyn Any + Send + Sync>,
+}
+
+impl ProcessBackend{
+    /// Children that run the current executable, for a channel of the same types. The executable must call *RemoteAgent::serve_if_child* with them.
+    pub fn new<T, R, S, E>() -> Self where
+    T: MessageData + 'static,
+    R: MessageInput<T> + 'static,
+    S: Message<T, R, E> + Serialize + DeserializeOwned + 'static,
+    E: Serialize + DeserializeOwned + Send + 'static,
+    {
+        let connect: ProcessConnect<S, E> = process_call::<T, R, S, E>;
+        ProcessBackend{
+            command: ProcessCommand::default(),
+            connect: Arc::new(connect),
+        }
+    }
+
+    /// Run program instead of the current executable.
+    pub fn with_program<P>(mut self, program: P) -> Self where P: Into<PathBuf>{
+        self.command.program = Some(program.into());
+        self
+    }
+
+    /// Add an argument to the command of the children.
+    pub fn with_arg<A>(mut self, arg: A) -> Self where A: Into<OsString>{
+        self.command.args.push(arg.into());
+        self
+    }
+
+    /// The call builder, if the backend was made for messages **S** and errors **E**. Used by kik_channel.
+    pub fn get_connect<S, E>(&self) -> Option<ProcessConnect<S, E>> where S: 'static, E: 'static{
+        self.connect.downcast_ref::<ProcessConnect<S, E>>().copied()
+    }
+
+    /// The command the children are spawned with.
+    pub fn get_command(&self) -> &ProcessCommand{
+        &self.command
+    }
+}
+
+/// How the children of a *ProcessBackend* are spawned.
+#[derive(Clone, Debug, Default)]
+pub struct ProcessCommand{
+    // None for the current executable.
+    program: Option<PathBuf>,
+    args: Vec<OsString>,
+}
+
+impl ProcessCommand{
+    /// Spawn a child with its standard input and output piped.
+    fn spawn(&self) -> io::Result<Child>{
+        let program = match &self.program{
+            Some(program) => program.clone(),
+            None => std::env::current_exe()?,
+        };
+        Command::new(program)
+            .args(&self.args)
+            .env(PROCESS_WORKER_VAR, "1")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .spawn()
+    }
+}
+
+// A running child and the link to it. Closing its input tells it to exit.
+struct ChildWorker<S, E>{
+    link: Option<RemoteLink<S, E>>,
+    child: Child,
+}
+
+impl<S, E> ChildWorker<S, E> where
+S: Serialize + DeserializeOwned + 'static,
+E: Serialize + DeserializeOwned + Send + 'static,
+{
+    fn spawn(command: &ProcessCommand) -> io::Result<Self>{
+        let mut child = command.spawn()?;
+        let pipes = (child.stdout.take(), child.stdin.take());
+        let link = match pipes{
+            (Some(stdout), Some(stdin)) => RemoteLink::from_pipes(Box::new(stdout), Box::new(stdin)),
+            _ => Err(io::Error::new(io::ErrorKind::BrokenPipe, "the child has no pipes")),
+        };
+        match link{
+            Ok(link) => Ok(ChildWorker{ link: Some(link), child }),
+            Err(error) => {
+                let _ = child.kill();
+                let _ = child.wait();
+                Err(error)
+            },
+        }
+    }
+}
+
+impl<S, E> Drop for ChildWorker<S, E>{
+    fn drop(&mut self){
+        // The child exits once its input is closed, after the message it's working.
+        self.link = None;
+        let _ = self.child.wait();
+    }
+}
+
+// The call of a worker. The child is spawned on the first message, and again after it dies.
+fn process_call<T, R, S, E>(command: ProcessCommand) -> RemoteCall<S, E> where
+T: MessageData + 'static,
+R: MessageInput<T> + 'static,
+S: Message<T, R, E> + Serialize + DeserializeOwned + 'static,
+E: Serialize + DeserializeOwned + Send + 'static,
+{
+    let mut child: Option<ChildWorker<S, E>> = None;
+    Box::new(move |package: &mut Package<S, E>| {
+        if child.is_none(){
+            // Couldn't spawn. Give up on this worker.
+            child = Some(ChildWorker::spawn(&command)?);
+        }
+        let lost = match child.as_mut().and_then(|child| child.link.as_mut()){
+            Some(link) => link.work_package::<T, R>(package).is_err(),
+            None => true,
+        };
+        if lost{
+            // The child died with the message. The next one gets a new child.
+            let mut dead = child.take();
+            if let Some(dead) = dead.as_mut(){
+                let _ = dead.child.kill();
+            }
+            package.error = Some(WorkError::Lost{ worker_id: package.worker_id });
+        }
+        Ok(())
+    })
+}
+
+impl<T, R, S, E> RemoteAgent<T, R, S, E> where
+T: MessageData + 'static,
+R: MessageInput<T> + 'static,
+S: Message<T, R, E> + Serialize + DeserializeOwned + 'static,
+E: Serialize + DeserializeOwned + Send + 'static,
+{
+    /// If this process was spawned by *Backend::Process*, work the messages of the parent through the standard input and output,
+    /// then exit once the parent is done with it. Otherwise return right away. Call it at the start of main, see the process module.
+    pub fn serve_if_child(&self){
+        if std::env::var_os(PROCESS_WORKER_VAR).is_none(){
+            return;
+        }
+        let code = match self.serve_pipes(io::stdin().lock(), io::stdout().lock()){
+            Ok(_) => 0,
+            Err(error) => {
+                eprintln!("kik_sync_service process worker {}: {}", process::id(), error);
+                1
+            },
+        };
+        process::exit(code);
+    }
+}
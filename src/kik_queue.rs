@@ -0,0 +1,612 @@
+//! # Queue
+//!
+//! The *inserter* queue, which carries packages from kik_feeder to the workers. Used by kik_channel, not meant to be used directly.
+//!
+//! By default every worker takes packages from one shared queue. Its backend is an *mpsc::sync_channel* whose *Receiver* is shared by the
+//! workers behind a *Mutex*. Only one worker can wait on it at a time, every other idle worker waits for the lock.
+//!
+//! With the **mpmc** feature, the shared queue is crossbeam's bounded lock-free *ArrayQueue* instead. Workers take packages without locking anything,
+//! and only touch a lock when the queue is empty and they're about to sleep.
+//!
+//! With *ChannelConfig::set_work_stealing*, there's no shared queue. The queue is split in lanes, one for each worker the channel started with.
+//! The feeder spreads the packages between the lanes, each worker takes from the front of its own, and a worker whose lane is empty steals from the back of the others.
+//! Workers mostly lock their own lane, so they don't fight over a single lock.
+//!
+//! With *ChannelConfig::set_keyed_dispatch*, the queue is split in lanes the same way, but nothing is stolen. Packages sent with a key always go
+//! to the same lane, and each lane is claimed by a single worker, so packages with the same key are worked one after the other, in the order they were sent.
+//! When a worker stops, its lanes are released and the first worker that looks for work adopts them. Workers added later only take lanes released like that.
+//!
+//! Either way, the feeder holds the only *WorkSender*. Dropping it disconnects the queue, and the workers close once it's empty.
+//! *DeliveryService* holds the only strong *WorkReceiver*, workers hold weak ones, so they also close once the channel is dropped.
+//!
+//!
+
+use std::time::{Duration, Instant};
+use std::collections::VecDeque;
+use std::sync::{Arc, Weak, Mutex, Condvar, PoisonError};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering, fence};
+use std::sync::mpsc::{TrySendError, TryRecvError, RecvTimeoutError};
+
+#[cfg(not(feature = "mpmc"))]
+use std::sync::mpsc::{Receiver, SyncSender, sync_channel};
+#[cfg(not(feature = "mpmc"))]
+use std::sync::TryLockError;
+
+#[cfg(feature = "mpmc")]
+use crossbeam_queue::ArrayQueue;
+
+/// Create a queue shared by every worker, that holds up to *capacity* packages. A capacity of zero is treated as one.
+pub fn work_queue<P>(capacity: usize) -> (WorkSender<P>, WorkReceiver<P>) where P: Send{
+    let (sender, receiver) = shared_queue(capacity.max(1));
+    (WorkSender{ backend: SenderBackend::Shared(sender) }, WorkReceiver{ backend: ReceiverBackend::Shared(receiver) })
+}
+
+/// Create a queue split in *lanes* lanes, that holds at least *capacity* packages. Each weak receiver made from it prefers the next lane. Zeros are treated as ones.
+pub fn stealing_queue<P>(capacity: usize, lanes: usize) -> (WorkSender<P>, WorkReceiver<P>) where P: Send{
+    lane_queue(capacity, lanes, false)
+}
+
+/// Create a queue split in *lanes* lanes for keyed dispatch. Each weak receiver made from it claims a free lane, if there's one left.
+/// 
+/// The lanes have no limit. Every package in flight could have the same key, and a full lane would leave the feeder waiting on a worker that's
+/// waiting for the feeder to take its result. The feeder's package window already limits how many packages are in the queue.
+pub fn keyed_queue<P>(lanes: usize) -> (WorkSender<P>, WorkReceiver<P>) where P: Send{
+    lane_queue(usize::MAX, lanes, true)
+}
+
+fn lane_queue<P>(capacity: usize, lanes: usize, keyed: bool) -> (WorkSender<P>, WorkReceiver<P>) where P: Send{
+    let lanes = lanes.max(1);
+    let queue = Arc::new(StealingQueue{
+        lanes: (0..lanes).map(|_| Mutex::new(VecDeque::new())).collect(),
+        // Rounded up, so the lanes together never hold less than asked.
+        lane_capacity: capacity.max(1).div_ceil(lanes),
+        owners: if keyed { Some((0..lanes).map(|_| AtomicUsize::new(0)).collect()) } else { None },
+        next_push: AtomicUsize::new(0),
+        next_lane: AtomicUsize::new(0),
+        signals: Signals::new(),
+    });
+    let handle = Arc::new(ReceiverHandle{ queue: queue.clone() });
+    (WorkSender{ backend: SenderBackend::Stealing(SenderHandle{ queue }) }, WorkReceiver{ backend: ReceiverBackend::Stealing{ handle, lane: 0, claim: 0 } })
+}
+
+/// Sending side of the queue. Held by kik_feeder.
+pub struct WorkSender<P>{
+    backend: SenderBackend<P>,
+}
+
+enum SenderBackend<P>{
+    Shared(SharedSender<P>),
+    Stealing(SenderHandle<StealingQueue<P>>),
+}
+
+/// Receiving side of the queue. Held by kik_channel, shared by the workers.
+pub struct WorkReceiver<P>{
+    backend: ReceiverBackend<P>,
+}
+
+enum ReceiverBackend<P>{
+    Shared(SharedReceiver<P>),
+    Stealing{
+        handle: Arc<ReceiverHandle<StealingQueue<P>>>,
+        // The lane taken from first.
+        lane: usize,
+        // With keyed dispatch, marks the lanes claimed by this receiver. Zero for the channel's own receiver.
+        claim: usize,
+    },
+}
+
+/// A *WorkReceiver* that doesn't keep the queue open. Held by each worker.
+pub struct WeakWorkReceiver<P>{
+    backend: WeakBackend<P>,
+}
+
+enum WeakBackend<P>{
+    Shared(WeakSharedReceiver<P>),
+    Stealing{
+        handle: Weak<ReceiverHandle<StealingQueue<P>>>,
+        lane: usize,
+        claim: usize,
+    },
+}
+
+impl<P> WorkSender<P>{
+    /// Send the package without blocking. Gives it back if the queue is full or the receiver was dropped.
+    /// With keyed dispatch, packages with the same key go to the same lane. The key is ignored otherwise.
+    pub fn try_send(&self, package: P, key: Option<u64>) -> Result<(), TrySendError<P>>{
+        match &self.backend{
+            SenderBackend::Shared(sender) => shared_send(sender, package),
+            SenderBackend::Stealing(sender) => {
+                if sender.queue.signals.abandoned.load(Ordering::SeqCst){
+                    return Err(TrySendError::Disconnected(package));
+                }
+                sender.queue.push(package, key).map_err(TrySendError::Full)?;
+                sender.queue.wake_receivers();
+                Ok(())
+            },
+        }
+    }
+
+    /// Send the package, sleeping until there's room for it. Gives it back if the receiver was dropped. The key is used as in *try_send*.
+    pub fn send(&self, package: P, key: Option<u64>) -> Result<(), P>{
+        match &self.backend{
+            SenderBackend::Shared(sender) => shared_send_blocking(sender, package),
+            SenderBackend::Stealing(sender) => {
+                sender.queue.signals.send(package, |package| sender.queue.push(package, key))?;
+                sender.queue.wake_receivers();
+                Ok(())
+            },
+        }
+    }
+}
+
+impl<P> WorkReceiver<P>{
+    /// Sleep until a package arrives. None once the sender is dropped and the queue is empty.
+    pub fn recv(&self) -> Option<P>{
+        match &self.backend{
+            ReceiverBackend::Shared(receiver) => shared_recv(receiver),
+            ReceiverBackend::Stealing{ handle, lane, claim } => handle.queue.signals.receive(|| handle.queue.pop(*lane, *claim), None).ok(),
+        }
+    }
+
+    /// Take a package if one is waiting, without sleeping. Empty if another worker is busy taking one.
+    pub fn try_recv(&self) -> Result<P, TryRecvError>{
+        match &self.backend{
+            ReceiverBackend::Shared(receiver) => shared_try_recv(receiver),
+            ReceiverBackend::Stealing{ handle, lane, claim } => handle.queue.signals.try_receive(|| handle.queue.pop(*lane, *claim)),
+        }
+    }
+
+    /// Same as *recv*, but gives up after the timeout.
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<P, RecvTimeoutError>{
+        match &self.backend{
+            ReceiverBackend::Shared(receiver) => shared_recv_timeout(receiver, timeout),
+            ReceiverBackend::Stealing{ handle, lane, claim } => handle.queue.signals.receive(|| handle.queue.pop(*lane, *claim), Some(Instant::now() + timeout)),
+        }
+    }
+
+    /// A weak reference to this receiver, for a worker. When stealing, each one made prefers the next lane. With keyed dispatch, each one claims a free lane.
+    pub fn downgrade(&self) -> WeakWorkReceiver<P>{
+        let backend = match &self.backend{
+            ReceiverBackend::Shared(receiver) => WeakBackend::Shared(shared_downgrade(receiver)),
+            ReceiverBackend::Stealing{ handle, .. } => {
+                let next = handle.queue.next_lane.fetch_add(1, Ordering::Relaxed);
+                let claim = if handle.queue.owners.is_some() { handle.queue.claim_free_lane(next + 1) } else { 0 };
+                WeakBackend::Stealing{
+                    handle: Arc::downgrade(handle),
+                    lane: next % handle.queue.lanes.len(),
+                    claim,
+                }
+            },
+        };
+        WeakWorkReceiver{ backend }
+    }
+}
+
+impl<P> WeakWorkReceiver<P>{
+    /// The receiver, or None if the channel that held it was dropped.
+    pub fn upgrade(&self) -> Option<WorkReceiver<P>>{
+        let backend = match &self.backend{
+            WeakBackend::Shared(receiver) => ReceiverBackend::Shared(receiver.upgrade()?),
+            WeakBackend::Stealing{ handle, lane, claim } => ReceiverBackend::Stealing{ handle: handle.upgrade()?, lane: *lane, claim: *claim },
+        };
+        Some(WorkReceiver{ backend })
+    }
+}
+
+impl<P> Drop for WeakWorkReceiver<P>{
+    fn drop(&mut self){
+        // The worker is stopping. Its lanes go to whoever looks for work next.
+        if let WeakBackend::Stealing{ handle, claim, .. } = &self.backend{
+            if let Some(handle) = handle.upgrade(){
+                handle.queue.release(*claim);
+            }
+        }
+    }
+}
+
+
+// Sleeping and waking, for the queues that aren't an mpsc channel.
+
+// Lets workers sleep while their queue is empty, and the sender while it's full. Each side only takes the lock to wake the other when it's sleeping.
+struct Signals{
+    // How many workers are sleeping, or about to.
+    sleeping: AtomicUsize,
+    // Whether the sender is sleeping, or about to.
+    sender_sleeping: AtomicBool,
+    // Set when the sender is dropped.
+    disconnected: AtomicBool,
+    // Set when the channel's receiver is dropped.
+    abandoned: AtomicBool,
+    lock: Mutex<()>,
+    wakeup: Condvar,
+    // Woken when a package is taken.
+    room: Condvar,
+}
+
+impl Signals{
+    fn new() -> Self{
+        Signals{
+            sleeping: AtomicUsize::new(0),
+            sender_sleeping: AtomicBool::new(false),
+            disconnected: AtomicBool::new(false),
+            abandoned: AtomicBool::new(false),
+            lock: Mutex::new(()),
+            wakeup: Condvar::new(),
+            room: Condvar::new(),
+        }
+    }
+
+    /// Wake the sender, if it's sleeping. Called after a package is taken.
+    fn wake_sender(&self){
+        // Pairs with the fence in send, the same way as wake_one.
+        fence(Ordering::SeqCst);
+        if self.sender_sleeping.load(Ordering::SeqCst){
+            let _guard = self.lock.lock().unwrap_or_else(PoisonError::into_inner);
+            self.room.notify_one();
+        }
+    }
+
+    /// Mark the queue as abandoned and wake the sender, so that it notices.
+    fn abandon(&self){
+        self.abandoned.store(true, Ordering::SeqCst);
+        let _guard = self.lock.lock().unwrap_or_else(PoisonError::into_inner);
+        self.room.notify_one();
+    }
+
+    /// Put the package in with push, sleeping until there's room. Gives it back if the receiver was dropped.
+    fn send<P, F>(&self, mut package: P, mut push: F) -> Result<(), P> where F: FnMut(P) -> Result<(), P>{
+        loop{
+            if self.abandoned.load(Ordering::SeqCst){
+                return Err(package);
+            }
+            package = match push(package){
+                Ok(_) => return Ok(()),
+                Err(returned) => returned,
+            };
+            let guard = self.lock.lock().unwrap_or_else(PoisonError::into_inner);
+            self.sender_sleeping.store(true, Ordering::SeqCst);
+            fence(Ordering::SeqCst);
+            // Check again now that the workers know the sender is sleeping.
+            package = match push(package){
+                Ok(_) => {
+                    self.sender_sleeping.store(false, Ordering::SeqCst);
+                    return Ok(());
+                },
+                Err(returned) => returned,
+            };
+            if !self.abandoned.load(Ordering::SeqCst){
+                drop(self.room.wait(guard).unwrap_or_else(PoisonError::into_inner));
+            }
+            self.sender_sleeping.store(false, Ordering::SeqCst);
+        }
+    }
+
+    /// Wake one sleeping worker, if there's any. Called after a package is pushed.
+    fn wake_one(&self){
+        // Pairs with the fence in receive, so either the worker sees the package or this sees the worker.
+        fence(Ordering::SeqCst);
+        if self.sleeping.load(Ordering::SeqCst) > 0{
+            let _guard = self.lock.lock().unwrap_or_else(PoisonError::into_inner);
+            self.wakeup.notify_one();
+        }
+    }
+
+    /// Same as *wake_one*, for every sleeping worker. Used when only some of them can take the package.
+    fn wake_all(&self){
+        fence(Ordering::SeqCst);
+        if self.sleeping.load(Ordering::SeqCst) > 0{
+            let _guard = self.lock.lock().unwrap_or_else(PoisonError::into_inner);
+            self.wakeup.notify_all();
+        }
+    }
+
+    /// Mark the queue as disconnected and wake every sleeping worker, so that they all notice.
+    fn disconnect(&self){
+        self.disconnected.store(true, Ordering::SeqCst);
+        let _guard = self.lock.lock().unwrap_or_else(PoisonError::into_inner);
+        self.wakeup.notify_all();
+    }
+
+    /// Take a package with pop, if there's one. Disconnected only once the sender is gone and the queue is empty.
+    fn try_receive<P, F>(&self, mut pop: F) -> Result<P, TryRecvError> where F: FnMut() -> Option<P>{
+        // Read before popping, so a package pushed right before disconnecting isn't missed.
+        let disconnected = self.disconnected.load(Ordering::SeqCst);
+        match pop(){
+            Some(package) => {
+                self.wake_sender();
+                Ok(package)
+            },
+            None if disconnected => Err(TryRecvError::Disconnected),
+            None => Err(TryRecvError::Empty),
+        }
+    }
+
+    /// Take a package with pop, sleeping until one arrives or the deadline passes. Sleeps with no deadline if it's None.
+    fn receive<P, F>(&self, mut pop: F, deadline: Option<Instant>) -> Result<P, RecvTimeoutError> where F: FnMut() -> Option<P>{
+        loop{
+            if let Some(package) = pop(){
+                self.wake_sender();
+                return Ok(package);
+            }
+            let mut guard = self.lock.lock().unwrap_or_else(PoisonError::into_inner);
+            self.sleeping.fetch_add(1, Ordering::SeqCst);
+            fence(Ordering::SeqCst);
+            // Check again now that the sender knows someone is sleeping.
+            if let Some(package) = pop(){
+                self.sleeping.fetch_sub(1, Ordering::SeqCst);
+                drop(guard);
+                self.wake_sender();
+                return Ok(package);
+            }
+            if self.disconnected.load(Ordering::SeqCst){
+                self.sleeping.fetch_sub(1, Ordering::SeqCst);
+                return Err(RecvTimeoutError::Disconnected);
+            }
+            match deadline{
+                None => {
+                    guard = self.wakeup.wait(guard).unwrap_or_else(PoisonError::into_inner);
+                },
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline{
+                        self.sleeping.fetch_sub(1, Ordering::SeqCst);
+                        return Err(RecvTimeoutError::Timeout);
+                    }
+                    guard = self.wakeup.wait_timeout(guard, deadline - now).unwrap_or_else(PoisonError::into_inner).0;
+                },
+            }
+            self.sleeping.fetch_sub(1, Ordering::SeqCst);
+            drop(guard);
+        }
+    }
+}
+
+// A queue that signals its workers through Signals.
+trait Signaled{
+    fn signals(&self) -> &Signals;
+}
+
+// Disconnects the queue when the feeder drops it.
+struct SenderHandle<Q> where Q: Signaled{
+    queue: Arc<Q>,
+}
+
+impl<Q> Drop for SenderHandle<Q> where Q: Signaled{
+    fn drop(&mut self){
+        self.queue.signals().disconnect();
+    }
+}
+
+// Marks the queue as abandoned when the channel's receiver and every upgraded copy of it is gone.
+struct ReceiverHandle<Q> where Q: Signaled{
+    queue: Arc<Q>,
+}
+
+impl<Q> Drop for ReceiverHandle<Q> where Q: Signaled{
+    fn drop(&mut self){
+        self.queue.signals().abandon();
+    }
+}
+
+
+// Work stealing.
+
+struct StealingQueue<P>{
+    lanes: Box<[Mutex<VecDeque<P>>]>,
+    lane_capacity: usize,
+    // Only for keyed dispatch. The claim of the receiver taking from each lane, zero if it was never claimed, RELEASED if its receiver is gone.
+    owners: Option<Box<[AtomicUsize]>>,
+    // Lane the next package is pushed to first.
+    next_push: AtomicUsize,
+    // Lane given to the next weak receiver.
+    next_lane: AtomicUsize,
+    signals: Signals,
+}
+
+// Owner of a keyed lane whose worker stopped. Any worker looking for work may adopt it.
+const RELEASED: usize = usize::MAX;
+
+impl<P> Signaled for StealingQueue<P>{
+    fn signals(&self) -> &Signals{
+        &self.signals
+    }
+}
+
+impl<P> StealingQueue<P>{
+    /// Push the package to the next lane with room, going around the lanes. Gives it back if every lane is full.
+    /// With keyed dispatch, a package with a key only goes to the lane of its key.
+    fn push(&self, package: P, key: Option<u64>) -> Result<(), P>{
+        if let (Some(key), Some(_)) = (key, &self.owners){
+            let mut lane = self.lanes[(key % self.lanes.len() as u64) as usize].lock().unwrap_or_else(PoisonError::into_inner);
+            if lane.len() < self.lane_capacity{
+                lane.push_back(package);
+                return Ok(());
+            }
+            return Err(package);
+        }
+        let first = self.next_push.fetch_add(1, Ordering::Relaxed);
+        for offset in 0..self.lanes.len(){
+            let mut lane = self.lanes[(first + offset) % self.lanes.len()].lock().unwrap_or_else(PoisonError::into_inner);
+            if lane.len() < self.lane_capacity{
+                lane.push_back(package);
+                return Ok(());
+            }
+        }
+        Err(package)
+    }
+
+    /// Take the oldest package of the given lane. If it's empty, steal the newest package of another lane. None if every lane is empty.
+    /// With keyed dispatch, take the oldest package of the lanes with the given claim instead, adopting the free ones on the way.
+    fn pop(&self, own_lane: usize, claim: usize) -> Option<P>{
+        if let Some(owners) = &self.owners{
+            return owners.iter().enumerate().find_map(|(index, owner)| {
+                let owner = match owner.compare_exchange(RELEASED, claim, Ordering::SeqCst, Ordering::SeqCst){
+                    Ok(_) => claim,
+                    Err(owner) => owner,
+                };
+                if owner != claim{
+                    return None;
+                }
+                self.lanes[index].lock().unwrap_or_else(PoisonError::into_inner).pop_front()
+            });
+        }
+        if let Some(package) = self.lanes[own_lane].lock().unwrap_or_else(PoisonError::into_inner).pop_front(){
+            return Some(package);
+        }
+        (1..self.lanes.len()).find_map(|offset| {
+            self.lanes[(own_lane + offset) % self.lanes.len()].lock().unwrap_or_else(PoisonError::into_inner).pop_back()
+        })
+    }
+
+    /// Claim the first free lane for a new receiver. Returns the claim, which stays valid even if every lane was taken.
+    fn claim_free_lane(&self, claim: usize) -> usize{
+        if let Some(owners) = &self.owners{
+            let free = |owner: &&AtomicUsize, from: usize| owner.compare_exchange(from, claim, Ordering::SeqCst, Ordering::SeqCst).is_ok();
+            // Lanes never claimed go first, so that the workers the channel starts with get one each.
+            let _ = owners.iter().find(|owner| free(owner, 0)).or_else(|| owners.iter().find(|owner| free(owner, RELEASED)));
+        }
+        claim
+    }
+
+    /// Free the lanes of a receiver that's gone, and wake the workers so that one of them adopts them.
+    fn release(&self, claim: usize){
+        if let (Some(owners), true) = (&self.owners, claim != 0){
+            for owner in owners.iter(){
+                let _ = owner.compare_exchange(claim, RELEASED, Ordering::SeqCst, Ordering::SeqCst);
+            }
+            self.signals.wake_all();
+        }
+    }
+
+    /// Wake the workers after a push. With keyed dispatch only the owner of the lane can take the package, so every one is woken.
+    fn wake_receivers(&self){
+        if self.owners.is_some(){
+            self.signals.wake_all();
+        } else {
+            self.signals.wake_one();
+        }
+    }
+}
+
+
+// Default shared backend.
+
+#[cfg(not(feature = "mpmc"))]
+type SharedSender<P> = SyncSender<P>;
+#[cfg(not(feature = "mpmc"))]
+type SharedReceiver<P> = Arc<Mutex<Receiver<P>>>;
+#[cfg(not(feature = "mpmc"))]
+type WeakSharedReceiver<P> = Weak<Mutex<Receiver<P>>>;
+
+#[cfg(not(feature = "mpmc"))]
+fn shared_queue<P>(capacity: usize) -> (SharedSender<P>, SharedReceiver<P>) where P: Send{
+    let (tx, rx) = sync_channel(capacity);
+    (tx, Arc::new(Mutex::new(rx)))
+}
+
+#[cfg(not(feature = "mpmc"))]
+fn shared_send<P>(sender: &SharedSender<P>, package: P) -> Result<(), TrySendError<P>>{
+    sender.try_send(package)
+}
+
+#[cfg(not(feature = "mpmc"))]
+fn shared_send_blocking<P>(sender: &SharedSender<P>, package: P) -> Result<(), P>{
+    sender.send(package).map_err(|error| error.0)
+}
+
+#[cfg(not(feature = "mpmc"))]
+fn shared_recv<P>(receiver: &SharedReceiver<P>) -> Option<P>{
+    // The worker holding the lock waits on the receiver, the others wait on the lock.
+    receiver.lock().unwrap_or_else(PoisonError::into_inner).recv().ok()
+}
+
+#[cfg(not(feature = "mpmc"))]
+fn shared_try_recv<P>(receiver: &SharedReceiver<P>) -> Result<P, TryRecvError>{
+    // A worker holding the lock is sleeping on the receiver, so there's nothing to take.
+    match receiver.try_lock(){
+        Ok(receiver) => receiver.try_recv(),
+        Err(TryLockError::Poisoned(poisoned)) => poisoned.into_inner().try_recv(),
+        Err(TryLockError::WouldBlock) => Err(TryRecvError::Empty),
+    }
+}
+
+#[cfg(not(feature = "mpmc"))]
+fn shared_recv_timeout<P>(receiver: &SharedReceiver<P>, timeout: Duration) -> Result<P, RecvTimeoutError>{
+    receiver.lock().unwrap_or_else(PoisonError::into_inner).recv_timeout(timeout)
+}
+
+#[cfg(not(feature = "mpmc"))]
+fn shared_downgrade<P>(receiver: &SharedReceiver<P>) -> WeakSharedReceiver<P>{
+    Arc::downgrade(receiver)
+}
+
+
+// Lock-free shared backend.
+
+#[cfg(feature = "mpmc")]
+type SharedSender<P> = SenderHandle<SharedQueue<P>>;
+#[cfg(feature = "mpmc")]
+type SharedReceiver<P> = Arc<ReceiverHandle<SharedQueue<P>>>;
+#[cfg(feature = "mpmc")]
+type WeakSharedReceiver<P> = Weak<ReceiverHandle<SharedQueue<P>>>;
+
+// crossbeam's array queue, plus the signals workers sleep on when it's empty.
+#[cfg(feature = "mpmc")]
+struct SharedQueue<P>{
+    packages: ArrayQueue<P>,
+    signals: Signals,
+}
+
+#[cfg(feature = "mpmc")]
+impl<P> Signaled for SharedQueue<P>{
+    fn signals(&self) -> &Signals{
+        &self.signals
+    }
+}
+
+#[cfg(feature = "mpmc")]
+fn shared_queue<P>(capacity: usize) -> (SharedSender<P>, SharedReceiver<P>) where P: Send{
+    let queue = Arc::new(SharedQueue{
+        packages: ArrayQueue::new(capacity),
+        signals: Signals::new(),
+    });
+    let handle = Arc::new(ReceiverHandle{ queue: queue.clone() });
+    (SenderHandle{ queue }, handle)
+}
+
+#[cfg(feature = "mpmc")]
+fn shared_send<P>(sender: &SharedSender<P>, package: P) -> Result<(), TrySendError<P>>{
+    if sender.queue.signals.abandoned.load(Ordering::SeqCst){
+        return Err(TrySendError::Disconnected(package));
+    }
+    sender.queue.packages.push(package).map_err(TrySendError::Full)?;
+    sender.queue.signals.wake_one();
+    Ok(())
+}
+
+#[cfg(feature = "mpmc")]
+fn shared_send_blocking<P>(sender: &SharedSender<P>, package: P) -> Result<(), P>{
+    sender.queue.signals.send(package, |package| sender.queue.packages.push(package))?;
+    sender.queue.signals.wake_one();
+    Ok(())
+}
+
+#[cfg(feature = "mpmc")]
+fn shared_recv<P>(receiver: &SharedReceiver<P>) -> Option<P>{
+    receiver.queue.signals.receive(|| receiver.queue.packages.pop(), None).ok()
+}
+
+#[cfg(feature = "mpmc")]
+fn shared_try_recv<P>(receiver: &SharedReceiver<P>) -> Result<P, TryRecvError>{
+    receiver.queue.signals.try_receive(|| receiver.queue.packages.pop())
+}
+
+#[cfg(feature = "mpmc")]
+fn shared_recv_timeout<P>(receiver: &SharedReceiver<P>, timeout: Duration) -> Result<P, RecvTimeoutError>{
+    receiver.queue.signals.receive(|| receiver.queue.packages.pop(), Some(Instant::now() + timeout))
+}
+
+#[cfg(feature = "mpmc")]
+fn shared_downgrade<P>(receiver: &SharedReceiver<P>) -> WeakSharedReceiver<P>{
+    Arc::downgrade(receiver)
+}
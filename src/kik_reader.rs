@@ -0,0 +1,124 @@
+//! # Reader
+//!
+//! Turns any *Read*, a file or a TCP stream for instance, into inputs for a channel. Used by *DeliveryService::feed_reader*.
+//!
+//! The reader is split into chunks of a fixed size, and each chunk is turned into an input by a closure. Chunks are only read when the
+//! feeder has a message free to send, the same way as *DeliveryService::feed_generator*, so the whole source is never held in memory.
+//! Every chunk is full except for the last one, which holds whatever was left before the end of the reader.
+//!
+//! The stream of inputs ends at the end of the reader, or at the first error it returns. A *ReadHandle* tells which of the two happened,
+//! and how many bytes were read so far.
+//!
+//!
+
+use std::io::{self, ErrorKind, Read};
+use std::sync::{Arc, Mutex, PoisonError};
+
+// What the chunk reader shares with its handles.
+#[derive(Default)]
+struct ReadState{
+    bytes_read: usize,
+    chunks: usize,
+    finished: bool,
+    error: Option<io::Error>,
+}
+
+/// Progress of a reader fed with *DeliveryService::feed_reader*. Can be cloned and sent to other threads.
+#[derive(Clone)]
+pub struct ReadHandle{
+    state: Arc<Mutex<ReadState>>,
+}
+
+impl ReadHandle{
+    /// How many bytes were read so far.
+    pub fn get_bytes_read(&self) -> usize{
+        self.state.lock().unwrap_or_else(PoisonError::into_inner).bytes_read
+    }
+
+    /// How many chunks were turned into inputs so far.
+    pub fn get_chunks(&self) -> usize{
+        self.state.lock().unwrap_or_else(PoisonError::into_inner).chunks
+    }
+
+    /// True once the reader reached its end or returned an error. No more inputs will come from it.
+    pub fn is_finished(&self) -> bool{
+        self.state.lock().unwrap_or_else(PoisonError::into_inner).finished
+    }
+
+    /// True if the reader stopped because of an error, which can be taken with *take_error*.
+    pub fn has_error(&self) -> bool{
+        self.state.lock().unwrap_or_else(PoisonError::into_inner).error.is_some()
+    }
+
+    /// Take the error that stopped the reader, if any. Following calls return None.
+    pub fn take_error(&self) -> Option<io::Error>{
+        self.state.lock().unwrap_or_else(PoisonError::into_inner).error.take()
+    }
+}
+
+/// Reads fixed-size chunks and converts them into inputs. Called by the feeder as a generator.
+pub struct ChunkReader<Rd, F>{
+    reader: Rd,
+    chunk_size: usize,
+    convert: F,
+    state: Arc<Mutex<ReadState>>,
+}
+
+impl<Rd, F> ChunkReader<Rd, F>{
+    /// Chunk reader with chunks of chunk_size bytes, at least 1. Returns the handle to follow it.
+    pub fn new(reader: Rd, chunk_size: usize, convert: F) -> (Self, ReadHandle){
+        let state = Arc::new(Mutex::new(ReadState::default()));
+        let handle = ReadHandle{ state: Arc::clone(&state) };
+        let chunk_reader = ChunkReader{
+            reader,
+            chunk_size: chunk_size.max(1),
+            convert,
+            state,
+        };
+        (chunk_reader, handle)
+    }
+}
+
+impl<Rd, F> ChunkReader<Rd, F> where Rd: Read{
+    // Fill a chunk as much as possible. It's only short at the end of the reader.
+    fn read_chunk(&mut self) -> io::Result<Vec<u8>>{
+        let mut chunk = vec![0; self.chunk_size];
+        let mut filled = 0;
+        while filled < chunk.len(){
+            match self.reader.read(&mut chunk[filled..]){
+                Ok(0) => break,
+                Ok(read) => filled += read,
+                Err(error) if error.kind() == ErrorKind::Interrupted => {},
+                Err(error) => return Err(error),
+            }
+        }
+        chunk.truncate(filled);
+        Ok(chunk)
+    }
+
+    /// The next input, or None once the reader ended or failed.
+    pub fn next_input<R>(&mut self) -> Option<R> where F: FnMut(Vec<u8>) -> R{
+        if self.state.lock().unwrap_or_else(PoisonError::into_inner).finished{
+            return None;
+        }
+        let chunk = self.read_chunk();
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        match chunk{
+            Ok(chunk) if chunk.is_empty() => {
+                state.finished = true;
+                None
+            },
+            Ok(chunk) => {
+                state.bytes_read += chunk.len();
+                state.chunks += 1;
+                drop(state);
+                Some((self.convert)(chunk))
+            },
+            Err(error) => {
+                state.finished = true;
+                state.error = Some(error);
+                None
+            },
+        }
+    }
+}
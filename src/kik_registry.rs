@@ -0,0 +1,139 @@
+//! # Registry
+//!
+//! Owns several *DeliveryService*s under names, for applications that embed more than one pipeline.
+//!
+//! The channels can have different **T**, **R**, **S** and **E** types. They're stored behind the *RegisteredPool* trait and
+//! found again with their full type, for example *registry.get::<DeliveryService<Number, Number, SquareMessage>>("squares")*.
+//!
+//! Channels are shut down in the reverse order they were registered, like local variables are dropped. Register the channels that feed
+//! others first, so that the ones downstream are stopped before them. Dropping the registry follows the same order.
+//!
+//!
+
+use std::any::Any;
+
+use crate::kik_message::{Message, MessageInput, MessageData};
+use crate::kik_channel::DeliveryService;
+use crate::kik_report::ShutdownReport;
+
+/// A *DeliveryService* with its types erased. Implemented for every *DeliveryService*, not meant to be implemented by the user.
+pub trait RegisteredPool: Send{
+    /// The channel as *Any*, for finding it again with its type.
+    fn as_any(&self) -> &dyn Any;
+
+    /// The channel as mutable *Any*, for finding it again with its type.
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+
+    /// Stop the channel and wait for its workers. Same as *DeliveryService::shutdown*.
+    fn shutdown_pool(self: Box<Self>) -> ShutdownReport;
+}
+
+impl<T, R, S, E> RegisteredPool for DeliveryService<T, R, S, E> where
+T: MessageData + 'static,
+R: MessageInput<T> + 'static,
+S: Message<T, R, E> + Sync + Send + 'static,
+E: Send + 'static,
+{
+    fn as_any(&self) -> &dyn Any{
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any{
+        self
+    }
+
+    fn shutdown_pool(self: Box<Self>) -> ShutdownReport{
+        (*self).shutdown()
+    }
+}
+
+/// Named collection of *DeliveryService*s of any types. Shuts them down in the reverse order they were registered.
+#[derive(Default)]
+pub struct PoolRegistry{
+    // Kept in the order they were registered.
+    pools: Vec<(String, Box<dyn RegisteredPool>)>,
+}
+
+impl PoolRegistry{
+    /// Create an empty registry.
+    pub fn new() -> Self{
+        Self::default()
+    }
+
+    /// Register a channel under the given name. If the name is already taken, the channel is given back (boxed) instead.
+    pub fn register<T, R, S, E>(&mut self, name: &str, channel: DeliveryService<T, R, S, E>) -> Result<(), Box<DeliveryService<T, R, S, E>>> where
+    T: MessageData + 'static,
+    R: MessageInput<T> + 'static,
+    S: Message<T, R, E> + Sync + Send + 'static,
+    E: Send + 'static,
+    {
+        if self.contains(name){
+            return Err(Box::new(channel));
+        }
+        self.pools.push((String::from(name), Box::new(channel)));
+        Ok(())
+    }
+
+    /// True if there's a channel registered under the name.
+    pub fn contains(&self, name: &str) -> bool{
+        self.position(name).is_some()
+    }
+
+    /// Find the channel registered under the name. **D** is its full type. None if there's no such name, or if the channel has another type.
+    pub fn get<D>(&self, name: &str) -> Option<&D> where D: 'static{
+        self.pools[self.position(name)?].1.as_any().downcast_ref::<D>()
+    }
+
+    /// Find the channel registered under the name, for feeding and iterating. **D** is its full type. None if there's no such name, or if the channel has another type.
+    pub fn get_mut<D>(&mut self, name: &str) -> Option<&mut D> where D: 'static{
+        let index = self.position(name)?;
+        self.pools[index].1.as_any_mut().downcast_mut::<D>()
+    }
+
+    /// Names of the channels, in the order they were registered.
+    pub fn names(&self) -> impl Iterator<Item = &str>{
+        self.pools.iter().map(|(name, _)| name.as_str())
+    }
+
+    /// How many channels are registered.
+    pub fn len(&self) -> usize{
+        self.pools.len()
+    }
+
+    /// True if no channel is registered.
+    pub fn is_empty(&self) -> bool{
+        self.pools.is_empty()
+    }
+
+    /// Remove the channel registered under the name and shut it down. None if there's no such name.
+    pub fn shutdown(&mut self, name: &str) -> Option<ShutdownReport>{
+        let index = self.position(name)?;
+        Some(self.pools.remove(index).1.shutdown_pool())
+    }
+
+    /// Shut down every channel, the last one registered first. Returns each name together with its report, in the order they were shut down.
+    pub fn shutdown_all(mut self) -> Vec<(String, ShutdownReport)>{
+        self.close()
+    }
+
+    /// Shut down every channel in reverse order. Used by shutdown_all and drop.
+    fn close(&mut self) -> Vec<(String, ShutdownReport)>{
+        let mut reports: Vec<(String, ShutdownReport)> = Vec::with_capacity(self.pools.len());
+        while let Some((name, pool)) = self.pools.pop(){
+            reports.push((name, pool.shutdown_pool()));
+        }
+        reports
+    }
+
+    /// Index of the channel registered under the name.
+    fn position(&self, name: &str) -> Option<usize>{
+        self.pools.iter().position(|(pool_name, _)| pool_name == name)
+    }
+}
+
+impl Drop for PoolRegistry{
+    fn drop(&mut self){
+        // Otherwise the channels would be dropped in the order they were registered.
+        self.close();
+    }
+}
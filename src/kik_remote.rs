@@ -0,0 +1,226 @@
+//! # Remote
+//!
+//! Workers in other processes or machines, behind the "remote" feature. The channel works the same way, only the transport behind some of its workers changes.
+//!
+//! *DeliveryService::add_remote_worker* connects to a *RemoteAgent* and spawns a worker thread for the connection. That worker takes packages
+//! from the queue like any other, but instead of working the message it sends it through the socket, waits for the agent to send it back
+//! worked, and hands it to the feeder. The results come out of the same iterator as the ones of local workers.
+//!
+//! Messages travel as bincode, so the message **S** and the error **E** implement serde's *Serialize* and *DeserializeOwned*. Those are what travel:
+//! the whole message goes, the whole message comes back.
+//!
+//! The agent starts each connection by writing a handshake, anything read before it is skipped. Then each frame is a u32 length followed
+//! by that many bytes, little endian. The agent answers with a tag byte before the frame: 0 when the message was worked, the frame is the message.
+//! 1 when it failed, the frame is the error. 2 when it panicked, the frame is empty.
+//!
+//! If the connection is lost, the message being worked comes back with *WorkError::Lost* and the remote worker stops. Like any failure, it's sent
+//! again if *ChannelConfig::set_quarantine* allows more attempts. The agent works messages with *Message::work_with_context*, its context has no
+//! worker state and isn't cancelled by the channel.
+//!
+//!
+
+use std::io::{self, BufWriter, ErrorKind, Read, Write};
+use std::marker::PhantomData;
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::thread;
+
+use crate::kik_message::{Message, MessageInput, MessageData};
+use crate::kik_package::Package;
+use crate::kik_error::WorkError;
+use crate::kik_context::{CancellationToken, WorkContext};
+use crate::kik_wire::{decode, encode, invalid_data, read_frame, write_frame};
+
+use serde::{Serialize, de::DeserializeOwned};
+
+// Tags in front of each answer of the agent.
+const WORKED: u8 = 0;
+const FAILED: u8 = 1;
+const PANICKED: u8 = 2;
+// Written by the agent before anything else.
+const HANDSHAKE: &[u8] = b"#kik_sync_service agent 1\n";
+
+/// Works a package through the connection. Sets the package's message and error, returns an error only if the connection was lost.
+pub type RemoteCall<S, E> = Box<dyn FnMut(&mut Package<S, E>) -> io::Result<()> + Send>;
+
+// Read one byte at a time until the last bytes read are the handshake. Whatever the agent's process printed before it started is skipped.
+fn skip_to_handshake<Rd>(stream: &mut Rd) -> io::Result<()> where Rd: Read + ?Sized{
+    let mut matched = 0;
+    let mut byte = [0];
+    while matched < HANDSHAKE.len(){
+        stream.read_exact(&mut byte)?;
+        if byte[0] == HANDSHAKE[matched]{
+            matched += 1;
+        } else {
+            // The handshake doesn't repeat its first byte, so a mismatch can only restart the match.
+            matched = usize::from(byte[0] == HANDSHAKE[0]);
+        }
+    }
+    Ok(())
+}
+
+/// The channel's end of a connection to a *RemoteAgent*. Used by kik_channel and kik_process, not meant to be used directly.
+pub struct RemoteLink<S, E>{
+    reader: Box<dyn Read + Send>,
+    writer: BufWriter<Box<dyn Write + Send>>,
+    buffer: Vec<u8>,
+    resource_type: PhantomData<fn(S) -> (S, E)>,
+}
+
+impl<S, E> RemoteLink<S, E> where
+S: Serialize + DeserializeOwned + 'static,
+E: Serialize + DeserializeOwned + Send + 'static,
+{
+    /// Connect to an agent.
+    pub fn connect<A>(address: A) -> io::Result<Self> where A: ToSocketAddrs{
+        let stream = TcpStream::connect(address)?;
+        // Frames are small and each one waits for an answer.
+        stream.set_nodelay(true)?;
+        let reader = stream.try_clone()?;
+        Self::from_pipes(Box::new(reader), Box::new(stream))
+    }
+
+    /// Talk to an agent through a pair of pipes, the standard output and input of a child process for instance. Waits for the handshake.
+    pub fn from_pipes(mut reader: Box<dyn Read + Send>, writer: Box<dyn Write + Send>) -> io::Result<Self>{
+        skip_to_handshake(&mut reader)?;
+        Ok(RemoteLink{
+            reader,
+            writer: BufWriter::new(writer),
+            buffer: Vec::new(),
+            resource_type: PhantomData,
+        })
+    }
+
+    // Send one message and replace it with the one that comes back. Ok(Err) if the agent says it failed.
+    fn work_message(&mut self, message: &mut S, worker_id: usize) -> io::Result<Result<(), WorkError<E>>>{
+        self.buffer.clear();
+        encode(message, &mut self.buffer)?;
+        write_frame(&mut self.writer, &self.buffer)?;
+        self.writer.flush()?;
+        let mut tag = [0];
+        self.reader.read_exact(&mut tag)?;
+        let bytes = read_frame(&mut self.reader)?;
+        match tag[0]{
+            WORKED => {
+                *message = decode(&bytes)?;
+                Ok(Ok(()))
+            },
+            FAILED => Ok(Err(WorkError::Failed{worker_id, error: decode(&bytes)?})),
+            PANICKED => Ok(Err(WorkError::Panicked{worker_id})),
+            _ => Err(invalid_data("unknown answer from the agent")),
+        }
+    }
+
+    /// Work a package through the agent, setting its message and error. Inputs packed by map_reduce are sent one at a time and merged here.
+    /// Returns an error only if the connection was lost.
+    pub fn work_package<T, R>(&mut self, package: &mut Package<S, E>) -> io::Result<()> where
+    T: MessageData + 'static,
+    R: MessageInput<T> + 'static,
+    S: Message<T, R, E>,
+    {
+        let worker_id = package.worker_id;
+        if let Err(error) = self.work_message(&mut package.message, worker_id)?{
+            package.error = Some(error);
+            return Ok(());
+        }
+        let merge = package.merge;
+        for mut other in package.merged.drain(..){
+            if let Err(error) = self.work_message(&mut other, worker_id)?{
+                package.error = Some(error);
+                return Ok(());
+            }
+            if let Some(merge) = merge{
+                merge(&mut package.message, other);
+            }
+        }
+        Ok(())
+    }
+
+    /// Turn the link into the call used by the remote worker.
+    pub fn into_call<T, R>(mut self) -> RemoteCall<S, E> where
+    T: MessageData + 'static,
+    R: MessageInput<T> + 'static,
+    S: Message<T, R, E>,
+    {
+        Box::new(move |package: &mut Package<S, E>| self.work_package(package))
+    }
+}
+
+/// Works the messages sent by *DeliveryService::add_remote_worker*. Runs in the process that should do the work. See the module documentation.
+pub struct RemoteAgent<T, R, S, E>{
+    resource_type: PhantomData<fn(T, R, S, E)>,
+}
+
+impl<T, R, S, E> Default for RemoteAgent<T, R, S, E>{
+    fn default() -> Self{
+        RemoteAgent{
+            resource_type: PhantomData,
+        }
+    }
+}
+
+impl<T, R, S, E> RemoteAgent<T, R, S, E> where
+T: MessageData + 'static,
+R: MessageInput<T> + 'static,
+S: Message<T, R, E> + Serialize + DeserializeOwned + 'static,
+E: Serialize + DeserializeOwned + Send + 'static,
+{
+    /// An agent for messages **S**.
+    pub fn new() -> Self{
+        Self::default()
+    }
+
+    /// Accept connections for as long as the listener works, each one served in a thread of its own. Returns the first error of the listener.
+    pub fn serve(&self, listener: &TcpListener) -> io::Result<()>{
+        thread::scope(|scope| {
+            for stream in listener.incoming(){
+                let stream = stream?;
+                // A lost connection only ends its own thread.
+                scope.spawn(move || self.serve_connection(stream));
+            }
+            Ok(())
+        })
+    }
+
+    /// Work the messages of a single connection until the channel closes it. Returns how many messages were worked.
+    pub fn serve_connection(&self, stream: TcpStream) -> io::Result<usize>{
+        stream.set_nodelay(true)?;
+        let reader = stream.try_clone()?;
+        self.serve_pipes(reader, stream)
+    }
+
+    /// Same as *serve_connection*, through a pair of pipes. Messages are read from reader and sent back through writer.
+    pub fn serve_pipes<Rd, W>(&self, mut reader: Rd, writer: W) -> io::Result<usize> where Rd: Read, W: Write{
+        let mut writer = BufWriter::new(writer);
+        writer.write_all(HANDSHAKE)?;
+        writer.flush()?;
+        let mut context = WorkContext::new(CancellationToken::new(), Default::default());
+        let mut buffer = Vec::new();
+        let mut worked = 0;
+        loop{
+            let bytes = match read_frame(&mut reader){
+                Ok(bytes) => bytes,
+                // The channel is gone.
+                Err(error) if error.kind() == ErrorKind::UnexpectedEof => return Ok(worked),
+                Err(error) => return Err(error),
+            };
+            let mut message: S = decode(&bytes)?;
+            buffer.clear();
+            let tag = match catch_unwind(AssertUnwindSafe(|| message.work_with_context(&mut context))){
+                Ok(Ok(())) => {
+                    encode(&message, &mut buffer)?;
+                    WORKED
+                },
+                Ok(Err(error)) => {
+                    encode(&error, &mut buffer)?;
+                    FAILED
+                },
+                Err(_) => PANICKED,
+            };
+            writer.write_all(&[tag])?;
+            write_frame(&mut writer, &buffer)?;
+            writer.flush()?;
+            worked += 1;
+        }
+    }
+}
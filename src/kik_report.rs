@@ -0,0 +1,795 @@
+//! # Report
+//!
+//! Tuning data returned by the draining methods of *DeliveryService*.
+//!
+//! A *BatchReport* tells how long a run took, how the *Message*s were spread between the *Worker*s, how long each *Message* took
+//! to be worked and how many *Message*s were recycled instead of freshly allocated. Useful for choosing *worker_number* and *package_number* in *ChannelConfig*.
+//!
+//! An *AllocStats* tells how many heap allocations the feeder made on the caller's thread, for checking that the steady-state path stays allocation-free.
+//!
+//! A *Throughput* tells how many *Message*s (and bytes) per second were finished recently, for autoscalers and dashboards.
+//!
+//! A *ChannelStats* tells how long *Message*s waited and worked, and how busy each *Worker* was, since metrics were enabled in *ChannelConfig*.
+//!
+//! A *PoolHealth* tells what each *Worker* is doing right now, and whether the channel is still making progress.
+//!
+//!
+
+use std::collections::{BTreeMap, VecDeque};
+use std::time::{Duration, Instant};
+
+use crate::kik_package::Package;
+
+/// Summary of a single drained run. Returned alongside the results by *DeliveryService::drain*.
+#[derive(Clone, Debug)]
+pub struct BatchReport{
+    name: Option<String>,
+    elapsed: Duration,
+    worker_messages: BTreeMap<usize, usize>,
+    // Sorted from fastest to slowest, for percentiles.
+    work_times: Vec<Duration>,
+    recycled: usize,
+    allocated: usize,
+}
+
+impl BatchReport{
+    /// Name of the channel that generated this report, if it was given one in *ChannelConfig::set_name*.
+    pub fn get_name(&self) -> Option<&str>{
+        self.name.as_deref()
+    }
+
+    /// Wall time between the start of the run and the last result.
+    pub fn get_elapsed(&self) -> Duration{
+        self.elapsed
+    }
+
+    /// How many messages each worker processed, indexed by worker id.
+    pub fn get_worker_messages(&self) -> &BTreeMap<usize, usize>{
+        &self.worker_messages
+    }
+
+    /// Total number of messages retrieved during the run.
+    pub fn get_message_count(&self) -> usize{
+        self.work_times.len()
+    }
+
+    /// Average time spent inside *Message::work*. Zero if no message was retrieved.
+    pub fn get_average_work_time(&self) -> Duration{
+        average(&self.work_times)
+    }
+
+    /// Work time below which the given percentage of the messages fall. Percentile is clamped between 0.0 and 100.0.
+    pub fn get_work_time_percentile(&self, percentile: f64) -> Duration{
+        nearest_rank(&self.work_times, percentile)
+    }
+
+    /// How many messages were reset with a new input and sent back to the workers instead of being dropped.
+    pub fn get_recycled_messages(&self) -> usize{
+        self.recycled
+    }
+
+    /// How many messages had to be constructed with *Message::new* during the run.
+    pub fn get_allocated_messages(&self) -> usize{
+        self.allocated
+    }
+}
+
+/// Mean of the durations. Zero if there are none.
+fn average(durations: &[Duration]) -> Duration{
+    if durations.is_empty(){
+        return Duration::from_secs(0);
+    }
+    let total: Duration = durations.iter().sum();
+    total / durations.len() as u32
+}
+
+/// Duration below which the given percentage of the sorted durations fall. Zero if there are none.
+fn nearest_rank(sorted: &[Duration], percentile: f64) -> Duration{
+    if sorted.is_empty(){
+        return Duration::from_secs(0);
+    }
+    let percentile = percentile.clamp(0.0, 100.0);
+    let index = ((sorted.len() - 1) as f64 * percentile / 100.0).round() as usize;
+    sorted[index]
+}
+
+
+/// Accumulates the data for a *BatchReport* while a run is active. Used by kik_feeder.
+pub struct BatchStats{
+    name: Option<String>,
+    start: Instant,
+    worker_messages: BTreeMap<usize, usize>,
+    work_times: Vec<Duration>,
+    recycled: usize,
+    allocated: usize,
+}
+
+impl BatchStats{
+    /// Start counting from now, for the channel with the given name.
+    pub fn new(name: Option<String>) -> Self{
+        BatchStats{
+            name,
+            start: Instant::now(),
+            worker_messages: BTreeMap::new(),
+            work_times: Vec::new(),
+            recycled: 0,
+            allocated: 0,
+        }
+    }
+
+    /// Register a package retrieved from the workers.
+    pub fn record_package<S, E>(&mut self, package: &Package<S, E>){
+        *self.worker_messages.entry(package.worker_id).or_insert(0) += 1;
+        self.work_times.push(package.work_time);
+    }
+
+    /// Register a message that was reset and sent back to the workers.
+    pub fn record_recycled(&mut self){
+        self.recycled += 1;
+    }
+
+    /// Register a message built from scratch.
+    pub fn record_allocated(&mut self){
+        self.allocated += 1;
+    }
+
+    /// Finish counting and build the report.
+    pub fn finish(self) -> BatchReport{
+        let mut work_times = self.work_times;
+        work_times.sort();
+        BatchReport{
+            name: self.name,
+            elapsed: self.start.elapsed(),
+            worker_messages: self.worker_messages,
+            work_times,
+            recycled: self.recycled,
+            allocated: self.allocated,
+        }
+    }
+}
+
+
+/// Memory observed for a single message slot. A slot is a message built by the feeder, followed across every time it was recycled.
+#[derive(Clone, Debug, Default)]
+pub struct SlotMemory{
+    peak: usize,
+    last: usize,
+    growths: usize,
+    samples: usize,
+}
+
+impl SlotMemory{
+    /// Largest payload size observed for this slot.
+    pub fn get_peak(&self) -> usize{
+        self.peak
+    }
+
+    /// Payload size observed the last time this slot was retrieved.
+    pub fn get_last(&self) -> usize{
+        self.last
+    }
+
+    /// How many times the payload grew past its previous peak. A high number means the buffer keeps reallocating.
+    pub fn get_growths(&self) -> usize{
+        self.growths
+    }
+
+    /// How many times this slot was retrieved from the workers.
+    pub fn get_samples(&self) -> usize{
+        self.samples
+    }
+}
+
+
+/// Peak payload size for every message slot. Returned by *DeliveryService::get_memory_stats* when memory tracking is enabled in *ChannelConfig*.
+/// 
+/// Compare the peaks with the sizes actually needed to know if recycled buffers are oversized. Many growths point to buffers being reallocated over and over.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryStats{
+    name: Option<String>,
+    slots: BTreeMap<usize, SlotMemory>,
+}
+
+impl MemoryStats{
+    /// Create empty stats for the channel with the given name.
+    pub fn new(name: Option<String>) -> Self{
+        MemoryStats{
+            name,
+            slots: BTreeMap::new(),
+        }
+    }
+
+    /// Name of the channel these stats came from, if it was given one in *ChannelConfig::set_name*.
+    pub fn get_name(&self) -> Option<&str>{
+        self.name.as_deref()
+    }
+
+    /// Register the payload size of a message retrieved from the workers. Used by kik_feeder.
+    pub fn record(&mut self, slot: usize, payload_size: usize){
+        let memory = self.slots.entry(slot).or_default();
+        // The first sample isn't a growth, it's the initial allocation.
+        if memory.samples > 0 && payload_size > memory.peak{
+            memory.growths += 1;
+        }
+        memory.peak = memory.peak.max(payload_size);
+        memory.last = payload_size;
+        memory.samples += 1;
+    }
+
+    /// Memory observed for each slot, indexed by slot.
+    pub fn get_slots(&self) -> &BTreeMap<usize, SlotMemory>{
+        &self.slots
+    }
+
+    /// Largest payload observed in any slot.
+    pub fn get_peak(&self) -> usize{
+        self.slots.values().map(SlotMemory::get_peak).max().unwrap_or(0)
+    }
+
+    /// Sum of the peaks of every slot. An upper bound for the memory held by messages at once.
+    pub fn get_total_peak(&self) -> usize{
+        self.slots.values().map(SlotMemory::get_peak).sum()
+    }
+
+    /// Sum of the growths of every slot.
+    pub fn get_growths(&self) -> usize{
+        self.slots.values().map(SlotMemory::get_growths).sum()
+    }
+}
+
+
+/// Heap allocations made on the caller's thread, split by path. Returned by *DeliveryService::alloc_stats* when allocation tracking is enabled in *ChannelConfig*.
+/// 
+/// Only counted if *CountingAllocator* is the global allocator. See kik_alloc for what is guaranteed to stay at zero.
+#[derive(Clone, Debug, Default)]
+pub struct AllocStats{
+    name: Option<String>,
+    feed: usize,
+    dispatch: usize,
+    recycle: usize,
+    yielded: usize,
+}
+
+impl AllocStats{
+    /// Create empty stats for the channel with the given name.
+    pub fn new(name: Option<String>) -> Self{
+        AllocStats{
+            name,
+            ..AllocStats::default()
+        }
+    }
+
+    /// Name of the channel these stats came from, if it was given one in *ChannelConfig::set_name*.
+    pub fn get_name(&self) -> Option<&str>{
+        self.name.as_deref()
+    }
+
+    /// Count allocations made while feeding inputs. Used by kik_feeder.
+    pub fn record_feed(&mut self, allocations: usize){
+        self.feed += allocations;
+    }
+
+    /// Count allocations made while building and sending messages. Used by kik_feeder.
+    pub fn record_dispatch(&mut self, allocations: usize){
+        self.dispatch += allocations;
+    }
+
+    /// Count allocations made while taking the data out of retrieved messages. Used by kik_feeder.
+    pub fn record_recycle(&mut self, allocations: usize){
+        self.recycle += allocations;
+    }
+
+    /// Count the rest of the allocations made while iterating. Used by kik_channel.
+    pub fn record_yield(&mut self, allocations: usize){
+        self.yielded += allocations;
+    }
+
+    /// Set every count back to zero, keeping the name.
+    pub fn reset(&mut self){
+        self.feed = 0;
+        self.dispatch = 0;
+        self.recycle = 0;
+        self.yielded = 0;
+    }
+
+    /// Allocations made by *feed_feeder* and the other feeding methods, including inputs collected from *WeakInputSender*s.
+    pub fn get_feed_allocations(&self) -> usize{
+        self.feed
+    }
+
+    /// Allocations made while building messages and sending them to the workers. When running inline, this includes the work itself.
+    pub fn get_dispatch_allocations(&self) -> usize{
+        self.dispatch
+    }
+
+    /// Allocations made while cloning or taking the data out of retrieved messages.
+    pub fn get_recycle_allocations(&self) -> usize{
+        self.recycle
+    }
+
+    /// Allocations made while iterating that aren't counted in any of the other paths.
+    pub fn get_yield_allocations(&self) -> usize{
+        self.yielded
+    }
+
+    /// Sum of every path.
+    pub fn get_total_allocations(&self) -> usize{
+        self.feed + self.dispatch + self.recycle + self.yielded
+    }
+}
+
+
+/// How far along a worker is in its current message, as told by its *WorkPacer*. Returned by *DeliveryService::get_work_progress*.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct WorkProgress{
+    done: usize,
+    total: Option<usize>,
+}
+
+impl WorkProgress{
+    /// Progress with the given checkpoints done, out of total if it's known.
+    pub fn new(done: usize, total: Option<usize>) -> Self{
+        WorkProgress{
+            done,
+            total,
+        }
+    }
+
+    /// How many checkpoints the message went through.
+    pub fn get_done(&self) -> usize{
+        self.done
+    }
+
+    /// How many checkpoints the message expects to go through. None if it didn't say.
+    pub fn get_total(&self) -> Option<usize>{
+        self.total
+    }
+
+    /// Done divided by total, capped at 1. None if the total isn't known.
+    pub fn get_fraction(&self) -> Option<f64>{
+        match self.total?{
+            0 => Some(1.0),
+            total => Some((self.done as f64 / total as f64).min(1.0)),
+        }
+    }
+}
+
+
+/// Deepest stack use probed in each worker. Returned by *DeliveryService::get_stack_usage* when the stack probe is enabled in *ChannelConfig*.
+/// 
+/// Only what was probed is counted: the worker probes before each message, and *work* can probe deeper with *WorkContext::probe_stack*.
+/// Use it to pick *ChannelConfig::set_stack_size* with some headroom, instead of guessing after a stack overflow.
+#[derive(Clone, Debug, Default)]
+pub struct StackUsage{
+    name: Option<String>,
+    stack_size: usize,
+    worker_peaks: BTreeMap<usize, usize>,
+}
+
+impl StackUsage{
+    /// Construct a new report. Used by kik_channel.
+    pub fn new(name: Option<String>, stack_size: usize, worker_peaks: BTreeMap<usize, usize>) -> Self{
+        StackUsage{
+            name,
+            stack_size,
+            worker_peaks,
+        }
+    }
+
+    /// Name of the channel these stats came from, if it was given one in *ChannelConfig::set_name*.
+    pub fn get_name(&self) -> Option<&str>{
+        self.name.as_deref()
+    }
+
+    /// Stack size each worker thread was given.
+    pub fn get_stack_size(&self) -> usize{
+        self.stack_size
+    }
+
+    /// Deepest stack use probed in each worker, in bytes, indexed by worker id.
+    pub fn get_worker_peaks(&self) -> &BTreeMap<usize, usize>{
+        &self.worker_peaks
+    }
+
+    /// Deepest stack use probed in any worker, in bytes.
+    pub fn get_peak(&self) -> usize{
+        self.worker_peaks.values().copied().max().unwrap_or(0)
+    }
+
+    /// Stack left unused by the deepest worker, in bytes.
+    pub fn get_headroom(&self) -> usize{
+        self.stack_size.saturating_sub(self.get_peak())
+    }
+}
+
+
+/// What happened when a *DeliveryService* was shut down. Returned by *DeliveryService::shutdown*.
+#[derive(Clone, Debug, Default)]
+pub struct ShutdownReport{
+    name: Option<String>,
+    abandoned_inputs: usize,
+    persisted_inputs: usize,
+    drained_messages: usize,
+    joined_workers: usize,
+    panicked_workers: usize,
+}
+
+impl ShutdownReport{
+    /// Construct a new report. Used by kik_channel.
+    pub fn new(name: Option<String>, abandoned_inputs: usize, persisted_inputs: usize, drained_messages: usize, joined_workers: usize, panicked_workers: usize) -> Self{
+        ShutdownReport{
+            name,
+            abandoned_inputs,
+            persisted_inputs,
+            drained_messages,
+            joined_workers,
+            panicked_workers,
+        }
+    }
+
+    /// Name of the channel that was shut down, if it was given one in *ChannelConfig::set_name*.
+    pub fn get_name(&self) -> Option<&str>{
+        self.name.as_deref()
+    }
+
+    /// Inputs that were fed but never sent to the workers.
+    pub fn get_abandoned_inputs(&self) -> usize{
+        self.abandoned_inputs
+    }
+
+    /// Inputs that were never sent to the workers, but were handed to the sink of *DropPolicy::Persist*.
+    pub fn get_persisted_inputs(&self) -> usize{
+        self.persisted_inputs
+    }
+
+    /// Messages that were sent to the workers, and waited for, but whose results were never returned.
+    pub fn get_drained_messages(&self) -> usize{
+        self.drained_messages
+    }
+
+    /// Worker threads that were joined.
+    pub fn get_joined_workers(&self) -> usize{
+        self.joined_workers
+    }
+
+    /// Worker threads that had panicked by the time they were joined.
+    pub fn get_panicked_workers(&self) -> usize{
+        self.panicked_workers
+    }
+}
+
+
+/// What a worker was doing when *DeliveryService::health* was called.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WorkerStatus{
+    /// Working a message.
+    Running,
+    /// Waiting for a message, or for the channel to be resumed.
+    Idle,
+    /// Silent inside a message for longer than the heartbeat timeout. See *ChannelConfig::set_heartbeat_timeout*.
+    Hung,
+    /// Died from a panic. Another worker takes its place on the next iteration.
+    Panicked,
+    /// Left the channel: removed, idle past its timeout, or disconnected.
+    Exited,
+}
+
+/// Snapshot of the workers and queues of a channel. Returned by *DeliveryService::health*.
+#[derive(Clone, Debug)]
+pub struct PoolHealth{
+    name: Option<String>,
+    workers: BTreeMap<usize, WorkerStatus>,
+    pending_inputs: usize,
+    in_flight: usize,
+    ready_results: usize,
+    since_last_completed: Option<Duration>,
+    paused: bool,
+}
+
+impl PoolHealth{
+    /// Construct a new snapshot. Used by kik_channel.
+    pub fn new(name: Option<String>, workers: BTreeMap<usize, WorkerStatus>, queues: (usize, usize, usize), since_last_completed: Option<Duration>, paused: bool) -> Self{
+        let (pending_inputs, in_flight, ready_results) = queues;
+        PoolHealth{
+            name,
+            workers,
+            pending_inputs,
+            in_flight,
+            ready_results,
+            since_last_completed,
+            paused,
+        }
+    }
+
+    /// Name of the channel, if it was given one in *ChannelConfig::set_name*.
+    pub fn get_name(&self) -> Option<&str>{
+        self.name.as_deref()
+    }
+
+    /// Status of each worker, indexed by worker id. Workers that ended are listed once, by the first snapshot taken after they did.
+    pub fn get_workers(&self) -> &BTreeMap<usize, WorkerStatus>{
+        &self.workers
+    }
+
+    /// Status of a single worker. None if it isn't listed.
+    pub fn get_status(&self, worker_id: usize) -> Option<WorkerStatus>{
+        self.workers.get(&worker_id).copied()
+    }
+
+    /// How many workers are listed with the given status.
+    pub fn count(&self, status: WorkerStatus) -> usize{
+        self.workers.values().filter(|worker_status| **worker_status == status).count()
+    }
+
+    /// Inputs fed and not yet sent to the workers.
+    pub fn get_pending_inputs(&self) -> usize{
+        self.pending_inputs
+    }
+
+    /// Messages sent to the workers that haven't come back yet.
+    pub fn get_in_flight(&self) -> usize{
+        self.in_flight
+    }
+
+    /// Results waiting to be returned by the iterator.
+    pub fn get_ready_results(&self) -> usize{
+        self.ready_results
+    }
+
+    /// How long ago the last message was completed by a worker. None if none was completed yet.
+    /// A value that keeps growing while there are messages in flight means the workers are stuck.
+    pub fn get_since_last_completed(&self) -> Option<Duration>{
+        self.since_last_completed
+    }
+
+    /// True if the channel was paused with *DeliveryService::pause*.
+    pub fn is_paused(&self) -> bool{
+        self.paused
+    }
+}
+
+
+/// Rates over a recent window of time. Returned by *DeliveryService::throughput* when throughput history is enabled in *ChannelConfig*.
+///
+/// Messages are counted when a worker finishes them, as long as they were retrieved by the feeder since. Bytes are the *Message::payload_size*
+/// of each of them, so they only mean something if *payload_size* was overridden.
+#[derive(Clone, Debug)]
+pub struct Throughput{
+    name: Option<String>,
+    window: Duration,
+    messages: usize,
+    bytes: usize,
+}
+
+impl Throughput{
+    /// Name of the channel this was measured in, if it was given one in *ChannelConfig::set_name*.
+    pub fn get_name(&self) -> Option<&str>{
+        self.name.as_deref()
+    }
+
+    /// How long the rates were measured over. Shorter than the window asked for if the channel or its history isn't that old.
+    pub fn get_window(&self) -> Duration{
+        self.window
+    }
+
+    /// How many messages were finished inside the window.
+    pub fn get_messages(&self) -> usize{
+        self.messages
+    }
+
+    /// Sum of the payload sizes of the messages finished inside the window.
+    pub fn get_bytes(&self) -> usize{
+        self.bytes
+    }
+
+    /// Messages finished per second. Zero if the window is empty.
+    pub fn get_messages_per_second(&self) -> f64{
+        rate(self.messages, self.window)
+    }
+
+    /// Bytes finished per second. Zero if the window is empty.
+    pub fn get_bytes_per_second(&self) -> f64{
+        rate(self.bytes, self.window)
+    }
+}
+
+/// Count per second over the window.
+fn rate(count: usize, window: Duration) -> f64{
+    let seconds = window.as_secs_f64();
+    if seconds > 0.0 { count as f64 / seconds } else { 0.0 }
+}
+
+
+/// When each retrieved message was finished, and its payload size, for as long as the history goes back. Used by kik_feeder.
+pub struct ThroughputHistory{
+    name: Option<String>,
+    start: Instant,
+    history: Duration,
+    // Oldest first, in the order they were retrieved.
+    samples: VecDeque<(Instant, usize)>,
+}
+
+impl ThroughputHistory{
+    /// Start keeping samples from now, for the channel with the given name. Samples older than the history are forgotten.
+    pub fn new(name: Option<String>, history: Duration) -> Self{
+        ThroughputHistory{
+            name,
+            start: Instant::now(),
+            history,
+            samples: VecDeque::new(),
+        }
+    }
+
+    /// Register a package retrieved from the workers.
+    pub fn record<S, E>(&mut self, package: &Package<S, E>, payload_size: usize){
+        self.samples.push_back((package.completed_at, payload_size));
+        let now = Instant::now();
+        // Retrieved out of order now and then, so a few samples may linger a little longer than the history. They're still filtered when measuring.
+        while let Some((completed_at, _)) = self.samples.front(){
+            if now.saturating_duration_since(*completed_at) <= self.history{
+                break;
+            }
+            self.samples.pop_front();
+        }
+    }
+
+    /// Rates over the last window, cut to the history and to how long the channel has existed.
+    pub fn measure(&self, window: Duration) -> Throughput{
+        let now = Instant::now();
+        let window = window.min(self.history).min(now.saturating_duration_since(self.start));
+        let mut messages: usize = 0;
+        let mut bytes: usize = 0;
+        for (completed_at, payload_size) in &self.samples{
+            if now.saturating_duration_since(*completed_at) <= window{
+                messages += 1;
+                bytes += payload_size;
+            }
+        }
+        Throughput{
+            name: self.name.clone(),
+            window,
+            messages,
+            bytes,
+        }
+    }
+}
+
+
+/// Timings of every message retrieved since the channel was created, or since *DeliveryService::reset_stats*. Returned by *DeliveryService::stats*
+/// when metrics are enabled in *ChannelConfig*.
+///
+/// Latency goes from the moment the feeder dispatched a message to the moment a worker finished it, so it includes the time spent queued.
+/// Work time is only the time spent inside *Message::work*.
+#[derive(Clone, Debug)]
+pub struct ChannelStats{
+    name: Option<String>,
+    elapsed: Duration,
+    failed: usize,
+    worker_messages: BTreeMap<usize, usize>,
+    worker_busy: BTreeMap<usize, Duration>,
+    // Both sorted from fastest to slowest, for percentiles.
+    work_times: Vec<Duration>,
+    latencies: Vec<Duration>,
+}
+
+impl ChannelStats{
+    /// Name of the channel these were measured in, if it was given one in *ChannelConfig::set_name*.
+    pub fn get_name(&self) -> Option<&str>{
+        self.name.as_deref()
+    }
+
+    /// Wall time since the metrics started counting.
+    pub fn get_elapsed(&self) -> Duration{
+        self.elapsed
+    }
+
+    /// How many messages were retrieved, failed ones included.
+    pub fn get_message_count(&self) -> usize{
+        self.work_times.len()
+    }
+
+    /// How many of the retrieved messages failed to be worked.
+    pub fn get_failed_count(&self) -> usize{
+        self.failed
+    }
+
+    /// Average time spent inside *Message::work*. Zero if no message was retrieved.
+    pub fn get_average_work_time(&self) -> Duration{
+        average(&self.work_times)
+    }
+
+    /// Work time below which the given percentage of the messages fall. Percentile is clamped between 0.0 and 100.0.
+    pub fn get_work_time_percentile(&self, percentile: f64) -> Duration{
+        nearest_rank(&self.work_times, percentile)
+    }
+
+    /// Average time between dispatch and completion. Zero if no message was retrieved.
+    pub fn get_average_latency(&self) -> Duration{
+        average(&self.latencies)
+    }
+
+    /// Latency below which the given percentage of the messages fall. Percentile is clamped between 0.0 and 100.0.
+    pub fn get_latency_percentile(&self, percentile: f64) -> Duration{
+        nearest_rank(&self.latencies, percentile)
+    }
+
+    /// How many messages each worker finished, indexed by worker id. Workers that haven't finished any aren't listed.
+    pub fn get_worker_messages(&self) -> &BTreeMap<usize, usize>{
+        &self.worker_messages
+    }
+
+    /// Messages finished per second by each worker, over the whole elapsed time.
+    pub fn get_worker_throughput(&self) -> BTreeMap<usize, f64>{
+        self.worker_messages.iter().map(|(worker_id, messages)| (*worker_id, rate(*messages, self.elapsed))).collect()
+    }
+
+    /// Time each worker spent inside *Message::work*.
+    pub fn get_worker_busy_time(&self) -> &BTreeMap<usize, Duration>{
+        &self.worker_busy
+    }
+
+    /// Time each worker spent outside *Message::work*. A worker added after the metrics started counting looks idle for the time before it existed.
+    pub fn get_worker_idle_time(&self) -> BTreeMap<usize, Duration>{
+        self.worker_busy.iter().map(|(worker_id, busy)| (*worker_id, self.elapsed.saturating_sub(*busy))).collect()
+    }
+}
+
+
+/// Accumulates the data for *ChannelStats* while metrics are enabled. Used by kik_feeder.
+pub struct MetricsCollector{
+    name: Option<String>,
+    start: Instant,
+    failed: usize,
+    worker_messages: BTreeMap<usize, usize>,
+    worker_busy: BTreeMap<usize, Duration>,
+    work_times: Vec<Duration>,
+    latencies: Vec<Duration>,
+}
+
+impl MetricsCollector{
+    /// Start counting from now, for the channel with the given name.
+    pub fn new(name: Option<String>) -> Self{
+        MetricsCollector{
+            name,
+            start: Instant::now(),
+            failed: 0,
+            worker_messages: BTreeMap::new(),
+            worker_busy: BTreeMap::new(),
+            work_times: Vec::new(),
+            latencies: Vec::new(),
+        }
+    }
+
+    /// Register a package retrieved from the workers.
+    pub fn record<S, E>(&mut self, package: &Package<S, E>){
+        if package.error.is_some(){
+            self.failed += 1;
+        }
+        *self.worker_messages.entry(package.worker_id).or_insert(0) += 1;
+        *self.worker_busy.entry(package.worker_id).or_default() += package.work_time;
+        self.work_times.push(package.work_time);
+        self.latencies.push(package.completed_at.saturating_duration_since(package.dispatched_at));
+    }
+
+    /// Forget everything recorded so far and start counting from now.
+    pub fn reset(&mut self){
+        *self = MetricsCollector::new(self.name.take());
+    }
+
+    /// Copy of what was recorded so far.
+    pub fn snapshot(&self) -> ChannelStats{
+        let mut work_times = self.work_times.clone();
+        work_times.sort();
+        let mut latencies = self.latencies.clone();
+        latencies.sort();
+        ChannelStats{
+            name: self.name.clone(),
+            elapsed: self.start.elapsed(),
+            failed: self.failed,
+            worker_messages: self.worker_messages.clone(),
+            worker_busy: self.worker_busy.clone(),
+            work_times,
+            latencies,
+        }
+    }
+}
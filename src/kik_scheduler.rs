@@ -0,0 +1,293 @@
+//! # Scheduler
+//!
+//! Decides which of the queued inputs the feeder dispatches next.
+//!
+//! *FeederRecycler* doesn't store the inputs itself. Every input fed into the *DeliveryService* is pushed into a *Scheduler*,
+//! and every time a *Message* slot frees up the feeder asks the *Scheduler* for the next input to send to the workers.
+//! All the workers pull from the same inserter channel, so the *Scheduler* decides the order of dispatch, not the worker.
+//!
+//! Three implementations are provided:
+//!
+//! - *FifoScheduler*: dispatches inputs in the same order they were fed. The default.
+//!
+//! - *LifoScheduler*: dispatches the last input fed first. This is how the feeder used to behave. Pick it with *ChannelConfig::set_dispatch_order*.
+//!
+//! - *DeadlineScheduler*: dispatches inputs by their *DeadlineClass*, the most urgent first. Inputs say which class they are by implementing *DeadlineInput*,
+//!   so application code tells what an input is for ("this is an interactive tile") instead of picking raw priorities. Inputs that waited past
+//!   their class' budget can be shed instead of dispatched.
+//!
+//! Implement the trait for custom policies (deadline-aware, fair-share, ...) and set it with *DeliveryService::set_scheduler*.
+//!
+//! The *Scheduler* only orders the inputs fed with *Priority::Normal*. Inputs fed with *DeliveryService::feed_feeder_with_priority* as *Priority::High*
+//! are dispatched before any of them, and *Priority::Low* ones after all of them, each lane in the order it was fed.
+//!
+//! The provided schedulers remember the *BatchId* of each input, so that *DeliveryService::iter_batch* can tell the results apart however
+//! the batches are mixed. A custom scheduler can do the same by overriding *push_batch* and *next_batch*. If it doesn't, the feeder still knows
+//! the batch while every input in the scheduler comes from the same one, which covers feeding a batch only after the one before was dispatched.
+//!
+//!
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use crate::kik_sender::BatchId;
+
+/// Which of the provided schedulers a new channel starts with. Set with *ChannelConfig::set_dispatch_order*.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum DispatchOrder{
+    /// Inputs are dispatched in the order they were fed, with *FifoScheduler*.
+    #[default]
+    Fifo,
+    /// The last input fed is dispatched first, with *LifoScheduler*.
+    Lifo,
+}
+
+
+/// Lane an input is fed into with *DeliveryService::feed_feeder_with_priority*. See the module documentation.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Priority{
+    /// Dispatched before everything else, like tiles near the viewport.
+    High,
+    /// Ordered by the *Scheduler*. What *feed_feeder* uses.
+    #[default]
+    Normal,
+    /// Dispatched once nothing else is waiting, like tiles off screen.
+    Low,
+}
+
+
+/// Queue of inputs waiting to be dispatched by the feeder. Must be *Send* so that the *DeliveryService* holding it can be moved between threads.
+pub trait Scheduler<R>: Send{
+    /// Add the inputs to the queue. Borrowed vector will become empty.
+    fn push(&mut self, input_vec: &mut Vec<R>);
+
+    /// Remove and return the input that should be dispatched next. None if there's nothing queued.
+    fn next(&mut self) -> Option<R>;
+
+    /// How many inputs are queued.
+    fn len(&self) -> usize;
+
+    /// Same as *push*, for inputs that were fed together as a batch. Override it together with *next_batch* to keep the batch of each input.
+    /// By default the batch is forgotten.
+    fn push_batch(&mut self, input_vec: &mut Vec<R>, batch: BatchId){
+        let _ = batch;
+        self.push(input_vec);
+    }
+
+    /// Same as *next*, also returning the batch the input was pushed with. None for the batch if it wasn't kept.
+    fn next_batch(&mut self) -> Option<(R, Option<BatchId>)>{
+        self.next().map(|input| (input, None))
+    }
+
+    /// True if there are no inputs queued.
+    fn is_empty(&self) -> bool{
+        self.len() == 0
+    }
+}
+
+
+/// Dispatches the last input fed first. Used when *ChannelConfig::set_dispatch_order* is *DispatchOrder::Lifo*.
+pub struct LifoScheduler<R>{
+    // Each input with its batch.
+    input_vec: Vec<(R, Option<BatchId>)>,
+}
+
+impl<R> LifoScheduler<R>{
+    /// Create an empty scheduler.
+    pub fn new() -> Self{
+        LifoScheduler{
+            input_vec: Vec::new(),
+        }
+    }
+}
+
+impl<R> Default for LifoScheduler<R>{
+    fn default() -> Self{
+        Self::new()
+    }
+}
+
+impl<R> Scheduler<R> for LifoScheduler<R> where R: Send{
+    fn push(&mut self, input_vec: &mut Vec<R>){
+        self.input_vec.extend(input_vec.drain(..).map(|input| (input, None)));
+    }
+
+    fn next(&mut self) -> Option<R>{
+        self.next_batch().map(|(input, _)| input)
+    }
+
+    fn push_batch(&mut self, input_vec: &mut Vec<R>, batch: BatchId){
+        self.input_vec.extend(input_vec.drain(..).map(|input| (input, Some(batch))));
+    }
+
+    fn next_batch(&mut self) -> Option<(R, Option<BatchId>)>{
+        self.input_vec.pop()
+    }
+
+    fn len(&self) -> usize{
+        self.input_vec.len()
+    }
+}
+
+
+/// Dispatches inputs in the same order they were fed. Default scheduler.
+pub struct FifoScheduler<R>{
+    // Each input with its batch.
+    input_queue: VecDeque<(R, Option<BatchId>)>,
+}
+
+impl<R> FifoScheduler<R>{
+    /// Create an empty scheduler.
+    pub fn new() -> Self{
+        FifoScheduler{
+            input_queue: VecDeque::new(),
+        }
+    }
+}
+
+impl<R> Default for FifoScheduler<R>{
+    fn default() -> Self{
+        Self::new()
+    }
+}
+
+impl<R> Scheduler<R> for FifoScheduler<R> where R: Send{
+    fn push(&mut self, input_vec: &mut Vec<R>){
+        self.input_queue.extend(input_vec.drain(..).map(|input| (input, None)));
+    }
+
+    fn next(&mut self) -> Option<R>{
+        self.next_batch().map(|(input, _)| input)
+    }
+
+    fn push_batch(&mut self, input_vec: &mut Vec<R>, batch: BatchId){
+        self.input_queue.extend(input_vec.drain(..).map(|input| (input, Some(batch))));
+    }
+
+    fn next_batch(&mut self) -> Option<(R, Option<BatchId>)>{
+        self.input_queue.pop_front()
+    }
+
+    fn len(&self) -> usize{
+        self.input_queue.len()
+    }
+}
+
+
+/// How soon an input must be dispatched. Classes are ordered from the most urgent to the least.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum DeadlineClass{
+    /// Someone is waiting on it right now, like a tile on screen. Late after 16 ms, about one frame.
+    Interactive,
+    /// Needed shortly, like a tile about to scroll in. Late after 250 ms.
+    Soon,
+    /// Needed eventually. Never late.
+    Background,
+}
+
+impl DeadlineClass{
+    /// How long an input of this class can wait in the scheduler before it's late. None if it's never late.
+    pub fn get_budget(&self) -> Option<Duration>{
+        match self{
+            DeadlineClass::Interactive => Some(Duration::from_millis(16)),
+            DeadlineClass::Soon => Some(Duration::from_millis(250)),
+            DeadlineClass::Background => None,
+        }
+    }
+
+    // Lane of this class in DeadlineScheduler.
+    fn lane(&self) -> usize{
+        match self{
+            DeadlineClass::Interactive => 0,
+            DeadlineClass::Soon => 1,
+            DeadlineClass::Background => 2,
+        }
+    }
+}
+
+/// Implemented by inputs that can be scheduled by *DeadlineScheduler*.
+pub trait DeadlineInput{
+    /// How soon this input must be dispatched.
+    fn deadline_class(&self) -> DeadlineClass;
+}
+
+
+/// Dispatches the inputs of the most urgent *DeadlineClass* first, each class in the order it was fed.
+/// 
+/// By default every input is dispatched, even late ones. With *set_shedding*, an input that waited past its class' budget is handed to the sink
+/// instead, so the workers don't spend time on results nobody wants anymore.
+pub struct DeadlineScheduler<R>{
+    // One lane for each class, with the time each input was pushed and its batch.
+    lanes: [VecDeque<(Instant, R, Option<BatchId>)>; 3],
+    shed_sink: Option<Box<dyn FnMut(R) + Send>>,
+}
+
+impl<R> DeadlineScheduler<R>{
+    /// Create an empty scheduler that sheds nothing.
+    pub fn new() -> Self{
+        DeadlineScheduler{
+            lanes: [VecDeque::new(), VecDeque::new(), VecDeque::new()],
+            shed_sink: None,
+        }
+    }
+
+    /// Hand inputs that are late to the sink instead of dispatching them. The sink runs on the thread iterating the channel.
+    pub fn set_shedding<F>(&mut self, sink: F) where F: FnMut(R) + Send + 'static{
+        self.shed_sink = Some(Box::new(sink));
+    }
+
+    /// Dispatch late inputs again, instead of shedding them.
+    pub fn clear_shedding(&mut self){
+        self.shed_sink = None;
+    }
+}
+
+impl<R> DeadlineScheduler<R> where R: DeadlineInput{
+    // Put each input in the lane of its class.
+    fn push_with(&mut self, input_vec: &mut Vec<R>, batch: Option<BatchId>){
+        let now = Instant::now();
+        for input in input_vec.drain(..){
+            self.lanes[input.deadline_class().lane()].push_back((now, input, batch));
+        }
+    }
+}
+
+impl<R> Default for DeadlineScheduler<R>{
+    fn default() -> Self{
+        Self::new()
+    }
+}
+
+impl<R> Scheduler<R> for DeadlineScheduler<R> where R: DeadlineInput + Send{
+    fn push(&mut self, input_vec: &mut Vec<R>){
+        self.push_with(input_vec, None);
+    }
+
+    fn next(&mut self) -> Option<R>{
+        self.next_batch().map(|(input, _)| input)
+    }
+
+    fn push_batch(&mut self, input_vec: &mut Vec<R>, batch: BatchId){
+        self.push_with(input_vec, Some(batch));
+    }
+
+    fn next_batch(&mut self) -> Option<(R, Option<BatchId>)>{
+        for lane in self.lanes.iter_mut(){
+            while let Some((pushed, input, batch)) = lane.pop_front(){
+                let late = match input.deadline_class().get_budget(){
+                    Some(budget) => pushed.elapsed() > budget,
+                    None => false,
+                };
+                match &mut self.shed_sink{
+                    Some(sink) if late => sink(input),
+                    _ => return Some((input, batch)),
+                }
+            }
+        }
+        None
+    }
+
+    fn len(&self) -> usize{
+        self.lanes.iter().map(VecDeque::len).sum()
+    }
+}
@@ -0,0 +1,189 @@
+//! # Select
+//!
+//! A *MultiChannel* iterates over the results of several *DeliveryService*s at once, yielding whichever is ready first. Each channel
+//! is added with a function that turns its results into the item type of the *MultiChannel*, usually a variant of an enum, so channels
+//! with different **T**s can be merged:
+//!
+//! ```ignore
+//! enum Media{
+//!     Audio(Samples),
+//!     Video(Frame),
+//! }
+//!
+//! let results = MultiChannel::new().with(&mut audio, Media::Audio).with(&mut video, Media::Video);
+//! for media in results{
+//!     match media{
+//!         Media::Audio(samples) => play(samples),
+//!         Media::Video(frame) => draw(frame),
+//!     }
+//! }
+//! ```
+//!
+//! The channels are polled in turn, starting after the one that gave the last result, so a busy channel can't starve the others.
+//! When none has a result ready, the thread backs off like the workers do (see kik_backoff), then waits on each channel in turn for
+//! at most *POLL_SLICE*. A result can be noticed that much late, but an idle *MultiChannel* doesn't keep a core busy.
+//!
+//! The iteration ends once every channel is empty. Channels in keep-alive mode aren't waited for once they're empty, use *try_next* to
+//! keep polling them. Failed messages are skipped, the *last_stop_reason* of their channel tells about them.
+//!
+//!
+
+use std::time::Duration;
+
+use crate::kik_message::{Message, MessageInput, MessageData};
+use crate::kik_channel::DeliveryService;
+use crate::kik_backoff::Backoff;
+use crate::kik_error::Timeout;
+
+/// Longest wait on a single channel while none of them has a result ready.
+pub const POLL_SLICE: Duration = Duration::from_millis(1);
+
+// A channel and the function that maps its results, behind a trait object so that channels of different types fit in one list.
+trait Source<U>{
+    fn try_next(&mut self) -> Option<U>;
+    fn next_timeout(&mut self, timeout: Duration) -> Result<Option<U>, Timeout>;
+    fn is_empty(&mut self) -> bool;
+}
+
+struct MappedChannel<'a, T, R, S, E, F> where
+T: MessageData + 'static,
+R: MessageInput<T> + 'static,
+S: Message<T, R, E> + Sync + Send + 'static,
+E: Send + 'static,
+{
+    channel: &'a mut DeliveryService<T, R, S, E>,
+    map: F,
+}
+
+impl<T, R, S, E, F, U> Source<U> for MappedChannel<'_, T, R, S, E, F> where
+T: MessageData + 'static,
+R: MessageInput<T> + 'static,
+S: Message<T, R, E> + Sync + Send + 'static,
+E: Send + 'static,
+F: FnMut(T) -> U,
+{
+    fn try_next(&mut self) -> Option<U>{
+        self.channel.try_next().map(&mut self.map)
+    }
+
+    fn next_timeout(&mut self, timeout: Duration) -> Result<Option<U>, Timeout>{
+        Ok(self.channel.next_timeout(timeout)?.map(&mut self.map))
+    }
+
+    fn is_empty(&mut self) -> bool{
+        self.channel.is_empty()
+    }
+}
+
+/// Results of several channels, merged into one iterator. See the select module.
+pub struct MultiChannel<'a, U>{
+    sources: Vec<Box<dyn Source<U> + 'a>>,
+    // Where the next poll starts.
+    next_source: usize,
+    last_source: Option<usize>,
+}
+
+impl<'a, U> MultiChannel<'a, U>{
+    /// A *MultiChannel* with no channels. It ends right away until some are added.
+    pub fn new() -> Self{
+        MultiChannel{
+            sources: Vec::new(),
+            next_source: 0,
+            last_source: None,
+        }
+    }
+
+    /// Add a channel, with the function that turns each of its results into an item. Channels are numbered in the order they're added, from 0.
+    pub fn with<T, R, S, E, F>(mut self, channel: &'a mut DeliveryService<T, R, S, E>, map: F) -> Self where
+    T: MessageData + 'static,
+    R: MessageInput<T> + 'static,
+    S: Message<T, R, E> + Sync + Send + 'static,
+    E: Send + 'static,
+    F: FnMut(T) -> U + 'a,
+    {
+        self.sources.push(Box::new(MappedChannel{ channel, map }));
+        self
+    }
+
+    /// How many channels were added.
+    pub fn len(&self) -> usize{
+        self.sources.len()
+    }
+
+    /// True if no channel was added.
+    pub fn is_empty(&self) -> bool{
+        self.sources.is_empty()
+    }
+
+    /// Number of the channel that gave the last item, in the order they were added. None before the first one.
+    pub fn get_last_source(&self) -> Option<usize>{
+        self.last_source
+    }
+
+    /// Return a result only if one of the channels already has one waiting, without blocking. Each channel is polled once at most.
+    /// None doesn't mean the channels are done, just that nothing arrived yet.
+    pub fn try_next(&mut self) -> Option<U>{
+        for _ in 0..self.sources.len(){
+            let index = self.advance();
+            if let Some(item) = self.sources[index].try_next(){
+                self.last_source = Some(index);
+                return Some(item);
+            }
+        }
+        None
+    }
+
+    /// True once every channel is empty.
+    fn is_done(&mut self) -> bool{
+        self.sources.iter_mut().all(|source| source.is_empty())
+    }
+
+    /// The channel to poll now. The next poll starts after it.
+    fn advance(&mut self) -> usize{
+        let index = self.next_source % self.sources.len();
+        self.next_source = index + 1;
+        index
+    }
+}
+
+impl<U> Default for MultiChannel<'_, U>{
+    fn default() -> Self{
+        Self::new()
+    }
+}
+
+impl<U> Iterator for MultiChannel<'_, U>{
+    type Item = U;
+
+    fn next(&mut self) -> Option<U>{
+        if self.sources.is_empty(){
+            return None;
+        }
+        // Channels whose run ended while waiting on them, so that one that fails doesn't keep the others waiting forever.
+        let mut ended = vec![false; self.sources.len()];
+        let mut backoff = Backoff::new();
+        loop{
+            if let Some(item) = self.try_next(){
+                return Some(item);
+            }
+            if self.is_done(){
+                return None;
+            }
+            if backoff.retry(){
+                continue;
+            }
+            let index = self.advance();
+            match self.sources[index].next_timeout(POLL_SLICE){
+                Ok(Some(item)) => {
+                    self.last_source = Some(index);
+                    return Some(item);
+                },
+                Ok(None) => ended[index] = true,
+                Err(Timeout) => {},
+            }
+            if ended.iter().all(|ended| *ended){
+                return None;
+            }
+        }
+    }
+}
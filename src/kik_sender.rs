@@ -0,0 +1,464 @@
+//! # Sender
+//!
+//! Handles for feeding a *DeliveryService* from somewhere else than the owner of the channel.
+//!
+//! A *WeakInputSender* only holds a *Weak* reference to the channel's inbox. It can be cloned into as many producers as needed
+//! without keeping the channel alive: once the *DeliveryService* is dropped, every feed returns a *Closed* error. This avoids
+//! reference cycles when several subsystems hold senders into a channel owned elsewhere.
+//!
+//! Inputs sent through the handle are moved into the feeder the next time the owner iterates or calls *len*.
+//!
+//! If the channel is in keep-alive mode (*ChannelConfig::set_keep_alive*), sending also wakes up the owner waiting for inputs. Any sender
+//! can end the stream with *close_input*, after which every feed returns *Closed*.
+//!
+//! A *FeederHandle* is one half of *DeliveryService::split*. Unlike *WeakInputSender*, it wakes up the *ResultReceiver* waiting for inputs,
+//! and the *ResultReceiver* only ends once every *FeederHandle* is dropped. It can be cloned into as many producers as needed.
+//!
+//! A *FeedReceipt* is returned by *DeliveryService::feed_feeder_ack*. It tells when every input of that call has been sent to the workers,
+//! so that a producer on another thread can throttle itself on actual dispatch progress instead of on how much it has queued.
+//!
+//! A *JobHandle* is returned by *DeliveryService::submit*. It gets the result of that single input instead of the iterator, for
+//! request/response code that offloads one computation and waits for its answer, while the rest of the channel streams as usual.
+//!
+//!
+
+use std::convert::Infallible;
+use std::sync::{Arc, Mutex, Weak, Condvar, PoisonError};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{Receiver, RecvTimeoutError, TryRecvError};
+use std::time::{Duration, Instant};
+use std::vec::Drain;
+
+use crate::kik_error::{Closed, Timeout, WorkError};
+use crate::kik_scheduler::Priority;
+
+/// Inputs waiting to be moved into the feeder, fed with *DeliveryService::feed_feeder* or through a sender. Shared between *DeliveryService* and its senders.
+pub type Inbox<R> = Arc<Mutex<InboxInputs<R>>>;
+
+/// Identifies the inputs of one call to *DeliveryService::feed_feeder*, and the results they generate. See *DeliveryService::iter_batch*.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BatchId(usize);
+
+/// What an *Inbox* holds: the inputs in the order they were fed, and the batch each of them belongs to.
+#[derive(Default)]
+pub struct InboxInputs<R>{
+    inputs: Vec<R>,
+    // Each batch with how many of the inputs are its own and its lane, in the same order as the inputs.
+    batches: Vec<(BatchId, usize, Priority)>,
+    next_batch: usize,
+}
+
+impl<R> InboxInputs<R>{
+    /// Create an empty inbox.
+    pub fn new() -> Self{
+        InboxInputs{
+            inputs: Vec::new(),
+            batches: Vec::new(),
+            next_batch: 0,
+        }
+    }
+
+    /// Add a single input as a batch of its own, with *Priority::Normal*.
+    pub fn push(&mut self, input: R) -> BatchId{
+        self.inputs.push(input);
+        self.new_batch(1, Priority::Normal)
+    }
+
+    /// Add the inputs as a new batch. Borrowed vector will become empty.
+    pub fn append(&mut self, input_vec: &mut Vec<R>, priority: Priority) -> BatchId{
+        let count = input_vec.len();
+        self.inputs.append(input_vec);
+        self.new_batch(count, priority)
+    }
+
+    /// Give an id to the last inputs added.
+    fn new_batch(&mut self, count: usize, priority: Priority) -> BatchId{
+        let batch = BatchId(self.next_batch);
+        self.next_batch += 1;
+        // An empty batch has nothing to wait for.
+        if count > 0{
+            self.batches.push((batch, count, priority));
+        }
+        batch
+    }
+
+    /// How many inputs are waiting.
+    pub fn len(&self) -> usize{
+        self.inputs.len()
+    }
+
+    /// True if there are no inputs waiting.
+    pub fn is_empty(&self) -> bool{
+        self.inputs.is_empty()
+    }
+
+    /// Hand every input over, one batch at a time, in the order they were fed. The inbox keeps its buffers.
+    pub fn drain_batches<F>(&mut self, mut receive: F) where F: FnMut(BatchId, Priority, Drain<'_, R>){
+        for (batch, count, priority) in self.batches.drain(..){
+            receive(batch, priority, self.inputs.drain(..count));
+        }
+    }
+}
+
+/// Cloneable handle for feeding inputs into a *DeliveryService* without keeping it alive. Created by *DeliveryService::weak_sender*.
+pub struct WeakInputSender<R>{
+    inbox: Weak<Mutex<InboxInputs<R>>>,
+    // Only flags, holding it doesn't keep the channel alive.
+    gate: Arc<InputGate>,
+}
+
+impl<R> WeakInputSender<R>{
+    /// Construct a new sender for the given inbox.
+    pub fn new(inbox: &Inbox<R>, gate: Arc<InputGate>) -> Self{
+        WeakInputSender{
+            inbox: Arc::downgrade(inbox),
+            gate,
+        }
+    }
+
+    /// Send a single input. Returns *Closed* if the channel has been dropped or its input closed.
+    pub fn send(&self, input: R) -> Result<(), Closed>{
+        let inbox = self.open_inbox()?;
+        inbox.lock().unwrap_or_else(PoisonError::into_inner).push(input);
+        self.gate.ready.notify_all();
+        Ok(())
+    }
+
+    /// Borrows a vector of inputs and append the values into the channel. Borrowed vector will become empty.
+    /// Returns *Closed* if the channel has been dropped or its input closed, in which case the vector is left untouched.
+    pub fn feed(&self, input_vec: &mut Vec<R>) -> Result<(), Closed>{
+        let inbox = self.open_inbox()?;
+        inbox.lock().unwrap_or_else(PoisonError::into_inner).append(input_vec, Priority::Normal);
+        self.gate.ready.notify_all();
+        Ok(())
+    }
+
+    /// Same as *DeliveryService::close_input*. Does nothing if the channel has been dropped.
+    pub fn close_input(&self){
+        if let Some(inbox) = self.inbox.upgrade(){
+            self.gate.close(&inbox);
+        }
+    }
+
+    /// True if the channel has been dropped or its input closed.
+    pub fn is_closed(&self) -> bool{
+        self.inbox.strong_count() == 0 || self.gate.is_closed()
+    }
+
+    /// The inbox, if feeding it is still allowed.
+    fn open_inbox(&self) -> Result<Inbox<R>, Closed>{
+        let inbox = self.inbox.upgrade().ok_or(Closed)?;
+        if self.gate.is_closed(){
+            return Err(Closed);
+        }
+        Ok(inbox)
+    }
+}
+
+// derive(Clone) would require R: Clone.
+impl<R> Clone for WeakInputSender<R>{
+    fn clone(&self) -> Self{
+        WeakInputSender{
+            inbox: Weak::clone(&self.inbox),
+            gate: Arc::clone(&self.gate),
+        }
+    }
+}
+
+
+/// Shared by a *DeliveryService* and its *WeakInputSender*s. Lets a keep-alive channel sleep until inputs arrive or the input is closed.
+#[derive(Default)]
+pub struct InputGate{
+    // Waited on with the inbox lock held.
+    ready: Condvar,
+    closed: AtomicBool,
+}
+
+impl InputGate{
+    /// Gate for a channel whose input is open.
+    pub fn new() -> Self{
+        Self::default()
+    }
+
+    /// Sleep until the inbox has inputs. Returns false instead if it's empty and the input was closed.
+    pub fn wait_for_inputs<R>(&self, inbox: &Inbox<R>) -> bool{
+        let mut inputs = inbox.lock().unwrap_or_else(PoisonError::into_inner);
+        while inputs.is_empty(){
+            if self.is_closed(){
+                return false;
+            }
+            inputs = self.ready.wait(inputs).unwrap_or_else(PoisonError::into_inner);
+        }
+        true
+    }
+
+    /// Same as *wait_for_inputs*, but gives up at the deadline with *Timeout*.
+    pub fn wait_for_inputs_until<R>(&self, inbox: &Inbox<R>, deadline: Instant) -> Result<bool, Timeout>{
+        let mut inputs = inbox.lock().unwrap_or_else(PoisonError::into_inner);
+        while inputs.is_empty(){
+            if self.is_closed(){
+                return Ok(false);
+            }
+            let now = Instant::now();
+            if now >= deadline{
+                return Err(Timeout);
+            }
+            inputs = self.ready.wait_timeout(inputs, deadline - now).unwrap_or_else(PoisonError::into_inner).0;
+        }
+        Ok(true)
+    }
+
+    /// Close the input for good and wake up whoever is waiting for it.
+    pub fn close<R>(&self, inbox: &Inbox<R>){
+        self.closed.store(true, Ordering::SeqCst);
+        // Taking the lock makes sure the owner is either waiting already, or will see the flag before it waits.
+        let _inputs = inbox.lock().unwrap_or_else(PoisonError::into_inner);
+        self.ready.notify_all();
+    }
+
+    /// True once the input was closed.
+    pub fn is_closed(&self) -> bool{
+        self.closed.load(Ordering::SeqCst)
+    }
+}
+
+
+/// Shared by the halves of a split channel. Tells the *ResultReceiver* when inputs arrive or producers leave, and the producers when it's gone.
+#[derive(Default)]
+pub struct FeedSignal{
+    // Waited on with the inbox lock held.
+    ready: Condvar,
+    handles: AtomicUsize,
+    closed: AtomicBool,
+}
+
+impl FeedSignal{
+    /// Signal for a channel fed by a single *FeederHandle*.
+    pub fn new() -> Self{
+        FeedSignal{
+            handles: AtomicUsize::new(1),
+            ..FeedSignal::default()
+        }
+    }
+
+    /// Sleep until the inbox has inputs. Returns false instead if it's empty and every *FeederHandle* is gone.
+    pub fn wait_for_inputs<R>(&self, inbox: &Inbox<R>) -> bool{
+        let mut inputs = inbox.lock().unwrap_or_else(PoisonError::into_inner);
+        while inputs.is_empty(){
+            if self.handles.load(Ordering::SeqCst) == 0{
+                return false;
+            }
+            inputs = self.ready.wait(inputs).unwrap_or_else(PoisonError::into_inner);
+        }
+        true
+    }
+
+    /// Tell the producers that nobody will take their inputs anymore. Used when the *ResultReceiver* is dropped.
+    pub fn close(&self){
+        self.closed.store(true, Ordering::SeqCst);
+    }
+}
+
+/// Cloneable handle for feeding a split channel from other threads. Created by *DeliveryService::split*.
+pub struct FeederHandle<R>{
+    inbox: Inbox<R>,
+    signal: Arc<FeedSignal>,
+}
+
+impl<R> FeederHandle<R>{
+    /// Construct the first handle of a split channel. The signal must count it already.
+    pub fn new(inbox: Inbox<R>, signal: Arc<FeedSignal>) -> Self{
+        FeederHandle{
+            inbox,
+            signal,
+        }
+    }
+
+    /// Send a single input. Returns *Closed* if the *ResultReceiver* has been dropped.
+    pub fn send(&self, input: R) -> Result<(), Closed>{
+        if self.is_closed(){
+            return Err(Closed);
+        }
+        self.inbox.lock().unwrap_or_else(PoisonError::into_inner).push(input);
+        self.signal.ready.notify_all();
+        Ok(())
+    }
+
+    /// Borrows a vector of inputs and append the values into the channel. Borrowed vector will become empty.
+    /// Returns *Closed* if the *ResultReceiver* has been dropped, in which case the vector is left untouched.
+    pub fn feed(&self, input_vec: &mut Vec<R>) -> Result<(), Closed>{
+        if self.is_closed(){
+            return Err(Closed);
+        }
+        self.inbox.lock().unwrap_or_else(PoisonError::into_inner).append(input_vec, Priority::Normal);
+        self.signal.ready.notify_all();
+        Ok(())
+    }
+
+    /// True if the *ResultReceiver* has been dropped.
+    pub fn is_closed(&self) -> bool{
+        self.signal.closed.load(Ordering::SeqCst)
+    }
+}
+
+// derive(Clone) would require R: Clone.
+impl<R> Clone for FeederHandle<R>{
+    fn clone(&self) -> Self{
+        self.signal.handles.fetch_add(1, Ordering::SeqCst);
+        FeederHandle{
+            inbox: Arc::clone(&self.inbox),
+            signal: Arc::clone(&self.signal),
+        }
+    }
+}
+
+impl<R> Drop for FeederHandle<R>{
+    fn drop(&mut self){
+        self.signal.handles.fetch_sub(1, Ordering::SeqCst);
+        // Taking the lock makes sure the receiver is either waiting already, or will see the new count before it waits.
+        let _inputs = self.inbox.lock().unwrap_or_else(PoisonError::into_inner);
+        self.signal.ready.notify_all();
+    }
+}
+
+
+// How many inputs of the call are still waiting, and how many were thrown away without being sent.
+#[derive(Default)]
+struct ReceiptState{
+    remaining: usize,
+    abandoned: usize,
+}
+
+/// Tells when the inputs of a *DeliveryService::feed_feeder_ack* call have been sent to the workers. Can be cloned and sent to other threads.
+/// 
+/// Inputs are only sent while the owner of the channel iterates, so waiting on the thread that iterates would never return.
+#[derive(Clone, Default)]
+pub struct FeedReceipt{
+    state: Arc<(Mutex<ReceiptState>, Condvar)>,
+}
+
+impl FeedReceipt{
+    /// Create a receipt for the given number of inputs. Used by kik_feeder.
+    pub fn new(inputs: usize) -> Self{
+        FeedReceipt{
+            state: Arc::new((Mutex::new(ReceiptState{remaining: inputs, abandoned: 0}), Condvar::new())),
+        }
+    }
+
+    /// Record that one of the inputs was sent. Used by kik_feeder.
+    pub fn dispatch_one(&self){
+        self.update(|state| state.remaining -= 1);
+    }
+
+    /// Record that one of the inputs was thrown away, by a cancellation or a shutdown. Used by kik_feeder.
+    pub fn abandon_one(&self){
+        self.update(|state| {
+            state.remaining -= 1;
+            state.abandoned += 1;
+        });
+    }
+
+    /// Change the state and wake up whoever is waiting.
+    fn update<F>(&self, change: F) where F: FnOnce(&mut ReceiptState){
+        let (state, condvar) = &*self.state;
+        change(&mut state.lock().unwrap_or_else(PoisonError::into_inner));
+        condvar.notify_all();
+    }
+
+    /// How many inputs of the call haven't been sent yet.
+    pub fn get_remaining(&self) -> usize{
+        self.state.0.lock().unwrap_or_else(PoisonError::into_inner).remaining
+    }
+
+    /// How many inputs of the call were thrown away without being sent.
+    pub fn get_abandoned(&self) -> usize{
+        self.state.0.lock().unwrap_or_else(PoisonError::into_inner).abandoned
+    }
+
+    /// True if every input of the call has been sent to the workers.
+    pub fn is_dispatched(&self) -> bool{
+        let state = self.state.0.lock().unwrap_or_else(PoisonError::into_inner);
+        state.remaining == 0 && state.abandoned == 0
+    }
+
+    /// Block until no input of the call is waiting anymore. Returns true if they were all sent, false if some were thrown away.
+    pub fn wait(&self) -> bool{
+        let (state, condvar) = &*self.state;
+        let mut state = state.lock().unwrap_or_else(PoisonError::into_inner);
+        while state.remaining > 0{
+            state = condvar.wait(state).unwrap_or_else(PoisonError::into_inner);
+        }
+        state.abandoned == 0
+    }
+
+    /// Same as *wait*, but gives up after the timeout. Returns true only if every input was sent in time.
+    pub fn wait_timeout(&self, timeout: Duration) -> bool{
+        let deadline = Instant::now() + timeout;
+        let (state, condvar) = &*self.state;
+        let mut state = state.lock().unwrap_or_else(PoisonError::into_inner);
+        while state.remaining > 0{
+            let now = Instant::now();
+            if now >= deadline{
+                return false;
+            }
+            state = condvar.wait_timeout(state, deadline - now).unwrap_or_else(PoisonError::into_inner).0;
+        }
+        state.abandoned == 0
+    }
+}
+
+
+/// Result of a single input fed with *DeliveryService::submit*. Can be sent to another thread and waited on there.
+/// 
+/// The result is handed over while the owner of the channel iterates, so waiting on the thread that iterates would never return.
+/// Until then it counts in *DeliveryService::len* like any other input, but it's never yielded by the iterators.
+pub struct JobHandle<T, E = Infallible>{
+    receiver: Receiver<Result<T, WorkError<E>>>,
+    // Taken from the receiver by is_ready, for wait.
+    received: Option<Result<T, WorkError<E>>>,
+}
+
+impl<T, E> JobHandle<T, E>{
+    /// Create a handle that gets its result from the receiver. Used by kik_channel.
+    pub fn new(receiver: Receiver<Result<T, WorkError<E>>>) -> Self{
+        JobHandle{
+            receiver,
+            received: None,
+        }
+    }
+
+    /// True once the result arrived, or once it's known it never will. Doesn't block.
+    pub fn is_ready(&mut self) -> bool{
+        if self.received.is_some(){
+            return true;
+        }
+        match self.receiver.try_recv(){
+            Ok(result) => {
+                self.received = Some(result);
+                true
+            },
+            Err(TryRecvError::Disconnected) => true,
+            Err(TryRecvError::Empty) => false,
+        }
+    }
+
+    /// Block until the result arrives. None if it never will: the run was cancelled, the result expired or the channel was dropped.
+    pub fn wait(mut self) -> Option<Result<T, WorkError<E>>>{
+        match self.received.take(){
+            Some(result) => Some(result),
+            None => self.receiver.recv().ok(),
+        }
+    }
+
+    /// Same as *wait*, but gives up after the timeout with *Timeout*. The handle can be waited on again afterwards.
+    pub fn wait_timeout(&mut self, timeout: Duration) -> Result<Option<Result<T, WorkError<E>>>, Timeout>{
+        if let Some(result) = self.received.take(){
+            return Ok(Some(result));
+        }
+        match self.receiver.recv_timeout(timeout){
+            Ok(result) => Ok(Some(result)),
+            Err(RecvTimeoutError::Disconnected) => Ok(None),
+            Err(RecvTimeoutError::Timeout) => Err(Timeout),
+        }
+    }
+}
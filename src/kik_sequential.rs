@@ -0,0 +1,569 @@
+//! # Sequential
+//!
+//! *SequentialDeliveryService* has the same public API as *DeliveryService*, but works every *Message* on the caller's thread while iterating.
+//! No worker threads are ever spawned.
+//!
+//! It's meant for comparisons. Put the channel type behind a type alias, and switch the alias to measure how much the worker threads
+//! actually help against a plain sequential map, without touching the rest of the code:
+//!
+//! ```ignore
+//! type Channel = DeliveryService<MessageArray, Coordinates, ThreadMessage>;
+//! // type Channel = SequentialDeliveryService<MessageArray, Coordinates, ThreadMessage>;
+//! ```
+//!
+//! Worker settings in *ChannelConfig* (worker number, stack size, idle hook) have no effect on it.
+//!
+//!
+
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+use std::time::{Duration, Instant};
+use std::sync::Arc;
+use std::sync::mpsc::Receiver;
+use std::io::{self, Read, Write};
+
+use crate::kik_message::{Message, MessageInput, MessageData, Merge};
+use crate::kik_channel::{ChannelConfig, DeliveryService, DropPolicy, Results, TryIter, BatchIter, IntoIter};
+use crate::kik_report::{AllocStats, BatchReport, ChannelStats, MemoryStats, PoolHealth, ShutdownReport, StackUsage, Throughput, WorkProgress};
+use crate::kik_error::{FailureReason, KikError, StopReason, Timeout};
+use crate::kik_scheduler::Priority;
+use crate::kik_sender::{BatchId, WeakInputSender, FeederHandle, FeedReceipt, JobHandle};
+use crate::kik_split::ResultReceiver;
+use crate::kik_tee::ResultTee;
+use crate::kik_reader::ReadHandle;
+#[cfg(feature = "serde")]
+use serde::{Serialize, de::DeserializeOwned};
+#[cfg(feature = "async")]
+use crate::kik_stream::{ResultStream, AsyncFeeder};
+use crate::kik_scheduler::Scheduler;
+use crate::kik_context::CancellationToken;
+use crate::kik_event::{ChannelObserver, PoolEvent};
+
+/// Drop-in replacement for *DeliveryService* that works every message on the caller's thread. See the module documentation.
+pub struct SequentialDeliveryService<T, R, S, E = Infallible>  where
+T: MessageData + 'static,
+R: MessageInput<T> + 'static,
+S: Message<T, R, E> + Sync + Send + 'static,
+E: Send + 'static,
+{
+    // Always inline.
+    channel: DeliveryService<T, R, S, E>,
+}
+
+impl<T, R, S, E> SequentialDeliveryService<T, R, S, E> where
+T: MessageData + 'static,
+R: MessageInput<T> + 'static,
+S: Message<T, R, E> + Sync + Send + 'static,
+E: Send + 'static,
+{
+    /// Create a new SequentialDeliveryService using details set in ChannelConfig, checked like *DeliveryService::try_new*. The config is always made inline.
+    pub fn try_new(config: ChannelConfig) -> Result<Self, KikError> where S: Default{
+        Self::try_with_builder(config, S::new)
+    }
+
+    /// Same as *DeliveryService::try_with_builder*, for messages without *Default*. The config is always made inline.
+    pub fn try_with_builder(mut config: ChannelConfig, builder: fn() -> S) -> Result<Self, KikError>{
+        config.set_inline(true);
+        Ok(SequentialDeliveryService{
+            channel: DeliveryService::try_with_builder(config, builder)?,
+        })
+    }
+
+    /// Same as *try_new*, without checking the config.
+    #[deprecated(since = "0.8.0", note = "use SequentialDeliveryService::try_new, which returns invalid configs as an error")]
+    pub fn new(mut config: ChannelConfig) -> Self where S: Default{
+        config.set_inline(true);
+        #[allow(deprecated)]
+        SequentialDeliveryService{
+            channel: DeliveryService::new(config),
+        }
+    }
+
+    /// Same as *DeliveryService::feed_feeder*.
+    pub fn feed_feeder(&self, input_vec: &mut Vec<R>) -> BatchId{
+        self.channel.feed_feeder(input_vec)
+    }
+
+    /// Same as *DeliveryService::feed_feeder_with_priority*.
+    pub fn feed_feeder_with_priority(&self, input_vec: &mut Vec<R>, priority: Priority) -> BatchId{
+        self.channel.feed_feeder_with_priority(input_vec, priority)
+    }
+
+    /// Same as *DeliveryService::feed_with_deadline*.
+    pub fn feed_with_deadline(&mut self, input_vec: &mut Vec<R>, deadline: Instant) -> BatchId{
+        self.channel.feed_with_deadline(input_vec, deadline)
+    }
+
+    /// Same as *DeliveryService::feed_feeder_ack*.
+    pub fn feed_feeder_ack(&mut self, input_vec: &mut Vec<R>) -> FeedReceipt{
+        self.channel.feed_feeder_ack(input_vec)
+    }
+
+    /// Same as *DeliveryService::submit*.
+    pub fn submit(&mut self, input: R) -> JobHandle<T, E>{
+        self.channel.submit(input)
+    }
+
+    /// Same as *DeliveryService::feed_generator*.
+    pub fn feed_generator<F>(&mut self, generator: F) where F: FnMut() -> Option<R> + Send + 'static{
+        self.channel.feed_generator(generator);
+    }
+
+    /// Same as *DeliveryService::feed_spilling*.
+    #[cfg(feature = "spill")]
+    pub fn feed_spilling(&mut self, input_vec: &mut Vec<R>) -> io::Result<usize> where R: Serialize + DeserializeOwned{
+        self.channel.feed_spilling(input_vec)
+    }
+
+    /// Same as *DeliveryService::get_spilled_inputs*.
+    #[cfg(feature = "spill")]
+    pub fn get_spilled_inputs(&self) -> usize{
+        self.channel.get_spilled_inputs()
+    }
+
+    /// Same as *DeliveryService::take_spill_error*.
+    #[cfg(feature = "spill")]
+    pub fn take_spill_error(&mut self) -> Option<io::Error>{
+        self.channel.take_spill_error()
+    }
+
+    /// Same as *DeliveryService::feed_reader*.
+    pub fn feed_reader<Rd, F>(&mut self, reader: Rd, chunk_size: usize, convert: F) -> ReadHandle where Rd: Read + Send + 'static, F: FnMut(Vec<u8>) -> R + Send + 'static{
+        self.channel.feed_reader(reader, chunk_size, convert)
+    }
+
+    /// Same as *DeliveryService::set_scheduler*.
+    pub fn set_scheduler<Q>(&mut self, scheduler: Q) where Q: Scheduler<R> + 'static{
+        self.channel.set_scheduler(scheduler);
+    }
+
+    /// Same as *DeliveryService::set_message_template*.
+    pub fn set_message_template(&mut self, template: S) where S: Clone{
+        self.channel.set_message_template(template);
+    }
+
+    /// Same as *DeliveryService::clear_message_template*.
+    pub fn clear_message_template(&mut self){
+        self.channel.clear_message_template();
+    }
+
+    /// Same as *DeliveryService::weak_sender*.
+    pub fn weak_sender(&self) -> WeakInputSender<R>{
+        self.channel.weak_sender()
+    }
+
+    /// Same as *DeliveryService::close_input*.
+    pub fn close_input(&self){
+        self.channel.close_input();
+    }
+
+    /// Same as *DeliveryService::is_input_closed*.
+    pub fn is_input_closed(&self) -> bool{
+        self.channel.is_input_closed()
+    }
+
+    /// Same as *DeliveryService::split*. Messages are worked on the thread that iterates the *ResultReceiver*.
+    pub fn split(self) -> (FeederHandle<R>, ResultReceiver<T>){
+        self.channel.split()
+    }
+
+    /// Same as *DeliveryService::async_feeder*.
+    #[cfg(feature = "async")]
+    pub fn async_feeder(&mut self, budget: usize) -> AsyncFeeder<R>{
+        self.channel.async_feeder(budget)
+    }
+
+    /// Same as *DeliveryService::tee*. Messages are worked on the thread of whichever consumer takes the next result.
+    pub fn tee(self, consumers: usize, capacity: Option<usize>) -> Vec<ResultTee<T>>{
+        self.channel.tee(consumers, capacity)
+    }
+
+    /// Same as *DeliveryService::into_stream*. Messages are worked on the stream's thread.
+    #[cfg(feature = "async")]
+    pub fn into_stream(self) -> ResultStream<T>{
+        self.channel.into_stream()
+    }
+
+    /// Same as *DeliveryService::len*.
+    pub fn len(&mut self) -> usize{
+        self.channel.len()
+    }
+
+    /// Same as *DeliveryService::has_pending*.
+    pub fn has_pending(&mut self) -> bool{
+        self.channel.has_pending()
+    }
+
+    /// Same as *DeliveryService::result_bounds*.
+    pub fn result_bounds(&self) -> (usize, Option<usize>){
+        self.channel.result_bounds()
+    }
+
+    /// Same as *DeliveryService::cancel*.
+    pub fn cancel(&mut self) -> usize{
+        self.channel.cancel()
+    }
+
+    /// Same as *DeliveryService::pause*. Nothing is being worked between iterations, so only the iteration stops.
+    pub fn pause(&mut self){
+        self.channel.pause()
+    }
+
+    /// Same as *DeliveryService::resume*.
+    pub fn resume(&mut self){
+        self.channel.resume()
+    }
+
+    /// Same as *DeliveryService::is_paused*.
+    pub fn is_paused(&self) -> bool{
+        self.channel.is_paused()
+    }
+
+    /// Same as *DeliveryService::health*. There are no workers to list, only the queues.
+    pub fn health(&mut self) -> PoolHealth{
+        self.channel.health()
+    }
+
+    /// Same as *DeliveryService::cancellation_token*.
+    pub fn cancellation_token(&self) -> CancellationToken{
+        self.channel.cancellation_token()
+    }
+
+    /// Same as *DeliveryService::events*. There are no workers, so only batch events are sent.
+    pub fn events(&self) -> Receiver<PoolEvent>{
+        self.channel.events()
+    }
+
+    /// Same as *DeliveryService::add_observer*. There are no workers, so *on_work_start* and *on_complete* are called on the caller's thread, right after *on_dispatch*.
+    pub fn add_observer(&self, observer: Arc<dyn ChannelObserver>){
+        self.channel.add_observer(observer);
+    }
+
+    /// Same as *DeliveryService::subscribe_results*.
+    pub fn subscribe_results(&mut self) -> Receiver<T>{
+        self.channel.subscribe_results()
+    }
+
+    /// Same as *DeliveryService::set_shared_context*.
+    pub fn set_shared_context<C>(&mut self, shared_context: Arc<C>) where C: Send + Sync + 'static{
+        self.channel.set_shared_context(shared_context);
+    }
+
+    /// Same as *DeliveryService::clear_shared_context*.
+    pub fn clear_shared_context(&mut self){
+        self.channel.clear_shared_context();
+    }
+
+    /// Same as *DeliveryService::get_name*.
+    pub fn get_name(&self) -> Option<&str>{
+        self.channel.get_name()
+    }
+
+    /// Same as *DeliveryService::last_stop_reason*.
+    pub fn last_stop_reason(&self) -> Option<&StopReason>{
+        self.channel.last_stop_reason()
+    }
+
+    /// Same as *DeliveryService::get_outstanding_weight*.
+    pub fn get_outstanding_weight(&self) -> usize{
+        self.channel.get_outstanding_weight()
+    }
+
+    /// Same as *DeliveryService::get_pooled_messages*.
+    pub fn get_pooled_messages(&self) -> usize{
+        self.channel.get_pooled_messages()
+    }
+
+    /// Same as *DeliveryService::get_pool_misses*.
+    pub fn get_pool_misses(&self) -> usize{
+        self.channel.get_pool_misses()
+    }
+
+    /// Same as *DeliveryService::return_buffer*.
+    pub fn return_buffer(&mut self, data: T){
+        self.channel.return_buffer(data);
+    }
+
+    /// Same as *DeliveryService::get_spare_buffers*.
+    pub fn get_spare_buffers(&self) -> usize{
+        self.channel.get_spare_buffers()
+    }
+
+    /// Same as *DeliveryService::get_in_flight_memory*.
+    pub fn get_in_flight_memory(&self) -> usize{
+        self.channel.get_in_flight_memory()
+    }
+
+    /// Same as *DeliveryService::get_discarded_results*.
+    pub fn get_discarded_results(&self) -> usize{
+        self.channel.get_discarded_results()
+    }
+
+    /// Same as *DeliveryService::get_expired_inputs*.
+    pub fn get_expired_inputs(&self) -> usize{
+        self.channel.get_expired_inputs()
+    }
+
+    /// Same as *DeliveryService::take_failed*.
+    pub fn take_failed(&mut self) -> Vec<(R, FailureReason<E>)>{
+        self.channel.take_failed()
+    }
+
+    /// Same as *DeliveryService::get_failed_inputs*.
+    pub fn get_failed_inputs(&self) -> usize{
+        self.channel.get_failed_inputs()
+    }
+
+    /// Same as *DeliveryService::get_memory_stats*.
+    pub fn get_memory_stats(&self) -> Option<&MemoryStats>{
+        self.channel.get_memory_stats()
+    }
+
+    /// Same as *DeliveryService::throughput*.
+    pub fn throughput(&self, window: Duration) -> Option<Throughput>{
+        self.channel.throughput(window)
+    }
+
+    /// Same as *DeliveryService::alloc_stats*. Messages are worked on the caller's thread, so allocations made by *work* are counted as dispatch.
+    pub fn alloc_stats(&self) -> Option<&AllocStats>{
+        self.channel.alloc_stats()
+    }
+
+    /// Same as *DeliveryService::reset_alloc_stats*.
+    pub fn reset_alloc_stats(&mut self){
+        self.channel.reset_alloc_stats();
+    }
+
+    /// Same as *DeliveryService::stats*. Messages are worked as they are dispatched, so latency is about the same as work time.
+    pub fn stats(&self) -> Option<ChannelStats>{
+        self.channel.stats()
+    }
+
+    /// Same as *DeliveryService::reset_stats*.
+    pub fn reset_stats(&mut self){
+        self.channel.reset_stats();
+    }
+
+    /// Same as *DeliveryService::get_stack_usage*. Always None, since there are no worker threads.
+    pub fn get_stack_usage(&self) -> Option<StackUsage>{
+        self.channel.get_stack_usage()
+    }
+
+    /// Same as *DeliveryService::get_work_progress*. Only worker 0 is listed, it's the caller's thread.
+    pub fn get_work_progress(&self) -> BTreeMap<usize, WorkProgress>{
+        self.channel.get_work_progress()
+    }
+
+    /// Same as *DeliveryService::pending_inputs*.
+    pub fn pending_inputs(&mut self) -> usize{
+        self.channel.pending_inputs()
+    }
+
+    /// Same as *DeliveryService::in_flight*. Always 0, messages are worked as soon as they're dispatched.
+    pub fn in_flight(&mut self) -> usize{
+        self.channel.in_flight()
+    }
+
+    /// Same as *DeliveryService::ready_results*.
+    pub fn ready_results(&mut self) -> usize{
+        self.channel.ready_results()
+    }
+
+    /// Same as *DeliveryService::is_empty*.
+    pub fn is_empty(&mut self) -> bool{
+        self.channel.is_empty()
+    }
+
+    /// Same as *DeliveryService::results*.
+    pub fn results(&mut self) -> Results<'_, T, R, S, E>{
+        self.channel.results()
+    }
+
+    /// Same as *DeliveryService::try_next*. Each call works the next message right here, so it only returns None once there's nothing left.
+    pub fn try_next(&mut self) -> Option<T>{
+        self.channel.try_next()
+    }
+
+    /// Same as *DeliveryService::next_timeout*. Messages are worked right here, so a message that takes longer than the timeout still finishes first.
+    pub fn next_timeout(&mut self, timeout: Duration) -> Result<Option<T>, Timeout>{
+        self.channel.next_timeout(timeout)
+    }
+
+    /// Same as *DeliveryService::try_iter*.
+    pub fn try_iter(&mut self) -> TryIter<'_, T, R, S, E>{
+        self.channel.try_iter()
+    }
+
+    /// Same as *DeliveryService::iter_batch*.
+    pub fn iter_batch(&mut self, batch: BatchId) -> BatchIter<'_, T, R, S, E>{
+        self.channel.iter_batch(batch)
+    }
+
+    /// Same as *DeliveryService::drain*.
+    pub fn drain(&mut self) -> (Vec<T>, BatchReport){
+        self.channel.drain()
+    }
+
+    /// Same as *DeliveryService::on_result*.
+    pub fn on_result<F>(&mut self, callback: F) where F: FnMut(T) + Send + 'static{
+        self.channel.on_result(callback)
+    }
+
+    /// Same as *DeliveryService::clear_on_result*.
+    pub fn clear_on_result(&mut self){
+        self.channel.clear_on_result()
+    }
+
+    /// Same as *DeliveryService::on_progress*.
+    pub fn on_progress<F>(&mut self, callback: F) where F: FnMut(usize, usize) + Send + 'static{
+        self.channel.on_progress(callback)
+    }
+
+    /// Same as *DeliveryService::clear_on_progress*.
+    pub fn clear_on_progress(&mut self){
+        self.channel.clear_on_progress()
+    }
+
+    /// Same as *DeliveryService::progress*.
+    pub fn progress(&mut self) -> (usize, usize){
+        self.channel.progress()
+    }
+
+    /// Same as *DeliveryService::run_until_empty*.
+    pub fn run_until_empty(&mut self) -> usize{
+        self.channel.run_until_empty()
+    }
+
+    /// Same as *DeliveryService::drain_to_writer*.
+    pub fn drain_to_writer<W, F>(&mut self, writer: W, serialize: F) -> io::Result<usize> where
+    W: Write,
+    F: FnMut(&T, &mut dyn Write) -> io::Result<()>,
+    {
+        self.channel.drain_to_writer(writer, serialize)
+    }
+
+    /// Same as *DeliveryService::checkpoint*.
+    #[cfg(feature = "serde")]
+    pub fn checkpoint<W>(&mut self, writer: W) -> io::Result<usize> where W: Write, R: Serialize{
+        self.channel.checkpoint(writer)
+    }
+
+    /// Same as *DeliveryService::checkpoint_all*.
+    #[cfg(feature = "serde")]
+    pub fn checkpoint_all<W>(&mut self, writer: W) -> io::Result<usize> where W: Write, R: Serialize{
+        self.channel.checkpoint_all(writer)
+    }
+
+    /// Same as *DeliveryService::restore*.
+    #[cfg(feature = "serde")]
+    pub fn restore<Rd>(&mut self, reader: Rd) -> io::Result<usize> where Rd: Read, R: DeserializeOwned{
+        self.channel.restore(reader)
+    }
+
+    /// Same as *DeliveryService::fold*.
+    pub fn fold<B, F>(&mut self, init: B, f: F) -> B where F: FnMut(B, T) -> B{
+        self.channel.fold(init, f)
+    }
+
+    /// Same as *DeliveryService::process*.
+    pub fn process(&mut self, input_vec: &mut Vec<R>, in_order: bool) -> Vec<T>{
+        self.channel.process(input_vec, in_order)
+    }
+
+    /// Same as *DeliveryService::map_reduce*.
+    pub fn map_reduce(&mut self, input_vec: &mut Vec<R>, inputs_per_message: usize) -> T where S: Merge<T>{
+        self.channel.map_reduce(input_vec, inputs_per_message)
+    }
+
+        /// Same as *DeliveryService::set_drop_policy*.
+    pub fn set_drop_policy(&mut self, drop_policy: DropPolicy<R>){
+        self.channel.set_drop_policy(drop_policy);
+    }
+
+    /// Same as *DeliveryService::set_drop_grace*. There are no workers to wait for.
+    pub fn set_drop_grace(&mut self, drop_grace: Duration){
+        self.channel.set_drop_grace(drop_grace);
+    }
+
+    /// Same as *DeliveryService::shutdown*. There are no workers to join.
+    pub fn shutdown(self) -> ShutdownReport{
+        self.channel.shutdown()
+    }
+
+    /// Same as *DeliveryService::warm_up*. There are no workers to spawn, it does nothing.
+    pub fn warm_up(&mut self) -> Result<(), KikError>{
+        self.channel.warm_up()
+    }
+
+    /// Same as *DeliveryService::add_workers*. Only the count changes, messages are still worked on the caller's thread.
+    pub fn add_workers(&mut self, worker_number: usize) -> Result<(), KikError>{
+        self.channel.add_workers(worker_number)
+    }
+
+    /// Same as *DeliveryService::remove_workers*. Only the count changes, messages are still worked on the caller's thread.
+    pub fn remove_workers(&mut self, worker_number: usize) -> usize{
+        self.channel.remove_workers(worker_number)
+    }
+
+    /// Same as *DeliveryService::get_package_limit*.
+    pub fn get_package_limit(&self) -> usize{
+        self.channel.get_package_limit()
+    }
+
+    /// Same as *DeliveryService::get_adaptive_band*.
+    pub fn get_adaptive_band(&self) -> Option<(usize, usize)>{
+        self.channel.get_adaptive_band()
+    }
+
+    /// Same as *DeliveryService::get_worker_number*.
+    pub fn get_worker_number(&self) -> usize{
+        self.channel.get_worker_number()
+    }
+}
+
+/// Creates new SequentialDeliveryService with default values.
+impl<T, R, S, E> Default for SequentialDeliveryService<T, R, S, E> where
+T: MessageData + 'static,
+R: MessageInput<T> + 'static,
+S: Message<T, R, E> + Default + Sync + Send + 'static,
+E: Send + 'static,
+{
+    fn default() -> Self{
+        match SequentialDeliveryService::try_new(ChannelConfig::default()){
+            Ok(channel) => channel,
+            Err(_) => unreachable!("The default config is valid"),
+        }
+    }
+}
+
+impl<T, R, S, E> Iterator for &mut SequentialDeliveryService<T, R, S, E>  where
+T: MessageData + 'static,
+R: MessageInput<T> + 'static,
+S: Message<T, R, E> + Sync + Send + 'static,
+E: Send + 'static,
+{
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        (&mut self.channel).next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, self.channel.result_bounds().1)
+    }
+}
+
+/// Same as the *IntoIterator* of *DeliveryService*.
+impl<T, R, S, E> IntoIterator for SequentialDeliveryService<T, R, S, E>  where
+T: MessageData + 'static,
+R: MessageInput<T> + 'static,
+S: Message<T, R, E> + Sync + Send + 'static,
+E: Send + 'static,
+{
+    type Item = T;
+    type IntoIter = IntoIter<T, R, S, E>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.channel.into_iter()
+    }
+}
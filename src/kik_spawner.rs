@@ -0,0 +1,180 @@
+//! # Spawner
+//!
+//! How the worker threads of a channel are created. By default each worker gets a thread of its own from *std::thread::Builder*, named
+//! after the channel and the worker, with the stack size of the *ChannelConfig*. *ChannelConfig::set_thread_spawner* replaces that with
+//! a *ThreadSpawner*, for embedders that need to name, sandbox or prioritize their threads themselves, or run the workers in a thread pool they already have.
+//!
+//! A spawner gets a *WorkerThread* and has to call *WorkerThread::run* on some thread, once. It may return before the worker starts,
+//! the channel waits for the worker through its own bookkeeping, not through the thread. A worker runs until the channel is done with it,
+//! so a pool lending its threads loses one for the whole life of the channel, for each worker.
+//!
+//! ```ignore
+//! config.set_thread_spawner(|thread: WorkerThread| {
+//!     std::thread::Builder::new()
+//!         .name(format!("render-{}", thread.get_worker_id()))
+//!         .stack_size(thread.get_stack_size())
+//!         .spawn(move || thread.run())
+//!         .map(|_| ())
+//! });
+//! ```
+//!
+//!
+
+use std::io;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::{Arc, Condvar, Mutex, PoisonError};
+use std::thread::Builder;
+use std::time::{Duration, Instant};
+
+/// Creates the worker threads of a channel. See the spawner module.
+pub trait ThreadSpawner: Send + Sync{
+    /// Call *WorkerThread::run* on a new thread, or one that can be given to the worker for as long as the channel lives.
+    /// An error leaves the worker out, like a thread that couldn't be spawned (see *KikError::SpawnFailed*).
+    fn spawn(&self, thread: WorkerThread) -> io::Result<()>;
+}
+
+impl<F> ThreadSpawner for F where F: Fn(WorkerThread) -> io::Result<()> + Send + Sync{
+    fn spawn(&self, thread: WorkerThread) -> io::Result<()>{
+        self(thread)
+    }
+}
+
+/// The default spawner, a thread from *std::thread::Builder* for each worker, with the name and stack size the channel asks for.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct StdSpawner;
+
+impl ThreadSpawner for StdSpawner{
+    fn spawn(&self, thread: WorkerThread) -> io::Result<()>{
+        Builder::new()
+            .name(thread.get_name().to_string())
+            .stack_size(thread.get_stack_size())
+            .spawn(move || thread.run())
+            .map(|_| ())
+    }
+}
+
+/// A worker waiting for a thread to run on. Given to *ThreadSpawner::spawn*.
+pub struct WorkerThread{
+    worker_id: usize,
+    name: String,
+    stack_size: usize,
+    // Taken by run.
+    work: Option<Box<dyn FnOnce() + Send>>,
+    exit: Arc<WorkerExit>,
+}
+
+impl WorkerThread{
+    /// A worker that runs work, followed through the returned handle.
+    pub fn new<F>(worker_id: usize, name: String, stack_size: usize, work: F) -> (Self, WorkerHandle) where F: FnOnce() + Send + 'static{
+        let exit = Arc::new(WorkerExit::default());
+        let handle = WorkerHandle{ worker_id, exit: exit.clone() };
+        let thread = WorkerThread{
+            worker_id,
+            name,
+            stack_size,
+            work: Some(Box::new(work)),
+            exit,
+        };
+        (thread, handle)
+    }
+
+    /// Id of the worker, the one *WorkError* and the events report.
+    pub fn get_worker_id(&self) -> usize{
+        self.worker_id
+    }
+
+    /// Name the channel would give the thread: the channel's name, if it has one, and the worker id.
+    pub fn get_name(&self) -> &str{
+        &self.name
+    }
+
+    /// Stack size set in *ChannelConfig*. A thread that already exists should have at least that much left.
+    pub fn get_stack_size(&self) -> usize{
+        self.stack_size
+    }
+
+    /// Run the worker on the calling thread, until the channel is done with it. A panic of the worker is caught here and counted as
+    /// a panicked worker, so that the thread stays usable afterwards.
+    pub fn run(mut self){
+        if let Some(work) = self.work.take(){
+            let panicked = catch_unwind(AssertUnwindSafe(work)).is_err();
+            self.exit.finish(panicked);
+        }
+    }
+}
+
+impl Drop for WorkerThread{
+    fn drop(&mut self){
+        // Dropped by a spawner without running it. Nobody is left to finish the worker, so it's done.
+        self.exit.finish(false);
+    }
+}
+
+// How a worker ended. None while it's running.
+#[derive(Default)]
+struct WorkerExit{
+    panicked: Mutex<Option<bool>>,
+    finished: Condvar,
+}
+
+impl WorkerExit{
+    // Only the first call counts.
+    fn finish(&self, panicked: bool){
+        let mut state = self.panicked.lock().unwrap_or_else(PoisonError::into_inner);
+        if state.is_none(){
+            *state = Some(panicked);
+            self.finished.notify_all();
+        }
+    }
+}
+
+/// The channel's end of a *WorkerThread*, for waiting until it finished.
+pub struct WorkerHandle{
+    worker_id: usize,
+    exit: Arc<WorkerExit>,
+}
+
+impl WorkerHandle{
+    /// Id of the worker followed.
+    pub fn get_worker_id(&self) -> usize{
+        self.worker_id
+    }
+
+    /// True once the worker finished running because it panicked.
+    pub fn has_panicked(&self) -> bool{
+        *self.exit.panicked.lock().unwrap_or_else(PoisonError::into_inner) == Some(true)
+    }
+
+    /// True once the worker finished running.
+    pub fn is_finished(&self) -> bool{
+        self.exit.panicked.lock().unwrap_or_else(PoisonError::into_inner).is_some()
+    }
+
+    /// Wait until the worker finished running. Returns false if it panicked.
+    pub fn join(self) -> bool{
+        let mut state = self.exit.panicked.lock().unwrap_or_else(PoisonError::into_inner);
+        loop{
+            match *state{
+                Some(panicked) => return !panicked,
+                None => state = self.exit.finished.wait(state).unwrap_or_else(PoisonError::into_inner),
+            }
+        }
+    }
+
+    /// Same as *join*, but gives up at the deadline. None if the worker was still running then, dropping the handle leaves it running on its own.
+    pub fn join_until(&self, deadline: Instant) -> Option<bool>{
+        let mut state = self.exit.panicked.lock().unwrap_or_else(PoisonError::into_inner);
+        loop{
+            match *state{
+                Some(panicked) => return Some(!panicked),
+                None => {
+                    let left = deadline.saturating_duration_since(Instant::now());
+                    if left == Duration::from_secs(0){
+                        return None;
+                    }
+                    state = self.exit.finished.wait_timeout(state, left).unwrap_or_else(PoisonError::into_inner).0;
+                },
+            }
+        }
+    }
+}
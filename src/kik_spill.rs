@@ -0,0 +1,157 @@
+//! # Spill
+//!
+//! Inputs kept on disk instead of memory, behind the "spill" feature. Used by *DeliveryService::feed_spilling*.
+//!
+//! Once more inputs are pending than the threshold set with *ChannelConfig::set_spill_threshold*, the rest are written to a temporary
+//! file as bincode, each in a frame of its own (see kik_wire). The file is read back in order as a generator, one input each time a message
+//! is free to be sent, so only the threshold's worth of inputs is ever held in memory.
+//!
+//! While a file isn't drained, later inputs are appended to it instead of the queue, so that every input still comes out in the order
+//! it was fed. The file is deleted once it's drained, or when the channel is dropped.
+//!
+//!
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufReader, BufWriter, Write};
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+
+use crate::kik_wire::{decode, encode, read_frame, write_frame};
+
+use serde::{Serialize, de::DeserializeOwned};
+
+// Tells apart the files of every channel in this process.
+static SPILL_FILES: AtomicUsize = AtomicUsize::new(0);
+
+/// A spill file shared by the channel, which appends to it, and its generator, which reads from it.
+pub type SharedSpill<R> = Arc<Mutex<SpillFile<R>>>;
+
+/// Inputs written to a temporary file, read back in the same order. See the module documentation.
+pub struct SpillFile<R>{
+    path: PathBuf,
+    // Both None once the file was deleted.
+    writer: Option<BufWriter<File>>,
+    reader: Option<BufReader<File>>,
+    // Inputs written and not read yet.
+    pending: usize,
+    // Set once the generator gave up on the file. New inputs need a new file then.
+    drained: bool,
+    error: Option<io::Error>,
+    buffer: Vec<u8>,
+    resource_type: PhantomData<fn() -> R>,
+}
+
+impl<R> SpillFile<R> where R: Serialize + DeserializeOwned{
+    /// Create an empty file in dir.
+    pub fn create(dir: &Path) -> io::Result<Self>{
+        let name = format!("kik_sync_service-spill-{}-{}", process::id(), SPILL_FILES.fetch_add(1, Ordering::Relaxed));
+        let path = dir.join(name);
+        let writer = OpenOptions::new().write(true).create_new(true).open(&path)?;
+        let reader = match File::open(&path){
+            Ok(reader) => reader,
+            Err(error) => {
+                let _ = fs::remove_file(&path);
+                return Err(error);
+            },
+        };
+        Ok(SpillFile{
+            path,
+            writer: Some(BufWriter::new(writer)),
+            reader: Some(BufReader::new(reader)),
+            pending: 0,
+            drained: false,
+            error: None,
+            buffer: Vec::new(),
+            resource_type: PhantomData,
+        })
+    }
+
+    /// Append every input to the file, in order. The vector is only emptied if all of them were written.
+    pub fn push_all(&mut self, input_vec: &mut Vec<R>) -> io::Result<()>{
+        let writer = match &mut self.writer{
+            Some(writer) => writer,
+            None => return Err(io::Error::new(io::ErrorKind::NotFound, "the spill file was deleted")),
+        };
+        for input in input_vec.iter(){
+            self.buffer.clear();
+            encode(input, &mut self.buffer)?;
+            write_frame(writer, &self.buffer)?;
+        }
+        // Whole frames only, so that the reader never finds half of one.
+        writer.flush()?;
+        self.pending += input_vec.len();
+        input_vec.clear();
+        Ok(())
+    }
+
+    /// Read the next input. None once there's none left, or if it couldn't be read, which is kept for *take_error*. The file is deleted then.
+    pub fn pop(&mut self) -> Option<R>{
+        if self.pending == 0{
+            self.finish();
+            return None;
+        }
+        let read = match &mut self.reader{
+            Some(reader) => read_frame(reader).and_then(|bytes| decode(&bytes)),
+            None => return None,
+        };
+        match read{
+            Ok(input) => {
+                self.pending -= 1;
+                Some(input)
+            },
+            Err(error) => {
+                self.error = Some(error);
+                self.finish();
+                None
+            },
+        }
+    }
+}
+
+impl<R> SpillFile<R>{
+    /// How many inputs are in the file, waiting to be read.
+    pub fn len(&self) -> usize{
+        self.pending
+    }
+
+    /// True once the file was deleted. Inputs can't be added to it anymore.
+    pub fn is_drained(&self) -> bool{
+        self.drained
+    }
+
+    /// Take the error that stopped the reading, if any. The inputs that were still in the file are lost.
+    pub fn take_error(&mut self) -> Option<io::Error>{
+        self.error.take()
+    }
+
+    /// Keep an error from an earlier file, until it's taken with *take_error*.
+    pub fn keep_error(&mut self, error: io::Error){
+        self.error = Some(error);
+    }
+
+    // Close the file and delete it. Inputs still in it are lost.
+    fn finish(&mut self){
+        if self.drained{
+            return;
+        }
+        self.drained = true;
+        self.pending = 0;
+        self.writer = None;
+        self.reader = None;
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+impl<R> Drop for SpillFile<R>{
+    fn drop(&mut self){
+        self.finish();
+    }
+}
+
+/// Lock a spill file, even if a thread panicked while holding it.
+pub fn lock_spill<R>(spill: &SharedSpill<R>) -> std::sync::MutexGuard<'_, SpillFile<R>>{
+    spill.lock().unwrap_or_else(PoisonError::into_inner)
+}
@@ -0,0 +1,134 @@
+//! # Split
+//!
+//! The two halves of a *DeliveryService* returned by *DeliveryService::split*, for feeding on one thread while iterating on another.
+//!
+//! The *ResultReceiver* owns the channel. It's an iterator over the results that, once there's nothing left to work, sleeps until a
+//! *FeederHandle* sends more inputs. It only returns None after every *FeederHandle* is dropped and every result was returned.
+//! Failed messages are skipped, like with *DeliveryService::subscribe_results*, so that one failure doesn't end the stream.
+//!
+//! ```ignore
+//! let (feeder, results) = channel.split();
+//! let producer = std::thread::spawn(move || {
+//!     for frame in 0..60{
+//!         feeder.feed(&mut tiles_of(frame)).unwrap();
+//!     }
+//!     // Dropping the handle ends the iteration below once its inputs are worked.
+//! });
+//! for tile in results{
+//!     draw(tile);
+//! }
+//! ```
+//!
+//!
+
+use std::sync::Arc;
+
+use crate::kik_message::{Message, MessageInput, MessageData};
+use crate::kik_channel::DeliveryService;
+use crate::kik_sender::{Inbox, FeedSignal};
+use crate::kik_report::ShutdownReport;
+use crate::kik_error::StopReason;
+use crate::kik_tee::{tee, ResultTee};
+
+/// What a *ResultReceiver* iterates. Implemented for the channel being split, so that the receiver only needs the type of the results.
+trait ResultSource<T>: Send{
+    /// The next result, waiting for more inputs if there's nothing to work. None once the producers are gone and there's nothing left.
+    fn next_result(&mut self) -> Option<T>;
+
+    /// Stop the channel. Same as *DeliveryService::shutdown*.
+    fn shutdown_source(&mut self) -> ShutdownReport;
+}
+
+// The channel together with what it needs to wait for its producers.
+struct SplitChannel<T, R, S, E> where
+T: MessageData + 'static,
+R: MessageInput<T> + 'static,
+S: Message<T, R, E> + Sync + Send + 'static,
+E: Send + 'static,
+{
+    // None once shut down.
+    channel: Option<DeliveryService<T, R, S, E>>,
+    inbox: Inbox<R>,
+    signal: Arc<FeedSignal>,
+}
+
+impl<T, R, S, E> ResultSource<T> for SplitChannel<T, R, S, E> where
+T: MessageData + 'static,
+R: MessageInput<T> + 'static,
+S: Message<T, R, E> + Sync + Send + 'static,
+E: Send + 'static,
+{
+    fn next_result(&mut self) -> Option<T>{
+        let channel = self.channel.as_mut()?;
+        loop{
+            // Inputs sent so far are collected by the channel itself.
+            if let Some(data) = channel.results().find_map(Result::ok){
+                return Some(data);
+            }
+            // The workers are gone, or stuck past the stall timeout. Waiting for more inputs wouldn't help.
+            if let Some(StopReason::Error(_)) = channel.last_stop_reason(){
+                return None;
+            }
+            if !self.signal.wait_for_inputs(&self.inbox){
+                return None;
+            }
+        }
+    }
+
+    fn shutdown_source(&mut self) -> ShutdownReport{
+        self.signal.close();
+        // Only called once, by ResultReceiver::shutdown.
+        self.channel.take().map(DeliveryService::shutdown).unwrap_or_default()
+    }
+}
+
+impl<T, R, S, E> Drop for SplitChannel<T, R, S, E> where
+T: MessageData + 'static,
+R: MessageInput<T> + 'static,
+S: Message<T, R, E> + Sync + Send + 'static,
+E: Send + 'static,
+{
+    fn drop(&mut self){
+        self.signal.close();
+    }
+}
+
+/// Iterator half of a split channel. Owns the channel. See the module documentation.
+pub struct ResultReceiver<T>{
+    source: Box<dyn ResultSource<T>>,
+}
+
+impl<T> ResultReceiver<T> where T: MessageData + 'static{
+    /// Take ownership of the channel. Used by *DeliveryService::split*.
+    pub fn new<R, S, E>(channel: DeliveryService<T, R, S, E>, inbox: Inbox<R>, signal: Arc<FeedSignal>) -> Self where
+    R: MessageInput<T> + 'static,
+    S: Message<T, R, E> + Sync + Send + 'static,
+    E: Send + 'static,
+    {
+        ResultReceiver{
+            source: Box::new(SplitChannel{
+                channel: Some(channel),
+                inbox,
+                signal,
+            }),
+        }
+    }
+
+    /// Give each of consumers a clone of every result, see kik_tee. The channel is shut down once every *ResultTee* is dropped.
+    pub fn tee(mut self, consumers: usize, capacity: Option<usize>) -> Vec<ResultTee<T>>{
+        tee(Box::new(move || self.next()), consumers, capacity)
+    }
+
+    /// Stop the channel and wait for every worker thread to finish, like *DeliveryService::shutdown*. The *FeederHandle*s get *Closed* from then on.
+    pub fn shutdown(mut self) -> ShutdownReport{
+        self.source.shutdown_source()
+    }
+}
+
+impl<T> Iterator for ResultReceiver<T>{
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item>{
+        self.source.next_result()
+    }
+}
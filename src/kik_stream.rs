@@ -0,0 +1,333 @@
+//! # Stream
+//!
+//! *ResultStream* is returned by *DeliveryService::into_stream*, behind the "async" feature. The channel is moved into a thread of its own that
+//! iterates it, so an async runtime can await the results instead of blocking one of its threads in *next*.
+//!
+//! It implements *futures_core::Stream*, so it can be passed to any stream combinator. Without one, *next_result* gives a future to await:
+//!
+//! ```ignore
+//! let mut results = channel.into_stream();
+//! while let Some(tile) = results.next_result().await{
+//!     socket.send(tile).await?;
+//! }
+//! ```
+//!
+//! The stream ends once the channel runs out of work. In keep-alive mode (see *ChannelConfig::set_keep_alive*) it goes on until the input
+//! is closed, so a *WeakInputSender* or an *AsyncFeeder* taken before calling *into_stream* can keep feeding it. Dropping the stream cancels
+//! the run and closes the input, the thread then drops the channel as set by *DeliveryService::set_drop_policy*.
+//!
+//! *AsyncFeeder* is the other half, returned by *DeliveryService::async_feeder*. Its *feed* waits, without blocking the thread, while the
+//! channel already holds as many inputs as its budget allows: queued, being worked, or worked but not yet taken. Room is made each time
+//! the channel yields a result, so a slow consumer slows the producers down too.
+//!
+//! ```ignore
+//! let feeder = channel.async_feeder(64);
+//! let mut results = channel.into_stream();
+//! tokio::spawn(async move {
+//!     while let Some(request) = socket.next_request().await{
+//!         feeder.feed(request).await?;
+//!     }
+//!     feeder.close_input();
+//! });
+//! ```
+//!
+//!
+
+use std::collections::VecDeque;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::task::{Context, Poll, Waker};
+use std::thread::Builder;
+
+use futures_core::Stream;
+
+use crate::kik_message::{Message, MessageInput, MessageData};
+use crate::kik_channel::DeliveryService;
+use crate::kik_sender::WeakInputSender;
+use crate::kik_context::CancellationToken;
+use crate::kik_error::Closed;
+
+// Results waiting for the stream, and whether more can come.
+struct StreamState<T>{
+    results: VecDeque<T>,
+    // The channel ran out of work, or its thread panicked.
+    finished: bool,
+    // The stream was dropped. The thread stops at the next result.
+    dropped: bool,
+    // Task that last found nothing to take.
+    waker: Option<Waker>,
+}
+
+type SharedState<T> = Arc<Mutex<StreamState<T>>>;
+
+/// Closes the input of the channel, whatever its input type.
+type InputCloser = Box<dyn Fn() + Send + Sync>;
+
+// Owned by the thread. Marks the stream as finished even if the iteration panics, so that nobody awaits forever.
+struct Finisher<T>{
+    state: SharedState<T>,
+}
+
+impl<T> Drop for Finisher<T>{
+    fn drop(&mut self){
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        state.finished = true;
+        if let Some(waker) = state.waker.take(){
+            waker.wake();
+        }
+    }
+}
+
+/// Results of a channel moved into its own thread, to be awaited. See the module documentation.
+pub struct ResultStream<T>{
+    state: SharedState<T>,
+    // Used to stop the thread when the stream is dropped.
+    cancellation: CancellationToken,
+    close_input: InputCloser,
+}
+
+impl<T> ResultStream<T> where T: MessageData + 'static{
+    /// Move the channel into a new thread that iterates it. Used by *DeliveryService::into_stream*.
+    pub fn new<R, S, E>(mut channel: DeliveryService<T, R, S, E>) -> Self where
+    R: MessageInput<T> + 'static,
+    S: Message<T, R, E> + Sync + Send + 'static,
+    E: Send + 'static,
+    {
+        let state: SharedState<T> = Arc::new(Mutex::new(StreamState{
+            results: VecDeque::new(),
+            finished: false,
+            dropped: false,
+            waker: None,
+        }));
+        let cancellation = channel.cancellation_token();
+        let sender: WeakInputSender<R> = channel.weak_sender();
+        let close_input: InputCloser = Box::new(move || sender.close_input());
+        let finisher = Finisher{
+            state: state.clone(),
+        };
+        let name = match channel.get_name(){
+            Some(name) => format!("{} stream", name),
+            None => String::from("Stream"),
+        };
+        let spawned = Builder::new().name(name).spawn(move || {
+            let finisher = finisher;
+            for data in &mut channel{
+                let mut state = finisher.state.lock().unwrap_or_else(PoisonError::into_inner);
+                if state.dropped{
+                    break;
+                }
+                state.results.push_back(data);
+                if let Some(waker) = state.waker.take(){
+                    waker.wake();
+                }
+            }
+            // The channel goes first, so that its workers are done before the stream ends.
+            drop(channel);
+        });
+        // If the thread couldn't be spawned, the closure was dropped with the finisher and the stream just ends.
+        drop(spawned);
+        ResultStream{
+            state,
+            cancellation,
+            close_input,
+        }
+    }
+
+    /// Take the next result if there's one, or register the task to be woken when one arrives. Ready(None) once the channel ran out of work.
+    /// Same as *Stream::poll_next*, callable without the trait in scope.
+    pub fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>>{
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(data) = state.results.pop_front(){
+            return Poll::Ready(Some(data));
+        }
+        if state.finished{
+            return Poll::Ready(None);
+        }
+        state.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+
+    /// Future resolving to the next result, or None once the channel ran out of work.
+    pub fn next_result(&mut self) -> NextResult<'_, T>{
+        NextResult{
+            stream: self,
+        }
+    }
+
+    /// How many results arrived and weren't taken yet.
+    pub fn ready_results(&self) -> usize{
+        self.state.lock().unwrap_or_else(PoisonError::into_inner).results.len()
+    }
+}
+
+impl<T> Drop for ResultStream<T>{
+    fn drop(&mut self){
+        self.state.lock().unwrap_or_else(PoisonError::into_inner).dropped = true;
+        self.cancellation.cancel();
+        (self.close_input)();
+    }
+}
+
+impl<T> Stream for ResultStream<T> where T: MessageData + 'static{
+    type Item = T;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>>{
+        ResultStream::poll_next(self, cx)
+    }
+
+    // Only the results already waiting are certain, the channel decides how many more come.
+    fn size_hint(&self) -> (usize, Option<usize>){
+        (self.ready_results(), None)
+    }
+}
+
+/// Future returned by *ResultStream::next_result*.
+pub struct NextResult<'a, T>{
+    stream: &'a mut ResultStream<T>,
+}
+
+impl<T> Future for NextResult<'_, T> where T: MessageData + 'static{
+    type Output = Option<T>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output>{
+        Pin::new(&mut *self.stream).poll_next(cx)
+    }
+}
+
+
+// How many inputs the channel holds against how many it may, and who waits for room.
+struct BudgetState{
+    limit: usize,
+    used: usize,
+    // The channel was dropped or its input closed.
+    closed: bool,
+    waiting: Vec<Waker>,
+}
+
+/// Shared by a channel and its *AsyncFeeder*s. The channel settles it each time it yields a result, the feeders take from it.
+pub struct InputBudget{
+    state: Mutex<BudgetState>,
+}
+
+impl InputBudget{
+    /// Budget of the given number of inputs, at least one.
+    pub fn new(limit: usize) -> Self{
+        InputBudget{
+            state: Mutex::new(BudgetState{
+                limit: limit.max(1),
+                used: 0,
+                closed: false,
+                waiting: Vec::new(),
+            }),
+        }
+    }
+
+    /// Change the limit, waking whoever waits in case there's room now.
+    pub fn set_limit(&self, limit: usize){
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        state.limit = limit.max(1);
+        Self::wake_all(&mut state);
+    }
+
+    /// Tell how many inputs the channel holds now. *count* is called with the budget locked, so that no feeder sends meanwhile.
+    pub fn settle<F>(&self, count: F) where F: FnOnce() -> usize{
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        state.used = count();
+        if state.used < state.limit{
+            Self::wake_all(&mut state);
+        }
+    }
+
+    /// Wake everyone for good. Used when the channel is dropped or its input closed.
+    pub fn close(&self){
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        state.closed = true;
+        Self::wake_all(&mut state);
+    }
+
+    fn wake_all(state: &mut BudgetState){
+        for waker in state.waiting.drain(..){
+            waker.wake();
+        }
+    }
+}
+
+/// Cloneable handle for feeding a channel from async code, waiting while the channel is full. Created by *DeliveryService::async_feeder*.
+/// Doesn't keep the channel alive. See the module documentation.
+pub struct AsyncFeeder<R>{
+    sender: WeakInputSender<R>,
+    budget: Arc<InputBudget>,
+}
+
+impl<R> AsyncFeeder<R>{
+    /// Construct a new feeder taking from the budget.
+    pub fn new(sender: WeakInputSender<R>, budget: Arc<InputBudget>) -> Self{
+        AsyncFeeder{
+            sender,
+            budget,
+        }
+    }
+
+    /// Send a single input once the channel has room for it. Returns *Closed*, dropping the input, if the channel has been dropped or its input closed.
+    pub async fn feed(&self, input: R) -> Result<(), Closed>{
+        Feed{
+            feeder: self,
+            input: Some(input),
+        }.await
+    }
+
+    /// Same as *DeliveryService::close_input*.
+    pub fn close_input(&self){
+        self.sender.close_input();
+        self.budget.close();
+    }
+
+    /// True if the channel has been dropped or its input closed.
+    pub fn is_closed(&self) -> bool{
+        self.sender.is_closed()
+    }
+}
+
+// derive(Clone) would require R: Clone.
+impl<R> Clone for AsyncFeeder<R>{
+    fn clone(&self) -> Self{
+        AsyncFeeder{
+            sender: self.sender.clone(),
+            budget: Arc::clone(&self.budget),
+        }
+    }
+}
+
+// Future behind AsyncFeeder::feed. Holds the input until there's room.
+struct Feed<'a, R>{
+    feeder: &'a AsyncFeeder<R>,
+    input: Option<R>,
+}
+
+// The input is only moved out, never pinned.
+impl<R> Unpin for Feed<'_, R>{}
+
+impl<R> Future for Feed<'_, R>{
+    type Output = Result<(), Closed>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output>{
+        let feeder = self.feeder;
+        let mut state = feeder.budget.state.lock().unwrap_or_else(PoisonError::into_inner);
+        if state.closed || feeder.sender.is_closed(){
+            return Poll::Ready(Err(Closed));
+        }
+        if state.used >= state.limit{
+            state.waiting.push(cx.waker().clone());
+            return Poll::Pending;
+        }
+        // Sent with the budget locked, so that the channel can't settle it in between.
+        let input = match self.input.take(){
+            Some(input) => input,
+            None => return Poll::Ready(Ok(())),
+        };
+        feeder.sender.send(input)?;
+        state.used += 1;
+        Poll::Ready(Ok(()))
+    }
+}
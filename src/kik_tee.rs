@@ -0,0 +1,149 @@
+//! # Tee
+//!
+//! Several consumers of the same results, each getting a clone of every one. Returned by *DeliveryService::tee* and *ResultReceiver::tee*,
+//! for when one consumer writes frames to disk while another displays them.
+//!
+//! Each *ResultTee* is an iterator with a queue of its own. There's no thread behind them: whichever consumer runs out of results
+//! takes the next one from the channel and hands a clone to every queue. The others keep taking from their queues meanwhile.
+//!
+//! Without a capacity, a consumer that falls behind only makes its queue grow. With one, the consumers that are ahead wait once the
+//! queue of the slowest is full, so the slowest sets the pace. Every *ResultTee* must then be iterated on a thread of its own, or
+//! dropped, or the others wait for it forever. A dropped *ResultTee* stops getting clones.
+//!
+//! ```ignore
+//! let mut tees = channel.tee(2, Some(8)).into_iter();
+//! let (display, disk) = (tees.next().unwrap(), tees.next().unwrap());
+//! let writer = std::thread::spawn(move || disk.for_each(write_frame));
+//! display.for_each(show_frame);
+//! ```
+//!
+//! Failed messages are skipped and not given to anyone, *WorkError* can't be cloned. The consumers go on with the results after them.
+//! A panic of the channel while a consumer takes the next result reaches that consumer, and ends the iteration of the others.
+//!
+//!
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError};
+
+/// Takes the next result from the channel.
+pub type Puller<T> = Box<dyn FnMut() -> Option<T> + Send>;
+
+struct TeeState<T>{
+    // None while a consumer is taking the next result.
+    puller: Option<Puller<T>>,
+    // One queue for each consumer. None once it's dropped.
+    queues: Vec<Option<VecDeque<T>>>,
+    capacity: Option<usize>,
+    // The channel has nothing left.
+    done: bool,
+}
+
+struct TeeShared<T>{
+    state: Mutex<TeeState<T>>,
+    // Notified whenever a result is queued or taken, and when the channel runs out.
+    changed: Condvar,
+}
+
+impl<T> TeeShared<T>{
+    fn lock(&self) -> MutexGuard<'_, TeeState<T>>{
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+// Ends the iteration of every consumer if the puller panics.
+struct PullGuard<'a, T>{
+    shared: &'a TeeShared<T>,
+    pulled: bool,
+}
+
+impl<T> Drop for PullGuard<'_, T>{
+    fn drop(&mut self){
+        if !self.pulled{
+            self.shared.lock().done = true;
+            self.shared.changed.notify_all();
+        }
+    }
+}
+
+/// One consumer of the results of a channel. See the tee module.
+pub struct ResultTee<T>{
+    shared: Arc<TeeShared<T>>,
+    index: usize,
+}
+
+/// Share the results taken by puller between consumers, each with a queue of at most capacity results. Used by *DeliveryService::tee* and *ResultReceiver::tee*.
+pub fn tee<T>(puller: Puller<T>, consumers: usize, capacity: Option<usize>) -> Vec<ResultTee<T>> where T: Clone{
+    let shared = Arc::new(TeeShared{
+        state: Mutex::new(TeeState{
+            puller: Some(puller),
+            queues: (0..consumers).map(|_| Some(VecDeque::new())).collect(),
+            // A queue that can't hold anything would stop everyone.
+            capacity: capacity.map(|capacity| capacity.max(1)),
+            done: false,
+        }),
+        changed: Condvar::new(),
+    });
+    (0..consumers).map(|index| ResultTee{ shared: shared.clone(), index }).collect()
+}
+
+impl<T> ResultTee<T>{
+    /// How many results are waiting in the queue of this consumer.
+    pub fn queued(&self) -> usize{
+        self.shared.lock().queues[self.index].as_ref().map_or(0, VecDeque::len)
+    }
+}
+
+impl<T> Iterator for ResultTee<T> where T: Clone{
+    type Item = T;
+
+    fn next(&mut self) -> Option<T>{
+        let mut state = self.shared.lock();
+        loop{
+            if let Some(data) = state.queues[self.index].as_mut().and_then(VecDeque::pop_front){
+                // There may be room for the others to go on.
+                self.shared.changed.notify_all();
+                return Some(data);
+            }
+            if state.done{
+                return None;
+            }
+            let full = match state.capacity{
+                Some(capacity) => state.queues.iter().flatten().any(|queue| queue.len() >= capacity),
+                None => false,
+            };
+            let puller = if full { None } else { state.puller.take() };
+            let mut puller = match puller{
+                Some(puller) => puller,
+                // Someone else is taking the next result, or a slower consumer has to catch up first.
+                None => {
+                    state = self.shared.changed.wait(state).unwrap_or_else(PoisonError::into_inner);
+                    continue;
+                },
+            };
+            // The others can take from their queues while this waits for the channel.
+            drop(state);
+            let mut guard = PullGuard{ shared: &self.shared, pulled: false };
+            let next = puller();
+            guard.pulled = true;
+            state = self.shared.lock();
+            state.puller = Some(puller);
+            match next{
+                Some(data) => {
+                    for queue in state.queues.iter_mut().flatten(){
+                        queue.push_back(data.clone());
+                    }
+                },
+                None => state.done = true,
+            }
+            self.shared.changed.notify_all();
+        }
+    }
+}
+
+impl<T> Drop for ResultTee<T>{
+    fn drop(&mut self){
+        self.shared.lock().queues[self.index] = None;
+        // A full queue may have been holding the others up.
+        self.shared.changed.notify_all();
+    }
+}
@@ -0,0 +1,129 @@
+//! # Throttle
+//!
+//! Caps how much of the cpu the workers take, for a pool that runs in the background of an interactive application. Set it with
+//! *ChannelConfig::set_throttle*. Used by kik_worker, not meant to be used directly.
+//!
+//! A throttled worker rests between messages, never in the middle of one. *Throttle::Pause* rests the same while after every message.
+//! *Throttle::DutyCycle* rests in proportion to how long the last messages took, so that the worker is busy at most that fraction of the time.
+//! Time spent waiting for work counts as rest, so a worker that isn't kept busy never sleeps because of it.
+//!
+//! Rest is kept as a debt and only slept once it adds up to *MIN_REST*, so quick messages aren't slowed down by the granularity of the sleep.
+//! While resting, the worker wakes up every *REST_SLICE* to see if the run was cancelled or the channel closed.
+//!
+//! A *PauseGate* stops the workers altogether, from *DeliveryService::pause* until *DeliveryService::resume*. Paused workers wait
+//! at the gate before each message, also checking for cancellation every *REST_SLICE*.
+//!
+//!
+
+use std::sync::{Condvar, Mutex, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Rest owed below this isn't slept yet, it adds up with the rest of the next messages.
+pub const MIN_REST: Duration = Duration::from_millis(1);
+
+/// Longest sleep between checks for cancellation while resting.
+pub const REST_SLICE: Duration = Duration::from_millis(10);
+
+// Lowest duty cycle accepted. Below it the workers would barely work at all.
+const MIN_DUTY_CYCLE: f64 = 0.01;
+
+/// How much each worker rests between messages. See the throttle module.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Throttle{
+    /// Rest this long after each message.
+    Pause(Duration),
+    /// Be busy at most this fraction of the time, between 0.01 and 1. 0.25 rests three times as long as each message took.
+    DutyCycle(f64),
+}
+
+impl Throttle{
+    /// The same throttle, with the duty cycle clamped between 0.01 and 1. NaN is taken as 1, no throttling.
+    pub fn clamped(self) -> Self{
+        match self{
+            Throttle::DutyCycle(duty_cycle) if duty_cycle.is_nan() => Throttle::DutyCycle(1.0),
+            Throttle::DutyCycle(duty_cycle) => Throttle::DutyCycle(duty_cycle.clamp(MIN_DUTY_CYCLE, 1.0)),
+            pause => pause,
+        }
+    }
+
+    /// How long to rest after a message that took work_time.
+    fn rest_for(&self, work_time: Duration) -> Duration{
+        match *self{
+            Throttle::Pause(pause) => pause,
+            Throttle::DutyCycle(duty_cycle) => work_time.mul_f64((1.0 - duty_cycle) / duty_cycle),
+        }
+    }
+}
+
+/// Rest owed by a single worker.
+pub struct Throttler{
+    throttle: Throttle,
+    debt: Duration,
+    // When the worker last went back to waiting for work.
+    resting_since: Option<Instant>,
+}
+
+impl Throttler{
+    /// A worker throttled by throttle, owing nothing.
+    pub fn new(throttle: Throttle) -> Self{
+        Throttler{
+            throttle: throttle.clamped(),
+            debt: Duration::from_secs(0),
+            resting_since: None,
+        }
+    }
+
+    /// Count a message that took work_time and started at started_at, and rest if enough is owed.
+    /// The rest is cut short as soon as stop returns true.
+    pub fn rest<F>(&mut self, started_at: Instant, work_time: Duration, stop: F) where F: Fn() -> bool{
+        // Waiting for this message already paid part of the debt.
+        if let Some(resting_since) = self.resting_since{
+            self.debt = self.debt.saturating_sub(started_at.saturating_duration_since(resting_since));
+        }
+        self.debt += self.throttle.rest_for(work_time);
+        if self.debt >= MIN_REST{
+            let wake_at = Instant::now() + self.debt;
+            loop{
+                let now = Instant::now();
+                if now >= wake_at || stop(){
+                    break;
+                }
+                thread::sleep((wake_at - now).min(REST_SLICE));
+            }
+            // Sleeping too long pays nothing forward, and cutting it short forgives the rest.
+            self.debt = Duration::from_secs(0);
+        }
+        self.resting_since = Some(Instant::now());
+    }
+}
+
+/// Holds the workers of a channel between messages while closed. Shared by the channel and its workers.
+#[derive(Default)]
+pub struct PauseGate{
+    paused: Mutex<bool>,
+    unpaused: Condvar,
+}
+
+impl PauseGate{
+    /// Close or open the gate. Opening it wakes up every worker waiting at it.
+    pub fn set_paused(&self, paused: bool){
+        *self.paused.lock().unwrap_or_else(PoisonError::into_inner) = paused;
+        if !paused{
+            self.unpaused.notify_all();
+        }
+    }
+
+    /// True while the gate is closed.
+    pub fn is_paused(&self) -> bool{
+        *self.paused.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Wait until the gate is open, or until stop returns true.
+    pub fn wait<F>(&self, stop: F) where F: Fn() -> bool{
+        let mut paused = self.paused.lock().unwrap_or_else(PoisonError::into_inner);
+        while *paused && !stop(){
+            paused = self.unpaused.wait_timeout(paused, REST_SLICE).unwrap_or_else(PoisonError::into_inner).0;
+        }
+    }
+}
@@ -0,0 +1,83 @@
+//! # Wire
+//!
+//! Values turned into bytes and back, behind the "serde" feature, which "remote" and "spill" turn on. Used by kik_remote, kik_spill and
+//! *DeliveryService::checkpoint*, not meant to be used directly.
+//!
+//! *encode* appends the bincode of any serde value to a buffer, *decode* builds it back from exactly those bytes. Framing is done here,
+//! so a value doesn't need to know its own length.
+//!
+//! Each frame is the length of the value's bytes as a u32, little endian, followed by the bytes. A checkpoint (see *DeliveryService::checkpoint*)
+//! starts with a header line and the number of values as a u64, little endian, followed by a frame with the bincode of each value.
+//!
+//!
+
+use std::convert::TryFrom;
+use std::io::{self, ErrorKind, Read, Write};
+
+use serde::{Serialize, de::DeserializeOwned};
+
+// First line of every checkpoint.
+const CHECKPOINT_HEADER: &[u8] = b"#kik_sync_service checkpoint 2\n";
+
+/// An *ErrorKind::InvalidData* error with the reason.
+pub fn invalid_data(reason: &str) -> io::Error{
+    io::Error::new(ErrorKind::InvalidData, reason)
+}
+
+/// Write the bytes as a frame: their length as a u32, little endian, then the bytes.
+pub fn write_frame<W>(stream: &mut W, bytes: &[u8]) -> io::Result<()> where W: Write{
+    let length = u32::try_from(bytes.len()).map_err(|_| invalid_data("frame longer than u32::MAX bytes"))?;
+    stream.write_all(&length.to_le_bytes())?;
+    stream.write_all(bytes)
+}
+
+/// Read a frame written by *write_frame*.
+pub fn read_frame<Rd>(stream: &mut Rd) -> io::Result<Vec<u8>> where Rd: Read + ?Sized{
+    let mut length = [0; 4];
+    stream.read_exact(&mut length)?;
+    let mut bytes = vec![0; u32::from_le_bytes(length) as usize];
+    stream.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// Write values as a checkpoint. Returns how many were written. Used by *DeliveryService::checkpoint*.
+pub fn write_checkpoint<V, W>(writer: &mut W, values: &[V]) -> io::Result<usize> where V: Serialize, W: Write{
+    writer.write_all(CHECKPOINT_HEADER)?;
+    writer.write_all(&(values.len() as u64).to_le_bytes())?;
+    let mut buffer = Vec::new();
+    for value in values{
+        buffer.clear();
+        encode(value, &mut buffer)?;
+        write_frame(writer, &buffer)?;
+    }
+    writer.flush()?;
+    Ok(values.len())
+}
+
+/// Read every value of a checkpoint written by *write_checkpoint*. Nothing is returned unless all of them could be read.
+pub fn read_checkpoint<V, Rd>(reader: &mut Rd) -> io::Result<Vec<V>> where V: DeserializeOwned, Rd: Read{
+    let mut header = [0; CHECKPOINT_HEADER.len()];
+    reader.read_exact(&mut header)?;
+    if header != CHECKPOINT_HEADER{
+        return Err(invalid_data("not a checkpoint"));
+    }
+    let mut count = [0; 8];
+    reader.read_exact(&mut count)?;
+    let count = u64::from_le_bytes(count);
+    // A damaged count shouldn't reserve the whole memory.
+    let mut values = Vec::with_capacity(count.min(1024) as usize);
+    for _ in 0..count{
+        values.push(decode(&read_frame(reader)?)?);
+    }
+    Ok(values)
+}
+
+/// Append the bincode bytes of a value to out.
+pub fn encode<V>(value: &V, out: &mut Vec<u8>) -> io::Result<()> where V: Serialize + ?Sized{
+    bincode::serialize_into(out, value).map_err(|error| io::Error::new(ErrorKind::InvalidData, error))
+}
+
+/// Build a value back from the bytes written by *encode*. Returns an *ErrorKind::InvalidData* error if they don't make one.
+pub fn decode<V>(bytes: &[u8]) -> io::Result<V> where V: DeserializeOwned{
+    bincode::deserialize(bytes).map_err(|error| io::Error::new(ErrorKind::InvalidData, error))
+}
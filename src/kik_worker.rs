@@ -8,38 +8,68 @@
 //! 
 //! This module is not meant to be used directly. But the project is free and open source, so feel free to do as you please.
 //! 
-//! # Panics!
+//! # Disconnects
 //! 
-//! The receivers will be *Weak Arc* + *Mutex* references for the original receiver that is held by the parent *DeliveryService* type. 
+//! The receivers will be weak references (see kik_queue) for the original receiver that is held by the parent *DeliveryService* type. 
 //! In other words, when *DeliveryService* drops, *Worker*s will lose the reference and drop without panicking. 
-//! But if they try to send a *Message* to the deliverer channel and get an *mpsc*'s *TrySendError::Disconnected* or *TrySendError::Poisoned* error, they will panic.
+//! If they try to send a *Message* to the deliverer channel after the feeder is gone, they tell the observers and stop without panicking too.
 //! 
 //! 
-//! # Contribute
+//! # Panics
 //! 
-//! There are currently no methods in kik_channel for catching dropped *Workers* due to panics. I, the original developer, On0n0k1 (Lucas Lemos), am not sure how to deal with it yet.
-//! Am also open for receiving any help regarding methods for checking the *Worker* threads for panics, reporting and/or restarting them as needed.
-//! 
-//! Maybe an async method for keeping workers in check...
+//! *Worker*s that die from a panic are reported through *DeliveryService::events* and *DeliveryService::health*, and replaced on the next iteration.
+//! Panics inside *Message::work* don't kill the *Worker*, they come back as *WorkError::Panicked*.
 //! 
 //! 
 
 use std::marker::PhantomData;
-use std::thread::{yield_now};
-use std::sync::{Weak, Mutex, TryLockError};
-use std::sync::mpsc::{Receiver, SyncSender, TrySendError, TryRecvError};
+use std::thread::panicking;
+use std::time::{Duration, Instant};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{SyncSender, TrySendError, TryRecvError, RecvTimeoutError};
 
 use crate::kik_message::{Message, MessageInput, MessageData};
+use crate::kik_package::Package;
+use crate::kik_queue::WeakWorkReceiver;
+use crate::kik_backoff::{Backoff, WaitStrategy};
+use crate::kik_throttle::{PauseGate, Throttle, Throttler};
+use crate::kik_error::WorkError;
+use crate::kik_context::{WorkContext, WorkInfo};
+use crate::kik_event::{EventSenders, PoolEvent};
+#[cfg(feature = "remote")]
+use crate::kik_remote::RemoteCall;
+
+/// Called by a worker with its id and how long it has been waiting, whenever it goes without work for longer than the threshold set in *ChannelConfig::set_idle_hook*.
+pub type IdleHook = Arc<dyn Fn(usize, Duration) + Send + Sync>;
 
 /// Extends kik_channel. Not meant to be used individually.
-pub struct Worker<T, R, S>  where 
+pub struct Worker<T, R, S, E>  where 
 T: MessageData + 'static,
 R: MessageInput<T> + 'static,
-S: Message<T, R> + Sync + Send + Clone + 'static,
+S: Message<T, R, E> + Sync + Send + 'static,
+E: Send + 'static,
 {
     id: usize,
-    rx_inserter: Weak<Mutex<Receiver<S>>>,
-    tx_deliverer: SyncSender<S>,
+    rx_inserter: WeakWorkReceiver<Package<S, E>>,
+    tx_deliverer: SyncSender<Package<S, E>>,
+    // Threshold and callback for reporting idle time.
+    idle_hook: Option<(Duration, IdleHook)>,
+    // How to wait for work, and for room to send results.
+    wait_strategy: WaitStrategy,
+    // How long to wait for work before leaving, and how many workers of the channel are running. The last one never leaves.
+    idle_timeout: Option<(Duration, Arc<AtomicUsize>)>,
+    // Set once the worker took itself off the running count to leave.
+    left_idle: AtomicBool,
+    // How much to rest between messages, if at all, and the flag that cuts the rest short when the channel closes.
+    throttle: Option<(Throttle, Arc<AtomicBool>)>,
+    // Closed by DeliveryService::pause. Shared by every worker of the channel.
+    pause_gate: Option<Arc<PauseGate>>,
+    // How many workers the channel still wants removed. Shared by every worker of the channel.
+    retiring: Arc<AtomicUsize>,
+    // Subscriptions of the channel, told when this worker starts and stops.
+    events: EventSenders,
 
     // PhantomData tells the compiler that generics T and R exist in the implementation but are not stored in the struct
     resource_type: PhantomData<T>,
@@ -47,86 +77,179 @@ S: Message<T, R> + Sync + Send + Clone + 'static,
 }
 
 // Not sure how to indent this giant block
-impl<T, R, S> Worker<T, R, S> where 
+impl<T, R, S, E> Worker<T, R, S, E> where 
 T: MessageData + 'static,
 R: MessageInput<T> + 'static,
-S: Message<T, R> + Sync + Send + Clone + 'static,
+S: Message<T, R, E> + Sync + Send + 'static,
+E: Send + 'static,
 {
-    /// Construct a new worker with given id, Weak Mutex Receiver and SyncSender.
-    pub fn new(id: usize, rx_inserter: Weak<Mutex<Receiver<S>>>, tx_deliverer: SyncSender<S>) ->  Self
+    /// Construct a new worker with given id, weak inserter receiver, SyncSender, the channel's retiring counter and event subscriptions.
+    /// The idle hook is optional.
+    pub fn new(id: usize, rx_inserter: WeakWorkReceiver<Package<S, E>>, tx_deliverer: SyncSender<Package<S, E>>, idle_hook: Option<(Duration, IdleHook)>, retiring: Arc<AtomicUsize>, events: EventSenders) ->  Self
     {
         Worker{
-            id: id,
+            id,
             rx_inserter,
             tx_deliverer,
+            idle_hook,
+            wait_strategy: WaitStrategy::default(),
+            idle_timeout: None,
+            left_idle: AtomicBool::new(false),
+            throttle: None,
+            pause_gate: None,
+            retiring,
+            events,
             // ::< used to specify type of const arguments
             resource_type: PhantomData::<T>,
             resource_type2: PhantomData::<R>,
         }
     }
 
-    /// Get a message from the 'inserter' channel receiver. Message is sent by kik_feeder.
-    fn get_message(&self) -> S{
+    /// Choose how the worker waits for work and for room to send results. Default *WaitStrategy::ExponentialBackoff*.
+    pub fn set_wait_strategy(&mut self, wait_strategy: WaitStrategy){
+        self.wait_strategy = wait_strategy;
+    }
+
+    /// Leave once no work arrived for timeout, unless this is the last worker counted by running. Counts this worker in running until it's dropped.
+    /// Default None, the worker waits for as long as the channel lives.
+    pub fn set_idle_timeout(&mut self, timeout: Option<Duration>, running: Arc<AtomicUsize>){
+        if let Some(timeout) = timeout{
+            running.fetch_add(1, Ordering::SeqCst);
+            self.idle_timeout = Some((timeout, running));
+        }
+    }
+
+    /// True if the worker may leave for being idle, and was taken off the running count. The last one stays, so that a message sent
+    /// while the others leave always finds someone.
+    fn leave_idle(&self) -> bool{
+        match &self.idle_timeout{
+            Some((_, running)) => {
+                let left = running.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |running| if running > 1 { Some(running - 1) } else { None }).is_ok();
+                self.left_idle.store(left, Ordering::SeqCst);
+                left
+            },
+            None => false,
+        }
+    }
+
+    /// Rest between messages, see kik_throttle. The rest is cut short once closing is set. Default None.
+    pub fn set_throttle(&mut self, throttle: Option<Throttle>, closing: Arc<AtomicBool>){
+        self.throttle = throttle.map(|throttle| (throttle, closing));
+    }
+
+    /// Rest after a package, if the worker is throttled. Woken up early if the run is cancelled or the channel closes.
+    fn rest(&self, throttler: &mut Option<Throttler>, package_info: (Instant, Duration), context: &WorkContext){
+        if let (Some(throttler), Some((_, closing))) = (throttler, &self.throttle){
+            let (completed_at, work_time) = package_info;
+            throttler.rest(completed_at - work_time, work_time, || context.is_cancelled() || closing.load(Ordering::SeqCst));
+        }
+    }
+
+    /// Wait at gate before each message while the channel is paused. Default None, never paused.
+    pub fn set_pause_gate(&mut self, gate: Option<Arc<PauseGate>>){
+        self.pause_gate = gate;
+    }
+
+    /// Hold on to a package received while paused until the channel is resumed. Cancelling the run lets it through, to be flagged as cancelled.
+    fn wait_if_paused(&self, context: &WorkContext){
+        if let Some(gate) = &self.pause_gate{
+            gate.wait(|| context.is_cancelled());
+        }
+    }
+
+    /// Get a message from the 'inserter' channel receiver. Message is sent by kik_feeder. Returns None when the channel is closed,
+    /// or when the worker leaves after waiting past its idle timeout.
+    /// 
+    /// Polls the receiver for as long as the wait strategy allows, then blocks until a message arrives. How the workers share the receiver depends on the kik_queue backend.
+    /// If there's an idle hook, the wait is cut into slices of the hook's threshold so the hook can be called between them. The same for the idle timeout.
+    fn get_message(&self) -> Option<Package<S, E>>{
+        let idle_since = Instant::now();
+        // The hook is called again each time another threshold passes without work.
+        let mut next_idle_report = self.idle_hook.as_ref().map(|(threshold, _)| *threshold);
+        // Dropped if the worker can't leave, it waits for work like the others then.
+        let mut leave_at = self.idle_timeout.as_ref().map(|(timeout, _)| *timeout);
+        let mut backoff = Backoff::with_strategy(self.wait_strategy);
         loop{
-            yield_now();
-            // turn the weak lock into a strong lock in order to access it
-            match self.rx_inserter.upgrade(){
-                Some(new_lock) => {
-                    // if successful, try accessing the lock
-                    match new_lock.try_lock(){
-                        Err(err) => {
-                            match err{
-                                // If a thread panicked while holding the lock, this will quit.
-                                TryLockError::Poisoned(_) => {
-                                    panic!("Closing thread nr {} due to channel poisoning.", self.id)
-                                },
-                                // If access is blocked, yield remaining time for the cpu and try again.
-                                TryLockError::WouldBlock => continue,
-                            };
-                        },
-                        // if successful, try to get a message from the receiver in the lock
-                        Ok(new_rx_inserter) => {
-                            match new_rx_inserter.try_recv(){
-                                Err(err) => {
-                                    match err{
-                                        // When the main feeder has finished sending and retrieving all the packages, it will disconnect the channel. 
-                                        // Therefore it means it's time for the workers to close.
-                                        TryRecvError::Disconnected => {
-                                            std::mem::drop(self);
-                                        },
-                                        TryRecvError::Empty => continue,
-                                    }
-                                },
-                                Ok(new_message) => return new_message,
-                            };
-                        },
-                    };
-                },
-                // Arc reference has been dropped by the parent channel.
-                None => {
-                    // Main reference dropped. Worker closing
-                    std::mem::drop(self);
+            if let (Some(report_at), Some((threshold, hook))) = (next_idle_report, &self.idle_hook){
+                let idle_for = idle_since.elapsed();
+                if idle_for >= report_at{
+                    hook(self.id, idle_for);
+                    next_idle_report = Some(report_at + *threshold);
                 }
+            }
+            if let Some(timeout) = leave_at{
+                if idle_since.elapsed() >= timeout{
+                    if self.leave_idle(){
+                        return None;
+                    }
+                    leave_at = None;
+                }
+            }
+            // turn the weak receiver into a strong one in order to access it. If it fails the parent channel has been dropped, so the worker closes.
+            let new_rx_inserter = self.rx_inserter.upgrade()?;
+            match new_rx_inserter.try_recv(){
+                Ok(new_message) => return Some(new_message),
+                Err(TryRecvError::Disconnected) => return None,
+                Err(TryRecvError::Empty) => {},
+            }
+            if backoff.retry(){
+                continue;
+            }
+            #[cfg(feature = "tracing")]
+            tracing::trace!(worker_id = self.id, "queue empty, waiting for work");
+            let wake_at = match (next_idle_report, leave_at){
+                (Some(report_at), Some(timeout)) => Some(report_at.min(timeout)),
+                (wake_at, None) | (None, wake_at) => wake_at,
             };
+            match wake_at{
+                // No hook and no timeout, just sleep until there's work.
+                None => {
+                    // When the main feeder is dropped, it will disconnect the channel. 
+                    // Therefore it means it's time for the workers to close.
+                    return new_rx_inserter.recv();
+                },
+                Some(wake_at) => {
+                    let wait_for = wake_at.saturating_sub(idle_since.elapsed());
+                    match new_rx_inserter.recv_timeout(wait_for){
+                        Ok(new_message) => return Some(new_message),
+                        Err(RecvTimeoutError::Disconnected) => return None,
+                        // Report idle time, or leave.
+                        Err(RecvTimeoutError::Timeout) => continue,
+                    }
+                },
+            }
         }
     }
     
     /// Send a message to the 'deliverer' channel SyncSender. Message is retrieved by kik_feeder.
-    fn send_message(&self, message: S){
+    /// While the channel is full, it's retried a few times (see kik_backoff), then the worker sleeps until the feeder makes room.
+    /// Returns false if the feeder is gone, then the result has nowhere to go and the worker should stop.
+    fn send_message(&self, mut package: Package<S, E>) -> bool{
+        let mut backoff = Backoff::with_strategy(self.wait_strategy);
         loop{
-            let new_message = message.clone();
-            match self.tx_deliverer.try_send(new_message){
+            match self.tx_deliverer.try_send(package){
                 Ok(_) => {
-                    break;
+                    return true;
                 },
                 Err(err) => {
                     match err{
-                        TrySendError::Full(_) => {
-                            yield_now();
-                            continue;
+                        // The channel gives the package back, so the same one is sent again without cloning the message.
+                        TrySendError::Full(returned) => {
+                            package = returned;
+                            if backoff.retry(){
+                                continue;
+                            }
+                            // Out of retries. Sleep until the feeder takes something.
+                            self.events.queue_full(package.sequence);
+                            if self.tx_deliverer.send(package).is_err(){
+                                self.events.disconnected();
+                                return false;
+                            }
+                            return true;
                         },
                         TrySendError::Disconnected(_) => {
-                            panic!("Error: Channel disconnected while sending.");
+                            self.events.disconnected();
+                            return false;
                         }
                     }
                 }
@@ -136,23 +259,143 @@ S: Message<T, R> + Sync + Send + Clone + 'static,
 
     // Thread doesn't change state while running
     /// Run continuously getting, working and retrieving messages in the channel. This is supposed to be run in a thread created by kik_channel.
-    pub fn run(&self) {
-        println!("Starting worker nr {}!", self.id);
-        loop{
-            let mut message: S = self.get_message();
-            message.work();
-            self.send_message(message);
+    /// The context is handed to every message worked. Returns when the channel is closed.
+    pub fn run(&self, mut context: WorkContext) {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("worker", worker_id = self.id).entered();
+        self.events.send(PoolEvent::WorkerStarted{worker_id: self.id});
+        let mut throttler = self.throttle.as_ref().map(|(throttle, _)| Throttler::new(*throttle));
+        while let Some(mut package) = self.get_message(){
+            self.wait_if_paused(&context);
+            work_package(self.id, &mut package, &mut context, &self.events);
+            let package_info = (package.completed_at, package.work_time);
+            if !self.send_message(package) || self.retire(){
+                break;
+            }
+            self.rest(&mut throttler, package_info, &context);
         }
     }
+
+    /// Same as *run*, but each message is worked by a *RemoteAgent* through call instead of in this thread. Returns when the channel is closed or the connection is lost.
+    /// If retires is false, the worker isn't removed by *DeliveryService::remove_workers*, like the ones added with *DeliveryService::add_remote_worker*.
+    #[cfg(feature = "remote")]
+    pub fn run_remote(&self, mut context: WorkContext, mut call: RemoteCall<S, E>, retires: bool){
+        self.events.send(PoolEvent::WorkerStarted{worker_id: self.id});
+        let mut throttler = self.throttle.as_ref().map(|(throttle, _)| Throttler::new(*throttle));
+        while let Some(mut package) = self.get_message(){
+            self.wait_if_paused(&context);
+            let connected = work_remote_package(self.id, &mut package, &mut context, &mut call, &self.events);
+            let package_info = (package.completed_at, package.work_time);
+            if !self.send_message(package) || !connected || (retires && self.retire()){
+                break;
+            }
+            self.rest(&mut throttler, package_info, &context);
+        }
+    }
+
+    /// True if the channel wants a worker removed and this one took the job. Only checked between messages, so the current one is always finished.
+    fn retire(&self) -> bool{
+        self.retiring.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |retiring| retiring.checked_sub(1)).is_ok()
+    }
 }
 
-impl<T, R, S> Drop for Worker<T, R, S> where 
+impl<T, R, S, E> Drop for Worker<T, R, S, E> where 
 T: MessageData + 'static,
 R: MessageInput<T> + 'static,
-S: Message<T, R> + Sync + Send + Clone + 'static,
+S: Message<T, R, E> + Sync + Send + 'static,
+E: Send + 'static,
 {
     fn drop(&mut self){
-        std::mem::drop(&self.tx_deliverer);
-        std::mem::drop(&self.rx_inserter);
+        if let Some((_, running)) = &self.idle_timeout{
+            if !self.left_idle.load(Ordering::SeqCst){
+                running.fetch_sub(1, Ordering::SeqCst);
+            }
+        }
+        // The worker is dropped while unwinding too, so this is where a dying thread gets noticed.
+        if panicking(){
+            self.events.send(PoolEvent::WorkerPanicked{worker_id: self.id});
+        } else {
+            self.events.send(PoolEvent::WorkerExited{worker_id: self.id});
+        }
+    }
+}
+
+/// Work a single package, filling in the bookkeeping the feeder needs. Used by the workers, and by kik_feeder when running inline.
+/// 
+/// If the run was cancelled, the message is only flagged as cancelled, not worked. Otherwise the observers are told when it starts and completes.
+pub fn work_package<T, R, S, E>(worker_id: usize, package: &mut Package<S, E>, context: &mut WorkContext, events: &EventSenders) where 
+T: MessageData + 'static,
+R: MessageInput<T> + 'static,
+S: Message<T, R, E> + Sync + Send + 'static,
+E: Send + 'static,
+{
+    package.worker_id = worker_id;
+    // The feeder is waiting to throw this away. Give it back right away.
+    if context.is_cancelled(){
+        package.cancelled = true;
+        return;
+    }
+    #[cfg(feature = "tracing")]
+    let _span = tracing::debug_span!("work", message_id = package.sequence, worker_id, attempt = package.attempt + 1).entered();
+    context.probe_stack();
+    context.reset_progress();
+    context.set_working(true);
+    events.started(package.sequence, worker_id);
+    package.attempt += 1;
+    let info = WorkInfo{
+        worker_id,
+        attempt: package.attempt,
+        dispatched_at: package.dispatched_at,
+        deadline: package.deadline,
+    };
+    let start = Instant::now();
+    // A panic inside the user's message is caught and sent back as an error, so the feeder doesn't wait forever for it.
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        package.message.work_with_info(context, &info)?;
+        // Inputs packed by map_reduce. If one fails, the whole package does.
+        let merge = package.merge;
+        for mut other in package.merged.drain(..){
+            other.work_with_info(context, &info)?;
+            if let Some(merge) = merge{
+                merge(&mut package.message, other);
+            }
+        }
+        Ok(())
+    }));
+    package.completed_at = Instant::now();
+    package.work_time = package.completed_at - start;
+    package.error = match result{
+        Ok(Ok(())) => None,
+        Ok(Err(error)) => Some(WorkError::Failed{worker_id, error}),
+        Err(_) => Some(WorkError::Panicked{worker_id}),
+    };
+    context.set_working(false);
+    events.completed(package.sequence, package.work_time);
+}
+
+/// Same as *work_package*, but the message is worked by a *RemoteAgent* through call. Returns false if the connection was lost, the package then holds *WorkError::Lost*.
+#[cfg(feature = "remote")]
+fn work_remote_package<S, E>(worker_id: usize, package: &mut Package<S, E>, context: &mut WorkContext, call: &mut RemoteCall<S, E>, events: &EventSenders) -> bool{
+    package.worker_id = worker_id;
+    if context.is_cancelled(){
+        package.cancelled = true;
+        return true;
     }
+    context.set_working(true);
+    events.started(package.sequence, worker_id);
+    package.attempt += 1;
+    let start = Instant::now();
+    package.error = None;
+    let connected = match call(package){
+        Ok(()) => true,
+        Err(_) => {
+            package.error = Some(WorkError::Lost{worker_id});
+            false
+        },
+    };
+    package.completed_at = Instant::now();
+    package.work_time = package.completed_at - start;
+    context.set_working(false);
+    events.completed(package.sequence, package.work_time);
+    connected
 }
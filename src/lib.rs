@@ -37,6 +37,7 @@
 //! 
 //!     use kik_sync_service::message::{Message, MessageData, MessageInput};
 //!     use kik_sync_service::channel::{DeliveryService};
+//!     use kik_sync_service::util::grid;
 //!     
 //! 
 //!     // What type of data should be returned.
@@ -56,15 +57,18 @@
 //!         }
 //!     }
 //!
-//!     // with this trait it can be used as data for a Message.
-//!     impl MessageData for MessageArray{
-//!         fn new() -> Self{
+//!     // ThreadMessage derives Default, so its data needs it too. Arrays this large have no derive(Default).
+//!     impl Default for MessageArray{
+//!         fn default() -> Self{
 //!             MessageArray{
 //!                 data: [0; 1024],
 //!             }
 //!         }
 //!     }
 //!
+//!     // with this trait it can be used as data for a Message.
+//!     impl MessageData for MessageArray{}
+//!
 //!     impl MessageArray{
 //!         pub fn get(&mut self) -> &mut [u32; 1024]{
 //!             &mut self.data
@@ -73,6 +77,7 @@
 //!
 //!
 //!     // What kind of input it needs.
+//!     #[derive(Default)]
 //!     pub struct Coordinates{
 //!         pub x0: usize,
 //!         pub y0: usize,
@@ -80,54 +85,29 @@
 //!         pub y1: usize,
 //!     }
 //!
-//!     // Doesn't need to implement Copy, but needs Clone.
-//!     impl Clone for Coordinates{
-//!         fn clone(&self) -> Self{
-//!             Coordinates{
-//!                 x0: self.x0,
-//!                 y0: self.y0,
-//!                 x1: self.x1,
-//!                 y1: self.y1,
-//!             }
-//!         }
-//!     }
+//!     // Doesn't need to implement Copy or Clone, each input is moved into a message.
 //!
 //!     // This implementation tells the compiler that this object can be 
 //!     // used as input for the worker threads, and it can only work with MessageArray.
-//!     impl MessageInput<MessageArray> for Coordinates{
-//!         fn new() -> Self{
-//!             Coordinates{
-//!                 x0: 0,
-//!                 y0: 0,
-//!                 x1: 0,
-//!                 y1: 0,
-//!             }
-//!         }
-//!     }
+//!     // Its new comes from Default.
+//!     impl MessageInput<MessageArray> for Coordinates{}
 //!
 //!
 //!     // This is the message that holds both the data and input. 
 //!     // Feel free to add anything else you might need to work with it.
+//!     // Its new comes from Default. Without Default, create the channel with DeliveryService::try_with_builder.
+//!     #[derive(Default)]
 //!     pub struct ThreadMessage{
 //!         pub array: MessageArray,
 //!         pub current_input: Coordinates,
 //!     }
 //!
-//!     impl Clone for ThreadMessage{
-//!         fn clone(&self) -> Self{
-//!             ThreadMessage{
-//!                 array: self.array.clone(),
-//!                 current_input: self.current_input.clone(),
-//!             }
-//!         }
-//!     }
-//!
 //!     // ThreadMessage uses MessageArray as data,
 //!     // ThreadMessage uses Coordinates as input to change the data.
 //!     impl Message<MessageArray, Coordinates> for ThreadMessage {
 //!
 //!         fn set_input(&mut self, message_input: Coordinates){
-//!             self.current_input = message_input.clone();
+//!             self.current_input = message_input;
 //!         }
 //!
 //!         fn work (&mut self){
@@ -160,37 +140,18 @@
 //!         fn clone_message_data(&self) -> MessageArray{
 //!             self.array.clone()
 //!         }
-//!
-//!         fn new() -> Self{
-//!             let new_data = MessageArray::new();
-//!             let new_input = Coordinates::new();
-//!
-//!             ThreadMessage{
-//!                 current_input: new_input,
-//!                 array: new_data,
-//!             }
-//!         }
-//!        
 //!     }
 //!
 //!     // Finally, Now that all the data structure is set, time to use the channel.
-//!     #[test]
 //!     fn test(){
 //!         let width: usize = 1024;
 //!         let height: usize = 768;
-//!         let mut coordinates: Vec<Coordinates> = Vec::with_capacity((height as f32/32.0 * width as f32/32.0)as usize);
-//!         assert_eq!(width % 32, 0);
-//!         assert_eq!(height % 32, 0);
 //!
-//!         // Creating a vec of coordinates to use as input.
-//!         // for y in 0..24
-//!         for y in 0..(((height as f32)/32.0) as usize){
-//!             // for x in 0..32
-//!             for x in 0..(((width as f32)/32.0) as usize){
-//!                 let (x0, y0) = (32 * x, 32 * y);
-//!                 coordinates.push(Coordinates{x0: x0, y0: y0, x1: x0 + 32, y1: y0 + 32});
-//!             }
-//!         }
+//!         // Creating a vec of coordinates to use as input, one for each cell of 32 by 32 (24 rows of 32).
+//!         // If the sizes weren't multiples of 32, the cells on the right and bottom edges would be smaller instead of missing.
+//!         let mut coordinates: Vec<Coordinates> = grid::cells(width, height, 32, 32).into_iter()
+//!             .map(|cell| Coordinates{x0: cell.x0, y0: cell.y0, x1: cell.x1, y1: cell.y1})
+//!             .collect();
 //!         // Personal Note:
 //!         // create a vec of inputs
 //!         // create channel
@@ -223,14 +184,8 @@
 //!         }
 //!
 //!         // Creating another vec to feed the structure again.
-//!         // for y in 0..24
-//!         for y in 0..(((height as f32)/32.0) as usize){
-//!             // for x in 0..32
-//!             for x in 0..(((width as f32)/32.0) as usize){
-//!                 let (x0, y0) = (32 * x, 32 * y);
-//!                 coordinates.push(Coordinates{x0: x0, y0: y0, x1: x0 + 32, y1: y0 + 32});
-//!             }
-//!         }
+//!         coordinates.extend(grid::cells(width, height, 32, 32).into_iter()
+//!             .map(|cell| Coordinates{x0: cell.x0, y0: cell.y0, x1: cell.x1, y1: cell.y1}));
 //!        
 //!         // You can feed more input values after emptying the results from last run.
 //!         kiki_channel.feed_feeder(&mut coordinates);
@@ -257,6 +212,8 @@
 //!             counter += 1;
 //!         }
 //!     }
+//!
+//!     test();
 //! 
 //! 
 
@@ -269,14 +226,157 @@ mod kik_message;
 mod kik_channel;
 mod kik_worker;
 mod kik_feeder;
+mod kik_package;
+mod kik_queue;
+mod kik_backoff;
+mod kik_adaptive;
+mod kik_pool;
+mod kik_report;
+mod kik_alloc;
+mod kik_error;
+mod kik_sender;
+mod kik_scheduler;
+mod kik_context;
+mod kik_event;
+mod kik_registry;
+mod kik_sequential;
+mod kik_split;
+mod kik_select;
+mod kik_tee;
+mod kik_reader;
+mod kik_throttle;
+mod kik_spawner;
+mod kik_heartbeat;
+#[cfg(feature = "os")]
+mod kik_os;
+#[cfg(feature = "spill")]
+mod kik_spill;
+#[cfg(feature = "serde")]
+mod kik_wire;
+#[cfg(feature = "remote")]
+mod kik_remote;
+#[cfg(feature = "process")]
+mod kik_process;
+#[cfg(feature = "async")]
+mod kik_stream;
+mod kik_buffer;
+mod kik_grid;
 mod kik_message_example;
 
+// The code written by derive(Message) names this crate, which has to work in its own tests too.
+#[cfg(feature = "derive")]
+extern crate self as kik_sync_service;
+
 /// Holds the traits used for message sharing and how to work them. They must be manually set by the user before using channel.
+/// Merge is only needed by messages used with DeliveryService::map_reduce.
+/// With the "derive" feature, #[derive(Message)] writes everything but Message::work and Default, see the kik_sync_service_derive crate.
 pub mod message{
-    pub use crate::kik_message::{Message,MessageInput, MessageData};
+    pub use crate::kik_message::{Message,MessageInput, MessageData, Merge};
+    #[cfg(feature = "derive")]
+    pub use kik_sync_service_derive::Message;
+}
+
+/// Ready-made messages for filling buffers, with no trait to implement. VecMessage fills a Chunk for each ChunkInput range, TileMessage fills a Tile for each TileInput corner.
+/// Each takes the function that fills the buffer in with_fill, and is given to the channel with DeliveryService::set_message_template.
+pub mod buffer{
+    pub use crate::kik_buffer::{ChunkInput, Chunk, VecMessage, FillChunk, TileInput, Tile, TileMessage, FillTile};
+}
+
+/// Helpers for building inputs.
+pub mod util{
+    /// Splits a 2D area in cells, tiles, rows or columns, covering the edges when the sizes don't divide it. GridCell is a rectangle of it, usable as an input.
+    pub mod grid{
+        pub use crate::kik_grid::{GridCell, cells, tiles, rows, columns};
+    }
 }
 
-/// DeliveryService is the channel used for the synchronous message-sharing and work. It can be created with DeliveryService::default values or be customized by using ChannelConfig as argument for DeliveryService::new.
+/// DeliveryService is the channel used for the synchronous message-sharing and work. It can be created with DeliveryService::default values or be customized by using ChannelConfig as argument for DeliveryService::try_new.
+/// SequentialDeliveryService has the same API but works every message on the caller's thread, for comparing against it.
+/// DeliveryService::split gives a FeederHandle and a ResultReceiver, for feeding on one thread while iterating on another.
+/// DeliveryService::submit gives a JobHandle that gets the result of that one input.
+/// DeliveryService::tee and ResultReceiver::tee give several ResultTees, each getting a clone of every result.
+/// An owned DeliveryService is iterated through IntoIter, which shuts the channel down once dropped, for one-shot jobs written as for data in channel.
+/// MultiChannel merges the results of several channels into one iterator, yielding whichever is ready first.
+/// WaitStrategy chooses whether the feeder and the workers spin, yield or sleep while they wait.
+/// ThreadSpawner decides how the worker threads are created, for embedders with their own threads.
+/// Throttle caps how much of the cpu the workers take, so that a background pool leaves room for the rest of the application.
+/// With the "async" feature, DeliveryService::into_stream gives a ResultStream, a futures_core::Stream of the results, and DeliveryService::async_feeder an AsyncFeeder that waits for room.
+/// With the "serde" feature, DeliveryService::checkpoint writes the inputs not yet worked somewhere, and DeliveryService::restore feeds them back after a restart.
+/// With the "spill" feature, DeliveryService::feed_spilling keeps inputs beyond ChannelConfig::set_spill_threshold in a temporary file until there's room for them.
+/// With the "tracing" feature, workers and the messages they work are wrapped in tracing spans, see kik_event.
+/// With the "os" feature, ChannelConfig::set_thread_priority and ChannelConfig::set_core_affinity choose how the system schedules the worker threads.
+/// With the "process" feature, Backend::Process runs each worker's messages in a child process, so that a crash in Message::work only takes down that child.
 pub mod channel{
-    pub use crate::kik_channel::{ChannelConfig, DeliveryService};
+    pub use crate::kik_channel::{ChannelConfig, ChannelConfigBuilder, MIN_STACK_SIZE, DeliveryService, DropPolicy, Results, TryIter, BatchIter, IntoIter};
+    pub use crate::kik_backoff::WaitStrategy;
+    pub use crate::kik_throttle::Throttle;
+    pub use crate::kik_spawner::{ThreadSpawner, StdSpawner, WorkerThread};
+    pub use crate::kik_sender::{WeakInputSender, FeederHandle, FeedReceipt, BatchId, JobHandle};
+    pub use crate::kik_split::ResultReceiver;
+    pub use crate::kik_select::{MultiChannel, POLL_SLICE};
+    pub use crate::kik_tee::ResultTee;
+    pub use crate::kik_reader::ReadHandle;
+    pub use crate::kik_sequential::SequentialDeliveryService;
+    #[cfg(feature = "async")]
+    pub use crate::kik_stream::{ResultStream, NextResult, AsyncFeeder};
+    #[cfg(feature = "os")]
+    pub use crate::kik_os::{ThreadPriority, CoreSet};
+    #[cfg(feature = "process")]
+    pub use crate::kik_process::{Backend, ProcessBackend, ProcessCommand, PROCESS_WORKER_VAR};
+}
+
+/// With the "remote" feature, DeliveryService::add_remote_worker adds workers that live in other processes or machines, connected over TCP to a RemoteAgent.
+/// Messages and errors that travel implement serde's Serialize and DeserializeOwned, and are sent as bincode.
+/// With the "process" feature, ChannelConfig::set_backend can run each worker's messages in a child process instead, served by RemoteAgent::serve_if_child.
+#[cfg(feature = "remote")]
+pub mod remote{
+    pub use crate::kik_remote::RemoteAgent;
+}
+
+/// MessagePool keeps messages built ahead of time, so that a channel with ChannelConfig::set_message_pool doesn't build any while it runs.
+pub mod pool{
+    pub use crate::kik_pool::MessagePool;
+}
+
+/// WorkContext is handed by each worker into Message::work_with_context, holding the worker's own state if ChannelConfig::set_worker_context was used
+/// and the value shared by every worker if DeliveryService::set_shared_context was used. CancellationToken aborts the current run.
+/// WorkPacer checks for cancellation, tells progress and yields the thread from inside long work loops, all in one call.
+/// WorkInfo is handed into Message::work_with_info, telling which worker took the message, on which attempt and when it was dispatched.
+pub mod context{
+    pub use crate::kik_context::{WorkContext, WorkInfo, CancellationToken, WorkPacer};
+}
+
+/// Scheduler decides which queued input is dispatched next. FifoScheduler is the default, LifoScheduler is picked with DispatchOrder::Lifo.
+/// DeadlineScheduler dispatches inputs by their DeadlineClass, shedding late ones if asked to. Priority picks the lane fed by DeliveryService::feed_feeder_with_priority.
+pub mod scheduler{
+    pub use crate::kik_scheduler::{Scheduler, LifoScheduler, FifoScheduler, DeadlineScheduler, DeadlineClass, DeadlineInput, Priority, DispatchOrder};
+}
+
+/// Errors returned by the channel instead of panicking. WorkError tells why a single message failed. Closed is returned when feeding a dropped channel.
+/// KikError is a failure of the channel itself, StopReason tells why the last iteration ended. ConfigError is returned by ChannelConfigBuilder::build and DeliveryService::try_new.
+/// Timeout is returned by DeliveryService::next_timeout when the deadline passes first. FailureReason comes with each input given back by DeliveryService::take_failed.
+pub mod error{
+    pub use crate::kik_error::{WorkError, FailureReason, Closed, Timeout, KikError, StopReason, ConfigError};
+}
+
+/// PoolEvent is sent to every subscription created by DeliveryService::events when a worker starts, exits or panics, and when a batch completes.
+/// ChannelObserver is called by the channel for every message dispatched and worked, for every worker started, exited or panicked, and when queues fill up.
+pub mod event{
+    pub use crate::kik_event::{PoolEvent, ChannelObserver};
+}
+
+/// PoolRegistry owns several named DeliveryServices of any types, finds them by name and shuts them down in the reverse order they were registered.
+pub mod registry{
+    pub use crate::kik_registry::{PoolRegistry, RegisteredPool};
+}
+
+/// BatchReport holds tuning data about a drained run: elapsed time, messages per worker, work time statistics and how many messages were recycled.
+/// MemoryStats holds the peak payload size of each message slot. StackUsage holds the deepest stack use probed in each worker.
+/// ShutdownReport tells what was lost when the channel was shut down. Throughput holds messages and bytes per second over a recent window.
+/// AllocStats counts the allocations made while feeding and iterating, once CountingAllocator is installed as the global allocator.
+/// WorkProgress tells how far along a worker is in its current message. ChannelStats holds latency percentiles and per-worker busy and idle time.
+/// PoolHealth holds the WorkerStatus of each worker, the queue depths and how long ago a message was last completed.
+pub mod report{
+    pub use crate::kik_report::{BatchReport, MemoryStats, SlotMemory, ShutdownReport, StackUsage, Throughput, AllocStats, WorkProgress, ChannelStats, PoolHealth, WorkerStatus};
+    pub use crate::kik_alloc::CountingAllocator;
 }